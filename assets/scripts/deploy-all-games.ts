@@ -383,13 +383,16 @@ async function deployGame(
         `Win amazing ${game.name} prizes!`,
         game.bannerUrl || '',
         new BN(playCostCents),
-        tokenMint
+        tokenMint,
+        null, // starts_at: open as soon as prizes are added
+        null // ends_at: no scheduled end
       )
       .accounts({
         authority: authority.publicKey,
         config: configPda,
         game: gamePda,
         treasury: treasury,
+        tokenMint,
       })
       .signers([authority])
       .rpc({ skipPreflight: true });
@@ -421,7 +424,8 @@ async function deployGame(
           Math.round(prize.lengthInches * 100),
           Math.round(prize.widthInches * 100),
           Math.round(prize.heightInches * 100),
-          prize.supply
+          prize.supply,
+          Boolean(prize.sku)
         )
         .accounts({
           authority: authority.publicKey,
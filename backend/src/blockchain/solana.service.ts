@@ -169,7 +169,7 @@ export class SolanaService {
 
     // Build instruction data:
     // discriminator (8) + game_id (8) + name (4+len) + description (4+len) + 
-    // image_url (4+len) + cost_usd (8) + token_mint (32)
+    // image_url (4+len) + cost_usd (8) + token_mint (32) + starts_at (1) + ends_at (1)
     const gameIdBuf = Buffer.alloc(8);
     new BN(params.gameId).toArrayLike(Buffer, 'le', 8).copy(gameIdBuf);
 
@@ -188,6 +188,7 @@ export class SolanaService {
       imageBuf,
       costBuf,
       tokenMint.toBuffer(),
+      Buffer.from([0, 0]), // starts_at / ends_at: None (no schedule)
     ]);
 
     const instruction = new TransactionInstruction({
@@ -197,6 +198,7 @@ export class SolanaService {
         { pubkey: configPda, isSigner: false, isWritable: false },
         { pubkey: gamePda, isSigner: false, isWritable: true },
         { pubkey: treasury, isSigner: false, isWritable: false },
+        { pubkey: tokenMint, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       data,
@@ -150,6 +150,7 @@ export function buildInitializeGameInstruction(
     imageBuf,
     costBuf,
     tokenMint.toBuffer(),
+    Buffer.from([0, 0]), // starts_at / ends_at: None (no schedule)
   ]);
 
   return new TransactionInstruction({
@@ -159,6 +160,7 @@ export function buildInitializeGameInstruction(
       { pubkey: configPda, isSigner: false, isWritable: false },
       { pubkey: gamePda, isSigner: false, isWritable: true },
       { pubkey: treasury, isSigner: false, isWritable: false },
+      { pubkey: tokenMint, isSigner: false, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     data,
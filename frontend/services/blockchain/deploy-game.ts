@@ -251,13 +251,16 @@ export async function deployGame(
           params.description || "",
           params.imageUrl || "",
           new BN(params.costUsdCents),
-          tokenMint
+          tokenMint,
+          null, // starts_at (no schedule)
+          null // ends_at
         )
         .accounts({
           authority: wallet.publicKey,
           config: configPda,
           game: gamePda,
           treasury: treasury,
+          tokenMint,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: true, commitment: "confirmed" });
@@ -307,7 +310,7 @@ export async function deployGame(
 
       log(`Step 2/2: Adding prize ${i + 1}/${params.prizes.length}: ${prize.name}`);
 
-      // add_prize(prize_index, prize_id, name, description, image_url, metadata_uri, physical_sku, tier, probability_bp, cost_usd, weight_grams, length_hundredths, width_hundredths, height_hundredths, supply_total)
+      // add_prize(prize_index, prize_id, name, description, image_url, metadata_uri, physical_sku, tier, probability_bp, cost_usd, weight_grams, length_hundredths, width_hundredths, height_hundredths, supply_total, is_physical)
       let prizeSig: string;
       try {
         prizeSig = await (program.methods as any)
@@ -326,7 +329,8 @@ export async function deployGame(
           lengthHundredths, // length_hundredths (u16)
           widthHundredths, // width_hundredths (u16)
           heightHundredths, // height_hundredths (u16)
-          prize.supplyTotal // supply_total (u32)
+          prize.supplyTotal, // supply_total (u32)
+          !!prize.physicalSku // is_physical (bool)
         )
         .accounts({
           authority: wallet.publicKey,
@@ -97,6 +97,35 @@ export function generateSessionSeed(): Uint8Array {
   return crypto.getRandomValues(new Uint8Array(32));
 }
 
+/**
+ * Find a nonce such that sha256(sessionSeed || user || nonce_le) has `difficulty`
+ * leading zero bits. Games with pow_difficulty = 0 accept any nonce.
+ */
+export async function solveProofOfWork(
+  sessionSeed: Uint8Array,
+  user: PublicKey,
+  difficulty: number
+): Promise<bigint> {
+  if (difficulty === 0) return BigInt(0);
+  const input = new Uint8Array(32 + 32 + 8);
+  input.set(sessionSeed, 0);
+  input.set(user.toBytes(), 32);
+  for (let nonce = BigInt(0); ; nonce++) {
+    input.set(toLEU64(nonce), 64);
+    const hash = new Uint8Array(await crypto.subtle.digest("SHA-256", input));
+    let bits = 0;
+    for (const byte of hash) {
+      if (byte === 0) {
+        bits += 8;
+        continue;
+      }
+      bits += Math.clz32(byte) - 24;
+      break;
+    }
+    if (bits >= difficulty) return nonce;
+  }
+}
+
 // Parse Game account to get prize probabilities and token mint
 interface GameAccountData {
   prizeCount: number;
@@ -264,6 +293,7 @@ export async function playOnChain(opts: {
   gamePda: string;
   tokenAmount?: number | bigint;
   costUsdCents?: number;
+  powDifficulty?: number; // the game's pow_difficulty (0 = off)
 }): Promise<{ tx: Transaction; sessionPda: PublicKey; sessionSeed: Uint8Array; tokenAmountPaid: bigint }> {
   if (!GAME_PROGRAM_ID) throw new Error("GAME_PROGRAM_ID not configured");
 
@@ -322,18 +352,23 @@ export async function playOnChain(opts: {
   const sessionSeed = generateSessionSeed();
   const sessionPda = findPlaySessionPda(gamePubkey, user, sessionSeed);
 
-  // Instruction data = discriminator + u64 token_amount + [u8; 32] session_seed
+  const powNonce = await solveProofOfWork(sessionSeed, user, opts.powDifficulty ?? 0);
+
+  // Instruction data = discriminator + u64 token_amount + [u8; 32] session_seed + u64 pow_nonce
   const amountLE = toLEU64(finalTokenAmount);
-  const data = new Uint8Array(PLAY_GAME_DISCRIMINATOR.length + amountLE.length + 32);
+  const data = new Uint8Array(PLAY_GAME_DISCRIMINATOR.length + amountLE.length + 32 + 8);
   data.set(PLAY_GAME_DISCRIMINATOR, 0);
   data.set(amountLE, PLAY_GAME_DISCRIMINATOR.length);
   data.set(sessionSeed, PLAY_GAME_DISCRIMINATOR.length + amountLE.length);
+  data.set(toLEU64(powNonce), PLAY_GAME_DISCRIMINATOR.length + amountLE.length + 32);
 
   const keys = [
     { pubkey: gamePubkey, isSigner: false, isWritable: true },
     { pubkey: user, isSigner: true, isWritable: true },
     { pubkey: userTokenAccount, isSigner: false, isWritable: true },
     { pubkey: treasuryTokenAccount, isSigner: false, isWritable: true },
+    // creator_token_account: the program ID stands in for None (games without a creator split)
+    { pubkey: programId, isSigner: false, isWritable: false },
     { pubkey: mint, isSigner: false, isWritable: false }, // token_mint for transfer_checked
     { pubkey: sessionPda, isSigner: false, isWritable: true },
     { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -459,7 +494,7 @@ export async function claimPrize(opts: {
   const keys = [
     { pubkey: sessionPubkey, isSigner: false, isWritable: true },
     { pubkey: gamePubkey, isSigner: false, isWritable: false },
-    { pubkey: prizePda, isSigner: false, isWritable: false },
+    { pubkey: prizePda, isSigner: false, isWritable: true }, // claims release the prize's pending_claims
     { pubkey: user, isSigner: true, isWritable: true },
     { pubkey: nftMint.publicKey, isSigner: false, isWritable: true },
     { pubkey: metadataPda, isSigner: false, isWritable: true },
@@ -13,6 +13,7 @@ export type EventName =
   | 'PlayResolved'   // New event from finalize_play
   | 'PrizeClaimed'   // New event from claim_prize
   | 'GameStatusUpdated'
+  | 'GameSoldOut'
   | 'SupplyReplenished'
   | 'TreasuryWithdrawn'
   | 'NFTListed'
@@ -40,6 +41,11 @@ export interface PrizeAddedEventData {
   probability_bp: number;
   supply_total: number;
   timestamp: number;
+  // Catalog fields (absent on events emitted by older program versions)
+  tier?: 'common' | 'uncommon' | 'rare' | 'legendary';
+  cost_usd?: BN;
+  weight_grams?: number;
+  physical_sku?: string;
 }
 
 export interface GamePlayInitiatedEventData {
@@ -48,6 +54,13 @@ export interface GamePlayInitiatedEventData {
   token_amount: BN;
   session: string;
   timestamp: number;
+  is_revenue?: boolean; // false for points-funded plays (absent on older events)
+  treasury_amount?: BN; // absent on events before the creator split
+  creator_amount?: BN;
+  decimals?: number; // token mint decimals (absent on older events)
+  commitment?: number[] | null; // commit-reveal plays' backend commitment (absent on older events)
+  max_token_amount?: BN; // slippage limit the user signed (absent on older events)
+  net_amount?: BN; // token_amount less Token-2022 transfer fees (absent on older events)
 }
 
 export interface PrizeWonEventData {
@@ -89,6 +102,10 @@ export interface PrizeClaimedEventData {
   tier: 'common' | 'uncommon' | 'rare' | 'legendary';
   nft_mint: string;
   timestamp: number;
+  is_physical?: boolean; // false for digital-only prizes (absent on older events)
+  cost_usd?: BN; // prize cost (absent on older events)
+  amount_paid?: BN; // what the winning session paid (absent on older events)
+  user_prize_wins?: number; // user's wins of this prize so far (absent when no claim record was passed)
 }
 
 export interface GameStatusUpdatedEventData {
@@ -97,12 +114,22 @@ export interface GameStatusUpdatedEventData {
   timestamp: number;
 }
 
+export interface GameSoldOutEventData {
+  game_id: BN;
+  total_plays: BN;
+  timestamp: number;
+}
+
 export interface SupplyReplenishedEventData {
   game_id: BN;
   prize_id: BN;
   prize_index: number;
   new_supply: number;
   timestamp: number;
+  // Absent on events emitted before these fields were appended
+  supply_total?: number;
+  original_supply?: number;
+  exceeds_original?: boolean;
 }
 
 export interface TreasuryWithdrawnEventData {
@@ -110,6 +137,7 @@ export interface TreasuryWithdrawnEventData {
   amount: BN;
   destination: string;
   timestamp: number;
+  decimals?: number; // token mint decimals (absent on older events)
 }
 
 export type EventData =
@@ -121,6 +149,7 @@ export type EventData =
   | PlayResolvedEventData
   | PrizeClaimedEventData
   | GameStatusUpdatedEventData
+  | GameSoldOutEventData
   | SupplyReplenishedEventData
   | TreasuryWithdrawnEventData;
 
@@ -269,6 +298,9 @@ export class EventParserService {
       case 'GameStatusUpdated':
         return this.parseGameStatusUpdated(data);
 
+      case 'GameSoldOut':
+        return this.parseGameSoldOut(data);
+
       case 'SupplyReplenished':
         return this.parseSupplyReplenished(data);
 
@@ -290,7 +322,8 @@ export class EventParserService {
 
   private parsePrizeAdded(data: Uint8Array): PrizeAddedEventData {
     // PrizeAdded: game_id (8), prize_index (1), prize_id (8), probability_bp (2), supply_total (4), timestamp (8)
-    return {
+    // followed by: tier (1), cost_usd (8), weight_grams (4), physical_sku (4 + len)
+    const event: PrizeAddedEventData = {
       game_id: this.readU64(data, 0),
       prize_index: data[8],
       prize_id: this.readU64(data, 9),
@@ -298,16 +331,41 @@ export class EventParserService {
       supply_total: this.readU32(data, 19),
       timestamp: this.readI64(data, 23),
     };
+    if (data.length <= 31) {
+      return event;
+    }
+
+    const tierMap: Array<'common' | 'uncommon' | 'rare' | 'legendary'> = [
+      'common', 'uncommon', 'rare', 'legendary'
+    ];
+    event.tier = tierMap[data[31]] || 'common';
+    event.cost_usd = this.readU64(data, 32);
+    event.weight_grams = this.readU32(data, 40);
+    const skuLength = this.readU32(data, 44);
+    event.physical_sku = Buffer.from(data.slice(48, 48 + skuLength)).toString('utf8');
+    return event;
   }
 
   private parseGamePlayInitiated(data: Uint8Array): GamePlayInitiatedEventData {
-    // GamePlayInitiated: user (32), game_id (8), token_amount (8), session (32), timestamp (8)
+    // GamePlayInitiated: user (32), game_id (8), token_amount (8), session (32), timestamp (8), is_revenue (1),
+    // treasury_amount (8), creator_amount (8), decimals (1), commitment Option<[u8; 32]> (1 or 1 + 32),
+    // max_token_amount (8), net_amount (8)
+    const hasSplit = data.length >= 105;
+    const hasCommitment = data.length > 106;
+    const maxAmountOffset = hasCommitment && data[106] === 1 ? 139 : 107;
     return {
       user: new PublicKey(data.slice(0, 32)).toBase58(),
       game_id: this.readU64(data, 32),
       token_amount: this.readU64(data, 40),
       session: new PublicKey(data.slice(48, 80)).toBase58(),
       timestamp: this.readI64(data, 80),
+      is_revenue: data.length > 88 ? data[88] === 1 : true,
+      treasury_amount: hasSplit ? this.readU64(data, 89) : undefined,
+      creator_amount: hasSplit ? this.readU64(data, 97) : undefined,
+      decimals: data.length > 105 ? data[105] : undefined,
+      commitment: hasCommitment ? (data[106] === 1 ? Array.from(data.slice(107, 139)) : null) : undefined,
+      max_token_amount: data.length >= maxAmountOffset + 8 ? this.readU64(data, maxAmountOffset) : undefined,
+      net_amount: data.length >= maxAmountOffset + 16 ? this.readU64(data, maxAmountOffset + 8) : undefined,
     };
   }
 
@@ -364,14 +422,28 @@ export class EventParserService {
     };
   }
 
+  private parseGameSoldOut(data: Uint8Array): GameSoldOutEventData {
+    // GameSoldOut: game_id (8), total_plays (8), timestamp (8)
+    return {
+      game_id: this.readU64(data, 0),
+      total_plays: this.readU64(data, 8),
+      timestamp: this.readI64(data, 16),
+    };
+  }
+
   private parseSupplyReplenished(data: Uint8Array): SupplyReplenishedEventData {
-    // SupplyReplenished: game_id (8), prize_id (8), prize_index (1), new_supply (4), timestamp (8)
+    // SupplyReplenished: game_id (8), prize_id (8), prize_index (1), new_supply (4), timestamp (8),
+    // supply_total (4), original_supply (4), exceeds_original (1)
+    const extended = data.length > 37;
     return {
       game_id: this.readU64(data, 0),
       prize_id: this.readU64(data, 8),
       prize_index: data[16],
       new_supply: this.readU32(data, 17),
       timestamp: this.readI64(data, 21),
+      supply_total: extended ? this.readU32(data, 29) : undefined,
+      original_supply: extended ? this.readU32(data, 33) : undefined,
+      exceeds_original: extended ? data[37] === 1 : undefined,
     };
   }
 
@@ -381,6 +453,7 @@ export class EventParserService {
       amount: this.readU64(data, 8),
       destination: new PublicKey(data.slice(16, 48)).toBase58(),
       timestamp: this.readI64(data, 48),
+      decimals: data.length > 56 ? data[56] : undefined,
     };
   }
 
@@ -443,7 +516,8 @@ export class EventParserService {
   private parsePrizeClaimed(data: Uint8Array): PrizeClaimedEventData {
     // PrizeClaimed structure:
     // user (32), game_id (8), session (32), prize_id (8), prize_index (1),
-    // tier (1), nft_mint (32), timestamp (8)
+    // tier (1), nft_mint (32), timestamp (8), is_physical (1), cost_usd (8), amount_paid (8),
+    // user_prize_wins (Option<u32>: 1 + 4)
     const user = new PublicKey(data.slice(0, 32)).toBase58();
     const gameId = this.readU64(data, 32);
     const session = new PublicKey(data.slice(40, 72)).toBase58();
@@ -467,6 +541,10 @@ export class EventParserService {
       tier,
       nft_mint: nftMint,
       timestamp,
+      is_physical: data.length > 122 ? data[122] === 1 : true,
+      cost_usd: data.length > 123 ? this.readU64(data, 123) : undefined,
+      amount_paid: data.length > 131 ? this.readU64(data, 131) : undefined,
+      user_prize_wins: data.length > 143 && data[139] === 1 ? this.readU32(data, 140) : undefined,
     };
   }
 
@@ -155,6 +155,10 @@ export class IndexerService implements OnModuleInit {
         await this.handleGameStatusUpdated(event);
         break;
 
+      case 'GameSoldOut':
+        await this.handleGameSoldOut(event);
+        break;
+
       case 'SupplyReplenished':
         await this.handleSupplyReplenished(event);
         break;
@@ -309,6 +313,17 @@ export class IndexerService implements OnModuleInit {
     await this.gameService.handleGameStatusUpdated(data);
   }
 
+  /**
+   * Handle GameSoldOut event
+   */
+  private async handleGameSoldOut(event: ParsedEvent): Promise<void> {
+    if (event.name !== 'GameSoldOut') return;
+
+    const data =
+      event.data as import('./events/event-parser.service').GameSoldOutEventData;
+    await this.gameService.handleGameSoldOut(data);
+  }
+
   /**
    * Handle SupplyReplenished event
    */
@@ -5,6 +5,7 @@ import { Connection, PublicKey } from '@solana/web3.js';
 import { DatabaseService } from '../../database/database.service';
 import {
   GameStatusUpdatedEventData,
+  GameSoldOutEventData,
   GameCreatedEventData,
 } from '../events/event-parser.service';
 
@@ -540,6 +541,13 @@ export class GameService {
     );
   }
 
+  /**
+   * Handle GameSoldOut event: the last prize was won and the game deactivated itself
+   */
+  async handleGameSoldOut(eventData: GameSoldOutEventData): Promise<void> {
+    await this.updateGameStatus(eventData.game_id, false, eventData.timestamp);
+  }
+
   /**
    * Log GameCreated event (legacy - now we create games)
    */
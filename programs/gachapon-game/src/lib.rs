@@ -1,15 +1,89 @@
 use anchor_lang::prelude::*;
 use anchor_lang::prelude::InterfaceAccount;
 use anchor_lang::prelude::Interface;
-use anchor_spl::token::{self, MintTo, Token};
+use anchor_spl::token::{self, Burn, CloseAccount, FreezeAccount, Mint, MintTo, SetAuthority, ThawAccount, Token, Transfer};
 use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, TransferChecked};
 use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::solana_program::program_option::COption;
+use gachapon_marketplace::program::GachaponMarketplace;
+use switchboard_on_demand::RandomnessAccountData;
+
+pub mod seeds;
 
 declare_id!("EKzLHZyU6WVfhYVXcE6R4hRE4YuWrva8NeLGMYB7ZDU6");
 
 // Maximum number of prizes per game
 pub const MAX_PRIZES: usize = 16;
 
+// Number of entries kept in a game's recent winners feed
+pub const MAX_RECENT_WINNERS: usize = 20;
+pub const MAX_RNG_AUDIT_ENTRIES: usize = 64;
+// Compliance draws keep full probability/supply snapshots (164 bytes each)
+pub const MAX_COMPLIANCE_ENTRIES: usize = 32;
+pub const COMPLIANCE_DRAW_SIZE: usize = 32 + 1 + 32 + (MAX_PRIZES * 2) + (MAX_PRIZES * 4) + 1 + 2;
+pub const MAX_AGGREGATE_CLAIMS: usize = 10;
+// Sessions per finalize_batch; wins there only touch one prize account each, no minting
+pub const MAX_FINALIZE_BATCH: usize = 10;
+// Sessions per reap_expired_batch; each refund or expiry is one transfer or prize write
+pub const MAX_REAP_BATCH: usize = 10;
+// Pulls per play_game_multi purchase; all are resolved by one finalize_play
+pub const MAX_MULTI_PULLS: u8 = 10;
+// Royalty creators per game; Metaplex allows at most 5 creators on a metadata account
+pub const MAX_ROYALTY_CREATORS: usize = 5;
+// Highest proof-of-work difficulty (leading zero bits); ~65k hashes per play on average
+pub const MAX_POW_DIFFICULTY: u8 = 16;
+
+// PrizeUpdated.changed_fields bits, one per field update_prize can edit
+pub const PRIZE_FIELD_NAME: u16 = 1 << 0;
+pub const PRIZE_FIELD_DESCRIPTION: u16 = 1 << 1;
+pub const PRIZE_FIELD_IMAGE_URL: u16 = 1 << 2;
+pub const PRIZE_FIELD_METADATA_URI: u16 = 1 << 3;
+pub const PRIZE_FIELD_PHYSICAL_SKU: u16 = 1 << 4;
+pub const PRIZE_FIELD_COST_USD: u16 = 1 << 5;
+pub const PRIZE_FIELD_WEIGHT_GRAMS: u16 = 1 << 6;
+pub const PRIZE_FIELD_DIMENSIONS: u16 = 1 << 7;
+
+// Number of PrizeTier variants (tier-weighted selection buckets)
+pub const TIER_COUNT: usize = 4;
+
+// Game account size. Grown by 512 bytes after launch for the tier-weighted mirrors and
+// future fields; games created before that are resized once with `migrate_game`.
+pub const GAME_SPACE: usize = 8 + 32 + 8 + (4+50) + (4+200) + (4+200) + 32 + 8 + 32 + 1 + (MAX_PRIZES*2) + 4 + 8 + 1 + 32 + 1
+    + 1 + 1 + 16 + 8 + 8 + 1 + 1
+    + 1 + (TIER_COUNT*2) + MAX_PRIZES + (MAX_PRIZES*4)
+    + 1 + 1 + 1
+    + 32 + 2 + 1 + 4 + 1 + 1
+    + (1+8) + 32 + 1 + (MAX_PRIZES*8) + 1
+    + 2 + (4 + MAX_ROYALTY_CREATORS*33)
+    + (1+4) + (1+4)
+    + 4 + 4 + 8
+    + 32 + 4
+    + 4
+    + (1+8) + (1+8)
+    + 1
+    + 16; // +16 padding
+
+// Prize account size, including the dimension fields
+pub const PRIZE_SPACE: usize = 8 + 32 + 1 + 8 + (4+50) + (4+150) + (4+200) + (4+200) + (4+50) + 1 + 2 + 8 + 4 + 2 + 2 + 2 + 4 + 4 + 1 + 1 + 4 + (1+4) + 1 + 4 + 4 + 31; // +31 padding
+
+// PlaySession account size. Grown after launch for the prize snapshot, created_at, the
+// VRF binding, the commitment, refund expiry and draw count; sessions opened earlier are
+// resized once with `migrate_play_session`.
+pub const PLAY_SESSION_SPACE: usize = 8 + 32 + 32 + 8 + 32 + 1 + 32 + 2 + 1 + 1 + 33 + 8 + 1
+    + (1 + (4+50) + (4+200)) + 8
+    + 33 + 8 + 33
+    + 8 + 1
+    + 1
+    + 8; // +8 padding
+
+// Pyth Solana receiver program, owner of PriceUpdateV2 price accounts
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+// Anchor discriminator of the receiver's PriceUpdateV2 account
+const PYTH_PRICE_UPDATE_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+// Bumped whenever instructions or account layouts change; advertised in ProgramFeatures
+pub const PROGRAM_VERSION: u16 = 1;
+
 // Metaplex Token Metadata Program ID
 const METAPLEX_TOKEN_METADATA_PROGRAM_ID_BYTES: [u8; 32] = [
     11, 112, 101, 177, 227, 209, 124, 69, 56, 157, 82, 127, 107, 4, 195, 205,
@@ -38,7 +112,43 @@ pub mod gachapon_game {
         Ok(())
     }
 
+    /// Set which optional capabilities this deployment advertises (bits from `Feature`)
+    /// Stored in its own PDA since the deployed Config has no spare space.
+    pub fn set_feature_flags(ctx: Context<SetFeatureFlags>, feature_flags: u64) -> Result<()> {
+        let features = &mut ctx.accounts.features;
+        features.version = PROGRAM_VERSION;
+        features.feature_flags = feature_flags;
+        features.bump = ctx.bumps.features;
+        emit!(FeatureFlagsUpdated {
+            version: PROGRAM_VERSION,
+            feature_flags,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Re-emit the advertised version and feature flags (permissionless, read-only)
+    pub fn emit_feature_flags(ctx: Context<EmitFeatureFlags>) -> Result<()> {
+        emit!(FeatureFlagsUpdated {
+            version: ctx.accounts.features.version,
+            feature_flags: ctx.accounts.features.feature_flags,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Set (or clear with None) the custodial signer allowed to call
+    /// claim_prize_as_delegate for any session. Its own PDA, like ProgramFeatures.
+    pub fn set_claim_delegate(ctx: Context<SetClaimDelegate>, delegate: Option<Pubkey>) -> Result<()> {
+        let claim_delegate = &mut ctx.accounts.claim_delegate;
+        claim_delegate.delegate = delegate;
+        claim_delegate.bump = ctx.bumps.claim_delegate;
+        Ok(())
+    }
+
     /// Initialize a new game (without prizes - add them separately)
+    /// starts_at / ends_at: Optional play window (see set_game_schedule)
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_game(
         ctx: Context<InitializeGame>,
         game_id: u64,
@@ -47,11 +157,16 @@ pub mod gachapon_game {
         image_url: String,
         cost_usd: u64,
         token_mint: Pubkey,
+        starts_at: Option<i64>,
+        ends_at: Option<i64>,
     ) -> Result<()> {
         require!(
             ctx.accounts.authority.key() == ctx.accounts.config.authority,
             ErrorCode::Unauthorized
         );
+        require_valid_schedule(starts_at, ends_at)?;
+        require_keys_eq!(ctx.accounts.token_mint.key(), token_mint, ErrorCode::WrongMint);
+        let token_decimals = mint_decimals(&ctx.accounts.token_mint)?;
 
         // Validate string lengths
         require!(name.len() <= 50, ErrorCode::StringTooLong);
@@ -73,6 +188,45 @@ pub mod gachapon_game {
         game.total_plays = 0;
         game.is_active = false; // Inactive until prizes are added
         game.last_random_value = [0u8; 32];
+        game.use_slot_hash = false;
+        game.enforce_house_edge = false;
+        game.prize_ev_sum = 0;
+        game.points_per_play = 0;
+        game.points_per_pull = 0;
+        game.rng_audit_enabled = false;
+        game.aggregate_claim = false;
+        game.tier_weighted = false;
+        game.tier_bp = [0u16; TIER_COUNT];
+        game.prize_tiers = [0u8; MAX_PRIZES];
+        game.prize_supply = [0u32; MAX_PRIZES];
+        game.min_pull_count = 1;
+        game.tier_in_name = false;
+        game.pow_difficulty = 0;
+        game.creator = Pubkey::default();
+        game.creator_share_bps = 0;
+        game.randomness_policy = RandomnessPolicy::AllowBackendSigned;
+        game.claim_cooldown_seconds = 0;
+        game.lock_supply = false;
+        game.compliance_mode = false;
+        game.treasury_cap = None;
+        game.overflow_destination = Pubkey::default();
+        game.unique_metadata_uris = false;
+        game.metadata_uri_hashes = [0u64; MAX_PRIZES];
+        game.config_frozen = false;
+        game.royalty_bps = 0;
+        game.creators = Vec::new();
+        game.max_replenish_per_call = None;
+        game.max_total_supply = None;
+        game.claim_window_secs = 0;
+        game.refund_timeout_secs = 0;
+        game.keeper_bounty = 0;
+        // No oracle: set_price_feed opts a USD-priced game into payment checks
+        game.price_feed = Pubkey::default();
+        game.max_price_age_secs = 0;
+        game.pity_threshold = 0;
+        game.starts_at = starts_at;
+        game.ends_at = ends_at;
+        game.token_decimals = token_decimals;
         game.bump = ctx.bumps.game;
 
         emit!(GameCreated {
@@ -84,6 +238,7 @@ pub mod gachapon_game {
     }
 
     /// Add a prize to a game
+    #[allow(clippy::too_many_arguments)]
     pub fn add_prize(
         ctx: Context<AddPrize>,
         prize_index: u8,
@@ -101,10 +256,12 @@ pub mod gachapon_game {
         width_hundredths: u16,   // Width in hundredths of an inch
         height_hundredths: u16,  // Height in hundredths of an inch
         supply_total: u32,
+        is_physical: bool,       // Ships a physical item on redemption (false = digital-only NFT)
     ) -> Result<()> {
         let game = &mut ctx.accounts.game;
         
         // Validate
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
         require!(prize_index < MAX_PRIZES as u8, ErrorCode::TooManyPrizes);
         require!(prize_index == game.prize_count, ErrorCode::InvalidPrizeIndex); // Must add sequentially
         require!(name.len() <= 50, ErrorCode::StringTooLong);
@@ -118,6 +275,22 @@ pub mod gachapon_game {
         let new_total = current_total.checked_add(probability_bp as u32).ok_or(ErrorCode::MathOverflow)?;
         require!(new_total <= 10_000, ErrorCode::InvalidProbabilities);
 
+        // Track expected prize value and optionally keep it within the play cost
+        game.prize_ev_sum = game.prize_ev_sum
+            .checked_add((probability_bp as u128).checked_mul(cost_usd as u128).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        check_house_edge(game)?;
+
+        // Opt-in 1/1 collections: no two prizes of the game may share artwork
+        let uri_hash = metadata_uri_hash(&metadata_uri);
+        if game.unique_metadata_uris {
+            require!(
+                !game.metadata_uri_hashes[..prize_index as usize].contains(&uri_hash),
+                ErrorCode::DuplicateMetadataUri
+            );
+        }
+        game.metadata_uri_hashes[prize_index as usize] = uri_hash;
+
         // Initialize prize account
         let prize = &mut ctx.accounts.prize;
         prize.game = game.key();
@@ -135,12 +308,20 @@ pub mod gachapon_game {
         prize.length_hundredths = length_hundredths;
         prize.width_hundredths = width_hundredths;
         prize.height_hundredths = height_hundredths;
+        prize.is_physical = is_physical;
+        prize.pending_claims = 0;
+        prize.low_stock_threshold = None;
+        prize.low_stock_warned = false;
         prize.supply_total = supply_total;
+        prize.original_supply = supply_total;
         prize.supply_remaining = supply_total;
+        prize.max_per_user = 0;
         prize.bump = ctx.bumps.prize;
 
         // Update game
         game.prize_probabilities[prize_index as usize] = probability_bp;
+        game.prize_tiers[prize_index as usize] = prize.tier.clone() as u8;
+        game.prize_supply[prize_index as usize] = supply_total;
         game.prize_count = prize_index + 1;
         game.total_supply_remaining = game.total_supply_remaining.checked_add(supply_total).ok_or(ErrorCode::MathOverflow)?;
         
@@ -156,6 +337,10 @@ pub mod gachapon_game {
             probability_bp,
             supply_total,
             timestamp: Clock::get()?.unix_timestamp,
+            tier: prize.tier.clone(),
+            cost_usd,
+            weight_grams,
+            physical_sku: prize.physical_sku.clone(),
         });
         Ok(())
     }
@@ -163,65 +348,320 @@ pub mod gachapon_game {
     /// Play the game - transfers tokens to treasury and creates a play session
     /// The backend will finalize the play by calling finalize_play with randomness
     /// session_seed: A unique 32-byte seed to derive the session PDA (client generates this)
-    pub fn play_game(ctx: Context<PlayGame>, token_amount: u64, session_seed: [u8; 32]) -> Result<()> {
+    /// pow_nonce: Proof-of-work nonce, only checked when the game has a pow_difficulty
+    /// expected_session: The session PDA the client derived and will poll; when given it
+    /// must match the derived PDA, so a client derivation bug fails the play up front
+    /// commitment: keccak of the backend's secret for a commit-reveal play (see finalize_play)
+    /// max_token_amount: Most the user will pay; a charge above it fails with SlippageExceeded.
+    /// Oracle-priced games charge cost_usd at the current price instead of token_amount.
+    pub fn play_game(
+        ctx: Context<PlayGame>,
+        token_amount: u64,
+        session_seed: [u8; 32],
+        pow_nonce: u64,
+        expected_session: Option<Pubkey>,
+        commitment: Option<[u8; 32]>,
+        max_token_amount: u64,
+    ) -> Result<()> {
         let game = &ctx.accounts.game;
         let clock = Clock::get()?;
         
         require!(game.is_active, ErrorCode::GameInactive);
+        game.require_in_schedule(clock.unix_timestamp)?;
         require!(game.total_supply_remaining > 0, ErrorCode::OutOfStock);
+        require_winnable(game)?;
+        require_min_pulls(game, 1)?;
+        // An all-zero seed is what a client that forgot to generate one sends; reusing it
+        // collides with the session PDA of the user's previous play
+        require!(session_seed != [0u8; 32], ErrorCode::InvalidSessionSeed);
+        require_proof_of_work(game, &session_seed, &ctx.accounts.user.key(), pow_nonce)?;
         require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+        if let Some(expected_session) = expected_session {
+            require!(ctx.accounts.play_session.key() == expected_session, ErrorCode::SessionPdaMismatch);
+        }
         
-        // Validate token accounts
-        require!(
-            ctx.accounts.user_token_account.owner == ctx.accounts.user.key(),
-            ErrorCode::Unauthorized
-        );
-        require!(
-            ctx.accounts.user_token_account.mint == game.token_mint,
-            ErrorCode::Unauthorized
-        );
-        require!(
-            ctx.accounts.treasury_token_account.mint == game.token_mint,
-            ErrorCode::Unauthorized
-        );
+        let payment = collect_play_payment(game, &PlayPaymentAccounts {
+            user: &ctx.accounts.user,
+            user_token_account: &ctx.accounts.user_token_account,
+            treasury_token_account: &ctx.accounts.treasury_token_account,
+            creator_token_account: ctx.accounts.creator_token_account.as_ref(),
+            token_mint: &ctx.accounts.token_mint,
+            token_program: &ctx.accounts.token_program,
+            price_update: ctx.accounts.price_update.as_ref(),
+        }, token_amount, 1, max_token_amount)?;
 
-        // Get token decimals from mint account
-        let mint_info = ctx.accounts.token_mint.to_account_info();
-        let mint_data = mint_info.try_borrow_data()?;
-        let decimals = mint_data[44]; // Decimals is at offset 44 in mint account data
+        // Initialize play session - awaiting backend finalization
+        let session = &mut ctx.accounts.play_session;
+        init_play_session(session, ctx.accounts.user.key(), game, payment.net_amount, session_seed, ctx.bumps.play_session)?;
+        session.commitment = commitment;
 
-        // Transfer tokens from user to treasury using token interface (supports both Token and Token-2022)
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.user_token_account.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            to: ctx.accounts.treasury_token_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token_interface::transfer_checked(cpi_ctx, token_amount, decimals)?;
+        // Accrue loyalty points for paid plays
+        let stats = &mut ctx.accounts.player_stats;
+        init_player_stats_if_needed(stats, ctx.accounts.user.key(), game.key(), ctx.bumps.player_stats);
+        stats.total_plays = stats.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        stats.points = stats.points.checked_add(game.points_per_play).ok_or(ErrorCode::MathOverflow)?;
 
-        // Initialize play session - awaiting backend finalization
+        emit!(GamePlayInitiated {
+            user: ctx.accounts.user.key(),
+            game_id: game.game_id,
+            token_amount: payment.token_amount,
+            session: session.key(),
+            timestamp: clock.unix_timestamp,
+            is_revenue: true,
+            treasury_amount: payment.treasury_amount,
+            creator_amount: payment.creator_amount,
+            decimals: payment.decimals,
+            commitment,
+            max_token_amount,
+            net_amount: payment.net_amount,
+        });
+        Ok(())
+    }
+
+    /// Buy `count` pulls (1..=MAX_MULTI_PULLS) in one transaction: `token_amount` per pull,
+    /// `token_amount * count` in total, charged to one PlaySession with `draws = count`.
+    /// finalize_play resolves every draw at once and mints each win right away, so games
+    /// that defer minting (aggregate claims, claim cooldowns) don't offer it.
+    /// max_token_amount: Most the user will pay in total (SlippageExceeded above it)
+    pub fn play_game_multi(
+        ctx: Context<PlayGame>,
+        token_amount: u64,
+        session_seed: [u8; 32],
+        count: u8,
+        pow_nonce: u64,
+        max_token_amount: u64,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let clock = Clock::get()?;
+        
+        require!(game.is_active, ErrorCode::GameInactive);
+        game.require_in_schedule(clock.unix_timestamp)?;
+        require!(game.total_supply_remaining > 0, ErrorCode::OutOfStock);
+        require_winnable(game)?;
+        require!(count > 0 && count <= MAX_MULTI_PULLS, ErrorCode::InvalidPullCount);
+        require_min_pulls(game, count)?;
+        require!(!game.aggregate_claim && game.claim_cooldown_seconds == 0, ErrorCode::MultiPullUnsupported);
+        require!(session_seed != [0u8; 32], ErrorCode::InvalidSessionSeed);
+        require_proof_of_work(game, &session_seed, &ctx.accounts.user.key(), pow_nonce)?;
+        require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+        let total_amount = token_amount.checked_mul(count as u64).ok_or(ErrorCode::MathOverflow)?;
+        
+        let payment = collect_play_payment(game, &PlayPaymentAccounts {
+            user: &ctx.accounts.user,
+            user_token_account: &ctx.accounts.user_token_account,
+            treasury_token_account: &ctx.accounts.treasury_token_account,
+            creator_token_account: ctx.accounts.creator_token_account.as_ref(),
+            token_mint: &ctx.accounts.token_mint,
+            token_program: &ctx.accounts.token_program,
+            price_update: ctx.accounts.price_update.as_ref(),
+        }, total_amount, count, max_token_amount)?;
         let session = &mut ctx.accounts.play_session;
-        session.user = ctx.accounts.user.key();
-        session.game = game.key();
-        session.amount_paid = token_amount;
-        session.session_seed = session_seed;
-        session.is_fulfilled = false;
-        session.random_value = [0u8; 32];
-        session.prize_index = None;
-        session.is_claimed = false;
-        session.bump = ctx.bumps.play_session;
+        init_play_session(session, ctx.accounts.user.key(), game, payment.net_amount, session_seed, ctx.bumps.play_session)?;
+        session.draws = count;
+
+        let stats = &mut ctx.accounts.player_stats;
+        init_player_stats_if_needed(stats, ctx.accounts.user.key(), game.key(), ctx.bumps.player_stats);
+        stats.total_plays = stats.total_plays.checked_add(count as u64).ok_or(ErrorCode::MathOverflow)?;
+        let points = game.points_per_play.checked_mul(count as u64).ok_or(ErrorCode::MathOverflow)?;
+        stats.points = stats.points.checked_add(points).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(GamePlayInitiated {
+            user: ctx.accounts.user.key(),
+            game_id: game.game_id,
+            token_amount: payment.token_amount,
+            session: session.key(),
+            timestamp: clock.unix_timestamp,
+            is_revenue: true,
+            treasury_amount: payment.treasury_amount,
+            creator_amount: payment.creator_amount,
+            decimals: payment.decimals,
+            commitment: None,
+            max_token_amount,
+            net_amount: payment.net_amount,
+        });
+        Ok(())
+    }
+
+    /// play_game for backends that key plays on a numeric request id: the session PDA is
+    /// [b"session", game, user, request_id (u64 LE)], so the backend can derive the
+    /// address before the user signs. The 8-byte last seed can't collide with play_game's
+    /// 32-byte seed. The session records the request id (LE, zero-extended) as its seed,
+    /// which the proof of work also covers.
+    /// max_token_amount: Most the user will pay (SlippageExceeded above it), as in play_game
+    pub fn play_game_with_request_id(
+        ctx: Context<PlayGameWithRequestId>,
+        token_amount: u64,
+        request_id: u64,
+        pow_nonce: u64,
+        expected_session: Option<Pubkey>,
+        max_token_amount: u64,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let clock = Clock::get()?;
+        
+        require!(game.is_active, ErrorCode::GameInactive);
+        game.require_in_schedule(clock.unix_timestamp)?;
+        require!(game.total_supply_remaining > 0, ErrorCode::OutOfStock);
+        require_winnable(game)?;
+        require_min_pulls(game, 1)?;
+        // Like an all-zero session seed, 0 is what a backend that forgot the id sends
+        require!(request_id != 0, ErrorCode::InvalidSessionSeed);
+        let session_seed = request_id_session_seed(request_id);
+        require_proof_of_work(game, &session_seed, &ctx.accounts.user.key(), pow_nonce)?;
+        require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+        if let Some(expected_session) = expected_session {
+            require!(ctx.accounts.play_session.key() == expected_session, ErrorCode::SessionPdaMismatch);
+        }
+        
+        let payment = collect_play_payment(game, &PlayPaymentAccounts {
+            user: &ctx.accounts.user,
+            user_token_account: &ctx.accounts.user_token_account,
+            treasury_token_account: &ctx.accounts.treasury_token_account,
+            creator_token_account: ctx.accounts.creator_token_account.as_ref(),
+            token_mint: &ctx.accounts.token_mint,
+            token_program: &ctx.accounts.token_program,
+            price_update: ctx.accounts.price_update.as_ref(),
+        }, token_amount, 1, max_token_amount)?;
+
+        let session = &mut ctx.accounts.play_session;
+        init_play_session(session, ctx.accounts.user.key(), game, payment.net_amount, session_seed, ctx.bumps.play_session)?;
+
+        let stats = &mut ctx.accounts.player_stats;
+        init_player_stats_if_needed(stats, ctx.accounts.user.key(), game.key(), ctx.bumps.player_stats);
+        stats.total_plays = stats.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        stats.points = stats.points.checked_add(game.points_per_play).ok_or(ErrorCode::MathOverflow)?;
 
         emit!(GamePlayInitiated {
             user: ctx.accounts.user.key(),
             game_id: game.game_id,
-            token_amount,
+            token_amount: payment.token_amount,
             session: session.key(),
             timestamp: clock.unix_timestamp,
+            is_revenue: true,
+            treasury_amount: payment.treasury_amount,
+            creator_amount: payment.creator_amount,
+            decimals: payment.decimals,
+            commitment: None,
+            max_token_amount,
+            net_amount: payment.net_amount,
+        });
+        Ok(())
+    }
+
+    /// Play the game with loyalty points instead of tokens
+    /// Points-funded plays draw from the same prize supply but are flagged as non-revenue.
+    pub fn play_with_points(ctx: Context<PlayWithPoints>, session_seed: [u8; 32]) -> Result<()> {
+        let game = &ctx.accounts.game;
+        
+        require!(game.is_active, ErrorCode::GameInactive);
+        game.require_in_schedule(Clock::get()?.unix_timestamp)?;
+        require!(game.total_supply_remaining > 0, ErrorCode::OutOfStock);
+        require_winnable(game)?;
+        require_min_pulls(game, 1)?;
+        require!(session_seed != [0u8; 32], ErrorCode::InvalidSessionSeed);
+        require!(game.points_per_pull > 0, ErrorCode::PointsPlayDisabled);
+        
+        let stats = &mut ctx.accounts.player_stats;
+        stats.points = stats.points.checked_sub(game.points_per_pull).ok_or(ErrorCode::InsufficientPoints)?;
+        stats.total_plays = stats.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        
+        let session = &mut ctx.accounts.play_session;
+        init_play_session(session, ctx.accounts.user.key(), game, 0, session_seed, ctx.bumps.play_session)?;
+        
+        emit!(GamePlayInitiated {
+            user: ctx.accounts.user.key(),
+            game_id: game.game_id,
+            token_amount: 0,
+            session: session.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            is_revenue: false,
+            treasury_amount: 0,
+            creator_amount: 0,
+            decimals: 0,
+            commitment: None,
+            max_token_amount: 0,
+            net_amount: 0,
+        });
+        Ok(())
+    }
+
+    /// Create a capped free-play promo ("first 100 players get a free pull")
+    pub fn create_promo_campaign(
+        ctx: Context<CreatePromoCampaign>,
+        campaign_id: u64,
+        free_plays: u32,
+        per_user_limit: u32,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<()> {
+        require!(free_plays > 0 && per_user_limit > 0, ErrorCode::InvalidPromoCampaign);
+        require!(starts_at < ends_at, ErrorCode::InvalidPromoCampaign);
+        
+        let campaign = &mut ctx.accounts.promo_campaign;
+        campaign.game = ctx.accounts.game.key();
+        campaign.campaign_id = campaign_id;
+        campaign.remaining_free_plays = free_plays;
+        campaign.per_user_limit = per_user_limit;
+        campaign.starts_at = starts_at;
+        campaign.ends_at = ends_at;
+        campaign.bump = ctx.bumps.promo_campaign;
+        Ok(())
+    }
+
+    /// Play the game for free out of a promo campaign (no token transfer).
+    /// Promo plays draw from the same prize supply but are flagged as non-revenue.
+    pub fn play_with_promo(ctx: Context<PlayWithPromo>, session_seed: [u8; 32]) -> Result<()> {
+        let game = &ctx.accounts.game;
+        
+        require!(game.is_active, ErrorCode::GameInactive);
+        game.require_in_schedule(Clock::get()?.unix_timestamp)?;
+        require!(game.total_supply_remaining > 0, ErrorCode::OutOfStock);
+        require_winnable(game)?;
+        require_min_pulls(game, 1)?;
+        require!(session_seed != [0u8; 32], ErrorCode::InvalidSessionSeed);
+        
+        let now = Clock::get()?.unix_timestamp;
+        let campaign = &mut ctx.accounts.promo_campaign;
+        require!(now >= campaign.starts_at && now < campaign.ends_at, ErrorCode::PromoNotActive);
+        campaign.remaining_free_plays = campaign.remaining_free_plays.checked_sub(1).ok_or(ErrorCode::PromoExhausted)?;
+        
+        let redemption = &mut ctx.accounts.promo_redemption;
+        if redemption.user == Pubkey::default() {
+            redemption.campaign = campaign.key();
+            redemption.user = ctx.accounts.user.key();
+            redemption.bump = ctx.bumps.promo_redemption;
+        }
+        require!(redemption.plays_used < campaign.per_user_limit, ErrorCode::PromoLimitReached);
+        redemption.plays_used += 1;
+        
+        let session = &mut ctx.accounts.play_session;
+        init_play_session(session, ctx.accounts.user.key(), game, 0, session_seed, ctx.bumps.play_session)?;
+        
+        emit!(GamePlayInitiated {
+            user: ctx.accounts.user.key(),
+            game_id: game.game_id,
+            token_amount: 0,
+            session: session.key(),
+            timestamp: now,
+            is_revenue: false,
+            treasury_amount: 0,
+            creator_amount: 0,
+            decimals: 0,
+            commitment: None,
+            max_token_amount: 0,
+            net_amount: 0,
         });
         Ok(())
     }
 
+    /// Configure loyalty points earned per paid play and spent per points-funded pull (0 disables)
+    pub fn set_points_config(ctx: Context<UpdateGame>, points_per_play: u64, points_per_pull: u64) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.points_per_play = points_per_play;
+        game.points_per_pull = points_per_pull;
+        Ok(())
+    }
+
     /// Update game status
     pub fn update_game_status(ctx: Context<UpdateGame>, is_active: bool) -> Result<()> {
         let game = &mut ctx.accounts.game;
@@ -234,915 +674,6272 @@ pub mod gachapon_game {
         Ok(())
     }
 
-    /// Replenish prize supply
-    pub fn replenish_prize_supply(
-        ctx: Context<ReplenishPrizeSupply>,
-        additional_supply: u32,
+    /// Edit a prize's catalog fields after add_prize. Only the fields passed as Some are
+    /// applied, with add_prize's length limits; PrizeUpdated flags the ones whose value
+    /// actually changed. A new cost_usd is re-checked against the house edge. Odds are
+    /// not editable here: use update_prize_probability, which revalidates the total.
+    /// A physical prize's metadata_uri is fixed once it has been won, and any prize's once
+    /// premint_prize has pooled NFTs for it (PrizeUriLocked), as redeem_physical matches
+    /// already minted NFTs by that URI. Changing it takes the prize's inventory account.
+    // Option::is_none_or isn't stable on the Solana platform tools' rustc
+    #[allow(clippy::too_many_arguments, clippy::unnecessary_map_or)]
+    pub fn update_prize(
+        ctx: Context<UpdatePrize>,
+        name: Option<String>,
+        description: Option<String>,
+        image_url: Option<String>,
+        metadata_uri: Option<String>,
+        physical_sku: Option<String>,
+        cost_usd: Option<u64>,
+        weight_grams: Option<u32>,
+        length_hundredths: Option<u16>,
+        width_hundredths: Option<u16>,
+        height_hundredths: Option<u16>,
     ) -> Result<()> {
         let game = &mut ctx.accounts.game;
-        let prize = &mut ctx.accounts.prize;
+        let prize: &mut Prize = &mut ctx.accounts.prize;
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(name.as_ref().map_or(true, |v| v.len() <= 50), ErrorCode::StringTooLong);
+        require!(description.as_ref().map_or(true, |v| v.len() <= 150), ErrorCode::StringTooLong);
+        require!(image_url.as_ref().map_or(true, |v| v.len() <= 200), ErrorCode::StringTooLong);
+        require!(metadata_uri.as_ref().map_or(true, |v| v.len() <= 200), ErrorCode::StringTooLong);
+        require!(physical_sku.as_ref().map_or(true, |v| v.len() <= 50), ErrorCode::StringTooLong);
         
-        // Validate prize belongs to game
-        require!(prize.game == game.key(), ErrorCode::Unauthorized);
-        
-        prize.supply_total = prize.supply_total.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
-        prize.supply_remaining = prize.supply_remaining.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
-        
-        game.total_supply_remaining = game.total_supply_remaining.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
+        let mut changed_fields: u16 = 0;
+        let mut apply = |field: &mut String, value: Option<String>, bit: u16| {
+            if let Some(value) = value.filter(|value| value != field) {
+                *field = value;
+                changed_fields |= bit;
+            }
+        };
+        apply(&mut prize.name, name, PRIZE_FIELD_NAME);
+        apply(&mut prize.description, description, PRIZE_FIELD_DESCRIPTION);
+        apply(&mut prize.image_url, image_url, PRIZE_FIELD_IMAGE_URL);
+        apply(&mut prize.metadata_uri, metadata_uri, PRIZE_FIELD_METADATA_URI);
+        apply(&mut prize.physical_sku, physical_sku, PRIZE_FIELD_PHYSICAL_SKU);
         
-        if additional_supply > 0 && !game.is_active {
-            game.is_active = true;
+        if changed_fields & PRIZE_FIELD_METADATA_URI != 0 {
+            // Wins not voided are minted (or will be, from their snapshot) with the old URI
+            require!(
+                !prize.is_physical || prize.supply_remaining >= prize.supply_total,
+                ErrorCode::PrizeUriLocked
+            );
+            // Pool NFTs carry the URI too, and premint_prize doesn't touch supply
+            let inventory = ctx.accounts.inventory.as_ref().ok_or(ErrorCode::PrizeInventoryMissing)?;
+            if !inventory.data_is_empty() {
+                let inventory = PrizeInventory::try_deserialize(&mut &inventory.try_borrow_data()?[..])?;
+                require!(inventory.minted == 0, ErrorCode::PrizeUriLocked);
+            }
+            let idx = prize.prize_index as usize;
+            let uri_hash = metadata_uri_hash(&prize.metadata_uri);
+            if game.unique_metadata_uris {
+                require!(
+                    !game.metadata_uri_hashes[..game.prize_count as usize]
+                        .iter()
+                        .enumerate()
+                        .any(|(other, &hash)| other != idx && hash == uri_hash),
+                    ErrorCode::DuplicateMetadataUri
+                );
+            }
+            game.metadata_uri_hashes[idx] = uri_hash;
         }
-
-        emit!(SupplyReplenished {
+        if let Some(cost_usd) = cost_usd.filter(|&cost_usd| cost_usd != prize.cost_usd) {
+            // Replace this prize's contribution to the expected value, as replace_prize_probability does
+            let old_ev = (prize.probability_bp as u128)
+                .checked_mul(prize.cost_usd as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let new_ev = (prize.probability_bp as u128)
+                .checked_mul(cost_usd as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            game.prize_ev_sum = game.prize_ev_sum.saturating_sub(old_ev)
+                .checked_add(new_ev)
+                .ok_or(ErrorCode::MathOverflow)?;
+            check_house_edge(game)?;
+            prize.cost_usd = cost_usd;
+            changed_fields |= PRIZE_FIELD_COST_USD;
+        }
+        if let Some(weight_grams) = weight_grams.filter(|&weight_grams| weight_grams != prize.weight_grams) {
+            prize.weight_grams = weight_grams;
+            changed_fields |= PRIZE_FIELD_WEIGHT_GRAMS;
+        }
+        for (field, value) in [
+            (&mut prize.length_hundredths, length_hundredths),
+            (&mut prize.width_hundredths, width_hundredths),
+            (&mut prize.height_hundredths, height_hundredths),
+        ] {
+            if let Some(value) = value.filter(|value| value != field) {
+                *field = value;
+                changed_fields |= PRIZE_FIELD_DIMENSIONS;
+            }
+        }
+        
+        emit!(PrizeUpdated {
             game_id: game.game_id,
-            prize_id: prize.prize_id,
             prize_index: prize.prize_index,
-            new_supply: prize.supply_remaining,
+            prize_id: prize.prize_id,
+            changed_fields,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
     }
 
-    /// Withdraw from treasury
-    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+    /// Mark a prize as physical (ships on redemption) or digital-only.
+    /// Used to backfill prizes created before the flag existed.
+    pub fn set_prize_physical(ctx: Context<UpdatePrize>, is_physical: bool) -> Result<()> {
+        require!(!ctx.accounts.game.config_frozen, ErrorCode::ConfigFrozen);
+        ctx.accounts.prize.is_physical = is_physical;
+        Ok(())
+    }
+
+    /// Set (or clear with None) the supply level below which a win emits LowStockWarning
+    pub fn set_low_stock_threshold(ctx: Context<UpdatePrize>, threshold: Option<u32>) -> Result<()> {
+        require!(!ctx.accounts.game.config_frozen, ErrorCode::ConfigFrozen);
+        let prize = &mut ctx.accounts.prize;
+        prize.low_stock_threshold = threshold;
+        prize.low_stock_warned = false;
+        Ok(())
+    }
+
+    /// Cap how many of this prize one user can win (0 = unlimited). Once capped, wins of
+    /// the prize need the user's PrizeClaimRecord (open_claim_record) in finalize_play, and
+    /// a win that would exceed the cap is resolved as a loss. Compliance games can't cap
+    /// prizes, since their recorded draws must reproduce every outcome.
+    pub fn set_prize_max_per_user(ctx: Context<UpdatePrize>, max_per_user: u32) -> Result<()> {
         let game = &ctx.accounts.game;
-        
-        // Get token decimals from mint account
-        let mint_info = ctx.accounts.token_mint.to_account_info();
-        let mint_data = mint_info.try_borrow_data()?;
-        let decimals = mint_data[44]; // Decimals is at offset 44 in mint account data
-        
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.treasury_token_account.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            to: ctx.accounts.destination_token_account.to_account_info(),
-            authority: ctx.accounts.treasury.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(
+            max_per_user == 0 || !game.compliance_mode,
+            ErrorCode::ComplianceModeConflict
+        );
+        let prize = &mut ctx.accounts.prize;
+        let old_max_per_user = prize.max_per_user;
+        if max_per_user != old_max_per_user {
+            prize.max_per_user = max_per_user;
+            emit!(PrizeMaxPerUserUpdated {
+                game_id: game.game_id,
+                prize_index: prize.prize_index,
+                prize_id: prize.prize_id,
+                old_max_per_user,
+                max_per_user,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+        Ok(())
+    }
 
-        emit!(TreasuryWithdrawn {
+    /// Open the record of a user's wins of one prize (permissionless; the payer covers rent)
+    pub fn open_claim_record(ctx: Context<OpenClaimRecord>) -> Result<()> {
+        let claim_record = &mut ctx.accounts.claim_record;
+        claim_record.prize = ctx.accounts.prize.key();
+        claim_record.user = ctx.accounts.user.key();
+        claim_record.wins = 0;
+        claim_record.bump = ctx.bumps.claim_record;
+        Ok(())
+    }
+
+    /// Change a prize's win probability: update_prize_probability without a live edit,
+    /// so an active game has to be paused first (LiveOddsEdit)
+    pub fn set_prize_probability(ctx: Context<UpdatePrize>, probability_bp: u16) -> Result<()> {
+        let prize_index = ctx.accounts.prize.prize_index;
+        update_prize_probability(ctx, prize_index, probability_bp, false)
+    }
+
+    /// Rebalance one prize's odds mid-campaign: updates the prize and the game's
+    /// probability entry, then revalidates the whole distribution (sum <= 10000) and the
+    /// house edge. An active game's odds change under plays already in flight, so that
+    /// needs an explicit `allow_live_edit`; otherwise pause the game first.
+    pub fn update_prize_probability(
+        ctx: Context<UpdatePrize>,
+        prize_index: u8,
+        new_probability_bp: u16,
+        allow_live_edit: bool,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(ctx.accounts.prize.prize_index == prize_index, ErrorCode::InvalidPrizeIndex);
+        require!(!game.is_active || allow_live_edit, ErrorCode::LiveOddsEdit);
+        
+        let old_probability_bp = replace_prize_probability(game, &mut ctx.accounts.prize, new_probability_bp)?;
+        emit!(ProbabilityUpdated {
             game_id: game.game_id,
-            amount,
-            destination: ctx.accounts.destination_token_account.key(),
+            prize_index,
+            prize_id: ctx.accounts.prize.prize_id,
+            old_probability_bp,
+            new_probability_bp,
+            live_edit: game.is_active,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
     }
 
-    /// Close a game (returns rent)
-    pub fn close_game(_ctx: Context<CloseGame>) -> Result<()> {
-        Ok(())
-    }
-    
-    /// Close a prize (returns rent)
-    pub fn close_prize(_ctx: Context<ClosePrize>) -> Result<()> {
+    /// Toggle the house-edge guardrail: expected prize value must stay <= game.cost_usd.
+    /// When enabling, pass every prize account (index order) in remaining_accounts so the
+    /// expected value is recomputed from scratch.
+    pub fn set_enforce_house_edge<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateGame<'info>>,
+        enforce: bool,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        if enforce {
+            require!(
+                ctx.remaining_accounts.len() == game.prize_count as usize,
+                ErrorCode::PrizeNotFound
+            );
+            let mut ev_sum: u128 = 0;
+            for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+                let prize = Account::<Prize>::try_from(prize_info)?;
+                require!(prize.game == game.key(), ErrorCode::WrongGame);
+                require!(prize.prize_index as usize == idx, ErrorCode::InvalidPrizeIndex);
+                let ev = (game.prize_probabilities[idx] as u128)
+                    .checked_mul(prize.cost_usd as u128)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                ev_sum = ev_sum.checked_add(ev).ok_or(ErrorCode::MathOverflow)?;
+            }
+            game.prize_ev_sum = ev_sum;
+        }
+        game.enforce_house_edge = enforce;
+        check_house_edge(game)?;
         Ok(())
     }
 
-    /// Finalize play - called by backend with random value
-    /// Backend authority must co-sign to prevent users from choosing their own random value
-    /// Finalize play with optional auto-mint on win
-    /// 
-    /// When user wins, additional accounts are expected in remaining_accounts:
-    /// [0] - Prize account (required for wins)
-    /// [1] - NFT mint account (signer, new keypair)
-    /// [2] - Metadata PDA
-    /// [3] - Master Edition PDA
-    /// [4] - User's NFT token account (ATA)
-    /// [5] - User account info (for ATA owner)
-    /// [6] - Token Program
-    /// [7] - Associated Token Program
-    /// [8] - Metaplex Token Metadata Program
-    /// [9] - System Program
-    /// [10] - Rent sysvar
-    pub fn finalize_play<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizePlay<'info>>, random_value: [u8; 32]) -> Result<()> {
-        use anchor_spl::associated_token::get_associated_token_address;
-        use mpl_token_metadata::{
-            instructions::{
-                CreateMetadataAccountV3Cpi, CreateMasterEditionV3Cpi,
-                CreateMetadataAccountV3CpiAccounts, CreateMasterEditionV3CpiAccounts,
-                CreateMetadataAccountV3InstructionArgs, CreateMasterEditionV3InstructionArgs,
-            },
-            types::DataV2,
-        };
-        
-        // Read all needed values first (before any mutable borrows)
-        let session_is_fulfilled = ctx.accounts.play_session.is_fulfilled;
-        let user_key = ctx.accounts.play_session.user;
-        let session_key = ctx.accounts.play_session.key();
-        let game_key = ctx.accounts.game.key();
-        let game_id = ctx.accounts.game.game_id;
-        let prize_probabilities = ctx.accounts.game.prize_probabilities;
-        let prize_count = ctx.accounts.game.prize_count;
-        let total_supply = ctx.accounts.game.total_supply_remaining;
-        let program_id = ctx.program_id;
-        
-        // Ensure session hasn't already been fulfilled
-        require!(!session_is_fulfilled, ErrorCode::AlreadyFulfilled);
-        
-        // Determine winner using stored probabilities
-        let winning_index = select_prize_index(
-            &prize_probabilities,
-            prize_count,
-            &random_value
-        );
-        
-        // If won, process the prize and mint NFT
-        let (nft_mint_result, prize_id, prize_tier) = if let Some(prize_idx) = winning_index {
-            // For wins, we expect 11 accounts in remaining_accounts
+    /// Switch between per-prize probabilities and tier-weighted selection.
+    /// In tier-weighted mode the draw first picks a tier by `tier_bp` (Common, Uncommon,
+    /// Rare, Legendary; any remainder up to 10000 is a loss), then an in-stock prize of
+    /// that tier weighted by remaining supply. If the chosen tier is sold out the draw
+    /// falls back to the next lower tier with stock, and is a loss if there is none.
+    ///
+    /// When enabling, pass every prize account in index order as remaining accounts so
+    /// the tier/supply mirrors can be rebuilt (games migrated from the old layout start zeroed).
+    pub fn set_tier_weights<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateGame<'info>>,
+        tier_weighted: bool,
+        tier_bp: [u16; TIER_COUNT],
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let total: u32 = tier_bp.iter().map(|&bp| bp as u32).sum();
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(total <= 10_000, ErrorCode::InvalidProbabilities);
+        require!(!(tier_weighted && game.compliance_mode), ErrorCode::ComplianceModeConflict);
+        if tier_weighted {
             require!(
-                ctx.remaining_accounts.len() >= 11,
+                ctx.remaining_accounts.len() == game.prize_count as usize,
                 ErrorCode::PrizeNotFound
             );
-            
-            let prize_account_info = &ctx.remaining_accounts[0];
-            let mut prize_data = prize_account_info.try_borrow_mut_data()?;
-            let mut prize: Prize = Prize::try_deserialize(&mut &prize_data[..])?;
-            
-            // Validate prize
-            require!(prize.game == game_key, ErrorCode::Unauthorized);
+            for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+                let prize = Account::<Prize>::try_from(prize_info)?;
+                require!(prize.game == game.key(), ErrorCode::WrongGame);
+                require!(prize.prize_index as usize == idx, ErrorCode::InvalidPrizeIndex);
+                game.prize_tiers[idx] = prize.tier.clone() as u8;
+                game.prize_supply[idx] = prize.supply_remaining;
+            }
+        }
+        game.tier_weighted = tier_weighted;
+        game.tier_bp = tier_bp;
+        Ok(())
+    }
+
+    /// Cache the game's token decimals on games created before initialize_game stored
+    /// them (token_decimals reads 0 there, which transfers would reject). Permissionless:
+    /// the value always comes from the game's own mint.
+    pub fn cache_token_decimals(ctx: Context<CacheTokenDecimals>) -> Result<()> {
+        ctx.accounts.game.token_decimals = mint_decimals(&ctx.accounts.token_mint)?;
+        Ok(())
+    }
+
+    /// Resize a game created before GAME_SPACE grew. The added bytes are zeroed, which
+    /// decode as defaults for the new fields. Idempotent; the game authority pays the rent.
+    pub fn migrate_game(ctx: Context<MigrateGame>) -> Result<()> {
+        let game_info = ctx.accounts.game.to_account_info();
+        require!(game_info.owner == ctx.program_id, ErrorCode::InvalidGameAccount);
+        {
+            // Raw checks: the account may not decode as Game until it has been resized
+            let data = game_info.try_borrow_data()?;
+            require!(data.len() >= 40 && data[..8] == *Game::DISCRIMINATOR, ErrorCode::InvalidGameAccount);
+            require!(data[8..40] == ctx.accounts.authority.key().to_bytes(), ErrorCode::Unauthorized);
+        }
+        if game_info.data_len() < GAME_SPACE {
+            let rent_diff = Rent::get()?
+                .minimum_balance(GAME_SPACE)
+                .saturating_sub(game_info.lamports());
+            if rent_diff > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: game_info.clone(),
+                        },
+                    ),
+                    rent_diff,
+                )?;
+            }
+            game_info.resize(GAME_SPACE)?;
+        }
+        Ok(())
+    }
+
+    /// Require purchases of at least `min_pull_count` pulls (e.g. 10 for bundle-only
+    /// promotions). 1 allows single pulls.
+    pub fn set_min_pull_count(ctx: Context<UpdateGame>, min_pull_count: u8) -> Result<()> {
+        require!(min_pull_count > 0 && min_pull_count <= MAX_MULTI_PULLS, ErrorCode::InvalidPullCount);
+        ctx.accounts.game.min_pull_count = min_pull_count;
+        Ok(())
+    }
+
+    /// Append the prize tier to minted NFT names, e.g. "Dragon [Legendary]"
+    pub fn set_tier_in_name(ctx: Context<UpdateGame>, tier_in_name: bool) -> Result<()> {
+        ctx.accounts.game.tier_in_name = tier_in_name;
+        Ok(())
+    }
+
+    /// Set the royalty written into prize NFT metadata minted from now on: `royalty_bps`
+    /// of secondary sales, split across `creators` by share. Shares must sum to 100 over
+    /// 1..=MAX_ROYALTY_CREATORS distinct addresses; an empty list (with royalty_bps 0)
+    /// turns royalties off. NFTs already minted keep their metadata.
+    pub fn set_game_creators(ctx: Context<UpdateGame>, creators: Vec<RoyaltyCreator>, royalty_bps: u16) -> Result<()> {
+        require!(royalty_bps <= 10_000, ErrorCode::InvalidRoyaltyCreators);
+        if creators.is_empty() {
+            require!(royalty_bps == 0, ErrorCode::InvalidRoyaltyCreators);
+        } else {
+            require!(creators.len() <= MAX_ROYALTY_CREATORS, ErrorCode::InvalidRoyaltyCreators);
+            let total: u32 = creators.iter().map(|c| c.share as u32).sum();
+            require!(total == 100, ErrorCode::InvalidRoyaltyCreators);
+            // Metaplex rejects duplicate creators
+            for (i, creator) in creators.iter().enumerate() {
+                require!(
+                    !creators[..i].iter().any(|c| c.address == creator.address),
+                    ErrorCode::InvalidRoyaltyCreators
+                );
+            }
+        }
+        let game = &mut ctx.accounts.game;
+        game.royalty_bps = royalty_bps;
+        game.creators = creators;
+        Ok(())
+    }
+
+    /// Split play revenue with a collaborator: `creator_share_bps` of every play_game
+    /// payment goes to the creator's token account, the rest to the treasury.
+    pub fn set_creator_split(ctx: Context<UpdateGame>, creator: Pubkey, creator_share_bps: u16) -> Result<()> {
+        require!(creator_share_bps <= 10_000, ErrorCode::InvalidCreatorShare);
+        let game = &mut ctx.accounts.game;
+        game.creator = creator;
+        game.creator_share_bps = creator_share_bps;
+        Ok(())
+    }
+
+    /// Permanently forbid replenishing this game's prizes, making its supply provably
+    /// limited. There is no unlock.
+    pub fn lock_supply(ctx: Context<UpdateGame>) -> Result<()> {
+        ctx.accounts.game.lock_supply = true;
+        Ok(())
+    }
+
+    /// Resize a play session opened before PLAY_SESSION_SPACE grew, so it decodes again.
+    /// Permissionless; `payer` funds the extra rent. The added fields read as unset:
+    /// no prize snapshot, created_at 0 (never times out for refunds), no VRF binding,
+    /// no commitment, expiry_seconds 0 (the game's current refund timeout applies) and
+    /// draws 0 (a single draw).
+    pub fn migrate_play_session(ctx: Context<MigratePlaySession>) -> Result<()> {
+        let session_info = ctx.accounts.play_session.to_account_info();
+        require!(session_info.owner == ctx.program_id, ErrorCode::InvalidSessionAccount);
+        {
+            // Raw check: the account doesn't decode as PlaySession until it has been resized
+            let data = session_info.try_borrow_data()?;
+            require!(data.len() >= 8 && data[..8] == *PlaySession::DISCRIMINATOR, ErrorCode::InvalidSessionAccount);
+        }
+        if session_info.data_len() < PLAY_SESSION_SPACE {
+            let rent_diff = Rent::get()?
+                .minimum_balance(PLAY_SESSION_SPACE)
+                .saturating_sub(session_info.lamports());
+            if rent_diff > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: session_info.clone(),
+                        },
+                    ),
+                    rent_diff,
+                )?;
+            }
+            session_info.resize(PLAY_SESSION_SPACE)?;
+        }
+        Ok(())
+    }
+
+    /// Set the timeouts reap_expired_batch enforces and its keeper bounty.
+    /// `claim_window_secs`: a win must be claimed within this long after it becomes
+    /// claimable (after any claim cooldown), or it expires back to supply.
+    /// `refund_timeout_secs`: a play the backend hasn't finalized this long after payment
+    /// may be refunded; plays keep the timeout in force when they were paid. 0 turns
+    /// either off. `keeper_bounty` is paid per reaped session.
+    pub fn set_expiry_policy(
+        ctx: Context<UpdateGame>,
+        claim_window_secs: u32,
+        refund_timeout_secs: u32,
+        keeper_bounty: u64,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.claim_window_secs = claim_window_secs;
+        game.refund_timeout_secs = refund_timeout_secs;
+        game.keeper_bounty = keeper_bounty;
+        Ok(())
+    }
+
+    /// Guard replenish_prize_supply against operator error: `max_replenish_per_call` caps
+    /// the supply one call may add and `max_total_supply` caps any prize's supply_total.
+    /// Calls past either fail with ExceedsSupplyCeiling; `None` leaves that side unlimited.
+    pub fn set_supply_ceilings(
+        ctx: Context<UpdateGame>,
+        max_replenish_per_call: Option<u32>,
+        max_total_supply: Option<u32>,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.max_replenish_per_call = max_replenish_per_call;
+        game.max_total_supply = max_total_supply;
+        Ok(())
+    }
+
+    /// Check paid plays against cost_usd: each play_game / play_game_with_request_id must
+    /// then pass `price_feed`, a Pyth price account (PriceUpdateV2) for the token in USD,
+    /// published at most `max_price_age_secs` ago, and pay at least cost_usd worth of
+    /// tokens at that price. `Pubkey::default()` turns the check off.
+    pub fn set_price_feed(ctx: Context<UpdateGame>, price_feed: Pubkey, max_price_age_secs: u32) -> Result<()> {
+        require!(
+            price_feed == Pubkey::default() || max_price_age_secs > 0,
+            ErrorCode::InvalidPriceFeed
+        );
+        let game = &mut ctx.accounts.game;
+        game.price_feed = price_feed;
+        game.max_price_age_secs = max_price_age_secs;
+        Ok(())
+    }
+
+    /// Turn on the pity mechanic: a player who has lost `pity_threshold` plays in a row
+    /// wins their next finalize_play with the highest-tier prize in stock. Players need a
+    /// PityCounter (open_pity_counter) before their plays can be finalized. 0 turns it off.
+    pub fn set_pity_threshold(ctx: Context<UpdateGame>, pity_threshold: u32) -> Result<()> {
+        ctx.accounts.game.pity_threshold = pity_threshold;
+        Ok(())
+    }
+
+    /// Schedule when plays open and close (None = no bound on that side), so a game goes
+    /// live at its drop time and stops taking plays at its end without status updates.
+    /// is_active still applies on top of the window.
+    pub fn set_game_schedule(ctx: Context<UpdateGame>, starts_at: Option<i64>, ends_at: Option<i64>) -> Result<()> {
+        require_valid_schedule(starts_at, ends_at)?;
+        let game = &mut ctx.accounts.game;
+        game.starts_at = starts_at;
+        game.ends_at = ends_at;
+        emit!(GameScheduleUpdated {
+            game_id: game.game_id,
+            starts_at,
+            ends_at,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Open a player's pity counter for a game. Permissionless: the player or the backend
+    /// (before finalizing) pays the rent.
+    pub fn open_pity_counter(ctx: Context<OpenPityCounter>) -> Result<()> {
+        let pity_counter = &mut ctx.accounts.pity_counter;
+        pity_counter.game = ctx.accounts.game.key();
+        pity_counter.user = ctx.accounts.user.key();
+        pity_counter.losses = 0;
+        pity_counter.bump = ctx.bumps.pity_counter;
+        Ok(())
+    }
+
+    /// Publicly commit to the current drop table: until thaw_config, add_prize,
+    /// set_prize_probability, set_prize_physical, set_tier_weights, replenish_prize_supply
+    /// and close_prize all fail with ConfigFrozen.
+    pub fn freeze_config(ctx: Context<UpdateGame>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.config_frozen = true;
+        emit!(PrizeConfigFreezeUpdated {
+            game_id: game.game_id,
+            frozen: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// End a freeze_config window so the prize list can be retuned
+    pub fn thaw_config(ctx: Context<UpdateGame>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.config_frozen = false;
+        emit!(PrizeConfigFreezeUpdated {
+            game_id: game.game_id,
+            frozen: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Hold wins back for `seconds` after finalize before they can be claimed (a reveal
+    /// window). While set, finalize_play never mints immediately. 0 restores instant claims.
+    pub fn set_claim_cooldown(ctx: Context<UpdateGame>, seconds: u32) -> Result<()> {
+        ctx.accounts.game.claim_cooldown_seconds = seconds;
+        Ok(())
+    }
+
+    /// Restrict which randomness sources may resolve this game's plays. Switching away
+    /// from AllowBackendSigned makes finalize_play/finalize_batch reject backend values.
+    pub fn set_randomness_policy(ctx: Context<UpdateGame>, policy: RandomnessPolicy) -> Result<()> {
+        ctx.accounts.game.randomness_policy = policy;
+        Ok(())
+    }
+
+    /// Require play_game callers to find a nonce such that
+    /// sha256(session_seed || user || nonce_le) has `difficulty` leading zero bits.
+    /// 0 disables the check; capped at MAX_POW_DIFFICULTY so plays stay cheap for humans.
+    pub fn set_pow_difficulty(ctx: Context<UpdateGame>, difficulty: u8) -> Result<()> {
+        require!(difficulty <= MAX_POW_DIFFICULTY, ErrorCode::InvalidPowDifficulty);
+        ctx.accounts.game.pow_difficulty = difficulty;
+        Ok(())
+    }
+
+    /// Toggle receipt mode: wins are no longer minted in finalize_play and are
+    /// instead claimed together via claim_batch_aggregate
+    pub fn set_aggregate_claim(ctx: Context<UpdateGame>, aggregate_claim: bool) -> Result<()> {
+        ctx.accounts.game.aggregate_claim = aggregate_claim;
+        Ok(())
+    }
+
+    /// Toggle mixing the most recent slot hash into the backend random value
+    pub fn set_use_slot_hash(ctx: Context<UpdateGame>, use_slot_hash: bool) -> Result<()> {
+        ctx.accounts.game.use_slot_hash = use_slot_hash;
+        Ok(())
+    }
+
+    /// Configure (or update) the consolation NFT minted to players on a loss
+    pub fn set_consolation_nft(ctx: Context<SetConsolationNft>, name: String, uri: String) -> Result<()> {
+        require!(name.len() <= 32, ErrorCode::StringTooLong);
+        require!(uri.len() <= 200, ErrorCode::StringTooLong);
+        let consolation = &mut ctx.accounts.consolation;
+        consolation.game = ctx.accounts.game.key();
+        consolation.name = name;
+        consolation.uri = uri;
+        consolation.bump = ctx.bumps.consolation;
+        Ok(())
+    }
+
+    /// Stop minting consolation NFTs for a game (returns rent)
+    pub fn clear_consolation_nft(_ctx: Context<ClearConsolationNft>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create the recent winners feed for a game (appended to by finalize_play)
+    pub fn initialize_recent_winners(ctx: Context<InitializeRecentWinners>) -> Result<()> {
+        let recent_winners = &mut ctx.accounts.recent_winners;
+        recent_winners.game = ctx.accounts.game.key();
+        recent_winners.head = 0;
+        recent_winners.count = 0;
+        recent_winners.entries = [WinnerEntry::default(); MAX_RECENT_WINNERS];
+        recent_winners.bump = ctx.bumps.recent_winners;
+        Ok(())
+    }
+
+    /// Create the game's RNG audit log holding the last `capacity` draws
+    /// (1..=MAX_RNG_AUDIT_ENTRIES). Once created, every finalize_play must append to it.
+    pub fn initialize_rng_audit(ctx: Context<InitializeRngAudit>, capacity: u8) -> Result<()> {
+        require!(
+            capacity > 0 && capacity as usize <= MAX_RNG_AUDIT_ENTRIES,
+            ErrorCode::InvalidAuditCapacity
+        );
+        let rng_audit = &mut ctx.accounts.rng_audit;
+        rng_audit.game = ctx.accounts.game.key();
+        rng_audit.capacity = capacity;
+        rng_audit.head = 0;
+        rng_audit.entries = Vec::with_capacity(capacity as usize);
+        rng_audit.bump = ctx.bumps.rng_audit;
+        ctx.accounts.game.rng_audit_enabled = true;
+        Ok(())
+    }
+
+    /// Turn on compliance mode for gaming-commission review. It can't be turned off.
+    ///
+    /// In compliance mode:
+    /// - Selection uses `compliance_select`: an unbiased draw (`unbiased_draw`, no modulo
+    ///   bias) over the in-stock prizes. Sold-out prizes are excluded by the supply
+    ///   snapshot, so every random value resolves and the outcome can be reproduced.
+    /// - Every finalize_play/finalize_batch draw appends its full inputs and outcome
+    ///   (`ComplianceDraw`: randomness source, final random value, probability and supply
+    ///   snapshots, prize count, result) to the ComplianceLog PDA, which keeps the last
+    ///   `capacity` draws (1..=MAX_COMPLIANCE_ENTRIES), and emits them as DrawAudited so
+    ///   the full history stays in the transaction logs.
+    /// - Auditors reproduce each outcome with `verify_compliance_draw`.
+    ///
+    /// Tier-weighted selection and per-user prize caps are not supported in compliance
+    /// mode. Pass every prize
+    /// account in index order as remaining accounts so the supply mirror the snapshots
+    /// are taken from is rebuilt first.
+    pub fn enable_compliance_mode<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EnableComplianceMode<'info>>,
+        capacity: u8,
+    ) -> Result<()> {
+        require!(
+            capacity > 0 && capacity as usize <= MAX_COMPLIANCE_ENTRIES,
+            ErrorCode::InvalidAuditCapacity
+        );
+        let game = &mut ctx.accounts.game;
+        require!(!game.tier_weighted, ErrorCode::ComplianceModeConflict);
+        require!(
+            ctx.remaining_accounts.len() == game.prize_count as usize,
+            ErrorCode::PrizeNotFound
+        );
+        for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+            let prize = Account::<Prize>::try_from(prize_info)?;
+            require!(prize.game == game.key(), ErrorCode::WrongGame);
+            require!(prize.prize_index as usize == idx, ErrorCode::InvalidPrizeIndex);
+            require!(prize.max_per_user == 0, ErrorCode::ComplianceModeConflict);
+            game.prize_tiers[idx] = prize.tier.clone() as u8;
+            game.prize_supply[idx] = prize.supply_remaining;
+        }
+        game.compliance_mode = true;
+        
+        let compliance_log = &mut ctx.accounts.compliance_log;
+        compliance_log.game = game.key();
+        compliance_log.capacity = capacity;
+        compliance_log.head = 0;
+        compliance_log.entries = Vec::with_capacity(capacity as usize);
+        compliance_log.bump = ctx.bumps.compliance_log;
+        Ok(())
+    }
+
+    /// Replenish prize supply
+    pub fn replenish_prize_supply(
+        ctx: Context<ReplenishPrizeSupply>,
+        additional_supply: u32,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let prize = &mut ctx.accounts.prize;
+        
+        // Validate prize belongs to game
+        require!(prize.game == game.key(), ErrorCode::WrongGame);
+        require!(!game.lock_supply, ErrorCode::SupplyLocked);
+        require!(!game.config_frozen, ErrorCode::ConfigFrozen);
+        if let Some(max_per_call) = game.max_replenish_per_call {
+            require!(additional_supply <= max_per_call, ErrorCode::ExceedsSupplyCeiling);
+        }
+        
+        // Prizes added before original_supply existed: their current total is the best record
+        if prize.original_supply == 0 {
+            prize.original_supply = prize.supply_total;
+        }
+        prize.supply_total = prize.supply_total.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
+        if let Some(max_total) = game.max_total_supply {
+            require!(prize.supply_total <= max_total, ErrorCode::ExceedsSupplyCeiling);
+        }
+        prize.supply_remaining = prize.supply_remaining.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
+        // Restocked: warn again the next time it runs low
+        if prize.low_stock_threshold.is_some_and(|threshold| prize.supply_remaining >= threshold) {
+            prize.low_stock_warned = false;
+        }
+        
+        game.total_supply_remaining = game.total_supply_remaining.checked_add(additional_supply).ok_or(ErrorCode::MathOverflow)?;
+        game.prize_supply[prize.prize_index as usize] = prize.supply_remaining;
+        
+        if additional_supply > 0 && !game.is_active {
+            game.is_active = true;
+        }
+
+        emit!(SupplyReplenished {
+            game_id: game.game_id,
+            prize_id: prize.prize_id,
+            prize_index: prize.prize_index,
+            new_supply: prize.supply_remaining,
+            timestamp: Clock::get()?.unix_timestamp,
+            supply_total: prize.supply_total,
+            original_supply: prize.original_supply,
+            exceeds_original: prize.supply_total > prize.original_supply,
+        });
+        Ok(())
+    }
+
+    /// Permissionless accounting check: compare the game's supply counter against the
+    /// sum of its prizes' remaining supply and emit SupplyDrift if they differ by more
+    /// than `tolerance`. Remaining accounts: every prize PDA in index order
+    /// (closed prizes may be passed as their empty PDA and count as 0).
+    pub fn check_supply<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CheckSupply<'info>>,
+        tolerance: u32,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        require!(
+            ctx.remaining_accounts.len() == game.prize_count as usize,
+            ErrorCode::PrizeNotFound
+        );
+        let mut prize_supply: u32 = 0;
+        for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+            let (prize_pda, _) = seeds::find_prize(&game.key(), idx as u8, ctx.program_id);
+            require!(prize_info.key() == prize_pda, ErrorCode::InvalidPrizeIndex);
+            if prize_info.data_is_empty() {
+                continue;
+            }
+            let prize = Account::<Prize>::try_from(prize_info)?;
+            prize_supply = prize_supply.checked_add(prize.supply_remaining).ok_or(ErrorCode::MathOverflow)?;
+        }
+        
+        if game.total_supply_remaining.abs_diff(prize_supply) > tolerance {
+            emit!(SupplyDrift {
+                game_id: game.game_id,
+                prize_index: None,
+                game_supply_remaining: game.total_supply_remaining,
+                prize_supply_remaining: prize_supply,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    /// Emit the odds a play actually has right now (see `effective_odds`), so displayed
+    /// odds come from the same math as the program. Uses the game's prize mirrors; run
+    /// verify_prizes first on games migrated before the mirrors existed. Permissionless.
+    pub fn emit_effective_odds(ctx: Context<EmitEffectiveOdds>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let (prize_odds_bp, loss_odds_bp) = effective_odds(&game.prize_probabilities, &game.prize_supply, game.prize_count);
+        emit!(EffectiveOdds {
+            game_id: game.game_id,
+            prize_odds_bp,
+            loss_odds_bp,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Dry run of a play's outcome for integration testing: selects a prize for
+    /// `random_value` against the game's live probabilities and supplies, exactly as
+    /// finalize does, and emits SimulatedOutcome. No account is writable, so nothing can
+    /// change. `random_value` is used as given (no slot-hash mixing, which depends on a
+    /// real session). A draw that finds no supply (a tier-weighted draw whose tiers are
+    /// sold out, or any draw on a sold-out game) reports a loss with `drawn_sold_out`, as
+    /// finalize_batch would treat it. Permissionless.
+    pub fn simulate_play(ctx: Context<SimulatePlay>, random_value: [u8; 32]) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let drawn = select_winning_index(game, &random_value);
+        let drawn_sold_out = game.total_supply_remaining == 0
+            || drawn.is_some_and(|idx| game.prize_supply[idx] == 0)
+            || (drawn.is_none() && game.tier_weighted && !game.compliance_mode
+                && tier_draw_hits(game, &random_value));
+        emit!(SimulatedOutcome {
+            game_id: game.game_id,
+            random_value,
+            prize_index: if drawn_sold_out { None } else { drawn.map(|idx| idx as u8) },
+            drawn_sold_out,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Emit which of the passed prizes can currently be won (see `is_winnable`), indexed by
+    /// prize_index. Indices not passed (e.g. closed prizes) report false. Permissionless.
+    pub fn emit_winnability<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmitWinnability<'info>>,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        require!(ctx.remaining_accounts.len() <= MAX_PRIZES, ErrorCode::TooManyPrizes);
+        let now = Clock::get()?.unix_timestamp;
+        
+        let mut winnable = [false; MAX_PRIZES];
+        for prize_info in ctx.remaining_accounts.iter() {
+            let prize = Account::<Prize>::try_from(prize_info)?;
+            require!(prize.game == game.key(), ErrorCode::WrongGame);
+            winnable[prize.prize_index as usize] = is_winnable(&prize, game, now);
+        }
+        
+        emit!(Winnability {
+            game_id: game.game_id,
+            winnable,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Emit every passed prize's remaining supply in one event, so a client can refresh
+    /// a whole prize grid from a single transaction's logs. Permissionless.
+    pub fn emit_supply_snapshot<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmitSupplySnapshot<'info>>,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        require!(ctx.remaining_accounts.len() <= MAX_PRIZES, ErrorCode::TooManyPrizes);
+        
+        let mut supplies = Vec::with_capacity(ctx.remaining_accounts.len());
+        for prize_info in ctx.remaining_accounts.iter() {
+            let prize = Account::<Prize>::try_from(prize_info)?;
+            require!(prize.game == game.key(), ErrorCode::WrongGame);
+            supplies.push(PrizeSupply {
+                prize_index: prize.prize_index,
+                supply_remaining: prize.supply_remaining,
+            });
+        }
+        
+        emit!(SupplySnapshot {
+            game_id: game.game_id,
+            supplies,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Rebuild prize_count and the per-prize mirrors from the live prize accounts.
+    /// Pass every prize PDA up to game.prize_count in remaining_accounts (index order);
+    /// closed slots drop out of selection and trailing closed slots free their index.
+    pub fn verify_prizes<'info>(ctx: Context<'_, '_, 'info, 'info, UpdateGame<'info>>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            ctx.remaining_accounts.len() == game.prize_count as usize,
+            ErrorCode::PrizeNotFound
+        );
+        
+        let mut occupied_mask: u16 = 0;
+        let mut prize_ev_sum: u128 = 0;
+        for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+            let (prize_pda, _) = seeds::find_prize(&game.key(), idx as u8, ctx.program_id);
+            require!(prize_info.key() == prize_pda, ErrorCode::InvalidPrizeIndex);
+            if prize_info.data_is_empty() {
+                game.prize_probabilities[idx] = 0;
+                game.prize_supply[idx] = 0;
+                continue;
+            }
+            // Owner and discriminator are checked here; a foreign prize fails loudly
+            let prize = Account::<Prize>::try_from(prize_info)?;
+            require!(prize.game == game.key(), ErrorCode::WrongGame);
+            require!(prize.prize_index as usize == idx, ErrorCode::InvalidPrizeIndex);
+            
+            occupied_mask |= 1 << idx;
+            game.prize_probabilities[idx] = prize.probability_bp;
+            game.prize_supply[idx] = prize.supply_remaining;
+            game.prize_tiers[idx] = prize.tier.clone() as u8;
+            prize_ev_sum = prize_ev_sum
+                .checked_add((prize.probability_bp as u128).checked_mul(prize.cost_usd as u128).ok_or(ErrorCode::MathOverflow)?)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        
+        game.prize_count = (u16::BITS - occupied_mask.leading_zeros()) as u8;
+        game.prize_ev_sum = prize_ev_sum;
+        
+        emit!(PrizesVerified {
+            game_id: game.game_id,
+            prize_count: game.prize_count,
+            occupied_mask,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Withdraw from treasury
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let decimals = game.token_decimals;
+        
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.treasury_token_account.to_account_info(),
+            mint: ctx.accounts.token_mint.to_account_info(),
+            to: ctx.accounts.destination_token_account.to_account_info(),
+            authority: ctx.accounts.treasury.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        emit!(TreasuryWithdrawn {
+            game_id: game.game_id,
+            amount,
+            destination: ctx.accounts.destination_token_account.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            decimals,
+        });
+        Ok(())
+    }
+
+    /// Require every prize of this game to have a distinct metadata_uri (1/1 collections).
+    /// Each prize's `metadata_uri_hash` is kept on the Game, and add_prize rejects a
+    /// duplicate with DuplicateMetadataUri.
+    ///
+    /// When enabling, pass every prize PDA in index order as remaining accounts (closed
+    /// prizes as their empty PDA) so the hashes are rebuilt; existing duplicates are rejected.
+    pub fn set_unique_metadata_uris<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateGame<'info>>,
+        enabled: bool,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        if enabled {
+            require!(
+                ctx.remaining_accounts.len() == game.prize_count as usize,
+                ErrorCode::PrizeNotFound
+            );
+            let mut hashes = [0u64; MAX_PRIZES];
+            for (idx, prize_info) in ctx.remaining_accounts.iter().enumerate() {
+                let (prize_pda, _) = seeds::find_prize(&game.key(), idx as u8, ctx.program_id);
+                require!(prize_info.key() == prize_pda, ErrorCode::InvalidPrizeIndex);
+                if prize_info.data_is_empty() {
+                    continue;
+                }
+                let prize = Account::<Prize>::try_from(prize_info)?;
+                let uri_hash = metadata_uri_hash(&prize.metadata_uri);
+                require!(!hashes[..idx].contains(&uri_hash), ErrorCode::DuplicateMetadataUri);
+                hashes[idx] = uri_hash;
+            }
+            game.metadata_uri_hashes = hashes;
+        }
+        game.unique_metadata_uris = enabled;
+        Ok(())
+    }
+
+    /// Bound the hot treasury: any treasury balance above `treasury_cap` can be forwarded
+    /// to `overflow_destination` with sweep_overflow. None removes the cap.
+    pub fn set_treasury_cap(
+        ctx: Context<UpdateGame>,
+        treasury_cap: Option<u64>,
+        overflow_destination: Pubkey,
+    ) -> Result<()> {
+        require!(
+            treasury_cap.is_none() || overflow_destination != Pubkey::default(),
+            ErrorCode::InvalidOverflowDestination
+        );
+        let game = &mut ctx.accounts.game;
+        game.treasury_cap = treasury_cap;
+        game.overflow_destination = overflow_destination;
+        Ok(())
+    }
+
+    /// Keeper instruction (permissionless): forward the treasury balance above the game's
+    /// cap to the overflow destination's token account. Plays never forward inline, which
+    /// keeps play_game's compute cost flat; run this on a schedule instead.
+    ///
+    /// The treasury is the operator's wallet, so it must first approve the game PDA as
+    /// delegate of its treasury token account. Each sweep moves at most the remaining
+    /// delegated amount.
+    pub fn sweep_overflow(ctx: Context<SweepOverflow>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let treasury_cap = game.treasury_cap.ok_or(ErrorCode::TreasuryCapNotSet)?;
+        let treasury_token_account = &ctx.accounts.treasury_token_account;
+        let overflow = treasury_token_account.amount.saturating_sub(treasury_cap);
+        require!(overflow > 0, ErrorCode::NoTreasuryOverflow);
+        
+        let delegated = if treasury_token_account.delegate == COption::Some(game.key()) {
+            treasury_token_account.delegated_amount
+        } else {
+            0
+        };
+        let amount = overflow.min(delegated);
+        require!(amount > 0, ErrorCode::OverflowNotDelegated);
+        
+        let game_id_bytes = game.game_id.to_le_bytes();
+        let game_bump_array = [game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.treasury_token_account.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.destination_token_account.to_account_info(),
+                    authority: ctx.accounts.game.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+        
+        emit!(OverflowForwarded {
+            game_id: game.game_id,
+            amount,
+            destination: ctx.accounts.destination_token_account.key(),
+            treasury_cap,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Keeper instruction (permissionless): clean up a game's expired sessions in bulk.
+    /// Each session is handled by its state:
+    /// - a win still unclaimed when its claim window (set_expiry_policy) ends expires: the
+    ///   prize unit goes back to supply and the session becomes a loss, with no refund
+    /// - a play still unfulfilled past its refund timeout is refunded its `amount_paid`
+    ///   and becomes a fulfilled loss, as with refund_expired_session
+    ///
+    /// Any other session (claimed, mid two-phase claim, not expired yet, or already
+    /// reaped, including earlier in the same batch) is skipped rather than failing the
+    /// batch, so each session is processed at most once. A batch that reaps nothing fails.
+    ///
+    /// Refunds and the bounty of `keeper_bounty` per reaped session come from the
+    /// treasury token account, which must approve the game PDA as delegate (as for
+    /// sweep_overflow). The bounty goes to `keeper_token_account`; without it none is paid.
+    ///
+    /// remaining_accounts: every prize PDA of the game in index order (prize_count,
+    /// writable), then 1..=MAX_REAP_BATCH pairs of [play session, session user's token
+    /// account] (both writable; the token account is only read for refunds).
+    pub fn reap_expired_batch<'info>(ctx: Context<'_, '_, 'info, 'info, ReapExpiredBatch<'info>>) -> Result<()> {
+        let prize_count = ctx.accounts.game.prize_count as usize;
+        require!(ctx.remaining_accounts.len() > prize_count, ErrorCode::InvalidBatchSize);
+        let (prize_infos, pairs) = ctx.remaining_accounts.split_at(prize_count);
+        require!(
+            pairs.len() % 2 == 0 && pairs.len() / 2 <= MAX_REAP_BATCH,
+            ErrorCode::InvalidBatchSize
+        );
+        
+        let game_key = ctx.accounts.game.key();
+        let game_id = ctx.accounts.game.game_id;
+        let now = Clock::get()?.unix_timestamp;
+        let game_id_bytes = game_id.to_le_bytes();
+        let game_bump_array = [ctx.accounts.game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+        
+        let mut wins_expired: u32 = 0;
+        let mut plays_refunded: u32 = 0;
+        let mut refunded_amount: u64 = 0;
+        for pair in pairs.chunks(2) {
+            let mut session = Account::<PlaySession>::try_from(&pair[0])?;
+            require!(session.game == game_key, ErrorCode::WrongGame);
+            
+            let win_expired = session.is_fulfilled
+                && !session.is_claimed
+                && session.pending_claim_mint.is_none()
+                && claim_expires_at(&session, &ctx.accounts.game).is_some_and(|expires_at| now >= expires_at);
+            let play_timed_out = !session.is_fulfilled
+                && refund_expires_at(&session, &ctx.accounts.game).is_some_and(|expires_at| now >= expires_at);
+            
+            match session.prize_index {
+                Some(prize_idx) if win_expired => {
+                    let prize_info = prize_infos.get(prize_idx as usize).ok_or(ErrorCode::PrizeNotFound)?;
+                    let mut prize = Account::<Prize>::try_from(prize_info)?;
+                    require!(prize.game == game_key, ErrorCode::WrongGame);
+                    require!(prize.prize_index == prize_idx, ErrorCode::PrizeNotFound);
+                    prize.supply_remaining = prize.supply_remaining.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+                    prize.release_pending_claim();
+                    prize.exit(ctx.program_id)?;
+                    let game = &mut ctx.accounts.game;
+                    game.prize_supply[prize_idx as usize] = prize.supply_remaining;
+                    game.total_supply_remaining = game.total_supply_remaining.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+                    
+                    session.prize_index = None;
+                    session.prize_snapshot = None;
+                    wins_expired += 1;
+                }
+                None if play_timed_out => {
+                    if session.amount_paid > 0 {
+                        let user_token_account = InterfaceAccount::<TokenAccount>::try_from(&pair[1])?;
+                        require!(user_token_account.owner == session.user, ErrorCode::WrongTokenOwner);
+                        require!(user_token_account.mint == ctx.accounts.game.token_mint, ErrorCode::WrongMint);
+                    }
+                    let refund = refund_timed_out_play(
+                        &mut session,
+                        &pair[1],
+                        &ctx.accounts.treasury_token_account,
+                        &ctx.accounts.token_mint,
+                        &ctx.accounts.game,
+                        &ctx.accounts.token_program,
+                        now,
+                    )?;
+                    plays_refunded += 1;
+                    refunded_amount = refunded_amount.checked_add(refund).ok_or(ErrorCode::MathOverflow)?;
+                }
+                _ => continue,
+            }
+            // Written back immediately so a session listed twice is skipped the second time
+            session.exit(ctx.program_id)?;
+        }
+        
+        let reaped = wins_expired + plays_refunded;
+        require!(reaped > 0, ErrorCode::NothingToReap);
+        let mut bounty_paid = 0;
+        if let Some(keeper_token_account) = ctx.accounts.keeper_token_account.as_ref() {
+            bounty_paid = ctx.accounts.game.keeper_bounty.checked_mul(reaped as u64).ok_or(ErrorCode::MathOverflow)?;
+            if bounty_paid > 0 {
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.treasury_token_account.to_account_info(),
+                            mint: ctx.accounts.token_mint.to_account_info(),
+                            to: keeper_token_account.to_account_info(),
+                            authority: ctx.accounts.game.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    bounty_paid,
+                    ctx.accounts.token_mint.decimals,
+                )?;
+            }
+        }
+        
+        emit!(ExpiredSessionsReaped {
+            game_id,
+            keeper: ctx.accounts.keeper.key(),
+            wins_expired,
+            plays_refunded,
+            refunded_amount,
+            bounty_paid,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Refund a play the backend never finalized (permissionless). Once the play's refund
+    /// timeout has passed since payment, `amount_paid` goes back to the user from the
+    /// treasury, which must approve the game PDA as delegate (as for reap_expired_batch),
+    /// and the session becomes a fulfilled loss flagged `timeout_refunded`.
+    pub fn refund_expired_session(ctx: Context<RefundExpiredSession>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.play_session;
+        require!(!session.is_fulfilled, ErrorCode::AlreadyFulfilled);
+        let expires_at = refund_expires_at(session, &ctx.accounts.game).ok_or(ErrorCode::SessionNotExpired)?;
+        require!(now >= expires_at, ErrorCode::SessionNotExpired);
+        
+        let amount = refund_timed_out_play(
+            session,
+            &ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.game,
+            &ctx.accounts.token_program,
+            now,
+        )?;
+        
+        emit!(PlayRefunded {
+            user: session.user,
+            game_id: ctx.accounts.game.game_id,
+            session: session.key(),
+            amount,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Close a game (returns rent)
+    pub fn close_game(_ctx: Context<CloseGame>) -> Result<()> {
+        Ok(())
+    }
+    
+    /// Close a prize (returns rent)
+    /// Blocked while won sessions still need this account to claim. The prize's remaining
+    /// supply leaves the game total so the counter keeps matching the live prizes.
+    pub fn close_prize(ctx: Context<ClosePrize>) -> Result<()> {
+        let prize = &ctx.accounts.prize;
+        require!(!ctx.accounts.game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(prize.pending_claims == 0, ErrorCode::PendingClaimsExist);
+        let idx = prize.prize_index as usize;
+        let supply_remaining = prize.supply_remaining;
+        
+        let game = &mut ctx.accounts.game;
+        game.total_supply_remaining = game.total_supply_remaining.saturating_sub(supply_remaining);
+        // Keep both selection modes from picking the closed prize
+        game.prize_probabilities[idx] = 0;
+        game.prize_supply[idx] = 0;
+        game.metadata_uri_hashes[idx] = 0;
+        Ok(())
+    }
+
+    /// Retire a prize for good: closes it (rent to the authority) like close_prize and
+    /// clears its entries in the game. Removing the last prize decrements prize_count.
+    /// Any other prize leaves an empty slot that can't be drawn; reindex_prize then moves
+    /// the last prize into it, so the game's prizes stay sequential.
+    pub fn remove_prize(ctx: Context<ClosePrize>) -> Result<()> {
+        let prize = &ctx.accounts.prize;
+        require!(!ctx.accounts.game.config_frozen, ErrorCode::ConfigFrozen);
+        require!(prize.pending_claims == 0, ErrorCode::PendingClaimsExist);
+        let idx = prize.prize_index as usize;
+        let supply_removed = prize.supply_remaining;
+        // Saturating, like replace_prize_probability, for games created before EV tracking
+        let prize_ev = (prize.probability_bp as u128)
+            .checked_mul(prize.cost_usd as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        let game = &mut ctx.accounts.game;
+        require!(idx < game.prize_count as usize, ErrorCode::InvalidPrizeIndex);
+        let count = game.prize_count as usize;
+        game.total_supply_remaining = game.total_supply_remaining.saturating_sub(supply_removed);
+        game.prize_ev_sum = game.prize_ev_sum.saturating_sub(prize_ev);
+        game.prize_probabilities[idx] = 0;
+        game.prize_tiers[idx] = 0;
+        game.prize_supply[idx] = 0;
+        game.metadata_uri_hashes[idx] = 0;
+        if idx == count - 1 {
+            game.prize_count -= 1;
+        }
+        
+        emit!(PrizeRemoved {
+            game_id: game.game_id,
+            prize_index: idx as u8,
+            prize_id: prize.prize_id,
+            supply_removed,
+            prize_count: game.prize_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Move the game's last prize into the slot a remove_prize emptied: its PDA is
+    /// recreated at `target_index` (rent from the authority) and the old one closed (rent
+    /// back to the authority), its game entries follow it and prize_count drops by one.
+    /// The moved prize must have no unclaimed wins, which still point at its old index,
+    /// no per-user cap, whose claim records are keyed by the old PDA, and no pool NFTs
+    /// left in its inventory, which is keyed by the old PDA too.
+    pub fn reindex_prize(ctx: Context<ReindexPrize>, target_index: u8) -> Result<()> {
+        require!(!ctx.accounts.game.config_frozen, ErrorCode::ConfigFrozen);
+        let last_prize = &ctx.accounts.last_prize;
+        let from = last_prize.prize_index as usize;
+        let to = target_index as usize;
+        require!(from + 1 == ctx.accounts.game.prize_count as usize, ErrorCode::InvalidPrizeIndex);
+        require!(to < from, ErrorCode::InvalidPrizeIndex);
+        require!(last_prize.pending_claims == 0, ErrorCode::PendingClaimsExist);
+        require!(last_prize.max_per_user == 0, ErrorCode::PrizeNotMovable);
+        let inventory = &ctx.accounts.inventory;
+        if !inventory.data_is_empty() {
+            let inventory = PrizeInventory::try_deserialize(&mut &inventory.try_borrow_data()?[..])?;
+            require!(inventory.next >= inventory.minted, ErrorCode::PrizeNotMovable);
+        }
+
+        // The target PDA is created here, so the slot can't still hold a live prize
+        let prize_id = last_prize.prize_id;
+        ctx.accounts.prize.set_inner(Prize {
+            prize_index: target_index,
+            bump: ctx.bumps.prize,
+            ..Prize::clone(last_prize)
+        });
+
+        let game = &mut ctx.accounts.game;
+        game.prize_probabilities[to] = game.prize_probabilities[from];
+        game.prize_tiers[to] = game.prize_tiers[from];
+        game.prize_supply[to] = game.prize_supply[from];
+        game.metadata_uri_hashes[to] = game.metadata_uri_hashes[from];
+        game.prize_probabilities[from] = 0;
+        game.prize_tiers[from] = 0;
+        game.prize_supply[from] = 0;
+        game.metadata_uri_hashes[from] = 0;
+        game.prize_count -= 1;
+
+        emit!(PrizeReindexed {
+            game_id: game.game_id,
+            prize_id,
+            from_index: from as u8,
+            to_index: target_index,
+            prize_count: game.prize_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Bind a Switchboard on-demand randomness account to an unfinalized play (player only).
+    /// The account must have been committed in the previous slot, so its value is not yet
+    /// known to anyone; finalize_play then has to settle the play from that account's
+    /// revealed value instead of a backend-supplied one. A play is bound at most once.
+    pub fn request_randomness(ctx: Context<RequestRandomness>) -> Result<()> {
+        ctx.accounts.game.randomness_policy.require_allows(RandomnessSource::Vrf)?;
+        let session = &mut ctx.accounts.play_session;
+        require!(!session.is_fulfilled, ErrorCode::AlreadyFulfilled);
+        require!(session.vrf_account.is_none(), ErrorCode::VrfAlreadyRequested);
+        
+        let randomness_info = ctx.accounts.randomness_account.to_account_info();
+        let clock = Clock::get()?;
+        let seed_slot = {
+            let randomness = parse_randomness_account(&randomness_info)?;
+            // Committed in the previous slot: the oracle can't have revealed it yet
+            require!(randomness.seed_slot == clock.slot.saturating_sub(1), ErrorCode::VrfSeedStale);
+            randomness.seed_slot
+        };
+        session.vrf_account = Some(randomness_info.key());
+        session.vrf_seed_slot = seed_slot;
+        
+        emit!(RandomnessRequested {
+            user: session.user,
+            game_id: ctx.accounts.game.game_id,
+            session: session.key(),
+            vrf_account: randomness_info.key(),
+            seed_slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Finalize play - called by backend with random value
+    /// Backend authority must co-sign to prevent users from choosing their own random value
+    ///
+    /// Plays bound with request_randomness must pass their Switchboard account as
+    /// `randomness_account`; its value, revealed in this slot, replaces `random_value`
+    /// (which is then ignored), so the backend can't choose the outcome. The backend-signed
+    /// value is only accepted in builds with the `backend-randomness` feature.
+    ///
+    /// Plays opened with a commitment instead pass the backend's secret as `reveal`:
+    /// keccak(reveal) must equal the commitment, and the play resolves from
+    /// reveal XOR session_seed, which neither side can pick alone.
+    /// Finalize play with optional auto-mint on win
+    /// 
+    /// When user wins, additional accounts are expected in remaining_accounts:
+    /// [0] - Prize account (required for wins), or Consolation account on a loss
+    ///       (optional - omit all remaining accounts to skip the consolation NFT)
+    /// [1] - NFT mint account (signer, new keypair)
+    /// [2] - Metadata PDA
+    /// [3] - Master Edition PDA
+    /// [4] - User's NFT token account (ATA)
+    /// [5] - User account info (for ATA owner)
+    /// [6] - Token Program
+    /// [7] - Associated Token Program
+    /// [8] - Metaplex Token Metadata Program
+    /// [9] - System Program
+    /// [10] - Rent sysvar
+    ///
+    /// A win always needs the Prize at [0] (the transaction is rejected otherwise, before
+    /// anything is recorded). The mint accounts [1..=10] are optional: without them the win
+    /// is still resolved and the session stays unclaimed, so the user mints via claim_prize.
+    ///
+    /// play_game_multi sessions resolve every draw in this call instead: draw i uses
+    /// sha256(random_value || i), and each win needs its own [Prize, mint accounts] group
+    /// of 11 in remaining_accounts, in draw order; see finalize_multi_draw.
+    pub fn finalize_play<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizePlay<'info>>,
+        random_value: [u8; 32],
+        reveal: Option<[u8; 32]>,
+    ) -> Result<()> {
+        // Read all needed values first (before any mutable borrows)
+        let session_is_fulfilled = ctx.accounts.play_session.is_fulfilled;
+        let user_key = ctx.accounts.play_session.user;
+        let session_key = ctx.accounts.play_session.key();
+        let game_key = ctx.accounts.game.key();
+        let game_id = ctx.accounts.game.game_id;
+        let prize_count = ctx.accounts.game.prize_count;
+        let total_supply = ctx.accounts.game.total_supply_remaining;
+        let aggregate_claim = ctx.accounts.game.aggregate_claim;
+        let claim_cooldown = ctx.accounts.game.claim_cooldown_seconds;
+        let program_id = ctx.program_id;
+        
+        // Ensure session hasn't already been fulfilled
+        require!(!session_is_fulfilled, ErrorCode::AlreadyFulfilled);
+        
+        // VRF-bound plays settle only from their Switchboard result, committed plays
+        // only from their reveal
+        let session = &ctx.accounts.play_session;
+        let (random_value, source) = match ctx.accounts.randomness_account.as_ref() {
+            Some(randomness_account) => (
+                consume_vrf(session, &randomness_account.to_account_info())?,
+                RandomnessSource::Vrf,
+            ),
+            None => {
+                require!(session.vrf_account.is_none(), ErrorCode::VrfRequired);
+                match session.commitment {
+                    Some(commitment) => (
+                        open_commitment(&commitment, reveal, &session.session_seed)?,
+                        RandomnessSource::CommitReveal,
+                    ),
+                    None => {
+                        require!(reveal.is_none(), ErrorCode::RevealMismatch);
+                        (random_value, RandomnessSource::BackendSigned)
+                    }
+                }
+            }
+        };
+        ctx.accounts.game.randomness_policy.require_allows(source)?;
+        
+        // Optionally mix in the most recent slot hash so neither the backend nor
+        // an observer of the slot hash alone controls the outcome
+        let random_value = if ctx.accounts.game.use_slot_hash {
+            let slot_hashes = ctx.accounts.slot_hashes.as_ref().ok_or(ErrorCode::SlotHashesMissing)?;
+            mix_slot_hash(&slot_hashes.to_account_info(), &random_value, &session_key)?
+        } else {
+            random_value
+        };
+        
+        // Multi-pull sessions resolve all their draws here
+        if ctx.accounts.play_session.draws > 1 {
+            return finalize_multi_draw(ctx, random_value, source);
+        }
+        
+        // Determine winner using stored probabilities (or tier odds in tier-weighted mode).
+        // Compliance games select from a snapshot that is recorded with the outcome.
+        let compliance_snapshot = ctx.accounts.game.compliance_mode
+            .then(|| (ctx.accounts.game.prize_probabilities, ctx.accounts.game.prize_supply));
+        // Pity: after pity_threshold straight losses the play wins the best prize in stock
+        let pity_threshold = ctx.accounts.game.pity_threshold;
+        let pity_losses = match ctx.accounts.pity_counter.as_ref() {
+            Some(pity_counter) => pity_counter.losses,
+            None => {
+                require!(pity_threshold == 0, ErrorCode::PityCounterMissing);
+                0
+            }
+        };
+        let pity_prize = if pity_threshold > 0 && pity_losses >= pity_threshold {
+            best_in_stock_prize(&ctx.accounts.game)
+        } else {
+            None
+        };
+        let winning_index = pity_prize.or_else(|| select_winning_index(&ctx.accounts.game, &random_value));
+        
+        // A win that would take the user past the prize's max_per_user is a loss
+        let mut capped = false;
+        let winning_index = match winning_index {
+            Some(prize_idx) if !ctx.remaining_accounts.is_empty() => {
+                let prize = Account::<Prize>::try_from(&ctx.remaining_accounts[0])?;
+                require!(prize.game == game_key, ErrorCode::WrongGame);
+                // Check the cap of the prize actually drawn, not whichever one was passed
+                require!(prize.prize_index as usize == prize_idx, ErrorCode::PrizeNotFound);
+                if user_limit_reached(prize.key(), &prize, ctx.accounts.claim_record.as_deref())? {
+                    emit!(UserLimitReached {
+                        user: user_key,
+                        game_id,
+                        session: session_key,
+                        prize_index: prize_idx as u8,
+                        max_per_user: prize.max_per_user,
+                        timestamp: Clock::get()?.unix_timestamp,
+                    });
+                    capped = true;
+                    None
+                } else {
+                    Some(prize_idx)
+                }
+            }
+            other => other,
+        };
+        
+        // If won, process the prize and mint NFT
+        let (nft_mint_result, prize_id, prize_tier, prize_is_physical, prize_cost_usd, won_snapshot, user_prize_wins) = if let Some(prize_idx) = winning_index {
+            // Wins need the Prize at [0]; mint now only if the 10 mint accounts follow it
+            // (aggregate-claim games always mint later as one receipt, cooldown games
+            // after their reveal window)
+            require!(!ctx.remaining_accounts.is_empty(), ErrorCode::PrizeNotFound);
+            let mint_now = !aggregate_claim && claim_cooldown == 0 && ctx.remaining_accounts.len() >= 11;
+            
+            let prize_account_info = &ctx.remaining_accounts[0];
+            let mut prize_data = prize_account_info.try_borrow_mut_data()?;
+            let mut prize: Prize = Prize::try_deserialize(&mut &prize_data[..])?;
+            
+            // Validate prize
+            require!(prize.game == game_key, ErrorCode::WrongGame);
             require!(prize.prize_index == prize_idx as u8, ErrorCode::PrizeNotFound);
             require!(prize.supply_remaining > 0, ErrorCode::OutOfStock);
-            
-            let p_id = prize.prize_id;
-            let p_tier = prize.tier.clone();
-            let prize_name = prize.name.clone();
-            let prize_metadata_uri = prize.metadata_uri.clone();
-            
-            // Decrement supply
+            require!(total_supply > 0, ErrorCode::OutOfStock);
+            // A single prize can never hold more stock than the whole game
+            if prize.supply_remaining > total_supply {
+                emit!(SupplyDrift {
+                    game_id,
+                    prize_index: Some(prize_idx as u8),
+                    game_supply_remaining: total_supply,
+                    prize_supply_remaining: prize.supply_remaining,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+            
+            let p_id = prize.prize_id;
+            let p_tier = prize.tier.clone();
+            let p_is_physical = prize.is_physical;
+            let p_cost_usd = prize.cost_usd;
+            let prize_name = prize_nft_name(&ctx.accounts.game, &prize.name, &p_tier);
+            let prize_metadata_uri = prize.metadata_uri.clone();
+            let p_snapshot = PrizeSnapshot { name: prize.name.clone(), metadata_uri: prize.metadata_uri.clone() };
+            
+            // Decrement supply
+            prize.supply_remaining = prize.supply_remaining.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+            if !mint_now {
+                prize.pending_claims = prize.pending_claims.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+            }
+            let low_stock = prize.take_low_stock_warning();
+            prize.try_serialize(&mut *prize_data)?;
+            drop(prize_data);
+            ctx.accounts.game.prize_supply[prize_idx] = prize.supply_remaining;
+            if low_stock {
+                emit!(LowStockWarning {
+                    game_id,
+                    prize_index: prize_idx as u8,
+                    prize_id: p_id,
+                    supply_remaining: prize.supply_remaining,
+                    threshold: prize.low_stock_threshold.unwrap_or_default(),
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+            
+            // Mint the prize NFT to the user, unless the win is claimed later
+            let nft_mint_key = if !mint_now {
+                None
+            } else {
+                Some(mint_nft_from_remaining_accounts(
+                    program_id,
+                    ctx.remaining_accounts,
+                    &ctx.accounts.backend_authority.to_account_info(),
+                    &ctx.accounts.game,
+                    user_key,
+                    &prize_name,
+                    &prize_metadata_uri,
+                )?)
+            };
+            
+            let user_prize_wins = record_user_win(prize_account_info.key(), ctx.accounts.claim_record.as_deref_mut());
+            (nft_mint_key, Some(p_id), Some(p_tier), p_is_physical, p_cost_usd, Some(p_snapshot), user_prize_wins)
+        } else {
+            // On a loss, mint the game's consolation NFT when the backend passes the
+            // Consolation account at [0] plus mint accounts (no prize supply is used).
+            // A capped win holds its Prize there instead and mints nothing.
+            if !capped && ctx.remaining_accounts.len() >= 11 {
+                let consolation = Account::<Consolation>::try_from(&ctx.remaining_accounts[0])?;
+                require!(consolation.game == game_key, ErrorCode::WrongGame);
+                let consolation_mint = mint_nft_from_remaining_accounts(
+                    program_id,
+                    ctx.remaining_accounts,
+                    &ctx.accounts.backend_authority.to_account_info(),
+                    &ctx.accounts.game,
+                    user_key,
+                    &consolation.name,
+                    &consolation.uri,
+                )?;
+                emit!(ConsolationMinted {
+                    user: user_key,
+                    game_id,
+                    session: session_key,
+                    nft_mint: consolation_mint,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+            (None, None, None, false, 0, None, None)
+        };
+        
+        // Now do mutable borrows for session and game updates
+        let session = &mut ctx.accounts.play_session;
+        let game = &mut ctx.accounts.game;
+        
+        // Update session
+        session.is_fulfilled = true;
+        session.random_value = random_value;
+        session.prize_index = winning_index.map(|i| i as u8);
+        session.resolved_at = Clock::get()?.unix_timestamp;
+        session.prize_snapshot = won_snapshot;
+        
+        // Wins reset the pity streak, losses extend it
+        if let Some(pity_counter) = ctx.accounts.pity_counter.as_mut() {
+            pity_counter.losses = match winning_index {
+                Some(_) => 0,
+                None => pity_counter.losses.saturating_add(1),
+            };
+        }
+        if let Some(prize_idx) = pity_prize.filter(|_| winning_index.is_some()) {
+            emit!(PityTriggered {
+                user: user_key,
+                game_id,
+                session: session_key,
+                prize_index: prize_idx as u8,
+                losses: pity_losses,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+        
+        // Update game stats
+        game.total_plays = game.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        game.last_random_value = random_value;
+        
+        // Record the draw for auditors; games with an audit log may not skip it
+        match ctx.accounts.rng_audit.as_mut() {
+            Some(rng_audit) => rng_audit.push(RngDraw {
+                session: session_key,
+                random_value,
+                draw: draw_from_random(&random_value),
+                prize_index: session.prize_index,
+            }),
+            None => require!(!game.rng_audit_enabled, ErrorCode::RngAuditMissing),
+        }
+        if let Some((probabilities, supplies)) = compliance_snapshot {
+            let compliance_log = ctx.accounts.compliance_log.as_mut().ok_or(ErrorCode::ComplianceLogMissing)?;
+            record_compliance_draw(compliance_log, game_id, ComplianceDraw {
+                session: session_key,
+                source,
+                random_value,
+                probabilities,
+                supplies,
+                prize_count,
+                prize_index: session.prize_index,
+            })?;
+        }
+        
+        // If won, update game supply and mark session claimed
+        if let Some(prize_idx) = winning_index {
+            game.total_supply_remaining = total_supply.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+            if game.total_supply_remaining == 0 {
+                game.is_active = false;
+                emit!(GameSoldOut {
+                    game_id: game.game_id,
+                    total_plays: game.total_plays,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+            session.is_claimed = nft_mint_result.is_some();
+            
+            if let Some(recent_winners) = ctx.accounts.recent_winners.as_mut() {
+                recent_winners.push(WinnerEntry {
+                    winner: user_key,
+                    prize_index: prize_idx as u8,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+            
+            let tier = prize_tier.clone().unwrap();
+            
+            emit!(PlayResolved {
+                user: user_key,
+                game_id,
+                session: session_key,
+                prize_id,
+                prize_index: Some(prize_idx as u8),
+                tier: prize_tier,
+                is_win: true,
+                random_value,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            
+            if let Some(nft_mint) = nft_mint_result {
+                emit!(PrizeClaimed {
+                    user: user_key,
+                    game_id,
+                    session: session_key,
+                    prize_id: prize_id.unwrap(),
+                    prize_index: prize_idx as u8,
+                    tier,
+                    nft_mint,
+                    timestamp: Clock::get()?.unix_timestamp,
+                    is_physical: prize_is_physical,
+                    cost_usd: prize_cost_usd,
+                    amount_paid: session.amount_paid,
+                    user_prize_wins,
+                });
+            }
+        } else {
+            emit!(PlayResolved {
+                user: user_key,
+                game_id,
+                session: session_key,
+                prize_id: None,
+                prize_index: None,
+                tier: None,
+                is_win: false,
+                random_value,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+        
+        Ok(())
+    }
+
+    /// Finalize several sessions of one game with a single random seed (backend only).
+    /// Each session's value is sha256(random_seed || session key), so outcomes stay
+    /// independent; slot-hash games then mix in the slot hash per session as finalize_play does.
+    /// Wins are recorded (supply decremented, session left unclaimed) but never minted here;
+    /// users mint them with claim_prize. Any invalid or already fulfilled session fails the
+    /// whole batch, so every session is fulfilled exactly once. A draw of a prize with a
+    /// per-user cap fails it too (ClaimRecordMissing); finalize those plays with finalize_play.
+    /// Games with a pity_threshold can't be batch-finalized at all (PityCounterMissing), as
+    /// pity needs each player's pity counter.
+    ///
+    /// A pull that resolves against no available supply (its drawn prize is sold out, its
+    /// tier-weighted tier and every fallback tier are empty, or the game has no supply left)
+    /// could never have won, so it is refunded instead: `amount_paid` goes back to the user
+    /// from the treasury and the session is flagged `supply_refunded`. Genuine probability
+    /// losses are not refunded. Refunds need the optional treasury accounts (the treasury
+    /// wallet co-signs); without them a no-supply pull fails the batch.
+    ///
+    /// remaining_accounts: every prize PDA of the game in index order (prize_count, writable),
+    /// then 1..=MAX_FINALIZE_BATCH play sessions (writable), then, only when the treasury
+    /// accounts are passed, each session's user token account in the same order (writable).
+    pub fn finalize_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeBatch<'info>>,
+        random_seed: [u8; 32],
+    ) -> Result<()> {
+        let prize_count = ctx.accounts.game.prize_count as usize;
+        require!(ctx.remaining_accounts.len() > prize_count, ErrorCode::InvalidBatchSize);
+        let (prize_infos, batch_infos) = ctx.remaining_accounts.split_at(prize_count);
+        let can_refund = ctx.accounts.treasury.is_some();
+        let (session_infos, user_token_infos) = if can_refund {
+            require!(batch_infos.len() % 2 == 0, ErrorCode::InvalidBatchSize);
+            batch_infos.split_at(batch_infos.len() / 2)
+        } else {
+            (batch_infos, &[][..])
+        };
+        require!(session_infos.len() <= MAX_FINALIZE_BATCH, ErrorCode::InvalidBatchSize);
+        ctx.accounts.game.randomness_policy.require_allows(RandomnessSource::BackendSigned)?;
+        // Pity counters are per player and only finalize_play takes them
+        require!(ctx.accounts.game.pity_threshold == 0, ErrorCode::PityCounterMissing);
+        
+        let game_key = ctx.accounts.game.key();
+        let game_id = ctx.accounts.game.game_id;
+        let now = Clock::get()?.unix_timestamp;
+        let mut refunded_count: u32 = 0;
+        let mut refunded_amount: u64 = 0;
+        
+        for (batch_idx, session_info) in session_infos.iter().enumerate() {
+            let mut session = Account::<PlaySession>::try_from(session_info)?;
+            require!(session.game == game_key, ErrorCode::WrongGame);
+            require!(!session.is_fulfilled, ErrorCode::AlreadyFulfilled);
+            // VRF-bound, committed and multi-pull plays resolve only through finalize_play
+            require!(session.draws <= 1, ErrorCode::MultiPullUnsupported);
+            require!(session.vrf_account.is_none(), ErrorCode::VrfRequired);
+            require!(session.commitment.is_none(), ErrorCode::RevealRequired);
+            let session_key = session.key();
+            
+            let random_value = solana_sha256_hasher::hashv(&[&random_seed, session_key.as_ref()]).to_bytes();
+            let random_value = if ctx.accounts.game.use_slot_hash {
+                let slot_hashes = ctx.accounts.slot_hashes.as_ref().ok_or(ErrorCode::SlotHashesMissing)?;
+                mix_slot_hash(&slot_hashes.to_account_info(), &random_value, &session_key)?
+            } else {
+                random_value
+            };
+            
+            let game = &mut ctx.accounts.game;
+            let compliance_snapshot = game.compliance_mode
+                .then(|| (game.prize_probabilities, game.prize_supply));
+            let winning_index = select_winning_index(game, &random_value);
+            
+            // Tell pulls that found no supply apart from genuine losses
+            let drawn_prize = match winning_index {
+                Some(prize_idx) => {
+                    let prize = Account::<Prize>::try_from(&prize_infos[prize_idx])?;
+                    require!(prize.game == game_key, ErrorCode::WrongGame);
+                    require!(prize.prize_index as usize == prize_idx, ErrorCode::PrizeNotFound);
+                    // Per-user caps need the user's claim record, which only finalize_play takes
+                    require!(prize.max_per_user == 0, ErrorCode::ClaimRecordMissing);
+                    Some((prize_idx, prize))
+                }
+                None => None,
+            };
+            let no_supply = game.total_supply_remaining == 0
+                || drawn_prize.as_ref().is_some_and(|(_, prize)| prize.supply_remaining == 0)
+                || (drawn_prize.is_none() && game.tier_weighted && !game.compliance_mode
+                    && tier_draw_hits(game, &random_value));
+            let drawn_prize = if no_supply { None } else { drawn_prize };
+            let winning_index = drawn_prize.as_ref().map(|(prize_idx, _)| *prize_idx);
+            let won_snapshot = drawn_prize.as_ref().map(|(_, prize)| PrizeSnapshot {
+                name: prize.name.clone(),
+                metadata_uri: prize.metadata_uri.clone(),
+            });
+            
+            let (prize_id, prize_tier) = match drawn_prize {
+                Some((prize_idx, mut prize)) => {
+                    prize.supply_remaining -= 1;
+                    prize.pending_claims = prize.pending_claims.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+                    if prize.take_low_stock_warning() {
+                        emit!(LowStockWarning {
+                            game_id,
+                            prize_index: prize_idx as u8,
+                            prize_id: prize.prize_id,
+                            supply_remaining: prize.supply_remaining,
+                            threshold: prize.low_stock_threshold.unwrap_or_default(),
+                            timestamp: now,
+                        });
+                    }
+                    prize.exit(ctx.program_id)?;
+                    game.prize_supply[prize_idx] = prize.supply_remaining;
+                    
+                    game.total_supply_remaining = game.total_supply_remaining.checked_sub(1).ok_or(ErrorCode::OutOfStock)?;
+                    if game.total_supply_remaining == 0 {
+                        game.is_active = false;
+                        emit!(GameSoldOut {
+                            game_id: game.game_id,
+                            // This session's play is counted below
+                            total_plays: game.total_plays.saturating_add(1),
+                            timestamp: now,
+                        });
+                    }
+                    if let Some(recent_winners) = ctx.accounts.recent_winners.as_mut() {
+                        recent_winners.push(WinnerEntry {
+                            winner: session.user,
+                            prize_index: prize_idx as u8,
+                            timestamp: now,
+                        });
+                    }
+                    (Some(prize.prize_id), Some(prize.tier.clone()))
+                }
+                None => (None, None),
+            };
+            
+            if no_supply {
+                let refund = session.amount_paid;
+                if refund > 0 {
+                    let (Some(treasury), Some(treasury_token_account), Some(token_mint), Some(token_program)) = (
+                        ctx.accounts.treasury.as_ref(),
+                        ctx.accounts.treasury_token_account.as_ref(),
+                        ctx.accounts.token_mint.as_ref(),
+                        ctx.accounts.token_program.as_ref(),
+                    ) else {
+                        return err!(ErrorCode::RefundAccountsMissing);
+                    };
+                    let user_token_account = InterfaceAccount::<TokenAccount>::try_from(&user_token_infos[batch_idx])?;
+                    require!(user_token_account.owner == session.user, ErrorCode::WrongTokenOwner);
+                    require!(user_token_account.mint == game.token_mint, ErrorCode::WrongMint);
+                    token_interface::transfer_checked(
+                        CpiContext::new(
+                            token_program.to_account_info(),
+                            TransferChecked {
+                                from: treasury_token_account.to_account_info(),
+                                mint: token_mint.to_account_info(),
+                                to: user_token_account.to_account_info(),
+                                authority: treasury.to_account_info(),
+                            },
+                        ),
+                        refund,
+                        token_mint.decimals,
+                    )?;
+                }
+                session.amount_paid = 0;
+                session.supply_refunded = true;
+                refunded_count += 1;
+                refunded_amount = refunded_amount.checked_add(refund).ok_or(ErrorCode::MathOverflow)?;
+            }
+            
+            session.is_fulfilled = true;
+            session.random_value = random_value;
+            session.prize_index = winning_index.map(|i| i as u8);
+            session.is_claimed = false;
+            session.resolved_at = now;
+            session.prize_snapshot = won_snapshot;
+            // Written back immediately so a session listed twice fails AlreadyFulfilled
+            session.exit(ctx.program_id)?;
+            
+            game.total_plays = game.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+            game.last_random_value = random_value;
+            
+            match ctx.accounts.rng_audit.as_mut() {
+                Some(rng_audit) => rng_audit.push(RngDraw {
+                    session: session_key,
+                    random_value,
+                    draw: draw_from_random(&random_value),
+                    prize_index: session.prize_index,
+                }),
+                None => require!(!game.rng_audit_enabled, ErrorCode::RngAuditMissing),
+            }
+            if let Some((probabilities, supplies)) = compliance_snapshot {
+                let compliance_log = ctx.accounts.compliance_log.as_mut().ok_or(ErrorCode::ComplianceLogMissing)?;
+                record_compliance_draw(compliance_log, game_id, ComplianceDraw {
+                    session: session_key,
+                    source: RandomnessSource::BackendSigned,
+                    random_value,
+                    probabilities,
+                    supplies,
+                    prize_count: game.prize_count,
+                    prize_index: session.prize_index,
+                })?;
+            }
+            
+            emit!(PlayResolved {
+                user: session.user,
+                game_id,
+                session: session_key,
+                prize_id,
+                prize_index: session.prize_index,
+                tier: prize_tier,
+                is_win: winning_index.is_some(),
+                random_value,
+                timestamp: now,
+            });
+        }
+        
+        if refunded_count > 0 {
+            emit!(PartialBatchRefund {
+                game_id,
+                refunded_count,
+                refunded_amount,
+                timestamp: now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Claim prize - mints NFT after randomness fulfilled and user won.
+    /// The prize mint may be a legacy SPL Token or a Token-2022 mint; pass its owning program.
+    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.user.key();
+        require!(ctx.accounts.play_session.user == user_key, ErrorCode::WrongSessionUser);
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = validate_claim(&ctx.accounts.play_session, game, prize)?;
+        
+        // Store values needed for event
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        // Mint NFT to user
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.user.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let (won_name, won_uri) = won_prize_metadata(&ctx.accounts.play_session, prize);
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &prize_nft_name(game, won_name, &prize_tier),
+            won_uri,
+            &prize_tier,
+            game_id,
+            prize_id,
+        )?;
+        
+        // Now mark as claimed (mutable borrow)
+        ctx.accounts.play_session.is_claimed = true;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint,
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical: prize.is_physical,
+            cost_usd: prize.cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Claim a prize and immediately list it on the marketplace (CPI into list_nft).
+    /// The winning user is the listing's seller; the NFT moves from their ATA into the
+    /// marketplace escrow in the same transaction.
+    pub fn claim_and_list(
+        ctx: Context<ClaimAndList>,
+        price_in_tokens: u64,
+        enforce_royalties: bool,
+        category: gachapon_marketplace::ListingCategory,
+        tags: u32,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.user.key();
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = validate_claim(&ctx.accounts.play_session, game, prize)?;
+        
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.user.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let (won_name, won_uri) = won_prize_metadata(&ctx.accounts.play_session, prize);
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &prize_nft_name(game, won_name, &prize_tier),
+            won_uri,
+            &prize_tier,
+            game_id,
+            prize_id,
+        )?;
+        let is_physical = prize.is_physical;
+        let cost_usd = prize.cost_usd;
+        
+        ctx.accounts.play_session.is_claimed = true;
+        
+        // The user's signature carries through the CPI, so the listing is theirs
+        let list_accounts = gachapon_marketplace::cpi::accounts::ListNFT {
+            seller: ctx.accounts.user.to_account_info(),
+            config: ctx.accounts.marketplace_config.to_account_info(),
+            metadata: Some(ctx.accounts.metadata.to_account_info()),
+            listing: ctx.accounts.listing.to_account_info(),
+            seller_stats: ctx.accounts.seller_stats.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            currency_mint: ctx.accounts.currency_mint.to_account_info(),
+            seller_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            escrow_authority: ctx.accounts.escrow_authority.to_account_info(),
+            escrow_nft_token_account: ctx.accounts.escrow_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        gachapon_marketplace::cpi::list_nft(
+            CpiContext::new(ctx.accounts.marketplace_program.to_account_info(), list_accounts),
+            price_in_tokens,
+            enforce_royalties,
+            category,
+            tags,
+        )?;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint,
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical,
+            cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Claim several won sessions with a single receipt NFT (aggregate-claim games).
+    /// Supply was already decremented per won prize in finalize_play; this only mints
+    /// one NFT whose URI lists the won prize ids, for physical fulfillment to read.
+    ///
+    /// Remaining accounts: up to MAX_AGGREGATE_CLAIMS `[session (mut), prize (mut)]` pairs.
+    // usize::is_multiple_of isn't stable on the Solana platform tools' rustc
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn claim_batch_aggregate<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimBatchAggregate<'info>>,
+        receipt_base_uri: String,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let game_id = game.game_id;
+        let user_key = ctx.accounts.user.key();
+        require!(game.aggregate_claim, ErrorCode::AggregateClaimDisabled);
+        
+        let pairs = ctx.remaining_accounts.len() / 2;
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0 && pairs > 0 && pairs <= MAX_AGGREGATE_CLAIMS,
+            ErrorCode::InvalidSessionAccounts
+        );
+        
+        let mut sessions = Vec::with_capacity(pairs);
+        let mut prize_ids = Vec::with_capacity(pairs);
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let mut session = Account::<PlaySession>::try_from(&pair[0])?;
+            let mut prize = Account::<Prize>::try_from(&pair[1])?;
+            require!(session.user == user_key, ErrorCode::WrongSessionUser);
+            validate_claim(&session, game, &prize)?;
+            
+            session.is_claimed = true;
+            session.exit(ctx.program_id)?;
+            prize.release_pending_claim();
+            prize.exit(ctx.program_id)?;
+            sessions.push(session.key());
+            prize_ids.push(prize.prize_id);
+        }
+        
+        // Encode the won prize ids in the receipt URI
+        let ids: Vec<String> = prize_ids.iter().map(|id| id.to_string()).collect();
+        let uri = format!("{}?game={}&prizes={}", receipt_base_uri, game_id, ids.join(","));
+        require!(uri.len() <= 200, ErrorCode::StringTooLong);
+        let name = format!("Gachapon Receipt #{}", game_id);
+        
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.user.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &name,
+            &uri,
+            &PrizeTier::Common,
+            game_id,
+            0,
+        )?;
+        
+        emit!(AggregateClaimed {
+            user: user_key,
+            game_id,
+            nft_mint,
+            sessions,
+            prize_ids,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Claim a prize on behalf of the session user (gasless claim)
+    /// Any relayer may sign and pays all rent and fees; the NFT always goes to
+    /// the session user's associated token account.
+    pub fn claim_prize_sponsored(ctx: Context<ClaimPrizeSponsored>) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.play_session.user;
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = validate_claim(&ctx.accounts.play_session, game, prize)?;
+        
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        // Relayer pays; recipient is pinned to the session user
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.relayer.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let (won_name, won_uri) = won_prize_metadata(&ctx.accounts.play_session, prize);
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &prize_nft_name(game, won_name, &prize_tier),
+            won_uri,
+            &prize_tier,
+            game_id,
+            prize_id,
+        )?;
+        
+        ctx.accounts.play_session.is_claimed = true;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint,
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical: prize.is_physical,
+            cost_usd: prize.cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Claim a prize for the session user with the program's claim delegate signing
+    /// (custodial wallets where the end user never signs). The delegate pays rent and
+    /// fees, but the NFT can only go to the session user's associated token account.
+    pub fn claim_prize_as_delegate(ctx: Context<ClaimPrizeAsDelegate>) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.play_session.user;
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = validate_claim(&ctx.accounts.play_session, game, prize)?;
+        
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        // Delegate pays; recipient is pinned to the session user
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.delegate.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let (won_name, won_uri) = won_prize_metadata(&ctx.accounts.play_session, prize);
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &prize_nft_name(game, won_name, &prize_tier),
+            won_uri,
+            &prize_tier,
+            game_id,
+            prize_id,
+        )?;
+        
+        ctx.accounts.play_session.is_claimed = true;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint,
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical: prize.is_physical,
+            cost_usd: prize.cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Pre-mint one NFT into a prize's inventory pool. The mint is the PDA
+    /// [b"premint", prize, index] and its token goes to the game PDA's associated
+    /// token account (the vault). `PrizeInventory.minted` counts pool mints and
+    /// `next` points at the oldest one not handed out yet.
+    ///
+    /// The pool never holds more NFTs than the prize can still award: supply_remaining
+    /// plus won sessions that haven't claimed.
+    pub fn premint_prize(ctx: Context<PremintPrize>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let inventory = &mut ctx.accounts.inventory;
+        if inventory.prize == Pubkey::default() {
+            inventory.game = game.key();
+            inventory.prize = prize.key();
+            inventory.bump = ctx.bumps.inventory;
+        }
+        
+        let stocked = inventory.minted - inventory.next;
+        require!(
+            stocked < prize.supply_remaining.saturating_add(prize.pending_claims),
+            ErrorCode::PrizePoolFull
+        );
+        
+        let game_key = game.key();
+        let mint_accounts = ClaimMintAccounts {
+            game: game.to_account_info(),
+            recipient: game_key,
+            payer: ctx.accounts.authority.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.vault_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let nft_mint = mint_prize_nft_for_claim(
+            ctx.program_id,
+            &mint_accounts,
+            &prize_nft_name(game, &prize.name, &prize.tier),
+            &prize.metadata_uri,
+            &prize.tier,
+            game.game_id,
+            prize.prize_id,
+        )?;
+        
+        inventory.minted += 1;
+        
+        emit!(PrizePreminted {
+            game_id: game.game_id,
+            prize_index: prize.prize_index,
+            nft_mint,
+            pool_size: stocked + 1,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Claim a prize from its pre-minted pool: transfers the NFT at `inventory.next`
+    /// out of the vault instead of minting a fresh one. Fresh-mint claims keep working
+    /// for prizes without a pool (or once it runs dry).
+    pub fn claim_from_pool(ctx: Context<ClaimFromPool>) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.user.key();
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = validate_claim(&ctx.accounts.play_session, game, prize)?;
+        
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        let game_id_bytes = game_id.to_le_bytes();
+        let game_bump_array = [game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault_nft_token_account.to_account_info(),
+                    to: ctx.accounts.user_nft_token_account.to_account_info(),
+                    authority: ctx.accounts.game.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+        
+        ctx.accounts.inventory.next += 1;
+        ctx.accounts.play_session.is_claimed = true;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint: ctx.accounts.nft_mint.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical: ctx.accounts.prize.is_physical,
+            cost_usd: ctx.accounts.prize.cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Two-phase claim, step 1: create the prize mint and the session user's ATA,
+    /// mint the single token and freeze it.
+    ///
+    /// Progress is tracked on the session and each step is reentry-safe:
+    /// - `pending_claim_mint == None && !is_claimed`: nothing minted; step 1 (or a
+    ///   one-shot claim) may run
+    /// - `pending_claim_mint == Some(mint)`: token minted but frozen and without
+    ///   metadata; only `finalize_prize_metadata` for that exact mint may run
+    /// - `is_claimed`: done; every claim path rejects
+    ///
+    /// The partial NFT can't be transferred or listed while frozen, and the game
+    /// keeps mint authority until the master edition is created in step 2.
+    pub fn init_prize_mint(ctx: Context<InitPrizeMint>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        validate_claim(&ctx.accounts.play_session, game, &ctx.accounts.prize)?;
+        
+        let game_id_bytes = game.game_id.to_le_bytes();
+        let game_bump_array = [game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+        
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_token_account.to_account_info(),
+                    authority: ctx.accounts.game.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+        token::freeze_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            FreezeAccount {
+                account: ctx.accounts.user_nft_token_account.to_account_info(),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                authority: ctx.accounts.game.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+        
+        ctx.accounts.play_session.pending_claim_mint = Some(ctx.accounts.nft_mint.key());
+        Ok(())
+    }
+
+    /// Two-phase claim, step 2: thaw the token, then create metadata and master
+    /// edition for the mint recorded in step 1 and mark the session claimed.
+    pub fn finalize_prize_metadata(ctx: Context<FinalizePrizeMetadata>) -> Result<()> {
+        let session_key = ctx.accounts.play_session.key();
+        let user_key = ctx.accounts.play_session.user;
+        let nft_mint_key = ctx.accounts.nft_mint.key();
+        require!(!ctx.accounts.play_session.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(
+            ctx.accounts.play_session.pending_claim_mint == Some(nft_mint_key),
+            ErrorCode::ClaimNotStarted
+        );
+        
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let prize_idx = ctx.accounts.play_session.prize_index.ok_or(ErrorCode::NoPrize)?;
+        require!(prize.prize_index == prize_idx, ErrorCode::PrizeNotFound);
+        
+        let game_id = game.game_id;
+        let prize_id = prize.prize_id;
+        let prize_tier = prize.tier.clone();
+        
+        // Thaw before the master edition takes over the freeze authority
+        let game_id_bytes = game_id.to_le_bytes();
+        let game_bump_array = [game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        token::thaw_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            ThawAccount {
+                account: ctx.accounts.user_nft_token_account.to_account_info(),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                authority: ctx.accounts.game.to_account_info(),
+            },
+            &[game_seeds],
+        ))?;
+        
+        let mint_accounts = ClaimMintAccounts {
+            game: ctx.accounts.game.to_account_info(),
+            recipient: user_key,
+            payer: ctx.accounts.payer.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            master_edition: ctx.accounts.master_edition.to_account_info(),
+            recipient_nft_token_account: ctx.accounts.user_nft_token_account.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            metaplex_program: ctx.accounts.metaplex_token_metadata_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let (won_name, won_uri) = won_prize_metadata(&ctx.accounts.play_session, prize);
+        let nft_name = prize_nft_name(game, won_name, &prize_tier);
+        create_prize_metadata(ctx.program_id, &mint_accounts, &nft_name, won_uri, game_id)?;
+        
+        let session = &mut ctx.accounts.play_session;
+        session.pending_claim_mint = None;
+        session.is_claimed = true;
+        
+        emit!(PrizeClaimed {
+            user: user_key,
+            game_id,
+            session: session_key,
+            prize_id,
+            prize_index: prize_idx,
+            tier: prize_tier,
+            nft_mint: nft_mint_key,
+            timestamp: Clock::get()?.unix_timestamp,
+            is_physical: prize.is_physical,
+            cost_usd: prize.cost_usd,
+            amount_paid: ctx.accounts.play_session.amount_paid,
+            user_prize_wins: None,
+        });
+        
+        ctx.accounts.prize.release_pending_claim();
+        Ok(())
+    }
+
+    /// Turn a won-but-unmintable session (e.g. the prize's metadata makes the Metaplex CPI
+    /// fail) into a refund: the user gets `amount_paid` back from the treasury and the prize
+    /// unit returns to supply. A failed claim reverts entirely, so failures can't be counted
+    /// on-chain; the backend detects them and the game authority and treasury co-sign this.
+    pub fn void_unmintable_win(ctx: Context<VoidUnmintableWin>) -> Result<()> {
+        let session = &ctx.accounts.play_session;
+        require!(session.is_fulfilled, ErrorCode::NotFulfilled);
+        require!(!session.is_claimed, ErrorCode::AlreadyClaimed);
+        let prize_idx = session.prize_index.ok_or(ErrorCode::NoPrize)?;
+        require!(ctx.accounts.prize.prize_index == prize_idx, ErrorCode::PrizeNotFound);
+        
+        let refund_amount = session.amount_paid;
+        let mint_info = ctx.accounts.token_mint.to_account_info();
+        let decimals = ctx.accounts.game.token_decimals;
+        if refund_amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.treasury_token_account.to_account_info(),
+                mint: mint_info,
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token_interface::transfer_checked(cpi_ctx, refund_amount, decimals)?;
+        }
+        
+        // Return the unit finalize_play took
+        let prize = &mut ctx.accounts.prize;
+        prize.supply_remaining = prize.supply_remaining.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        prize.release_pending_claim();
+        let game = &mut ctx.accounts.game;
+        game.prize_supply[prize_idx as usize] = prize.supply_remaining;
+        game.total_supply_remaining = game.total_supply_remaining.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        
+        // The session becomes a refunded loss, which the user can close normally
+        let session = &mut ctx.accounts.play_session;
+        session.prize_index = None;
+        session.amount_paid = 0;
+        session.pending_claim_mint = None;
+        
+        emit!(WinVoided {
+            user: session.user,
+            game_id: game.game_id,
+            session: session.key(),
+            prize_index: prize_idx,
+            refund_amount,
+            decimals,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Incident recovery for a prize mint left half-minted by init_prize_mint (e.g. its
+    /// metadata step keeps failing, or the token was routed somewhere the winner can't use).
+    /// Only the program authority may run it, and only when all of these hold:
+    /// - the session is unclaimed and its `pending_claim_mint` is this mint, so the mint
+    ///   was created by this game's two-phase claim and never completed
+    /// - the game PDA still holds mint authority and supply is exactly 1; completing a
+    ///   claim hands mint authority to the master edition, so a finished NFT never passes
+    /// - `stuck_token_account` holds that single token
+    ///
+    /// SPL Token doesn't let the game burn or move a token it doesn't own, so the stuck
+    /// mint is retired instead: its token is frozen for good and the game gives up mint
+    /// authority. The prize is re-routed by reopening the session's claim, which the
+    /// winner then completes with a fresh mint through any claim path.
+    pub fn recover_stuck_mint(ctx: Context<RecoverStuckMint>) -> Result<()> {
+        let session = &ctx.accounts.play_session;
+        let nft_mint = &ctx.accounts.nft_mint;
+        let game_key = ctx.accounts.game.key();
+        require!(!session.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(session.pending_claim_mint == Some(nft_mint.key()), ErrorCode::ClaimNotStarted);
+        require!(
+            nft_mint.mint_authority == COption::Some(game_key) && nft_mint.supply == 1,
+            ErrorCode::MintNotRecoverable
+        );
+        require!(ctx.accounts.stuck_token_account.amount == 1, ErrorCode::MintNotRecoverable);
+        
+        let game_id = ctx.accounts.game.game_id;
+        let game_id_bytes = game_id.to_le_bytes();
+        let game_bump_array = [ctx.accounts.game.bump];
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+        let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+        
+        if !ctx.accounts.stuck_token_account.is_frozen() {
+            token::freeze_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                FreezeAccount {
+                    account: ctx.accounts.stuck_token_account.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    authority: ctx.accounts.game.to_account_info(),
+                },
+                signer_seeds,
+            ))?;
+        }
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: ctx.accounts.game.to_account_info(),
+                    account_or_mint: ctx.accounts.nft_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            anchor_spl::token::spl_token::instruction::AuthorityType::MintTokens,
+            None,
+        )?;
+        
+        let session = &mut ctx.accounts.play_session;
+        session.pending_claim_mint = None;
+        
+        emit!(MintRecovered {
+            game_id,
+            session: session.key(),
+            winner: session.user,
+            nft_mint: ctx.accounts.nft_mint.key(),
+            token_account: ctx.accounts.stuck_token_account.key(),
+            holder: ctx.accounts.stuck_token_account.owner,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Close a play session (returns rent after claiming or if lost)
+    pub fn close_play_session(ctx: Context<ClosePlaySession>) -> Result<()> {
+        let session = &ctx.accounts.play_session;
+        
+        // Can only close if fulfilled and either claimed or lost
+        require!(session.is_fulfilled, ErrorCode::NotFulfilled);
+        if session.prize_index.is_some() {
+            require!(session.is_claimed, ErrorCode::NotClaimed);
+        }
+        
+        // Account will be closed by close = user attribute
+        Ok(())
+    }
+
+    /// Admin teardown: close resolved sessions of this game, refunding rent to each session's user
+    ///
+    /// remaining_accounts are pairs of [play_session (writable), session user (writable)].
+    /// Sessions that are unresolved, won-but-unclaimed, or mid two-phase claim are skipped.
+    // usize::is_multiple_of isn't stable on the Solana platform tools' rustc
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn admin_close_resolved_sessions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdminCloseResolvedSessions<'info>>,
+    ) -> Result<()> {
+        require!(ctx.remaining_accounts.len() % 2 == 0, ErrorCode::InvalidSessionAccounts);
+        let game_key = ctx.accounts.game.key();
+        let mut closed: u32 = 0;
+        let mut skipped: u32 = 0;
+        
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let session = Account::<PlaySession>::try_from(&pair[0])?;
+            let user_info = &pair[1];
+            require!(session.game == game_key, ErrorCode::WrongGame);
+            require!(session.user == user_info.key(), ErrorCode::WrongSessionUser);
+            
+            let resolved = session.is_fulfilled
+                && (session.prize_index.is_none() || session.is_claimed)
+                && session.pending_claim_mint.is_none();
+            if !resolved {
+                skipped += 1;
+                continue;
+            }
+            session.close(user_info.clone())?;
+            closed += 1;
+        }
+        
+        emit!(SessionsClosed {
+            game_id: ctx.accounts.game.game_id,
+            closed,
+            skipped,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Redeem a physical prize - burns the prize NFT to request shipping
+    /// The NFT must have been minted by this game (metadata update authority is the game PDA)
+    /// and its metadata URI must match the prize, which update_prize keeps fixed once the
+    /// prize has been won. Once burned it can't be resold.
+    /// `address_hash` commits to the shipping details entered off-chain; the backend
+    /// checks the submitted address against it before fulfilling.
+    pub fn redeem_physical(ctx: Context<RedeemPhysical>, address_hash: [u8; 32]) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let prize = &ctx.accounts.prize;
+        let nft_mint_key = ctx.accounts.nft_mint.key();
+
+        require!(prize.is_physical, ErrorCode::NotPhysicalPrize);
+
+        // Derive and verify metadata PDA
+        let metadata_seeds = &[
+            b"metadata",
+            METAPLEX_TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            nft_mint_key.as_ref(),
+        ];
+        let (metadata_pda, _) = Pubkey::find_program_address(metadata_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
+        require!(ctx.accounts.metadata.key() == metadata_pda, ErrorCode::WrongPdaDerivation);
+        require!(
+            *ctx.accounts.metadata.owner == METAPLEX_TOKEN_METADATA_PROGRAM_ID,
+            ErrorCode::NotPrizeNft
+        );
+
+        // Verify the NFT is a prize from this game
+        let metadata = {
+            let metadata_data = ctx.accounts.metadata.try_borrow_data()?;
+            mpl_token_metadata::accounts::Metadata::safe_deserialize(&metadata_data)
+                .map_err(|_| error!(ErrorCode::NotPrizeNft))?
+        };
+        require!(metadata.mint == nft_mint_key, ErrorCode::NotPrizeNft);
+        require!(metadata.update_authority == game.key(), ErrorCode::NotPrizeNft);
+        // Metaplex pads stored strings with null bytes
+        require!(
+            metadata.uri.trim_end_matches('\0') == prize.metadata_uri,
+            ErrorCode::NotPrizeNft
+        );
+
+        // Record the shipping address commitment (hash only, no PII on-chain)
+        let redemption = &mut ctx.accounts.redemption;
+        redemption.owner = ctx.accounts.owner.key();
+        redemption.game = game.key();
+        redemption.prize_index = prize.prize_index;
+        redemption.nft_mint = nft_mint_key;
+        redemption.address_hash = address_hash;
+        redemption.redeemed_at = Clock::get()?.unix_timestamp;
+        redemption.bump = ctx.bumps.redemption;
+
+        // Burn the NFT and close the emptied token account (rent back to owner)
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    from: ctx.accounts.owner_nft_token_account.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.owner_nft_token_account.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ))?;
+
+        emit!(PhysicalRedemptionRequested {
+            winner: ctx.accounts.owner.key(),
+            game_id: game.game_id,
+            prize_id: prize.prize_id,
+            prize_index: prize.prize_index,
+            nft_mint: nft_mint_key,
+            physical_sku: prize.physical_sku.clone(),
+            weight_grams: prize.weight_grams,
+            length_hundredths: prize.length_hundredths,
+            width_hundredths: prize.width_hundredths,
+            height_hundredths: prize.height_hundredths,
+            address_hash,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+}
+
+// ============================================
+// Account Structures
+// ============================================
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum PrizeTier {
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl PrizeTier {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PrizeTier::Common => "Common",
+            PrizeTier::Uncommon => "Uncommon",
+            PrizeTier::Rare => "Rare",
+            PrizeTier::Legendary => "Legendary",
+        }
+    }
+}
+
+#[account]
+pub struct Config {
+    pub authority: Pubkey,
+    pub bump: u8,
+}
+
+/// ProgramFeatures account - program version and enabled capabilities, for client UI gating
+/// Size: 8 + 2 + 8 + 1 = 19 bytes (+32 padding)
+#[account]
+pub struct ProgramFeatures {
+    pub version: u16,                // 2 - PROGRAM_VERSION at the last update
+    pub feature_flags: u64,          // 8 - Bitfield of `Feature`
+    pub bump: u8,                    // 1
+}
+
+/// Where a play's random value comes from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RandomnessSource {
+    BackendSigned,
+    Vrf,
+    CommitReveal,
+}
+
+/// Which randomness sources a game accepts. Games created before this field read the
+/// zeroed padding as AllowBackendSigned, the original behavior.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RandomnessPolicy {
+    AllowBackendSigned,
+    VrfOnly,
+    CommitRevealOnly,
+}
+
+impl RandomnessPolicy {
+    pub fn require_allows(self, source: RandomnessSource) -> Result<()> {
+        // Builds without `backend-randomness` never trust a backend-chosen value
+        if source == RandomnessSource::BackendSigned {
+            require!(cfg!(feature = "backend-randomness"), ErrorCode::BackendRandomnessDisabled);
+        }
+        let allowed = match self {
+            RandomnessPolicy::AllowBackendSigned => true,
+            RandomnessPolicy::VrfOnly => source == RandomnessSource::Vrf,
+            RandomnessPolicy::CommitRevealOnly => source == RandomnessSource::CommitReveal,
+        };
+        require!(allowed, ErrorCode::RandomnessPolicyViolation);
+        Ok(())
+    }
+}
+
+/// Optional capabilities advertised in `ProgramFeatures::feature_flags` (bit = discriminant)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Feature {
+    Pity = 0,
+    Oracle = 1,
+    Vrf = 2,
+    SolPayments = 3,
+    SlotHashMixing = 4,
+    ConsolationNft = 5,
+    PointsPlay = 6,
+    AggregateClaim = 7,
+    RngAudit = 8,
+    PromoPlay = 9,
+    DelegateClaim = 10,
+    PrizePool = 11,
+    ComplianceMode = 12,
+}
+
+impl Feature {
+    pub fn bit(self) -> u64 {
+        1u64 << (self as u8)
+    }
+}
+
+impl ProgramFeatures {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.feature_flags & feature.bit() != 0
+    }
+}
+
+/// ClaimDelegate account - custodial signer allowed to claim for any session user
+/// Size: 8 + 33 + 1 = 42 bytes
+#[account]
+pub struct ClaimDelegate {
+    pub delegate: Option<Pubkey>,    // 33 - None disables delegate claims
+    pub bump: u8,                    // 1
+}
+
+/// Game account - now lightweight without embedded prizes
+/// Size: GAME_SPACE (~1.2KB including padding)
+#[account]
+pub struct Game {
+    pub authority: Pubkey,           // 32
+    pub game_id: u64,                // 8
+    pub name: String,                // 4 + 50 max
+    pub description: String,         // 4 + 200 max
+    pub image_url: String,           // 4 + 200 max
+    pub token_mint: Pubkey,          // 32
+    pub cost_usd: u64,               // 8
+    pub treasury: Pubkey,            // 32
+    pub prize_count: u8,             // 1 - Number of prizes
+    pub prize_probabilities: [u16; MAX_PRIZES], // 32 - Probabilities for quick selection
+    pub total_supply_remaining: u32, // 4 - Track total supply across all prizes
+    pub total_plays: u64,            // 8
+    pub is_active: bool,             // 1
+    pub last_random_value: [u8; 32], // 32
+    pub bump: u8,                    // 1
+    // Fields below were added after launch and live in the former padding, so
+    // games created earlier still decode (zeroed bytes = defaults)
+    pub use_slot_hash: bool,         // 1 - Mix recent slot hash into random_value
+    pub enforce_house_edge: bool,    // 1 - Require expected prize value <= cost_usd
+    pub prize_ev_sum: u128,          // 16 - sum(probability_bp * prize.cost_usd)
+    pub points_per_play: u64,        // 8 - Loyalty points earned per paid play
+    pub points_per_pull: u64,        // 8 - Points spent per play_with_points (0 = disabled)
+    pub rng_audit_enabled: bool,     // 1 - finalize_play must append to the RngAudit PDA
+    pub aggregate_claim: bool,       // 1 - Wins are claimed as one receipt NFT via claim_batch_aggregate
+    // Fields below live in the space added by migrate_game
+    pub tier_weighted: bool,         // 1 - Select by tier odds, then by supply within the tier
+    pub tier_bp: [u16; TIER_COUNT],  // 8 - Per-tier odds in basis points (PrizeTier order)
+    pub prize_tiers: [u8; MAX_PRIZES],   // 16 - Mirror of each prize's tier
+    pub prize_supply: [u32; MAX_PRIZES], // 64 - Mirror of each prize's supply_remaining
+    pub min_pull_count: u8,          // 1 - Smallest allowed pulls per purchase (0 is treated as 1)
+    pub tier_in_name: bool,          // 1 - Minted NFT names get a " [Tier]" suffix
+    pub pow_difficulty: u8,          // 1 - Leading zero bits play_game's nonce must produce (0 = off)
+    pub creator: Pubkey,             // 32 - Collaborator paid creator_share_bps of each play
+    pub creator_share_bps: u16,      // 2 - Creator's share of play payments (0 = all to treasury)
+    pub randomness_policy: RandomnessPolicy, // 1 - Randomness sources allowed to resolve plays
+    pub claim_cooldown_seconds: u32, // 4 - Delay after finalize before a win can be claimed (0 = none)
+    pub lock_supply: bool,           // 1 - Prize supply can never be replenished (limited drop)
+    pub compliance_mode: bool,       // 1 - Reproducible selection; every draw goes to the ComplianceLog PDA
+    pub treasury_cap: Option<u64>,   // 1 + 8 - Treasury balance above this may be swept to overflow_destination
+    pub overflow_destination: Pubkey, // 32 - Cold wallet receiving swept overflow
+    pub unique_metadata_uris: bool,  // 1 - add_prize rejects a metadata_uri another prize already uses
+    pub metadata_uri_hashes: [u64; MAX_PRIZES], // 128 - metadata_uri_hash of each prize (0 = closed)
+    pub config_frozen: bool,         // 1 - Prize list and odds can't change until thaw_config
+    pub royalty_bps: u16,            // 2 - Secondary-sale royalty written to prize NFT metadata
+    pub creators: Vec<RoyaltyCreator>, // 4 + 5*33 - Royalty recipients (max MAX_ROYALTY_CREATORS, shares sum to 100)
+    pub max_replenish_per_call: Option<u32>, // 1 + 4 - Most supply one replenish_prize_supply may add (None = unlimited)
+    pub max_total_supply: Option<u32>, // 1 + 4 - Ceiling on any prize's supply_total after a replenish (None = unlimited)
+    pub claim_window_secs: u32,      // 4 - Wins unclaimed this long after becoming claimable expire (0 = never)
+    pub refund_timeout_secs: u32,    // 4 - Plays unfulfilled this long after payment may be refunded (0 = never)
+    pub keeper_bounty: u64,          // 8 - Tokens paid from the treasury per session reap_expired_batch processes
+    pub price_feed: Pubkey,          // 32 - Pyth price account for token_mint in USD (default = payments unchecked)
+    pub max_price_age_secs: u32,     // 4 - Oldest price paid plays accept
+    pub pity_threshold: u32,         // 4 - Straight losses after which finalize_play forces a win (0 = off)
+    pub starts_at: Option<i64>,      // 1 + 8 - Plays open at this time (inclusive; None = no start)
+    pub ends_at: Option<i64>,        // 1 + 8 - Plays close at this time (exclusive; None = no end)
+    pub token_decimals: u8,          // 1 - token_mint's decimals, read once (initialize_game / cache_token_decimals)
+}
+
+impl Game {
+    /// Reject plays outside the game's scheduled window
+    // Option::is_none_or isn't stable on the Solana platform tools' rustc
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn require_in_schedule(&self, now: i64) -> Result<()> {
+        require!(self.starts_at.map_or(true, |starts_at| now >= starts_at), ErrorCode::GameNotStarted);
+        require!(self.ends_at.map_or(true, |ends_at| now < ends_at), ErrorCode::GameEnded);
+        Ok(())
+    }
+
+    /// Whether `now` falls inside the game's scheduled window
+    pub fn in_schedule(&self, now: i64) -> bool {
+        self.require_in_schedule(now).is_ok()
+    }
+}
+
+/// One royalty recipient of a game's prize NFTs
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RoyaltyCreator {
+    pub address: Pubkey,             // 32
+    pub share: u8,                   // 1 - Percent of royalties
+}
+
+/// Prize account - separate PDA for each prize
+/// Size: 8 + 32 + 1 + 8 + (4+50) + (4+150) + (4+200) + (4+200) + (4+50) + 1 + 2 + 8 + 4 + 2 + 2 + 2 + 4 + 4 + 1 + 1 = ~760 bytes
+#[account]
+pub struct Prize {
+    pub game: Pubkey,                // 32 - Parent game
+    pub prize_index: u8,             // 1 - Index in game's probability array
+    pub prize_id: u64,               // 8 - Unique ID for this prize
+    pub name: String,                // 4 + 50 max
+    pub description: String,         // 4 + 150 max
+    pub image_url: String,           // 4 + 200 max
+    pub metadata_uri: String,        // 4 + 200 max
+    pub physical_sku: String,        // 4 + 50 max
+    pub tier: PrizeTier,             // 1
+    pub probability_bp: u16,         // 2
+    pub cost_usd: u64,               // 8
+    pub weight_grams: u32,           // 4
+    pub length_hundredths: u16,      // 2 - Length in hundredths of an inch (650 = 6.50")
+    pub width_hundredths: u16,       // 2 - Width in hundredths of an inch
+    pub height_hundredths: u16,      // 2 - Height in hundredths of an inch
+    pub supply_total: u32,           // 4
+    pub supply_remaining: u32,       // 4
+    pub bump: u8,                    // 1
+    // Added after launch (lives in the former padding)
+    pub is_physical: bool,           // 1 - Ships a physical item; digital-only prizes skip fulfillment
+    pub pending_claims: u32,         // 4 - Won sessions not yet claimed or voided
+    pub low_stock_threshold: Option<u32>, // 1 + 4 - Warn once supply_remaining drops below this
+    pub low_stock_warned: bool,      // 1 - LowStockWarning already emitted for this stretch
+    pub original_supply: u32,        // 4 - supply_total at add_prize; never changes (0 = before this field)
+    pub max_per_user: u32,           // 4 - Most wins of this prize per user (0 = unlimited)
+}
+
+impl Prize {
+    /// A won session was claimed or voided. Saturates: wins recorded before this
+    /// counter existed were never counted.
+    pub fn release_pending_claim(&mut self) {
+        self.pending_claims = self.pending_claims.saturating_sub(1);
+    }
+
+    /// True the first time supply_remaining is below low_stock_threshold; the flag is
+    /// cleared again when a replenish brings supply back up to the threshold.
+    pub fn take_low_stock_warning(&mut self) -> bool {
+        match self.low_stock_threshold {
+            Some(threshold) if self.supply_remaining < threshold && !self.low_stock_warned => {
+                self.low_stock_warned = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// PlaySession account - tracks a pending play awaiting backend finalization
+/// Size: PLAY_SESSION_SPACE
+#[account]
+pub struct PlaySession {
+    pub user: Pubkey,                // 32 - User who paid
+    pub game: Pubkey,                // 32 - Game being played
+    pub amount_paid: u64,            // 8  - Token amount the treasury received, net of the creator's share and Token-2022 transfer fees (what refunds return)
+    pub session_seed: [u8; 32],      // 32 - Unique seed for PDA derivation
+    pub is_fulfilled: bool,          // 1  - Has randomness been provided by backend
+    pub random_value: [u8; 32],      // 32 - Random bytes (after fulfillment)
+    pub prize_index: Option<u8>,     // 1 + 1 = 2 - Winning prize index (None = lost)
+    pub is_claimed: bool,            // 1  - Has prize been claimed (NFT minted)
+    pub bump: u8,                    // 1
+    // Added after launch (lives in the former padding)
+    pub pending_claim_mint: Option<Pubkey>, // 1 + 32 - Two-phase claim: mint created, metadata pending
+    pub resolved_at: i64,            // 8 - When finalize resolved the play (0 = before this field existed)
+    pub supply_refunded: bool,       // 1 - Pull found no supply and amount_paid was refunded
+    pub prize_snapshot: Option<PrizeSnapshot>, // 1 + 258 - Won prize at resolution; claims mint from it
+    pub created_at: i64,             // 8 - When the play was paid (0 = opened before this field existed)
+    pub vrf_account: Option<Pubkey>, // 1 + 32 - Switchboard randomness account bound by request_randomness
+    pub vrf_seed_slot: u64,          // 8 - The bound account's commit slot
+    pub commitment: Option<[u8; 32]>, // 1 + 32 - keccak of the backend's secret, revealed at finalize
+    pub expiry_seconds: i64,         // 8 - Game's refund timeout when the play was paid (0 = use the game's current one)
+    pub timeout_refunded: bool,      // 1 - Never finalized; amount_paid was refunded after it expired
+    pub draws: u8,                   // 1 - Pulls the session pays for (play_game_multi; 0 or 1 = single)
+}
+
+/// Pyth PriceUpdateV2 account data after its discriminator, decoded by hand rather than
+/// through the Pyth SDK
+#[derive(AnchorDeserialize)]
+struct PythPriceUpdate {
+    _write_authority: Pubkey,
+    verification_level: PythVerificationLevel,
+    price_message: PythPriceMessage,
+    _posted_slot: u64,
+}
+
+#[derive(AnchorDeserialize, PartialEq, Eq)]
+enum PythVerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorDeserialize)]
+struct PythPriceMessage {
+    _feed_id: [u8; 32],
+    price: i64,
+    _conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    _prev_publish_time: i64,
+    _ema_price: i64,
+    _ema_conf: u64,
+}
+
+/// A prize's metadata as it was when a play won it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PrizeSnapshot {
+    pub name: String,                // 4 + 50 max
+    pub metadata_uri: String,        // 4 + 200 max
+}
+
+/// PlayerStats account - per-user, per-game play count and loyalty points
+/// Size: 8 + 32 + 32 + 8 + 8 + 1 = 89 bytes
+#[account]
+pub struct PlayerStats {
+    pub user: Pubkey,                // 32
+    pub game: Pubkey,                // 32
+    pub total_plays: u64,            // 8 - Paid and points-funded plays
+    pub points: u64,                 // 8 - Spendable loyalty points
+    pub bump: u8,                    // 1
+}
+
+/// PrizeClaimRecord account - how many of one prize a player has won (max_per_user)
+/// Size: 8 + 32 + 32 + 4 + 1 = 77 bytes
+#[account]
+pub struct PrizeClaimRecord {
+    pub prize: Pubkey,               // 32
+    pub user: Pubkey,                // 32
+    pub wins: u32,                   // 4 - Wins of this prize recorded by finalize_play
+    pub bump: u8,                    // 1
+}
+
+/// PityCounter account - a player's current losing streak in one game
+/// Size: 8 + 32 + 32 + 4 + 1 = 77 bytes
+#[account]
+pub struct PityCounter {
+    pub game: Pubkey,                // 32
+    pub user: Pubkey,                // 32
+    pub losses: u32,                 // 4 - Consecutive losses since the last win
+    pub bump: u8,                    // 1
+}
+
+/// PromoCampaign account - capped pool of free plays for a game
+/// Size: 8 + 32 + 8 + 4 + 4 + 8 + 8 + 1 = 73 bytes
+#[account]
+pub struct PromoCampaign {
+    pub game: Pubkey,                // 32
+    pub campaign_id: u64,            // 8
+    pub remaining_free_plays: u32,   // 4 - Free plays left across all users
+    pub per_user_limit: u32,         // 4 - Free plays each user may redeem
+    pub starts_at: i64,              // 8
+    pub ends_at: i64,                // 8 - Exclusive
+    pub bump: u8,                    // 1
+}
+
+/// PromoRedemption account - free plays a user has redeemed from one campaign
+/// Size: 8 + 32 + 32 + 4 + 1 = 77 bytes
+#[account]
+pub struct PromoRedemption {
+    pub campaign: Pubkey,            // 32
+    pub user: Pubkey,                // 32
+    pub plays_used: u32,             // 4
+    pub bump: u8,                    // 1
+}
+
+/// Consolation account - optional "better luck next time" NFT minted on a loss
+/// Size: 8 + 32 + (4+32) + (4+200) + 1 = 281 bytes
+#[account]
+pub struct Consolation {
+    pub game: Pubkey,                // 32
+    pub name: String,                // 4 + 32 max (Metaplex name limit)
+    pub uri: String,                 // 4 + 200 max
+    pub bump: u8,                    // 1
+}
+
+/// RecentWinners account - ring buffer of the last MAX_RECENT_WINNERS wins for a game
+/// Once full, each new win overwrites the oldest entry (at `head`).
+/// Size: 8 + 32 + 1 + 1 + (20 * (32 + 1 + 8)) + 1 = 863 bytes
+#[account]
+pub struct RecentWinners {
+    pub game: Pubkey,                // 32
+    pub head: u8,                    // 1 - Next slot to write (oldest entry once full)
+    pub count: u8,                   // 1 - Number of populated entries
+    pub entries: [WinnerEntry; MAX_RECENT_WINNERS], // 20 * 41
+    pub bump: u8,                    // 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct WinnerEntry {
+    pub winner: Pubkey,              // 32
+    pub prize_index: u8,             // 1
+    pub timestamp: i64,              // 8
+}
+
+impl RecentWinners {
+    /// Append a win, evicting the oldest entry when the buffer is full
+    pub fn push(&mut self, entry: WinnerEntry) {
+        self.entries[self.head as usize] = entry;
+        self.head = ((self.head as usize + 1) % MAX_RECENT_WINNERS) as u8;
+        if (self.count as usize) < MAX_RECENT_WINNERS {
+            self.count += 1;
+        }
+    }
+}
+
+/// RngAudit account - ring buffer of the last `capacity` draws for a game
+/// Entries grow until `capacity`; after that each new draw overwrites the oldest (at `head`).
+/// Size: 8 + 32 + 1 + 1 + (4 + capacity * (32 + 32 + 2 + 2)) + 1
+#[account]
+pub struct RngAudit {
+    pub game: Pubkey,                // 32
+    pub capacity: u8,                // 1 - Max entries (<= MAX_RNG_AUDIT_ENTRIES)
+    pub head: u8,                    // 1 - Next slot to overwrite once full
+    pub entries: Vec<RngDraw>,       // 4 + capacity * 68
+    pub bump: u8,                    // 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct RngDraw {
+    pub session: Pubkey,             // 32
+    pub random_value: [u8; 32],      // 32 - Final value (after any slot hash mixing)
+    pub draw: u16,                   // 2 - random_value reduced to 0..9999
+    pub prize_index: Option<u8>,     // 2 - None on a loss
+}
+
+impl RngAudit {
+    /// Append a draw, evicting the oldest entry when the buffer is full
+    pub fn push(&mut self, entry: RngDraw) {
+        if self.entries.len() < self.capacity as usize {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.head as usize] = entry;
+            self.head = ((self.head as usize + 1) % self.capacity as usize) as u8;
+        }
+    }
+}
+
+/// ComplianceLog account - ring buffer of the last `capacity` compliance-mode draws
+/// Entries grow until `capacity`; after that each new draw overwrites the oldest (at `head`).
+/// Size: 8 + 32 + 1 + 1 + (4 + capacity * COMPLIANCE_DRAW_SIZE) + 1
+#[account]
+pub struct ComplianceLog {
+    pub game: Pubkey,                // 32
+    pub capacity: u8,                // 1 - Max entries (<= MAX_COMPLIANCE_ENTRIES)
+    pub head: u8,                    // 1 - Next slot to overwrite once full
+    pub entries: Vec<ComplianceDraw>, // 4 + capacity * 164
+    pub bump: u8,                    // 1
+}
+
+/// Every input of one compliance-mode selection and its outcome
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ComplianceDraw {
+    pub session: Pubkey,             // 32
+    pub source: RandomnessSource,    // 1 - Who supplied the randomness
+    pub random_value: [u8; 32],      // 32 - Final value fed to selection (after any slot hash mixing)
+    pub probabilities: [u16; MAX_PRIZES], // 32 - game.prize_probabilities at the draw
+    pub supplies: [u32; MAX_PRIZES], // 64 - game.prize_supply at the draw
+    pub prize_count: u8,             // 1
+    pub prize_index: Option<u8>,     // 2 - None on a loss
+}
+
+impl ComplianceLog {
+    /// Append a draw, evicting the oldest entry when the buffer is full
+    pub fn push(&mut self, entry: ComplianceDraw) {
+        if self.entries.len() < self.capacity as usize {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.head as usize] = entry;
+            self.head = ((self.head as usize + 1) % self.capacity as usize) as u8;
+        }
+    }
+}
+
+/// Redemption account - provenance record binding a burned prize NFT to a shipping address hash
+/// Size: 8 + 32 + 32 + 1 + 32 + 32 + 8 + 1 = 146 bytes
+#[account]
+pub struct Redemption {
+    pub owner: Pubkey,               // 32 - Winner who redeemed
+    pub game: Pubkey,                // 32
+    pub prize_index: u8,             // 1
+    pub nft_mint: Pubkey,            // 32 - Burned prize NFT
+    pub address_hash: [u8; 32],      // 32 - Hash of the off-chain shipping details
+    pub redeemed_at: i64,            // 8
+    pub bump: u8,                    // 1
+}
+
+/// PrizeInventory account - pre-minted NFT pool for one prize
+/// Pool mints are [b"premint", prize, index (u32 LE)] for index in next..minted,
+/// each holding its token in the game PDA's associated token account.
+/// Size: 8 + 32 + 32 + 4 + 4 + 1 = 81 bytes
+#[account]
+pub struct PrizeInventory {
+    pub game: Pubkey,                // 32
+    pub prize: Pubkey,               // 32
+    pub minted: u32,                 // 4 - Pool mints created so far
+    pub next: u32,                   // 4 - Index of the next mint to hand out
+    pub bump: u8,                    // 1
+}
+
+// ============================================
+// Account Contexts
+// ============================================
+
+#[derive(Accounts)]
+pub struct InitializeProgram<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramAuthority<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeatureFlags<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized, seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 2 + 8 + 1 + 32, // +32 padding
+        seeds = [seeds::FEATURES],
+        bump
+    )]
+    pub features: Account<'info, ProgramFeatures>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetClaimDelegate<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized, seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + (1 + 32) + 1,
+        seeds = [seeds::CLAIM_DELEGATE],
+        bump
+    )]
+    pub claim_delegate: Account<'info, ClaimDelegate>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmitFeatureFlags<'info> {
+    #[account(seeds = [seeds::FEATURES], bump = features.bump)]
+    pub features: Account<'info, ProgramFeatures>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: u64)]
+pub struct InitializeGame<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = authority,
+        space = GAME_SPACE,
+        seeds = [seeds::GAME, game_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    /// CHECK: Treasury wallet
+    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: The game's token mint (the token_mint argument); its decimals are cached
+    pub token_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CacheTokenDecimals<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    /// CHECK: The game's token mint, decoded by mint_decimals
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(prize_index: u8)]
+pub struct AddPrize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(
+        init,
+        payer = authority,
+        space = PRIZE_SPACE,
+        seeds = [seeds::PRIZE, game.key().as_ref(), &[prize_index]],
+        bump
+    )]
+    pub prize: Account<'info, Prize>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_amount: u64, session_seed: [u8; 32])]
+pub struct PlayGame<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Creator's token account; required when the game has a creator_share_bps
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Token mint account - must be the game's mint (decimals come from the game)
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: AccountInfo<'info>,
+    /// CHECK: Pyth price account - required when the game has a price_feed; key, owner
+    /// and layout are checked in collect_play_payment
+    pub price_update: Option<UncheckedAccount<'info>>,
+    
+    // PlaySession PDA - unique per game + user + session_seed
+    #[account(
+        init,
+        payer = user,
+        space = PLAY_SESSION_SPACE,
+        seeds = [seeds::SESSION, game.key().as_ref(), user.key().as_ref(), &session_seed],
+        bump
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 32 + 8 + 8 + 1 + 32, // +32 padding
+        seeds = [seeds::STATS, game.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_amount: u64, request_id: u64)]
+pub struct PlayGameWithRequestId<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Creator's token account; required when the game has a creator_share_bps
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Token mint account - must be the game's mint (decimals come from the game)
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: AccountInfo<'info>,
+    /// CHECK: Pyth price account - required when the game has a price_feed; key, owner
+    /// and layout are checked in collect_play_payment
+    pub price_update: Option<UncheckedAccount<'info>>,
+    
+    // PlaySession PDA - unique per game + user + request_id
+    #[account(
+        init,
+        payer = user,
+        space = PLAY_SESSION_SPACE,
+        seeds = [seeds::SESSION, game.key().as_ref(), user.key().as_ref(), &request_id.to_le_bytes()],
+        bump
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 32 + 8 + 8 + 1 + 32, // +32 padding
+        seeds = [seeds::STATS, game.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_seed: [u8; 32])]
+pub struct PlayWithPoints<'info> {
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::STATS, game.key().as_ref(), user.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(
+        init,
+        payer = user,
+        space = PLAY_SESSION_SPACE,
+        seeds = [seeds::SESSION, game.key().as_ref(), user.key().as_ref(), &session_seed],
+        bump
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u64)]
+pub struct CreatePromoCampaign<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 8 + 4 + 4 + 8 + 8 + 1,
+        seeds = [seeds::PROMO, game.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub promo_campaign: Account<'info, PromoCampaign>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_seed: [u8; 32])]
+pub struct PlayWithPromo<'info> {
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::PROMO, game.key().as_ref(), &promo_campaign.campaign_id.to_le_bytes()],
+        bump = promo_campaign.bump
+    )]
+    pub promo_campaign: Account<'info, PromoCampaign>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 32 + 4 + 1,
+        seeds = [seeds::PROMO_REDEMPTION, promo_campaign.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub promo_redemption: Account<'info, PromoRedemption>,
+    #[account(
+        init,
+        payer = user,
+        space = PLAY_SESSION_SPACE,
+        seeds = [seeds::SESSION, game.key().as_ref(), user.key().as_ref(), &session_seed],
+        bump
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePrize<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut, constraint = prize.game == game.key() @ ErrorCode::WrongGame)]
+    pub prize: Account<'info, Prize>,
+    pub authority: Signer<'info>,
+    /// CHECK: The prize's pool inventory, if it was ever preminted; required by
+    /// update_prize to change the metadata URI, only read
+    #[account(seeds = [seeds::INVENTORY, prize.key().as_ref()], bump)]
+    pub inventory: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGame<'info> {
+    #[account(mut, has_one = authority)]
+    pub game: Account<'info, Game>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGame<'info> {
+    /// CHECK: Game PDA in the pre-migration layout; owner, discriminator and
+    /// authority are checked manually since it may not decode as Game yet
+    #[account(mut)]
+    pub game: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePlaySession<'info> {
+    /// CHECK: PlaySession PDA in the pre-migration layout; owner and discriminator are
+    /// checked manually since it may not decode as PlaySession yet
+    #[account(mut)]
+    pub play_session: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetConsolationNft<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 32 + (4+32) + (4+200) + 1,
+        seeds = [seeds::CONSOLATION, game.key().as_ref()],
+        bump
+    )]
+    pub consolation: Account<'info, Consolation>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearConsolationNft<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        seeds = [seeds::CONSOLATION, game.key().as_ref()],
+        bump = consolation.bump,
+        close = authority
+    )]
+    pub consolation: Account<'info, Consolation>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(capacity: u8)]
+pub struct InitializeRngAudit<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + 1 + (4 + capacity as usize * (32 + 32 + 2 + 2)) + 1,
+        seeds = [seeds::RNG_AUDIT, game.key().as_ref()],
+        bump
+    )]
+    pub rng_audit: Box<Account<'info, RngAudit>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(capacity: u8)]
+pub struct EnableComplianceMode<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + 1 + (4 + capacity as usize * COMPLIANCE_DRAW_SIZE) + 1,
+        seeds = [seeds::COMPLIANCE, game.key().as_ref()],
+        bump
+    )]
+    pub compliance_log: Box<Account<'info, ComplianceLog>>,
+    pub system_program: Program<'info, System>,
+    // NOTE: all prize PDAs passed via remaining_accounts in index order
+}
+
+#[derive(Accounts)]
+pub struct InitializeRecentWinners<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + 1 + (MAX_RECENT_WINNERS * (32 + 1 + 8)) + 1,
+        seeds = [seeds::WINNERS, game.key().as_ref()],
+        bump
+    )]
+    pub recent_winners: Box<Account<'info, RecentWinners>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReplenishPrizeSupply<'info> {
+    #[account(mut, has_one = authority)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub prize: Account<'info, Prize>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckSupply<'info> {
+    pub game: Account<'info, Game>,
+    // NOTE: all prize PDAs passed via remaining_accounts in index order
+}
+
+#[derive(Accounts)]
+pub struct EmitEffectiveOdds<'info> {
+    pub game: Account<'info, Game>,
+}
+
+#[derive(Accounts)]
+pub struct SimulatePlay<'info> {
+    /// Read-only: simulate_play never writes
+    pub game: Account<'info, Game>,
+}
+
+#[derive(Accounts)]
+pub struct EmitWinnability<'info> {
+    pub game: Account<'info, Game>,
+    // NOTE: prize PDAs to report on via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct EmitSupplySnapshot<'info> {
+    pub game: Account<'info, Game>,
+    // NOTE: prize accounts to include are passed via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(has_one = authority, has_one = treasury @ ErrorCode::WrongTreasury)]
+    pub game: Account<'info, Game>,
+    pub authority: Signer<'info>,
+    /// CHECK: Treasury wallet - must match game.treasury and sign the transaction
+    #[account(
+        mut,
+        constraint = treasury.key() == game.treasury @ ErrorCode::WrongTreasury
+    )]
+    pub treasury: Signer<'info>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == treasury.key() @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = destination_token_account.mint == game.token_mint @ ErrorCode::WrongMint)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Token mint account - needed for transfer_checked
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SweepOverflow<'info> {
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == game.treasury @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = destination_token_account.owner == game.overflow_destination @ ErrorCode::InvalidOverflowDestination
+    )]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: InterfaceAccount<'info, anchor_spl::token_interface::Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReapExpiredBatch<'info> {
+    /// Anyone may reap
+    pub keeper: Signer<'info>,
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == game.treasury @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Receives the keeper bounty; omit it to reap without one
+    #[account(
+        mut,
+        constraint = keeper_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = keeper_token_account.owner == keeper.key() @ ErrorCode::WrongTokenOwner
+    )]
+    pub keeper_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: InterfaceAccount<'info, anchor_spl::token_interface::Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // NOTE: prize PDAs, then [play_session, user_token_account] pairs via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct RefundExpiredSession<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == game.treasury @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = user_token_account.owner == play_session.user @ ErrorCode::WrongTokenOwner
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: InterfaceAccount<'info, anchor_spl::token_interface::Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseGame<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized, close = authority)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePrize<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(mut, constraint = prize.game == game.key(), close = authority)]
+    pub prize: Account<'info, Prize>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(target_index: u8)]
+pub struct ReindexPrize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    /// The game's last prize, moved into the empty slot and closed
+    #[account(
+        mut,
+        constraint = last_prize.game == game.key() @ ErrorCode::WrongGame,
+        close = authority
+    )]
+    pub last_prize: Box<Account<'info, Prize>>,
+    /// CHECK: The last prize's pool inventory, if it was ever preminted; only read
+    #[account(seeds = [seeds::INVENTORY, last_prize.key().as_ref()], bump)]
+    pub inventory: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = PRIZE_SPACE,
+        seeds = [seeds::PRIZE, game.key().as_ref(), &[target_index]],
+        bump
+    )]
+    pub prize: Box<Account<'info, Prize>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenClaimRecord<'info> {
+    pub prize: Account<'info, Prize>,
+    /// CHECK: The player the record tracks; only used as a seed
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 4 + 1,
+        seeds = [seeds::CLAIM_RECORD, prize.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub claim_record: Account<'info, PrizeClaimRecord>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPityCounter<'info> {
+    pub game: Account<'info, Game>,
+    /// CHECK: The player the counter tracks; only used as a seed
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 4 + 1,
+        seeds = [seeds::PITY, game.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub pity_counter: Account<'info, PityCounter>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestRandomness<'info> {
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    pub user: Signer<'info>,
+    /// CHECK: Switchboard randomness account; owner and layout are checked when parsed
+    pub randomness_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizePlay<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// Backend authority must co-sign - pays for NFT minting on wins
+    #[account(
+        mut,
+        constraint = backend_authority.key() == config.authority @ ErrorCode::Unauthorized
+    )]
+    pub backend_authority: Signer<'info>,
+    /// CHECK: SlotHashes sysvar - required when game.use_slot_hash is set
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: Option<UncheckedAccount<'info>>,
+    /// Recent winners feed - appended to on a win when provided
+    #[account(
+        mut,
+        seeds = [seeds::WINNERS, game.key().as_ref()],
+        bump = recent_winners.bump
+    )]
+    pub recent_winners: Option<Box<Account<'info, RecentWinners>>>,
+    /// RNG audit log - required once the game has one
+    #[account(
+        mut,
+        seeds = [seeds::RNG_AUDIT, game.key().as_ref()],
+        bump = rng_audit.bump
+    )]
+    pub rng_audit: Option<Box<Account<'info, RngAudit>>>,
+    /// Compliance log - required in compliance mode
+    #[account(
+        mut,
+        seeds = [seeds::COMPLIANCE, game.key().as_ref()],
+        bump = compliance_log.bump
+    )]
+    pub compliance_log: Option<Box<Account<'info, ComplianceLog>>>,
+    /// CHECK: Switchboard randomness account - required for plays bound by request_randomness;
+    /// key, owner and layout are checked in consume_vrf
+    pub randomness_account: Option<UncheckedAccount<'info>>,
+    /// Player's pity counter - required once the game has a pity_threshold
+    #[account(
+        mut,
+        seeds = [seeds::PITY, game.key().as_ref(), play_session.user.as_ref()],
+        bump = pity_counter.bump
+    )]
+    pub pity_counter: Option<Box<Account<'info, PityCounter>>>,
+    /// The user's record for the drawn prize; required when that prize has max_per_user
+    #[account(mut, constraint = claim_record.user == play_session.user @ ErrorCode::ClaimRecordMismatch)]
+    pub claim_record: Option<Box<Account<'info, PrizeClaimRecord>>>,
+    // NOTE: For wins (or consolation mints on a loss), additional accounts passed via remaining_accounts:
+    // [0] Prize (or Consolation), [1] NFT mint (signer), [2] Metadata PDA, [3] Master Edition PDA,
+    // [4] User's ATA, [5] User account, [6] Token Program, [7] Associated Token Program,
+    // [8] Metaplex Program, [9] System Program, [10] Rent
+}
+
+#[derive(Accounts)]
+pub struct FinalizeBatch<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// Backend authority must co-sign
+    #[account(constraint = backend_authority.key() == config.authority @ ErrorCode::Unauthorized)]
+    pub backend_authority: Signer<'info>,
+    /// CHECK: SlotHashes sysvar - required when game.use_slot_hash is set
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: Option<UncheckedAccount<'info>>,
+    /// Recent winners feed - appended to on each win when provided
+    #[account(
+        mut,
+        seeds = [seeds::WINNERS, game.key().as_ref()],
+        bump = recent_winners.bump
+    )]
+    pub recent_winners: Option<Box<Account<'info, RecentWinners>>>,
+    /// RNG audit log - required once the game has one
+    #[account(
+        mut,
+        seeds = [seeds::RNG_AUDIT, game.key().as_ref()],
+        bump = rng_audit.bump
+    )]
+    pub rng_audit: Option<Box<Account<'info, RngAudit>>>,
+    /// Compliance log - required in compliance mode
+    #[account(
+        mut,
+        seeds = [seeds::COMPLIANCE, game.key().as_ref()],
+        bump = compliance_log.bump
+    )]
+    pub compliance_log: Option<Box<Account<'info, ComplianceLog>>>,
+    /// Treasury wallet - co-signs refunds of pulls that found no supply
+    #[account(address = game.treasury @ ErrorCode::WrongTreasury)]
+    pub treasury: Option<Signer<'info>>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == game.treasury @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: Option<Box<InterfaceAccount<'info, anchor_spl::token_interface::Mint>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    // NOTE: all prize PDAs (index order), then the play sessions, then (with the treasury
+    // accounts) each session's user token account, via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame,
+        constraint = play_session.user == user.key() @ ErrorCode::WrongSessionUser
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // NFT Minting accounts
+    /// CHECK: Mint account for the NFT
+    #[account(mut)]
+    pub nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: User's NFT token account (ATA under token_program)
+    #[account(mut)]
+    pub user_nft_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAndList<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame,
+        constraint = play_session.user == user.key() @ ErrorCode::WrongSessionUser
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    /// Winning user; becomes the listing's seller
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // NFT Minting accounts
+    /// CHECK: Mint account for the NFT
+    #[account(mut)]
+    pub nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Metadata PDA (also snapshotted into the listing)
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: User's NFT token account
+    #[account(mut)]
+    pub user_nft_token_account: UncheckedAccount<'info>,
+    
+    // Marketplace listing accounts (validated by the marketplace program)
+    pub marketplace_program: Program<'info, GachaponMarketplace>,
+    /// CHECK: Marketplace config PDA [b"config"]
+    pub marketplace_config: UncheckedAccount<'info>,
+    /// CHECK: Listing PDA [b"listing", nft_mint] (created by list_nft)
+    #[account(mut)]
+    pub listing: UncheckedAccount<'info>,
+    /// CHECK: Seller stats PDA [b"seller_stats", user] (created by list_nft)
+    #[account(mut)]
+    pub seller_stats: UncheckedAccount<'info>,
+    /// CHECK: Currency the listing is priced in
+    pub currency_mint: UncheckedAccount<'info>,
+    /// CHECK: Escrow authority PDA [b"escrow", nft_mint]
+    pub escrow_authority: UncheckedAccount<'info>,
+    /// CHECK: Escrow's NFT ATA (created by list_nft)
+    #[account(mut)]
+    pub escrow_nft_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBatchAggregate<'info> {
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Receipt NFT minting accounts
+    /// CHECK: Mint account for the receipt NFT
+    #[account(mut)]
+    pub nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: User's NFT token account
+    #[account(mut)]
+    pub user_nft_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+    // NOTE: [session, prize] pairs passed via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrizeSponsored<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    /// Pays rent for the token account, metadata and master edition
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// CHECK: Session user receiving the NFT; does not need to sign
+    #[account(address = play_session.user @ ErrorCode::WrongSessionUser)]
+    pub user: UncheckedAccount<'info>,
+    
+    // NFT Minting accounts
+    /// CHECK: Mint account for the NFT
+    #[account(mut)]
+    pub nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: User's NFT token account (verified as the session user's ATA)
+    #[account(mut)]
+    pub user_nft_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrizeAsDelegate<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    #[account(
+        seeds = [seeds::CLAIM_DELEGATE],
+        bump = claim_delegate.bump,
+        constraint = claim_delegate.delegate == Some(delegate.key()) @ ErrorCode::Unauthorized
+    )]
+    pub claim_delegate: Account<'info, ClaimDelegate>,
+    /// Custodial signer; pays rent for the token account, metadata and master edition
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+    /// CHECK: Session user receiving the NFT; does not need to sign
+    #[account(address = play_session.user @ ErrorCode::WrongSessionUser)]
+    pub user: UncheckedAccount<'info>,
+    
+    // NFT Minting accounts
+    /// CHECK: Mint account for the NFT
+    #[account(mut)]
+    pub nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: User's NFT token account (verified as the session user's ATA)
+    #[account(mut)]
+    pub user_nft_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PremintPrize<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    #[account(constraint = prize.game == game.key() @ ErrorCode::WrongGame)]
+    pub prize: Account<'info, Prize>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 32 + 32 + 4 + 4 + 1,
+        seeds = [seeds::INVENTORY, prize.key().as_ref()],
+        bump
+    )]
+    pub inventory: Account<'info, PrizeInventory>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [seeds::PREMINT, prize.key().as_ref(), &inventory.minted.to_le_bytes()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = game,
+        mint::freeze_authority = game,
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = nft_mint,
+        associated_token::authority = game,
+    )]
+    pub vault_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFromPool<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame,
+        constraint = play_session.user == user.key() @ ErrorCode::WrongSessionUser
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    #[account(
+        mut,
+        seeds = [seeds::INVENTORY, prize.key().as_ref()],
+        bump = inventory.bump,
+        constraint = inventory.next < inventory.minted @ ErrorCode::PrizePoolEmpty
+    )]
+    pub inventory: Account<'info, PrizeInventory>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// Oldest pool mint not handed out yet
+    #[account(seeds = [seeds::PREMINT, prize.key().as_ref(), &inventory.next.to_le_bytes()], bump)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = game,
+    )]
+    pub vault_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = nft_mint,
+        associated_token::authority = user,
+    )]
+    pub user_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPrizeMint<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    /// Session user or a relayer; pays rent for the mint and token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Session user receiving the NFT
+    #[account(address = play_session.user @ ErrorCode::WrongSessionUser)]
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = game,
+        mint::freeze_authority = game,
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = user,
+    )]
+    pub user_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizePrizeMetadata<'info> {
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    /// Pays rent for the metadata and master edition
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Session user holding the NFT
+    #[account(address = play_session.user @ ErrorCode::WrongSessionUser)]
+    pub user: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = user,
+    )]
+    pub user_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    /// CHECK: Metadata PDA
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Master Edition PDA
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Metaplex Token Metadata program
+    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePlaySession<'info> {
+    #[account(
+        mut,
+        constraint = play_session.user == user.key() @ ErrorCode::WrongSessionUser,
+        close = user
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecoverStuckMint<'info> {
+    #[account(seeds = [seeds::CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(constraint = authority.key() == config.authority @ ErrorCode::Unauthorized)]
+    pub authority: Signer<'info>,
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    #[account(mut)]
+    pub nft_mint: Account<'info, Mint>,
+    /// Wherever the half-minted token ended up
+    #[account(
+        mut,
+        constraint = stuck_token_account.mint == nft_mint.key() @ ErrorCode::WrongMint
+    )]
+    pub stuck_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct VoidUnmintableWin<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized, has_one = treasury @ ErrorCode::WrongTreasury)]
+    pub game: Account<'info, Game>,
+    pub authority: Signer<'info>,
+    /// Treasury wallet - signs the refund out of its token account
+    pub treasury: Signer<'info>,
+    #[account(
+        mut,
+        constraint = play_session.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub play_session: Account<'info, PlaySession>,
+    #[account(
+        mut,
+        constraint = prize.game == game.key() @ ErrorCode::WrongGame
+    )]
+    pub prize: Account<'info, Prize>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = treasury_token_account.owner == treasury.key() @ ErrorCode::WrongTokenOwner
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == game.token_mint @ ErrorCode::WrongMint,
+        constraint = user_token_account.owner == play_session.user @ ErrorCode::WrongTokenOwner
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Token mint account - needed for transfer_checked
+    #[account(address = game.token_mint @ ErrorCode::WrongMint)]
+    pub token_mint: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct AdminCloseResolvedSessions<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub game: Account<'info, Game>,
+    pub authority: Signer<'info>,
+    // NOTE: [play_session, user] pairs passed via remaining_accounts
+}
+
+#[derive(Accounts)]
+pub struct RedeemPhysical<'info> {
+    pub game: Account<'info, Game>,
+    #[account(constraint = prize.game == game.key() @ ErrorCode::WrongGame)]
+    pub prize: Account<'info, Prize>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        constraint = owner_nft_token_account.owner == owner.key() @ ErrorCode::WrongTokenOwner,
+        constraint = owner_nft_token_account.mint == nft_mint.key() @ ErrorCode::WrongMint,
+        constraint = owner_nft_token_account.amount == 1 @ ErrorCode::NftNotHeld
+    )]
+    pub owner_nft_token_account: Account<'info, anchor_spl::token::TokenAccount>,
+    /// CHECK: Metadata PDA - derived and deserialized in instruction
+    pub metadata: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + 32 + 32 + 1 + 32 + 32 + 8 + 1,
+        seeds = [seeds::REDEMPTION, nft_mint.key().as_ref()],
+        bump
+    )]
+    pub redemption: Account<'info, Redemption>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================
+// Events
+// ============================================
+
+#[event]
+pub struct GameCreated {
+    pub game_id: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrizeAdded {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub probability_bp: u16,
+    pub supply_total: u32,
+    pub timestamp: i64,
+    // Catalog fields appended after timestamp so existing decoders keep working
+    pub tier: PrizeTier,
+    pub cost_usd: u64,
+    pub weight_grams: u32,
+    pub physical_sku: String,
+}
+
+#[event]
+pub struct GamePlayInitiated {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub token_amount: u64,
+    pub session: Pubkey,
+    pub timestamp: i64,
+    // Appended after timestamp so existing decoders keep working
+    pub is_revenue: bool,            // false for points-funded plays
+    pub treasury_amount: u64,        // Part of token_amount paid to the treasury
+    pub creator_amount: u64,         // Part of token_amount paid to the game's creator
+    pub decimals: u8,                // Token mint decimals for formatting amounts (0 when no tokens move)
+    pub commitment: Option<[u8; 32]>, // Backend's keccak commitment for commit-reveal plays
+    pub max_token_amount: u64,       // Most the user authorized for token_amount (slippage limit)
+    pub net_amount: u64,             // What the treasury received (treasury_amount less Token-2022 transfer fees); the session's amount_paid
+}
+
+#[event]
+pub struct GameStatusUpdated {
+    pub game_id: u64,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
+
+/// Emitted by freeze_config (frozen = true) and thaw_config (frozen = false)
+#[event]
+pub struct PrizeConfigFreezeUpdated {
+    pub game_id: u64,
+    pub frozen: bool,
+    pub timestamp: i64,
+}
+
+/// update_prize changed some of a prize's catalog fields
+#[event]
+pub struct PrizeUpdated {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub changed_fields: u16,         // PRIZE_FIELD_* bits of the fields whose value changed
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GameScheduleUpdated {
+    pub game_id: u64,
+    pub starts_at: Option<i64>,
+    pub ends_at: Option<i64>,
+    pub timestamp: i64,
+}
+
+/// A drawn prize was resolved as a loss because the user reached its max_per_user
+#[event]
+pub struct UserLimitReached {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub prize_index: u8,
+    pub max_per_user: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProbabilityUpdated {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub old_probability_bp: u16,
+    pub new_probability_bp: u16,
+    pub live_edit: bool,             // Changed while the game was active
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrizeRemoved {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub supply_removed: u32,
+    pub prize_count: u8,
+    pub timestamp: i64,
+}
+
+/// reindex_prize moved the last prize into an emptied slot
+#[event]
+pub struct PrizeReindexed {
+    pub game_id: u64,
+    pub prize_id: u64,
+    pub from_index: u8,
+    pub to_index: u8,
+    pub prize_count: u8,
+    pub timestamp: i64,
+}
+
+/// set_prize_max_per_user changed a prize's per-user win cap (0 = unlimited)
+#[event]
+pub struct PrizeMaxPerUserUpdated {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub old_max_per_user: u32,
+    pub max_per_user: u32,
+    pub timestamp: i64,
+}
+
+/// Emitted when a win takes the last prize and finalize deactivates the game
+#[event]
+pub struct GameSoldOut {
+    pub game_id: u64,
+    pub total_plays: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SupplyReplenished {
+    pub game_id: u64,
+    pub prize_id: u64,
+    pub prize_index: u8,
+    pub new_supply: u32,
+    pub timestamp: i64,
+    // Appended after timestamp so existing decoders keep working
+    pub supply_total: u32,
+    pub original_supply: u32,        // Supply planned at add_prize
+    pub exceeds_original: bool,      // supply_total is now above original_supply
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub game_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+    // Appended after timestamp so existing decoders keep working
+    pub decimals: u8,                // Token mint decimals for formatting `amount`
+}
+
+/// Pulls in a finalize_batch found no supply and were refunded
+#[event]
+pub struct PartialBatchRefund {
+    pub game_id: u64,
+    pub refunded_count: u32,
+    pub refunded_amount: u64,        // Sum of the refunded sessions' amount_paid
+    pub timestamp: i64,
+}
+
+/// finalize_play forced a win after the player's losing streak reached pity_threshold
+#[event]
+pub struct PityTriggered {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub prize_index: u8,
+    pub losses: u32,                 // Streak that triggered it
+    pub timestamp: i64,
+}
+
+/// A play was bound to a Switchboard randomness account
+#[event]
+pub struct RandomnessRequested {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub vrf_account: Pubkey,
+    pub seed_slot: u64,
+    pub timestamp: i64,
+}
+
+/// A play the backend never finalized was refunded after its timeout
+#[event]
+pub struct PlayRefunded {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// A reap_expired_batch run: expired wins returned to supply and timed-out plays refunded
+#[event]
+pub struct ExpiredSessionsReaped {
+    pub game_id: u64,
+    pub keeper: Pubkey,
+    pub wins_expired: u32,
+    pub plays_refunded: u32,
+    pub refunded_amount: u64,        // Sum of the refunded sessions' amount_paid
+    pub bounty_paid: u64,            // Paid to the keeper from the treasury
+    pub timestamp: i64,
+}
+
+/// Treasury balance above the game's cap was forwarded to its overflow destination
+#[event]
+pub struct OverflowForwarded {
+    pub game_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,         // Destination token account
+    pub treasury_cap: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PlayResolved {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub prize_id: Option<u64>,
+    pub prize_index: Option<u8>,
+    pub tier: Option<PrizeTier>,
+    pub is_win: bool,
+    pub random_value: [u8; 32],
+    pub timestamp: i64,
+}
+
+/// Full inputs and outcome of a compliance-mode draw (also kept in the ComplianceLog)
+#[event]
+pub struct DrawAudited {
+    pub game_id: u64,
+    pub draw: ComplianceDraw,
+    pub timestamp: i64,
+}
+
+/// An NFT was pre-minted into a prize's inventory pool
+#[event]
+pub struct PrizePreminted {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub nft_mint: Pubkey,
+    pub pool_size: u32,              // NFTs waiting in the pool after this one
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrizeClaimed {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub prize_id: u64,
+    pub prize_index: u8,
+    pub tier: PrizeTier,
+    pub nft_mint: Pubkey,
+    pub timestamp: i64,
+    // Appended after timestamp so existing decoders keep working
+    pub is_physical: bool,           // false for digital-only prizes (no shipping)
+    pub cost_usd: u64,               // Prize's cost, for payout-ratio tracking
+    pub amount_paid: u64,            // What the winning session paid
+    pub user_prize_wins: Option<u32>, // User's wins of this prize so far (finalize_play with a claim record)
+}
+
+/// A half-minted prize mint was retired and its session's claim reopened (recover_stuck_mint)
+#[event]
+pub struct MintRecovered {
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub winner: Pubkey,              // Session user, who can now claim a fresh mint
+    pub nft_mint: Pubkey,
+    pub token_account: Pubkey,       // Account left holding the frozen token
+    pub holder: Pubkey,              // Owner of that account
+    pub timestamp: i64,
+}
+
+/// A won session that could not be minted was refunded (void_unmintable_win)
+#[event]
+pub struct WinVoided {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub prize_index: u8,
+    pub refund_amount: u64,
+    pub decimals: u8,
+    pub timestamp: i64,
+}
+
+/// Live per-prize and loss odds in basis points, summing to 10000 (emit_effective_odds)
+#[event]
+pub struct EffectiveOdds {
+    pub game_id: u64,
+    pub prize_odds_bp: [u16; MAX_PRIZES],
+    pub loss_odds_bp: u16,
+    pub timestamp: i64,
+}
+
+/// Outcome simulate_play computed for a random value; nothing was recorded
+#[event]
+pub struct SimulatedOutcome {
+    pub game_id: u64,
+    pub random_value: [u8; 32],
+    pub prize_index: Option<u8>,     // None = loss
+    pub drawn_sold_out: bool,        // The draw hit a prize (or tier) with no supply left
+    pub timestamp: i64,
+}
+
+/// Whether each prize can currently be won, by prize_index (emit_winnability)
+#[event]
+pub struct Winnability {
+    pub game_id: u64,
+    pub winnable: [bool; MAX_PRIZES],
+    pub timestamp: i64,
+}
+
+/// Remaining supply for a set of prizes (emit_supply_snapshot)
+#[event]
+pub struct SupplySnapshot {
+    pub game_id: u64,
+    pub supplies: Vec<PrizeSupply>,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PrizeSupply {
+    pub prize_index: u8,
+    pub supply_remaining: u32,
+}
+
+/// verify_prizes rebuilt the game's prize bookkeeping
+#[event]
+pub struct PrizesVerified {
+    pub game_id: u64,
+    pub prize_count: u8,
+    pub occupied_mask: u16,          // Bit i set = prize PDA i is live
+    pub timestamp: i64,
+}
+
+/// Game and prize supply counters disagree (accounting bug or manual intervention)
+#[event]
+pub struct SupplyDrift {
+    pub game_id: u64,
+    pub prize_index: Option<u8>,     // Prize being awarded, or None for a full check_supply
+    pub game_supply_remaining: u32,
+    pub prize_supply_remaining: u32, // That prize's supply, or the sum over all prizes
+    pub timestamp: i64,
+}
+
+/// A win took a prize below its low_stock_threshold (emitted once until restocked)
+#[event]
+pub struct LowStockWarning {
+    pub game_id: u64,
+    pub prize_index: u8,
+    pub prize_id: u64,
+    pub supply_remaining: u32,
+    pub threshold: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeatureFlagsUpdated {
+    pub version: u16,
+    pub feature_flags: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AggregateClaimed {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub nft_mint: Pubkey,            // Receipt NFT
+    pub sessions: Vec<Pubkey>,
+    pub prize_ids: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConsolationMinted {
+    pub user: Pubkey,
+    pub game_id: u64,
+    pub session: Pubkey,
+    pub nft_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SessionsClosed {
+    pub game_id: u64,
+    pub closed: u32,
+    pub skipped: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PhysicalRedemptionRequested {
+    pub winner: Pubkey,
+    pub game_id: u64,
+    pub prize_id: u64,
+    pub prize_index: u8,
+    pub nft_mint: Pubkey,
+    pub physical_sku: String,
+    pub weight_grams: u32,
+    pub length_hundredths: u16,
+    pub width_hundredths: u16,
+    pub height_hundredths: u16,
+    pub address_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+// ============================================
+// Errors
+// ============================================
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Invalid probabilities; must sum to <= 10000")]
+    InvalidProbabilities,
+    #[msg("Game is inactive")]
+    GameInactive,
+    #[msg("All prizes are out of stock")]
+    OutOfStock,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Prize not found")]
+    PrizeNotFound,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Invalid token amount")]
+    InvalidTokenAmount,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("String exceeds maximum length")]
+    StringTooLong,
+    #[msg("Too many prizes (max 16)")]
+    TooManyPrizes,
+    #[msg("Play session already fulfilled")]
+    AlreadyFulfilled,
+    #[msg("Play session not fulfilled yet")]
+    NotFulfilled,
+    #[msg("No prize won in this session")]
+    NoPrize,
+    #[msg("Prize already claimed")]
+    AlreadyClaimed,
+    #[msg("Must claim prize before closing session")]
+    NotClaimed,
+    #[msg("Invalid prize index")]
+    InvalidPrizeIndex,
+    #[msg("NFT is not a prize from this game")]
+    NotPrizeNft,
+    #[msg("Prize has no physical item to redeem")]
+    NotPhysicalPrize,
+    #[msg("SlotHashes sysvar required for this game")]
+    SlotHashesMissing,
+    #[msg("A two-phase claim is already in progress for this session")]
+    ClaimInProgress,
+    #[msg("Two-phase claim has not been started for this mint")]
+    ClaimNotStarted,
+    #[msg("Expected prize value exceeds the play cost")]
+    NegativeHouseEdge,
+    #[msg("Session accounts must be passed as [session, user] pairs")]
+    InvalidSessionAccounts,
+    #[msg("Points-funded plays are disabled for this game")]
+    PointsPlayDisabled,
+    #[msg("Not enough loyalty points")]
+    InsufficientPoints,
+    #[msg("Game has no winnable prize probability")]
+    NoWinnableProbability,
+    #[msg("RNG audit capacity must be between 1 and 64")]
+    InvalidAuditCapacity,
+    #[msg("This game's RNG audit account must be provided")]
+    RngAuditMissing,
+    #[msg("Aggregate claims are disabled for this game")]
+    AggregateClaimDisabled,
+    #[msg("Pull count must be at least 1")]
+    InvalidPullCount,
+    #[msg("Fewer pulls than this game's minimum")]
+    BelowMinPulls,
+    #[msg("Promo campaign needs free plays, a per-user limit and a valid window")]
+    InvalidPromoCampaign,
+    #[msg("Promo campaign is not active")]
+    PromoNotActive,
+    #[msg("Promo campaign has no free plays left")]
+    PromoExhausted,
+    #[msg("Per-user promo limit reached")]
+    PromoLimitReached,
+    #[msg("Invalid batch size")]
+    InvalidBatchSize,
+    #[msg("Proof-of-work difficulty exceeds the maximum")]
+    InvalidPowDifficulty,
+    #[msg("Proof-of-work nonce does not meet the game's difficulty")]
+    InvalidProofOfWork,
+    #[msg("Token account is owned by the wrong wallet")]
+    WrongTokenOwner,
+    #[msg("Token account or mint does not match the expected mint")]
+    WrongMint,
+    #[msg("Treasury does not match the game's treasury")]
+    WrongTreasury,
+    #[msg("Invalid Metaplex Token Metadata program")]
+    InvalidMetaplexProgram,
+    #[msg("Account does not match its expected PDA or ATA derivation")]
+    WrongPdaDerivation,
+    #[msg("Signer or account is not the session user")]
+    WrongSessionUser,
+    #[msg("Account belongs to a different game")]
+    WrongGame,
+    #[msg("Owner does not hold the NFT")]
+    NftNotHeld,
+    #[msg("Account is not a game owned by this program")]
+    InvalidGameAccount,
+    #[msg("Creator share exceeds 10000 basis points")]
+    InvalidCreatorShare,
+    #[msg("Creator token account is required for this game")]
+    MissingCreatorTokenAccount,
+    #[msg("Prize has won sessions that are not claimed yet")]
+    PendingClaimsExist,
+    #[msg("Randomness source is not allowed by the game's randomness policy")]
+    RandomnessPolicyViolation,
+    #[msg("Prize can't be claimed until the game's claim cooldown has passed")]
+    ClaimTooSoon,
+    #[msg("Session seed must not be all zeros")]
+    InvalidSessionSeed,
+    #[msg("Prize pool already covers the prize's remaining supply")]
+    PrizePoolFull,
+    #[msg("Prize pool has no pre-minted NFTs left")]
+    PrizePoolEmpty,
+    #[msg("Game supply is locked; prizes can't be replenished")]
+    SupplyLocked,
+    #[msg("Token program must be SPL Token or Token-2022")]
+    InvalidTokenProgram,
+    #[msg("Tier-weighted selection and per-user prize caps can't be used in compliance mode")]
+    ComplianceModeConflict,
+    #[msg("Compliance log account is required in compliance mode")]
+    ComplianceLogMissing,
+    #[msg("A treasury cap needs an overflow destination")]
+    InvalidOverflowDestination,
+    #[msg("Game has no treasury cap")]
+    TreasuryCapNotSet,
+    #[msg("Treasury balance is not above the cap")]
+    NoTreasuryOverflow,
+    #[msg("Treasury has not delegated any tokens to the game for sweeping")]
+    OverflowNotDelegated,
+    #[msg("Another prize of this game already uses this metadata URI")]
+    DuplicateMetadataUri,
+    #[msg("Session PDA doesn't match the expected session the client derived")]
+    SessionPdaMismatch,
+    #[msg("Treasury accounts are required to refund pulls that found no supply")]
+    RefundAccountsMissing,
+    #[msg("Game's prize configuration is frozen")]
+    ConfigFrozen,
+    #[msg("Mint is not a recoverable half-minted prize")]
+    MintNotRecoverable,
+    #[msg("Royalty creators must be 1-5 distinct addresses with shares summing to 100")]
+    InvalidRoyaltyCreators,
+    #[msg("Replenish exceeds the game's supply ceiling")]
+    ExceedsSupplyCeiling,
+    #[msg("Account is not a play session")]
+    InvalidSessionAccount,
+    #[msg("The claim window for this win has ended")]
+    ClaimExpired,
+    #[msg("No session in the batch has expired")]
+    NothingToReap,
+    #[msg("Play has not passed its refund timeout")]
+    SessionNotExpired,
+    #[msg("Missing or invalid Pyth price account")]
+    InvalidPriceFeed,
+    #[msg("Price is older than the game's max price age")]
+    StalePrice,
+    #[msg("Token amount doesn't cover the game's USD cost")]
+    Underpaid,
+    #[msg("Token amount exceeds the authorized maximum")]
+    SlippageExceeded,
+    #[msg("Pity counter account required for this game")]
+    PityCounterMissing,
+    #[msg("Multi-pull plays aren't available for this game or instruction")]
+    MultiPullUnsupported,
+    #[msg("Game is active; pass allow_live_edit to change its odds")]
+    LiveOddsEdit,
+    #[msg("Prize has a per-user limit; pass the user's claim record for it")]
+    ClaimRecordMissing,
+    #[msg("Claim record belongs to another user")]
+    ClaimRecordMismatch,
+    #[msg("Game has not started yet")]
+    GameNotStarted,
+    #[msg("Game has ended")]
+    GameEnded,
+    #[msg("Game schedule must end after it starts")]
+    InvalidSchedule,
+    #[msg("Backend-signed randomness is disabled in this build")]
+    BackendRandomnessDisabled,
+    #[msg("Not a Switchboard randomness account")]
+    InvalidVrfAccount,
+    #[msg("Randomness account was not committed in the previous slot")]
+    VrfSeedStale,
+    #[msg("Play is already bound to a randomness account")]
+    VrfAlreadyRequested,
+    #[msg("Play has no bound randomness account")]
+    VrfNotRequested,
+    #[msg("Randomness account doesn't match the play's binding")]
+    VrfAccountMismatch,
+    #[msg("Randomness has not been revealed in this slot")]
+    VrfNotResolved,
+    #[msg("Play is bound to VRF; finalize it with its randomness account")]
+    VrfRequired,
+    #[msg("Reveal doesn't match the play's commitment")]
+    RevealMismatch,
+    #[msg("Play is committed; finalize it with its reveal")]
+    RevealRequired,
+    #[msg("Prize has a per-user cap or pool NFTs keyed to its address and can't be moved")]
+    PrizeNotMovable,
+    #[msg("Prize NFTs already carry this metadata URI; it can't change")]
+    PrizeUriLocked,
+    #[msg("Prize inventory account required to change the metadata URI")]
+    PrizeInventoryMissing,
+}
+
+// ============================================
+// Helper Functions
+// ============================================
+
+/// Convert first 8 bytes to u64 and normalize to 0..9999
+fn draw_from_random(random_value: &[u8; 32]) -> u16 {
+    let rand_u64 = u64::from_le_bytes(random_value[0..8].try_into().unwrap());
+    (rand_u64 % 10_000) as u16
+}
+
+/// Tier-weighted selection: pick a tier by `game.tier_bp`, falling back to lower tiers
+/// when it is sold out, then an in-stock prize of that tier weighted by supply
+fn select_tier_weighted_prize(game: &Game, random_value: &[u8; 32]) -> Option<usize> {
+    let draw = draw_from_random(random_value);
+    let mut cumulative: u16 = 0;
+    let mut chosen_tier = None;
+    for (tier, &bp) in game.tier_bp.iter().enumerate() {
+        cumulative = cumulative.saturating_add(bp);
+        if draw < cumulative {
+            chosen_tier = Some(tier);
+            break;
+        }
+    }
+    
+    // Independent bytes pick the prize within the tier
+    let pick = u64::from_le_bytes(random_value[8..16].try_into().unwrap());
+    let prize_count = game.prize_count as usize;
+    for tier in (0..=chosen_tier?).rev() {
+        let in_tier = |idx: &usize| game.prize_tiers[*idx] as usize == tier && game.prize_supply[*idx] > 0;
+        let tier_supply: u64 = (0..prize_count).filter(in_tier).map(|idx| game.prize_supply[idx] as u64).sum();
+        if tier_supply == 0 {
+            continue;
+        }
+        let mut target = pick % tier_supply;
+        for idx in (0..prize_count).filter(in_tier) {
+            let supply = game.prize_supply[idx] as u64;
+            if target < supply {
+                return Some(idx);
+            }
+            target -= supply;
+        }
+    }
+    None
+}
+
+/// finalize_play for a play_game_multi session. Draw i resolves from
+/// sha256(random_value || i) like a single play (pity and per-user caps included), and
+/// each drawn prize takes the next group of 11 remaining accounts ([0] Prize, then the
+/// finalize_play mint accounts) and is minted to the user at once. A draw past the user's
+/// cap is a loss that still takes its group. One claim record can be passed, so only one
+/// capped prize can be drawn per finalize. Once the game sells out, the remaining draws lose.
+/// The session records its first win and is claimed when any draw won.
+fn finalize_multi_draw<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FinalizePlay<'info>>,
+    random_value: [u8; 32],
+    source: RandomnessSource,
+) -> Result<()> {
+    let user_key = ctx.accounts.play_session.user;
+    let session_key = ctx.accounts.play_session.key();
+    let game_key = ctx.accounts.game.key();
+    let game_id = ctx.accounts.game.game_id;
+    let pity_threshold = ctx.accounts.game.pity_threshold;
+    require!(ctx.accounts.pity_counter.is_some() || pity_threshold == 0, ErrorCode::PityCounterMissing);
+    let now = Clock::get()?.unix_timestamp;
+    
+    let mut mint_groups = ctx.remaining_accounts.chunks_exact(11);
+    require!(mint_groups.remainder().is_empty(), ErrorCode::InvalidBatchSize);
+    let mut first_win = None;
+    for draw in 0..ctx.accounts.play_session.draws {
+        let draw_value = solana_sha256_hasher::hashv(&[&random_value, &[draw]]).to_bytes();
+        let game = &ctx.accounts.game;
+        let compliance_snapshot = game.compliance_mode.then(|| (game.prize_probabilities, game.prize_supply));
+        let pity_losses = ctx.accounts.pity_counter.as_ref().map_or(0, |pity_counter| pity_counter.losses);
+        let pity_prize = if pity_threshold > 0 && pity_losses >= pity_threshold {
+            best_in_stock_prize(game)
+        } else {
+            None
+        };
+        let mut winning_index = if game.total_supply_remaining == 0 {
+            None
+        } else {
+            pity_prize.or_else(|| select_winning_index(game, &draw_value))
+        };
+        
+        // Every drawn prize takes its group; a draw past the user's cap mints nothing
+        let mut drawn = None;
+        if let Some(prize_idx) = winning_index {
+            let group = mint_groups.next().ok_or(ErrorCode::PrizeNotFound)?;
+            let prize = Account::<Prize>::try_from(&group[0])?;
+            require!(prize.game == game_key, ErrorCode::WrongGame);
+            require!(prize.prize_index as usize == prize_idx, ErrorCode::PrizeNotFound);
+            if user_limit_reached(prize.key(), &prize, ctx.accounts.claim_record.as_deref())? {
+                emit!(UserLimitReached {
+                    user: user_key,
+                    game_id,
+                    session: session_key,
+                    prize_index: prize_idx as u8,
+                    max_per_user: prize.max_per_user,
+                    timestamp: now,
+                });
+                winning_index = None;
+            } else {
+                drawn = Some((prize_idx, group, prize));
+            }
+        }
+        
+        let mut won = None;
+        if let Some((prize_idx, group, mut prize)) = drawn {
+            require!(prize.supply_remaining > 0, ErrorCode::OutOfStock);
             prize.supply_remaining = prize.supply_remaining.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
-            prize.try_serialize(&mut *prize_data)?;
-            drop(prize_data);
-            
-            // ========== INLINE NFT MINTING ==========
-            // Get NFT minting accounts from remaining_accounts
-            let nft_mint = &ctx.remaining_accounts[1];
-            let metadata = &ctx.remaining_accounts[2];
-            let master_edition = &ctx.remaining_accounts[3];
-            let user_nft_token_account = &ctx.remaining_accounts[4];
-            let user_account = &ctx.remaining_accounts[5];
-            let token_program = &ctx.remaining_accounts[6];
-            let associated_token_program = &ctx.remaining_accounts[7];
-            let metaplex_program = &ctx.remaining_accounts[8];
-            let system_program = &ctx.remaining_accounts[9];
-            let rent = &ctx.remaining_accounts[10];
-            
-            let nft_mint_key = nft_mint.key();
-            let payer = &ctx.accounts.backend_authority;
-            let game_account = &ctx.accounts.game;
-            
-            // Verify Metaplex program
-            require!(
-                metaplex_program.key() == METAPLEX_TOKEN_METADATA_PROGRAM_ID,
-                ErrorCode::Unauthorized
-            );
-            
-            // Verify user account matches session user
-            require!(user_account.key() == user_key, ErrorCode::Unauthorized);
-            
-            // Derive and verify metadata PDA
-            let nft_mint_bytes = nft_mint_key.as_ref();
-            let metadata_seeds = &[
-                b"metadata",
-                METAPLEX_TOKEN_METADATA_PROGRAM_ID.as_ref(),
-                nft_mint_bytes,
-            ];
-            let (metadata_pda, _) = Pubkey::find_program_address(metadata_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
-            require!(metadata.key() == metadata_pda, ErrorCode::Unauthorized);
-            
-            // Derive and verify master edition PDA
-            let master_edition_seeds = &[
-                b"metadata",
-                METAPLEX_TOKEN_METADATA_PROGRAM_ID.as_ref(),
-                nft_mint_bytes,
-                b"edition",
-            ];
-            let (master_edition_pda, _) = Pubkey::find_program_address(master_edition_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
-            require!(master_edition.key() == master_edition_pda, ErrorCode::Unauthorized);
-            
-            // Verify user's token account
-            let expected_ata = get_associated_token_address(&user_key, &nft_mint_key);
-            require!(user_nft_token_account.key() == expected_ata, ErrorCode::Unauthorized);
-            
-            // Derive game PDA bump for signing
-            let (game_pda, game_bump) = Pubkey::find_program_address(
-                &[b"game", &game_id.to_le_bytes()],
-                program_id,
-            );
-            require!(game_key == game_pda, ErrorCode::Unauthorized);
-            
-            // Game PDA seeds for signing
-            let game_id_bytes = game_id.to_le_bytes();
-            let game_bump_array = [game_bump];
-            let game_seeds: &[&[u8]] = &[b"game", &game_id_bytes, &game_bump_array];
-            
-            // 1. Create mint account
-            let mint_rent = Rent::get()?.minimum_balance(82);
-            anchor_lang::system_program::create_account(
-                CpiContext::new(
-                    system_program.to_account_info(),
-                    anchor_lang::system_program::CreateAccount {
-                        from: payer.to_account_info(),
-                        to: nft_mint.to_account_info(),
-                    },
-                ),
-                mint_rent,
-                82,
-                &anchor_spl::token::ID,
-            )?;
-            
-            // 2. Initialize mint with game as authority
-            let init_mint_ix = anchor_spl::token::spl_token::instruction::initialize_mint(
-                &anchor_spl::token::ID,
-                &nft_mint_key,
-                &game_pda,
-                Some(&game_pda),
-                0,
-            )?;
-            anchor_lang::solana_program::program::invoke(
-                &init_mint_ix,
-                &[
-                    nft_mint.to_account_info(),
-                    rent.to_account_info(),
-                ],
-            )?;
-            
-            // 3. Create associated token account for user
-            anchor_spl::associated_token::create(
-                CpiContext::new(
-                    associated_token_program.to_account_info(),
-                    anchor_spl::associated_token::Create {
-                        payer: payer.to_account_info(),
-                        associated_token: user_nft_token_account.to_account_info(),
-                        authority: user_account.to_account_info(),
-                        mint: nft_mint.to_account_info(),
-                        system_program: system_program.to_account_info(),
-                        token_program: token_program.to_account_info(),
-                    },
-                ),
-            )?;
-            
-            // Build metadata
-            let data_v2 = DataV2 {
-                name: prize_name.clone(),
-                symbol: "PRIZE".to_string(),
-                uri: prize_metadata_uri.clone(),
-                seller_fee_basis_points: 0,
-                creators: None,
-                collection: None,
-                uses: None,
-            };
-            
-            // Get account infos
-            let game_info = game_account.to_account_info();
-            let payer_info = payer.to_account_info();
-            
-            // 4. Create metadata account
-            CreateMetadataAccountV3Cpi::new(
-                &metaplex_program.to_account_info(),
-                CreateMetadataAccountV3CpiAccounts {
-                    metadata: &metadata.to_account_info(),
-                    mint: &nft_mint.to_account_info(),
-                    mint_authority: &game_info,
-                    payer: &payer_info,
-                    update_authority: (&game_info, true),
-                    system_program: &system_program.to_account_info(),
-                    rent: Some(&rent.to_account_info()),
-                },
-                CreateMetadataAccountV3InstructionArgs {
-                    data: data_v2,
-                    is_mutable: false,
-                    collection_details: None,
-                },
-            ).invoke_signed(&[game_seeds])?;
+            let low_stock = prize.take_low_stock_warning();
+            prize.exit(ctx.program_id)?;
+            if low_stock {
+                emit!(LowStockWarning {
+                    game_id,
+                    prize_index: prize_idx as u8,
+                    prize_id: prize.prize_id,
+                    supply_remaining: prize.supply_remaining,
+                    threshold: prize.low_stock_threshold.unwrap_or_default(),
+                    timestamp: now,
+                });
+            }
             
-            // 5. Mint 1 token to user
-            let cpi_accounts = MintTo {
-                mint: nft_mint.to_account_info(),
-                to: user_nft_token_account.to_account_info(),
-                authority: game_info.clone(),
-            };
-            let signer_seeds: &[&[&[u8]]] = &[game_seeds];
-            token::mint_to(
-                CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds),
-                1,
+            let prize_name = prize_nft_name(game, &prize.name, &prize.tier);
+            let nft_mint = mint_nft_from_remaining_accounts(
+                ctx.program_id,
+                group,
+                &ctx.accounts.backend_authority.to_account_info(),
+                game,
+                user_key,
+                &prize_name,
+                &prize.metadata_uri,
             )?;
             
-            // 6. Create master edition
-            CreateMasterEditionV3Cpi::new(
-                &metaplex_program.to_account_info(),
-                CreateMasterEditionV3CpiAccounts {
-                    edition: &master_edition.to_account_info(),
-                    mint: &nft_mint.to_account_info(),
-                    update_authority: &game_info,
-                    mint_authority: &game_info,
-                    payer: &payer_info,
-                    metadata: &metadata.to_account_info(),
-                    token_program: &token_program.to_account_info(),
-                    system_program: &system_program.to_account_info(),
-                    rent: Some(&rent.to_account_info()),
-                },
-                CreateMasterEditionV3InstructionArgs { max_supply: None },
-            ).invoke_signed(&[game_seeds])?;
-            
-            (Some(nft_mint_key), Some(p_id), Some(p_tier))
-        } else {
-            (None, None, None)
-        };
-        
-        // Now do mutable borrows for session and game updates
-        let session = &mut ctx.accounts.play_session;
-        let game = &mut ctx.accounts.game;
-        
-        // Update session
-        session.is_fulfilled = true;
-        session.random_value = random_value;
-        session.prize_index = winning_index.map(|i| i as u8);
-        
-        // Update game stats
-        game.total_plays = game.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
-        game.last_random_value = random_value;
-        
-        // If won, update game supply and mark session claimed
-        if let Some(prize_idx) = winning_index {
-            game.total_supply_remaining = total_supply.saturating_sub(1);
+            let game = &mut ctx.accounts.game;
+            game.prize_supply[prize_idx] = prize.supply_remaining;
+            game.total_supply_remaining = game.total_supply_remaining.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
             if game.total_supply_remaining == 0 {
                 game.is_active = false;
+                emit!(GameSoldOut {
+                    game_id,
+                    total_plays: game.total_plays.saturating_add(1),
+                    timestamp: now,
+                });
             }
-            session.is_claimed = true;
-            
-            let nft_mint = nft_mint_result.unwrap();
-            let tier = prize_tier.clone().unwrap();
-            
-            emit!(PlayResolved {
-                user: user_key,
-                game_id,
-                session: session_key,
-                prize_id,
-                prize_index: Some(prize_idx as u8),
-                tier: prize_tier,
-                is_win: true,
-                random_value,
-                timestamp: Clock::get()?.unix_timestamp,
-            });
-            
+            if let Some(recent_winners) = ctx.accounts.recent_winners.as_mut() {
+                recent_winners.push(WinnerEntry {
+                    winner: user_key,
+                    prize_index: prize_idx as u8,
+                    timestamp: now,
+                });
+            }
+            let user_prize_wins = record_user_win(prize.key(), ctx.accounts.claim_record.as_deref_mut());
             emit!(PrizeClaimed {
                 user: user_key,
                 game_id,
                 session: session_key,
-                prize_id: prize_id.unwrap(),
+                prize_id: prize.prize_id,
                 prize_index: prize_idx as u8,
-                tier,
+                tier: prize.tier.clone(),
                 nft_mint,
-                timestamp: Clock::get()?.unix_timestamp,
+                timestamp: now,
+                is_physical: prize.is_physical,
+                cost_usd: prize.cost_usd,
+                amount_paid: ctx.accounts.play_session.amount_paid,
+                user_prize_wins,
             });
-        } else {
-            emit!(PlayResolved {
+            first_win = first_win.or(Some(prize_idx as u8));
+            won = Some((prize.prize_id, prize.tier.clone()));
+        }
+        
+        let game = &mut ctx.accounts.game;
+        game.total_plays = game.total_plays.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        if let Some(pity_counter) = ctx.accounts.pity_counter.as_mut() {
+            pity_counter.losses = match winning_index {
+                Some(_) => 0,
+                None => pity_counter.losses.saturating_add(1),
+            };
+        }
+        if let Some(prize_idx) = pity_prize.filter(|_| winning_index.is_some()) {
+            emit!(PityTriggered {
                 user: user_key,
                 game_id,
                 session: session_key,
-                prize_id: None,
-                prize_index: None,
-                tier: None,
-                is_win: false,
-                random_value,
-                timestamp: Clock::get()?.unix_timestamp,
+                prize_index: prize_idx as u8,
+                losses: pity_losses,
+                timestamp: now,
             });
         }
-        
-        Ok(())
-    }
-
-    /// Claim prize - mints NFT after randomness fulfilled and user won
-    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
-        // Read session data first (immutable borrow)
-        let session_key = ctx.accounts.play_session.key();
-        let is_fulfilled = ctx.accounts.play_session.is_fulfilled;
-        let prize_idx_opt = ctx.accounts.play_session.prize_index;
-        let is_claimed = ctx.accounts.play_session.is_claimed;
-        let session_user = ctx.accounts.play_session.user;
-        let session_game = ctx.accounts.play_session.game;
-        
-        let game = &ctx.accounts.game;
-        let prize = &ctx.accounts.prize;
-        let user_key = ctx.accounts.user.key();
-        
-        // Validate session state
-        require!(is_fulfilled, ErrorCode::NotFulfilled);
-        require!(prize_idx_opt.is_some(), ErrorCode::NoPrize);
-        require!(!is_claimed, ErrorCode::AlreadyClaimed);
-        require!(session_user == user_key, ErrorCode::Unauthorized);
-        require!(session_game == game.key(), ErrorCode::Unauthorized);
-        
-        let prize_idx = prize_idx_opt.unwrap();
-        
-        // Validate prize
-        require!(prize.game == game.key(), ErrorCode::Unauthorized);
-        require!(prize.prize_index == prize_idx, ErrorCode::PrizeNotFound);
-        
-        // Store values needed for event
-        let game_id = game.game_id;
-        let prize_id = prize.prize_id;
-        let prize_tier = prize.tier.clone();
-        let prize_name = prize.name.clone();
-        let prize_metadata_uri = prize.metadata_uri.clone();
-        
-        // Mint NFT to user
-        let nft_mint = mint_prize_nft_for_claim(
-            &ctx,
-            &prize_name,
-            &prize_metadata_uri,
-            &prize_tier,
-            game_id,
-            prize_id,
-        )?;
-        
-        // Now mark as claimed (mutable borrow)
-        ctx.accounts.play_session.is_claimed = true;
-        
-        emit!(PrizeClaimed {
+        let prize_index = winning_index.map(|i| i as u8);
+        match ctx.accounts.rng_audit.as_mut() {
+            Some(rng_audit) => rng_audit.push(RngDraw {
+                session: session_key,
+                random_value: draw_value,
+                draw: draw_from_random(&draw_value),
+                prize_index,
+            }),
+            None => require!(!game.rng_audit_enabled, ErrorCode::RngAuditMissing),
+        }
+        if let Some((probabilities, supplies)) = compliance_snapshot {
+            let compliance_log = ctx.accounts.compliance_log.as_mut().ok_or(ErrorCode::ComplianceLogMissing)?;
+            record_compliance_draw(compliance_log, game_id, ComplianceDraw {
+                session: session_key,
+                source,
+                random_value: draw_value,
+                probabilities,
+                supplies,
+                prize_count: game.prize_count,
+                prize_index,
+            })?;
+        }
+        let (prize_id, tier) = match won {
+            Some((prize_id, tier)) => (Some(prize_id), Some(tier)),
+            None => (None, None),
+        };
+        emit!(PlayResolved {
             user: user_key,
             game_id,
             session: session_key,
             prize_id,
-            prize_index: prize_idx,
-            tier: prize_tier,
-            nft_mint,
-            timestamp: Clock::get()?.unix_timestamp,
+            prize_index,
+            tier,
+            is_win: prize_index.is_some(),
+            random_value: draw_value,
+            timestamp: now,
         });
-        
-        Ok(())
     }
+    // Every win group must have been used
+    require!(mint_groups.next().is_none(), ErrorCode::InvalidBatchSize);
+    
+    ctx.accounts.game.last_random_value = random_value;
+    let session = &mut ctx.accounts.play_session;
+    session.is_fulfilled = true;
+    session.random_value = random_value;
+    session.prize_index = first_win;
+    session.is_claimed = first_win.is_some();
+    session.resolved_at = now;
+    Ok(())
+}
+
+/// True when winning `prize` would take the user past its max_per_user. Capped prizes
+/// need the user's claim record for that prize (ClaimRecordMissing otherwise).
+fn user_limit_reached(prize_key: Pubkey, prize: &Prize, claim_record: Option<&Account<PrizeClaimRecord>>) -> Result<bool> {
+    if prize.max_per_user == 0 {
+        return Ok(false);
+    }
+    let claim_record = claim_record
+        .filter(|claim_record| claim_record.prize == prize_key)
+        .ok_or(ErrorCode::ClaimRecordMissing)?;
+    Ok(claim_record.wins >= prize.max_per_user)
+}
 
-    /// Close a play session (returns rent after claiming or if lost)
-    pub fn close_play_session(ctx: Context<ClosePlaySession>) -> Result<()> {
-        let session = &ctx.accounts.play_session;
-        
-        // Can only close if fulfilled and either claimed or lost
-        require!(session.is_fulfilled, ErrorCode::NotFulfilled);
-        if session.prize_index.is_some() {
-            require!(session.is_claimed, ErrorCode::NotClaimed);
+/// Count a win of `prize_key` on the passed claim record (if it is that prize's);
+/// returns the user's updated win count
+fn record_user_win(prize_key: Pubkey, claim_record: Option<&mut Account<PrizeClaimRecord>>) -> Option<u32> {
+    let claim_record = claim_record.filter(|claim_record| claim_record.prize == prize_key)?;
+    claim_record.wins = claim_record.wins.saturating_add(1);
+    Some(claim_record.wins)
+}
+
+/// Pity pick: the in-stock prize of the highest tier (the lowest index among equals)
+fn best_in_stock_prize(game: &Game) -> Option<usize> {
+    (0..game.prize_count as usize)
+        .filter(|&idx| game.prize_supply[idx] > 0)
+        .max_by_key(|&idx| (game.prize_tiers[idx], std::cmp::Reverse(idx)))
+}
+
+/// True when a tier-weighted draw picked a tier (rather than the loss remainder), so a
+/// None from select_tier_weighted_prize means that tier and all below it were sold out
+fn tier_draw_hits(game: &Game, random_value: &[u8; 32]) -> bool {
+    let tiers_bp: u32 = game.tier_bp.iter().map(|&bp| bp as u32).sum();
+    (draw_from_random(random_value) as u32) < tiers_bp
+}
+
+/// Odds of each prize and of losing for the next play, in basis points summing to 10000.
+///
+/// Selection (`select_prize_index_instock`, `compliance_select`) never draws a sold-out
+/// prize: sold-out prizes drop out and the rest (losing included) is scaled by the live
+/// denominator `10000 - sold-out probability`. With nothing sold out this is the
+/// configured probabilities plus the unassigned remainder as loss odds. Per-prize odds
+/// are floored; the rounding remainder goes to the loss odds. Tier-weighted games select
+/// by `tier_bp` instead and are not described by this.
+pub fn effective_odds(
+    probabilities: &[u16; MAX_PRIZES],
+    supplies: &[u32; MAX_PRIZES],
+    prize_count: u8,
+) -> ([u16; MAX_PRIZES], u16) {
+    let count = (prize_count as usize).min(MAX_PRIZES);
+    let in_stock = |idx: &usize| supplies[*idx] > 0;
+    let live_bp = live_range_bp(probabilities, supplies, count);
+
+    let mut odds = [0u16; MAX_PRIZES];
+    if live_bp == 0 {
+        return (odds, 10_000);
+    }
+    let mut prizes_total: u32 = 0;
+    for idx in (0..count).filter(in_stock) {
+        // probability * 10000 fits in u32 (65535 * 10000 < 2^32)
+        let bp = probabilities[idx] as u32 * 10_000 / live_bp;
+        odds[idx] = bp as u16;
+        prizes_total += bp;
+    }
+    (odds, (10_000 - prizes_total) as u16)
+}
+
+/// `10000 - probability of sold-out prizes`: the draw range that can still resolve.
+/// Never below the in-stock total, so odds can't exceed 10000 even if the
+/// probabilities were misconfigured.
+fn live_range_bp(probabilities: &[u16; MAX_PRIZES], supplies: &[u32; MAX_PRIZES], count: usize) -> u32 {
+    let in_stock = |idx: &usize| supplies[*idx] > 0;
+    let in_stock_bp: u32 = (0..count).filter(in_stock).map(|idx| probabilities[idx] as u32).sum();
+    let sold_out_bp: u32 = (0..count).filter(|idx| !in_stock(idx)).map(|idx| probabilities[idx] as u32).sum();
+    10_000u32.saturating_sub(sold_out_bp).max(in_stock_bp)
+}
+
+/// Uniform draw in 0..bound (bound > 0) without modulo bias. Each 8-byte chunk of
+/// `random_value` is tried in turn and rejected if it falls below 2^64 mod bound, the
+/// values that would over-represent low results. All four chunks are rejected with
+/// probability below 2^-200; the last one is then used as is, so the result is always a
+/// pure function of the input.
+pub fn unbiased_draw(random_value: &[u8; 32], bound: u32) -> u32 {
+    let bound = bound as u64;
+    let threshold = bound.wrapping_neg() % bound;
+    let mut value = 0;
+    for chunk in random_value.chunks_exact(8) {
+        value = u64::from_le_bytes(chunk.try_into().unwrap());
+        if value >= threshold {
+            break;
         }
-        
-        // Account will be closed by close = user attribute
-        Ok(())
     }
+    (value % bound) as u32
 }
 
-// ============================================
-// Account Structures
-// ============================================
+/// Compliance-mode selection: an unbiased draw over the live range (see `effective_odds`),
+/// walked through the in-stock prizes in index order; a draw past them is a loss.
+/// Depends only on its arguments, which are exactly what a ComplianceDraw records.
+pub fn compliance_select(
+    random_value: &[u8; 32],
+    probabilities: &[u16; MAX_PRIZES],
+    supplies: &[u32; MAX_PRIZES],
+    prize_count: u8,
+) -> Option<usize> {
+    let count = (prize_count as usize).min(MAX_PRIZES);
+    let live_bp = live_range_bp(probabilities, supplies, count);
+    if live_bp == 0 {
+        return None;
+    }
+    in_stock_prize_at(probabilities, supplies, count, unbiased_draw(random_value, live_bp))
+}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum PrizeTier {
-    Common,
-    Uncommon,
-    Rare,
-    Legendary,
+/// The in-stock prize whose cumulative probability range holds `draw` (None = loss)
+fn in_stock_prize_at(probabilities: &[u16; MAX_PRIZES], supplies: &[u32; MAX_PRIZES], count: usize, draw: u32) -> Option<usize> {
+    let mut cumulative: u32 = 0;
+    for idx in (0..count).filter(|idx| supplies[*idx] > 0 && probabilities[*idx] > 0) {
+        cumulative += probabilities[idx] as u32;
+        if draw < cumulative {
+            return Some(idx);
+        }
+    }
+    None
 }
 
-#[account]
-pub struct Config {
-    pub authority: Pubkey,
-    pub bump: u8,
+/// Reproduce a recorded compliance draw: true when its inputs select exactly its outcome
+pub fn verify_compliance_draw(draw: &ComplianceDraw) -> bool {
+    compliance_select(&draw.random_value, &draw.probabilities, &draw.supplies, draw.prize_count)
+        .map(|idx| idx as u8)
+        == draw.prize_index
 }
 
-/// Game account - now lightweight without embedded prizes
-/// Size: 8 + 32 + 8 + (4+50) + (4+200) + (4+200) + 32 + 8 + 32 + 1 + (16*2) + 4 + 8 + 1 + 32 + 1 = ~650 bytes
-#[account]
-pub struct Game {
-    pub authority: Pubkey,           // 32
-    pub game_id: u64,                // 8
-    pub name: String,                // 4 + 50 max
-    pub description: String,         // 4 + 200 max
-    pub image_url: String,           // 4 + 200 max
-    pub token_mint: Pubkey,          // 32
-    pub cost_usd: u64,               // 8
-    pub treasury: Pubkey,            // 32
-    pub prize_count: u8,             // 1 - Number of prizes
-    pub prize_probabilities: [u16; MAX_PRIZES], // 32 - Probabilities for quick selection
-    pub total_supply_remaining: u32, // 4 - Track total supply across all prizes
-    pub total_plays: u64,            // 8
-    pub is_active: bool,             // 1
-    pub last_random_value: [u8; 32], // 32
-    pub bump: u8,                    // 1
+/// Append a compliance draw to the log and emit it
+fn record_compliance_draw(compliance_log: &mut ComplianceLog, game_id: u64, draw: ComplianceDraw) -> Result<()> {
+    compliance_log.push(draw);
+    emit!(DrawAudited {
+        game_id,
+        draw,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
 }
 
-/// Prize account - separate PDA for each prize
-/// Size: 8 + 32 + 1 + 8 + (4+50) + (4+150) + (4+200) + (4+200) + (4+50) + 1 + 2 + 8 + 4 + 2 + 2 + 2 + 4 + 4 + 1 = ~760 bytes
-#[account]
-pub struct Prize {
-    pub game: Pubkey,                // 32 - Parent game
-    pub prize_index: u8,             // 1 - Index in game's probability array
-    pub prize_id: u64,               // 8 - Unique ID for this prize
-    pub name: String,                // 4 + 50 max
-    pub description: String,         // 4 + 150 max
-    pub image_url: String,           // 4 + 200 max
-    pub metadata_uri: String,        // 4 + 200 max
-    pub physical_sku: String,        // 4 + 50 max
-    pub tier: PrizeTier,             // 1
-    pub probability_bp: u16,         // 2
-    pub cost_usd: u64,               // 8
-    pub weight_grams: u32,           // 4
-    pub length_hundredths: u16,      // 2 - Length in hundredths of an inch (650 = 6.50")
-    pub width_hundredths: u16,       // 2 - Width in hundredths of an inch
-    pub height_hundredths: u16,      // 2 - Height in hundredths of an inch
-    pub supply_total: u32,           // 4
-    pub supply_remaining: u32,       // 4
-    pub bump: u8,                    // 1
+/// First 8 bytes of sha256(metadata_uri), the per-prize key for unique_metadata_uris
+fn metadata_uri_hash(metadata_uri: &str) -> u64 {
+    let hash = solana_sha256_hasher::hash(metadata_uri.as_bytes()).to_bytes();
+    u64::from_le_bytes(hash[..8].try_into().unwrap())
 }
 
-/// PlaySession account - tracks a pending play awaiting backend finalization
-/// Size: 8 + 32 + 32 + 8 + 32 + 1 + 32 + 2 + 1 + 1 = 151 bytes
-#[account]
-pub struct PlaySession {
-    pub user: Pubkey,                // 32 - User who paid
-    pub game: Pubkey,                // 32 - Game being played
-    pub amount_paid: u64,            // 8  - Token amount paid
-    pub session_seed: [u8; 32],      // 32 - Unique seed for PDA derivation
-    pub is_fulfilled: bool,          // 1  - Has randomness been provided by backend
-    pub random_value: [u8; 32],      // 32 - Random bytes (after fulfillment)
-    pub prize_index: Option<u8>,     // 1 + 1 = 2 - Winning prize index (None = lost)
-    pub is_claimed: bool,            // 1  - Has prize been claimed (NFT minted)
-    pub bump: u8,                    // 1
+/// Winning prize for a random value under the game's selection mode: the compliance
+/// draw, tier weights, or the flat probabilities over in-stock prizes (None = loss)
+fn select_winning_index(game: &Game, random_value: &[u8; 32]) -> Option<usize> {
+    if game.compliance_mode {
+        compliance_select(random_value, &game.prize_probabilities, &game.prize_supply, game.prize_count)
+    } else if game.tier_weighted {
+        select_tier_weighted_prize(game, random_value)
+    } else {
+        select_prize_index_instock(&game.prize_probabilities, &game.prize_supply, game.prize_count, random_value)
+    }
 }
 
-// ============================================
-// Account Contexts
-// ============================================
+/// Select a prize by the flat probabilities with sold-out prizes zeroed out: the draw
+/// covers the live range (see `effective_odds`) instead of 0..10000, so the remaining
+/// prizes and the loss keep their relative odds and a sold-out prize is never drawn.
+/// With everything in stock this is the plain cumulative draw over 0..10000.
+fn select_prize_index_instock(
+    probabilities: &[u16; MAX_PRIZES],
+    supplies: &[u32; MAX_PRIZES],
+    prize_count: u8,
+    random_value: &[u8; 32],
+) -> Option<usize> {
+    let count = (prize_count as usize).min(MAX_PRIZES);
+    let live_bp = live_range_bp(probabilities, supplies, count);
+    if live_bp == 0 {
+        return None;
+    }
+    let rand_u64 = u64::from_le_bytes(random_value[0..8].try_into().unwrap());
+    in_stock_prize_at(probabilities, supplies, count, (rand_u64 % live_bp as u64) as u32)
+}
 
-#[derive(Accounts)]
-pub struct InitializeProgram<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 1,
-        seeds = [b"config"],
-        bump
-    )]
-    pub config: Account<'info, Config>,
-    pub system_program: Program<'info, System>,
+/// Account infos used to mint a claimed prize NFT. The payer covers rent and
+/// may differ from the recipient (see `claim_prize_sponsored`).
+struct ClaimMintAccounts<'info> {
+    game: AccountInfo<'info>,
+    recipient: Pubkey,
+    payer: AccountInfo<'info>,
+    nft_mint: AccountInfo<'info>,
+    metadata: AccountInfo<'info>,
+    master_edition: AccountInfo<'info>,
+    recipient_nft_token_account: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    metaplex_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
 }
 
-#[derive(Accounts)]
-pub struct UpdateProgramAuthority<'info> {
-    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
-    pub config: Account<'info, Config>,
-    pub authority: Signer<'info>,
+/// Check that a session holds an unclaimed win for `prize` and return its index
+fn validate_claim(session: &PlaySession, game: &Account<Game>, prize: &Prize) -> Result<u8> {
+    let game_key = game.key();
+    require!(session.is_fulfilled, ErrorCode::NotFulfilled);
+    let prize_idx = session.prize_index.ok_or(ErrorCode::NoPrize)?;
+    require!(!session.is_claimed, ErrorCode::AlreadyClaimed);
+    require!(session.pending_claim_mint.is_none(), ErrorCode::ClaimInProgress);
+    require!(session.game == game_key, ErrorCode::WrongGame);
+    require!(prize.game == game_key, ErrorCode::WrongGame);
+    require!(prize.prize_index == prize_idx, ErrorCode::PrizeNotFound);
+    let now = Clock::get()?.unix_timestamp;
+    let claimable_at = session.resolved_at.saturating_add(game.claim_cooldown_seconds as i64);
+    require!(now >= claimable_at, ErrorCode::ClaimTooSoon);
+    if let Some(expires_at) = claim_expires_at(session, game) {
+        require!(now < expires_at, ErrorCode::ClaimExpired);
+    }
+    Ok(prize_idx)
 }
 
-#[derive(Accounts)]
-#[instruction(game_id: u64)]
-pub struct InitializeGame<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(seeds = [b"config"], bump = config.bump)]
-    pub config: Account<'info, Config>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 8 + (4+50) + (4+200) + (4+200) + 32 + 8 + 32 + 1 + (MAX_PRIZES*2) + 4 + 8 + 1 + 32 + 1 + 100, // +100 padding
-        seeds = [b"game", game_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    /// CHECK: Treasury wallet
-    pub treasury: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+/// When an unclaimed win of `session` expires: `claim_window_secs` after it became
+/// claimable. None when the game has no window, or the session resolved before
+/// resolution times were recorded.
+fn claim_expires_at(session: &PlaySession, game: &Game) -> Option<i64> {
+    if game.claim_window_secs == 0 || session.resolved_at == 0 {
+        return None;
+    }
+    Some(
+        session.resolved_at
+            .saturating_add(game.claim_cooldown_seconds as i64)
+            .saturating_add(game.claim_window_secs as i64),
+    )
 }
 
-#[derive(Accounts)]
-#[instruction(prize_index: u8)]
-pub struct AddPrize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
-    pub game: Account<'info, Game>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 1 + 8 + (4+50) + (4+150) + (4+200) + (4+200) + (4+50) + 1 + 2 + 8 + 4 + 2 + 2 + 2 + 4 + 4 + 1 + 50, // +50 padding, includes dimension fields
-        seeds = [b"prize", game.key().as_ref(), &[prize_index]],
-        bump
-    )]
-    pub prize: Account<'info, Prize>,
-    pub system_program: Program<'info, System>,
+/// When an unfulfilled play may be refunded: its expiry after payment, or the game's
+/// current refund timeout for plays opened without one. None when neither is set, or the
+/// play was opened before payment times were recorded.
+fn refund_expires_at(session: &PlaySession, game: &Game) -> Option<i64> {
+    let timeout = if session.expiry_seconds > 0 {
+        session.expiry_seconds
+    } else {
+        game.refund_timeout_secs as i64
+    };
+    if timeout == 0 || session.created_at == 0 {
+        return None;
+    }
+    Some(session.created_at.saturating_add(timeout))
 }
 
-#[derive(Accounts)]
-#[instruction(token_amount: u64, session_seed: [u8; 32])]
-pub struct PlayGame<'info> {
-    #[account(mut)]
-    pub game: Account<'info, Game>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut)]
-    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: Token mint account - validated in instruction
-    pub token_mint: AccountInfo<'info>,
-    
-    // PlaySession PDA - unique per game + user + session_seed
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 8 + 32 + 1 + 32 + 2 + 1 + 1 + 50, // +50 padding
-        seeds = [b"session", game.key().as_ref(), user.key().as_ref(), &session_seed],
-        bump
-    )]
-    pub play_session: Account<'info, PlaySession>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+/// Refund a timed-out play's amount_paid from the treasury with the game PDA as delegate,
+/// and close it out as a fulfilled loss. Returns the refunded amount. The caller checks
+/// that `user_token_info` is the session user's account for the game's mint.
+fn refund_timed_out_play<'info>(
+    session: &mut PlaySession,
+    user_token_info: &AccountInfo<'info>,
+    treasury_token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_mint: &InterfaceAccount<'info, anchor_spl::token_interface::Mint>,
+    game: &Account<'info, Game>,
+    token_program: &Interface<'info, TokenInterface>,
+    now: i64,
+) -> Result<u64> {
+    let refund = session.amount_paid;
+    if refund > 0 {
+        let game_id_bytes = game.game_id.to_le_bytes();
+        let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &[game.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: treasury_token_account.to_account_info(),
+                    mint: token_mint.to_account_info(),
+                    to: user_token_info.clone(),
+                    authority: game.to_account_info(),
+                },
+                &[game_seeds],
+            ),
+            refund,
+            token_mint.decimals,
+        )?;
+    }
+    session.is_fulfilled = true;
+    session.resolved_at = now;
+    session.amount_paid = 0;
+    session.timeout_refunded = true;
+    Ok(refund)
 }
 
-#[derive(Accounts)]
-pub struct UpdateGame<'info> {
-    #[account(mut, has_one = authority)]
-    pub game: Account<'info, Game>,
-    pub authority: Signer<'info>,
+/// Name and metadata_uri a claim mints: the prize as it was when the play resolved,
+/// or the live prize for sessions resolved without a snapshot
+fn won_prize_metadata<'a>(session: &'a PlaySession, prize: &'a Prize) -> (&'a str, &'a str) {
+    match &session.prize_snapshot {
+        Some(snapshot) => (&snapshot.name, &snapshot.metadata_uri),
+        None => (&prize.name, &prize.metadata_uri),
+    }
 }
 
-#[derive(Accounts)]
-pub struct ReplenishPrizeSupply<'info> {
-    #[account(mut, has_one = authority)]
-    pub game: Account<'info, Game>,
-    #[account(mut)]
-    pub prize: Account<'info, Prize>,
-    pub authority: Signer<'info>,
+/// Reject plays on games whose prize probabilities sum to 0 (every draw would lose)
+fn require_winnable(game: &Game) -> Result<()> {
+    let total: u32 = if game.tier_weighted {
+        game.tier_bp.iter().map(|&bp| bp as u32).sum()
+    } else {
+        game.prize_probabilities[..game.prize_count as usize]
+            .iter()
+            .map(|&p| p as u32)
+            .sum()
+    };
+    require!(total > 0, ErrorCode::NoWinnableProbability);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct WithdrawTreasury<'info> {
-    #[account(has_one = authority, has_one = treasury @ ErrorCode::Unauthorized)]
-    pub game: Account<'info, Game>,
-    pub authority: Signer<'info>,
-    /// CHECK: Treasury wallet - must match game.treasury and sign the transaction
-    #[account(
-        mut,
-        constraint = treasury.key() == game.treasury @ ErrorCode::Unauthorized
-    )]
-    pub treasury: Signer<'info>,
-    #[account(
-        mut,
-        constraint = treasury_token_account.mint == game.token_mint @ ErrorCode::Unauthorized,
-        constraint = treasury_token_account.owner == treasury.key() @ ErrorCode::Unauthorized
-    )]
-    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut, constraint = destination_token_account.mint == game.token_mint @ ErrorCode::Unauthorized)]
-    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: Token mint account - needed for transfer_checked
-    pub token_mint: AccountInfo<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
+/// Whether the next play of `game` can win `prize`: the game is active with supply left,
+/// the prize is in stock, and selection gives it a non-zero chance. Under tier weights
+/// that means its tier, or a higher weighted tier whose draws fall back to it because
+/// every tier in between is sold out. Outside the game's schedule nothing is winnable.
+pub fn is_winnable(prize: &Prize, game: &Game, now: i64) -> bool {
+    if !game.is_active || !game.in_schedule(now) || game.total_supply_remaining == 0 || prize.supply_remaining == 0 {
+        return false;
+    }
+    if !game.tier_weighted {
+        return game.prize_probabilities[prize.prize_index as usize] > 0;
+    }
+    let prize_count = game.prize_count as usize;
+    let tier_in_stock = |tier: usize| {
+        (0..prize_count).any(|idx| game.prize_tiers[idx] as usize == tier && game.prize_supply[idx] > 0)
+    };
+    let prize_tier = prize.tier.clone() as usize;
+    for tier in prize_tier..TIER_COUNT {
+        if tier > prize_tier && tier_in_stock(tier) {
+            // Draws of this tier and above stop here before reaching the prize's tier
+            return false;
+        }
+        if game.tier_bp[tier] > 0 {
+            return true;
+        }
+    }
+    false
 }
 
-#[derive(Accounts)]
-pub struct CloseGame<'info> {
-    #[account(mut, has_one = authority @ ErrorCode::Unauthorized, close = authority)]
-    pub game: Account<'info, Game>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
+/// A schedule must end after it starts
+fn require_valid_schedule(starts_at: Option<i64>, ends_at: Option<i64>) -> Result<()> {
+    if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+        require!(starts_at < ends_at, ErrorCode::InvalidSchedule);
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct ClosePrize<'info> {
-    #[account(has_one = authority @ ErrorCode::Unauthorized)]
-    pub game: Account<'info, Game>,
-    #[account(mut, constraint = prize.game == game.key(), close = authority)]
-    pub prize: Account<'info, Prize>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
+/// Reject purchases of fewer pulls than the game's configured minimum
+fn require_min_pulls(game: &Game, pulls: u8) -> Result<()> {
+    require!(pulls >= game.min_pull_count.max(1), ErrorCode::BelowMinPulls);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct FinalizePlay<'info> {
-    #[account(
-        mut,
-        constraint = play_session.game == game.key() @ ErrorCode::Unauthorized
-    )]
-    pub play_session: Account<'info, PlaySession>,
-    #[account(mut)]
-    pub game: Account<'info, Game>,
-    #[account(seeds = [b"config"], bump = config.bump)]
-    pub config: Account<'info, Config>,
-    /// Backend authority must co-sign - pays for NFT minting on wins
-    #[account(
-        mut,
-        constraint = backend_authority.key() == config.authority @ ErrorCode::Unauthorized
-    )]
-    pub backend_authority: Signer<'info>,
-    // NOTE: For wins, additional accounts passed via remaining_accounts:
-    // [0] Prize, [1] NFT mint (signer), [2] Metadata PDA, [3] Master Edition PDA,
-    // [4] User's ATA, [5] User account, [6] Token Program, [7] Associated Token Program,
-    // [8] Metaplex Program, [9] System Program, [10] Rent
+/// Decimals of an SPL Token or Token-2022 mint. The base mint is parsed through
+/// StateWithExtensions rather than read at a fixed byte offset, so mints carrying a TLV
+/// extension region (transfer fee, interest-bearing, ...) decode the same way.
+fn mint_decimals(mint: &AccountInfo) -> Result<u8> {
+    use anchor_spl::token_2022::spl_token_2022::{extension::StateWithExtensions, state::Mint};
+    require!(
+        *mint.owner == anchor_spl::token::ID || *mint.owner == anchor_spl::token_2022::ID,
+        ErrorCode::InvalidTokenProgram
+    );
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data).map_err(|_| ErrorCode::WrongMint)?;
+    Ok(mint.base.decimals)
 }
 
-#[derive(Accounts)]
-pub struct ClaimPrize<'info> {
-    #[account(
-        mut,
-        constraint = play_session.game == game.key() @ ErrorCode::Unauthorized,
-        constraint = play_session.user == user.key() @ ErrorCode::Unauthorized
-    )]
-    pub play_session: Account<'info, PlaySession>,
-    pub game: Account<'info, Game>,
-    #[account(
-        constraint = prize.game == game.key() @ ErrorCode::Unauthorized
-    )]
-    pub prize: Account<'info, Prize>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    // NFT Minting accounts
-    /// CHECK: Mint account for the NFT
-    #[account(mut)]
-    pub nft_mint: UncheckedAccount<'info>,
-    /// CHECK: Metadata PDA
-    #[account(mut)]
-    pub metadata: UncheckedAccount<'info>,
-    /// CHECK: Master Edition PDA
-    #[account(mut)]
-    pub master_edition: UncheckedAccount<'info>,
-    /// CHECK: User's NFT token account
-    #[account(mut)]
-    pub user_nft_token_account: UncheckedAccount<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    /// CHECK: Metaplex Token Metadata program
-    pub metaplex_token_metadata_program: UncheckedAccount<'info>,
-    /// CHECK: Rent sysvar
-    pub rent: UncheckedAccount<'info>,
+/// Token accounts a paid play moves the payment between
+struct PlayPaymentAccounts<'a, 'info> {
+    user: &'a Signer<'info>,
+    user_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+    treasury_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+    creator_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    token_mint: &'a AccountInfo<'info>,
+    token_program: &'a Interface<'info, TokenInterface>,
+    price_update: Option<&'a UncheckedAccount<'info>>,
 }
 
-#[derive(Accounts)]
-pub struct ClosePlaySession<'info> {
-    #[account(
-        mut,
-        constraint = play_session.user == user.key() @ ErrorCode::Unauthorized,
-        close = user
-    )]
-    pub play_session: Account<'info, PlaySession>,
-    #[account(mut)]
-    pub user: Signer<'info>,
+/// What collect_play_payment charged and where it went
+struct PlayPayment {
+    token_amount: u64, // charged to the user
+    treasury_amount: u64,
+    creator_amount: u64,
+    decimals: u8,
+    net_amount: u64,   // what the treasury received: treasury_amount less Token-2022 transfer fees
 }
 
-// ============================================
-// Events
-// ============================================
+/// Validate a paid play's token accounts and charge the user for `pulls` pulls: `token_amount`,
+/// or for games priced through an oracle cost_usd at the current price. The charge is checked
+/// against `max_token_amount` before anything moves. The creator's share goes to the creator
+/// and the rest to the treasury.
+fn collect_play_payment(
+    game: &Game,
+    accounts: &PlayPaymentAccounts,
+    token_amount: u64,
+    pulls: u8,
+    max_token_amount: u64,
+) -> Result<PlayPayment> {
+    require!(accounts.user_token_account.owner == accounts.user.key(), ErrorCode::WrongTokenOwner);
+    require!(accounts.user_token_account.mint == game.token_mint, ErrorCode::WrongMint);
+    require!(accounts.treasury_token_account.mint == game.token_mint, ErrorCode::WrongMint);
+    let decimals = game.token_decimals;
+
+    // Games priced through an oracle charge exactly cost_usd at the current price
+    let token_amount = if game.price_feed != Pubkey::default() {
+        let price_update = accounts.price_update.ok_or(ErrorCode::InvalidPriceFeed)?;
+        require_keys_eq!(price_update.key(), game.price_feed, ErrorCode::InvalidPriceFeed);
+        required_token_amount(game, price_update, decimals)?
+            .checked_mul(pulls as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        token_amount
+    };
+    // Slippage guard: never charge more than the user authorized
+    require!(token_amount <= max_token_amount, ErrorCode::SlippageExceeded);
+
+    // Collaboration games route creator_share_bps of the payment to the creator
+    let creator_amount = creator_share(game, token_amount)?;
+    let treasury_amount = token_amount - creator_amount;
+
+    // Transfer tokens from user to treasury using token interface (supports both Token and Token-2022)
+    if treasury_amount > 0 {
+        let cpi_accounts = TransferChecked {
+            from: accounts.user_token_account.to_account_info(),
+            mint: accounts.token_mint.to_account_info(),
+            to: accounts.treasury_token_account.to_account_info(),
+            authority: accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, treasury_amount, decimals)?;
+    }
+    if creator_amount > 0 {
+        let creator_token_account = accounts.creator_token_account
+            .ok_or(ErrorCode::MissingCreatorTokenAccount)?;
+        require!(creator_token_account.owner == game.creator, ErrorCode::WrongTokenOwner);
+        require!(creator_token_account.mint == game.token_mint, ErrorCode::WrongMint);
+        let cpi_accounts = TransferChecked {
+            from: accounts.user_token_account.to_account_info(),
+            mint: accounts.token_mint.to_account_info(),
+            to: creator_token_account.to_account_info(),
+            authority: accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, creator_amount, decimals)?;
+    }
+
+    // Refunds come out of the treasury alone, so record only what actually arrived there:
+    // not the creator's share, and not what a fee-bearing mint withheld on the way
+    let net_amount = treasury_amount - transfer_fee(accounts.token_mint, treasury_amount)?;
+    Ok(PlayPayment {
+        token_amount,
+        treasury_amount,
+        creator_amount,
+        decimals,
+        net_amount,
+    })
+}
+
+/// Tokens a Token-2022 TransferFee extension withholds from a transfer of `amount` in the
+/// current epoch (0 for SPL Token mints and mints without the extension)
+fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+        state::Mint,
+    };
+    if amount == 0 || *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data).map_err(|_| ErrorCode::WrongMint)?;
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or_else(|| error!(ErrorCode::MathOverflow)),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Tokens (base units, rounded up) worth the game's cost_usd at its Pyth price.
+/// The price must be fully verified and no older than max_price_age_secs.
+fn required_token_amount(game: &Game, price_update: &AccountInfo, decimals: u8) -> Result<u64> {
+    require_keys_eq!(*price_update.owner, PYTH_RECEIVER_PROGRAM_ID, ErrorCode::InvalidPriceFeed);
+    let data = price_update.try_borrow_data()?;
+    require!(data.len() >= 8 && data[..8] == PYTH_PRICE_UPDATE_DISCRIMINATOR, ErrorCode::InvalidPriceFeed);
+    let update = PythPriceUpdate::deserialize(&mut &data[8..]).map_err(|_| error!(ErrorCode::InvalidPriceFeed))?;
+    require!(update.verification_level == PythVerificationLevel::Full, ErrorCode::InvalidPriceFeed);
+    let message = update.price_message;
+    require!(message.price > 0, ErrorCode::InvalidPriceFeed);
+    let age = Clock::get()?.unix_timestamp.saturating_sub(message.publish_time);
+    require!(age <= game.max_price_age_secs as i64, ErrorCode::StalePrice);
+
+    // cost_usd is in cents; one token is worth price * 10^exponent USD
+    let pow10 = |exp: u32| 10u128.checked_pow(exp).ok_or(ErrorCode::MathOverflow);
+    let mut numerator = (game.cost_usd as u128)
+        .checked_mul(pow10(decimals as u32)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let mut denominator = (message.price as u128).checked_mul(100).ok_or(ErrorCode::MathOverflow)?;
+    if message.exponent < 0 {
+        numerator = numerator.checked_mul(pow10(message.exponent.unsigned_abs())?).ok_or(ErrorCode::MathOverflow)?;
+    } else {
+        denominator = denominator.checked_mul(pow10(message.exponent as u32)?).ok_or(ErrorCode::MathOverflow)?;
+    }
+    let required = numerator.div_ceil(denominator);
+    u64::try_from(required).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Session seed recorded for a play_game_with_request_id session: the request id
+/// (u64 LE) zero-extended to 32 bytes
+fn request_id_session_seed(request_id: u64) -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    seed[..8].copy_from_slice(&request_id.to_le_bytes());
+    seed
+}
+
+/// Creator's cut of a play payment (floor of amount * creator_share_bps / 10_000)
+fn creator_share(game: &Game, amount: u64) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(game.creator_share_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / 10_000;
+    Ok(share as u64)
+}
 
-#[event]
-pub struct GameCreated {
-    pub game_id: u64,
-    pub authority: Pubkey,
-    pub timestamp: i64,
+/// Check the anti-bot proof of work for a play. The hash covers the user so one
+/// solved nonce can't be reused across wallets with the same session seed.
+fn require_proof_of_work(game: &Game, session_seed: &[u8; 32], user: &Pubkey, nonce: u64) -> Result<()> {
+    if game.pow_difficulty == 0 {
+        return Ok(());
+    }
+    let hash = solana_sha256_hasher::hashv(&[session_seed, user.as_ref(), &nonce.to_le_bytes()]).to_bytes();
+    require!(leading_zero_bits(&hash) >= game.pow_difficulty as u32, ErrorCode::InvalidProofOfWork);
+    Ok(())
 }
 
-#[event]
-pub struct PrizeAdded {
-    pub game_id: u64,
-    pub prize_index: u8,
-    pub prize_id: u64,
-    pub probability_bp: u16,
-    pub supply_total: u32,
-    pub timestamp: i64,
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for &b in bytes {
+        bits += b.leading_zeros();
+        if b != 0 {
+            break;
+        }
+    }
+    bits
 }
 
-#[event]
-pub struct GamePlayInitiated {
-    pub user: Pubkey,
-    pub game_id: u64,
-    pub token_amount: u64,
-    pub session: Pubkey,
-    pub timestamp: i64,
+/// NFT name for a prize: the prize name, plus " [Tier]" when the game has tier_in_name.
+/// The base name is truncated so the result fits Metaplex's 32-byte name limit.
+fn prize_nft_name(game: &Game, name: &str, tier: &PrizeTier) -> String {
+    if !game.tier_in_name {
+        return name.to_string();
+    }
+    let suffix = format!(" [{}]", tier.label());
+    let mut base_len = name.len().min(mpl_token_metadata::MAX_NAME_LENGTH - suffix.len());
+    while !name.is_char_boundary(base_len) {
+        base_len -= 1;
+    }
+    format!("{}{}", &name[..base_len], suffix)
 }
 
-#[event]
-pub struct GameStatusUpdated {
-    pub game_id: u64,
-    pub is_active: bool,
-    pub timestamp: i64,
+/// Reset a freshly created play session to its awaiting-finalization state
+fn init_play_session(
+    session: &mut PlaySession,
+    user: Pubkey,
+    game: &Account<Game>,
+    amount_paid: u64,
+    session_seed: [u8; 32],
+    bump: u8,
+) -> Result<()> {
+    session.user = user;
+    session.game = game.key();
+    session.amount_paid = amount_paid;
+    session.session_seed = session_seed;
+    session.is_fulfilled = false;
+    session.random_value = [0u8; 32];
+    session.prize_index = None;
+    session.is_claimed = false;
+    session.bump = bump;
+    session.pending_claim_mint = None;
+    session.supply_refunded = false;
+    session.prize_snapshot = None;
+    session.created_at = Clock::get()?.unix_timestamp;
+    session.vrf_account = None;
+    session.vrf_seed_slot = 0;
+    session.commitment = None;
+    session.expiry_seconds = game.refund_timeout_secs as i64;
+    session.timeout_refunded = false;
+    session.draws = 1;
+    Ok(())
 }
 
-#[event]
-pub struct SupplyReplenished {
-    pub game_id: u64,
-    pub prize_id: u64,
-    pub prize_index: u8,
-    pub new_supply: u32,
-    pub timestamp: i64,
+/// Fill in a PlayerStats account created by init_if_needed
+fn init_player_stats_if_needed(stats: &mut PlayerStats, user: Pubkey, game: Pubkey, bump: u8) {
+    if stats.user == Pubkey::default() {
+        stats.user = user;
+        stats.game = game;
+        stats.bump = bump;
+    }
 }
 
-#[event]
-pub struct TreasuryWithdrawn {
-    pub game_id: u64,
-    pub amount: u64,
-    pub destination: Pubkey,
-    pub timestamp: i64,
+/// Set a prize's probability in the prize and the game's entry, keeping the whole
+/// distribution within 10000 bp and the expected value (house edge) current.
+/// Returns the previous probability.
+fn replace_prize_probability(game: &mut Game, prize: &mut Prize, probability_bp: u16) -> Result<u16> {
+    let idx = prize.prize_index as usize;
+    let new_total: u32 = game.prize_probabilities.iter().enumerate()
+        .map(|(i, &p)| if i == idx { probability_bp as u32 } else { p as u32 })
+        .sum();
+    require!(new_total <= 10_000, ErrorCode::InvalidProbabilities);
+    
+    // Replace this prize's contribution to the expected value. Saturating so games
+    // created before EV tracking (sum starts at 0) can still be edited.
+    let old_ev = (prize.probability_bp as u128)
+        .checked_mul(prize.cost_usd as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let new_ev = (probability_bp as u128)
+        .checked_mul(prize.cost_usd as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    game.prize_ev_sum = game.prize_ev_sum.saturating_sub(old_ev)
+        .checked_add(new_ev)
+        .ok_or(ErrorCode::MathOverflow)?;
+    check_house_edge(game)?;
+    
+    let old_probability_bp = prize.probability_bp;
+    prize.probability_bp = probability_bp;
+    game.prize_probabilities[idx] = probability_bp;
+    Ok(old_probability_bp)
 }
 
-#[event]
-pub struct PlayResolved {
-    pub user: Pubkey,
-    pub game_id: u64,
-    pub session: Pubkey,
-    pub prize_id: Option<u64>,
-    pub prize_index: Option<u8>,
-    pub tier: Option<PrizeTier>,
-    pub is_win: bool,
-    pub random_value: [u8; 32],
-    pub timestamp: i64,
+/// With enforce_house_edge set, require sum(prob_bp/10000 * prize.cost_usd) <= game.cost_usd
+fn check_house_edge(game: &Game) -> Result<()> {
+    if game.enforce_house_edge {
+        let max_ev = (game.cost_usd as u128).checked_mul(10_000).ok_or(ErrorCode::MathOverflow)?;
+        require!(game.prize_ev_sum <= max_ev, ErrorCode::NegativeHouseEdge);
+    }
+    Ok(())
 }
 
-#[event]
-pub struct PrizeClaimed {
-    pub user: Pubkey,
-    pub game_id: u64,
-    pub session: Pubkey,
-    pub prize_id: u64,
-    pub prize_index: u8,
-    pub tier: PrizeTier,
-    pub nft_mint: Pubkey,
-    pub timestamp: i64,
+/// Decode a Switchboard on-demand randomness account, rejecting accounts the
+/// Switchboard program doesn't own
+fn parse_randomness_account<'a>(randomness_info: &'a AccountInfo) -> Result<std::cell::Ref<'a, RandomnessAccountData>> {
+    require!(
+        randomness_info.owner.to_bytes() == switchboard_on_demand::SWITCHBOARD_ON_DEMAND_PROGRAM_ID.to_bytes(),
+        ErrorCode::InvalidVrfAccount
+    );
+    RandomnessAccountData::parse(randomness_info.try_borrow_data()?).map_err(|_| error!(ErrorCode::InvalidVrfAccount))
 }
 
-// ============================================
-// Errors
-// ============================================
+/// The value revealed for a VRF-bound session. The account must be the one bound by
+/// request_randomness, still on the same commit, and revealed in the current slot.
+fn consume_vrf(session: &PlaySession, randomness_info: &AccountInfo) -> Result<[u8; 32]> {
+    let bound = session.vrf_account.ok_or(ErrorCode::VrfNotRequested)?;
+    require_keys_eq!(randomness_info.key(), bound, ErrorCode::VrfAccountMismatch);
+    let randomness = parse_randomness_account(randomness_info)?;
+    // A re-commit would let its authority roll again
+    require!(randomness.seed_slot == session.vrf_seed_slot, ErrorCode::VrfAccountMismatch);
+    randomness.get_value(&Clock::get()?).map_err(|_| error!(ErrorCode::VrfNotResolved))
+}
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid probabilities; must sum to <= 10000")]
-    InvalidProbabilities,
-    #[msg("Game is inactive")]
-    GameInactive,
-    #[msg("All prizes are out of stock")]
-    OutOfStock,
-    #[msg("Unauthorized")]
-    Unauthorized,
-    #[msg("Prize not found")]
-    PrizeNotFound,
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-    #[msg("Invalid token amount")]
-    InvalidTokenAmount,
-    #[msg("Math overflow")]
-    MathOverflow,
-    #[msg("String exceeds maximum length")]
-    StringTooLong,
-    #[msg("Too many prizes (max 16)")]
-    TooManyPrizes,
-    #[msg("Play session already fulfilled")]
-    AlreadyFulfilled,
-    #[msg("Play session not fulfilled yet")]
-    NotFulfilled,
-    #[msg("No prize won in this session")]
-    NoPrize,
-    #[msg("Prize already claimed")]
-    AlreadyClaimed,
-    #[msg("Must claim prize before closing session")]
-    NotClaimed,
-    #[msg("Invalid prize index")]
-    InvalidPrizeIndex,
+/// The random value of a commit-reveal play: keccak(reveal) must match the commitment
+/// stored at play time, and the value mixes the backend's reveal with the user's seed
+fn open_commitment(commitment: &[u8; 32], reveal: Option<[u8; 32]>, session_seed: &[u8; 32]) -> Result<[u8; 32]> {
+    let reveal = reveal.ok_or(ErrorCode::RevealRequired)?;
+    require!(solana_keccak_hasher::hash(&reveal).to_bytes() == *commitment, ErrorCode::RevealMismatch);
+    Ok(std::array::from_fn(|i| reveal[i] ^ session_seed[i]))
 }
 
-// ============================================
-// Helper Functions
-// ============================================
+/// Hash the backend random value with the most recent SlotHashes entry and the session key
+fn mix_slot_hash(slot_hashes: &AccountInfo, random_value: &[u8; 32], session_key: &Pubkey) -> Result<[u8; 32]> {
+    // SlotHashes layout: u64 entry count, then (slot: u64, hash: [u8; 32]) newest first
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= 48, ErrorCode::SlotHashesMissing);
+    let entries = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    require!(entries > 0, ErrorCode::SlotHashesMissing);
+    let recent_hash = &data[16..48];
+    Ok(solana_sha256_hasher::hashv(&[random_value, recent_hash, session_key.as_ref()]).to_bytes())
+}
 
-/// Select a prize index based on random value and probability distribution
-fn select_prize_index(probabilities: &[u16; MAX_PRIZES], prize_count: u8, random_value: &[u8; 32]) -> Option<usize> {
-    // Convert first 8 bytes to u64 and normalize to 0..9999
-    let rand_u64 = u64::from_le_bytes(random_value[0..8].try_into().unwrap());
-    let draw = (rand_u64 % 10_000) as u16;
+/// Create, mint and finalize a 1/1 NFT owned by `user_key` from finalize_play's
+/// remaining_accounts ([1] mint .. [10] rent, see finalize_play). The game PDA is
+/// mint and update authority; `payer` covers all rent.
+fn mint_nft_from_remaining_accounts<'info>(
+    program_id: &Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    game: &Account<'info, Game>,
+    user_key: Pubkey,
+    name: &str,
+    uri: &str,
+) -> Result<Pubkey> {
+    use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+    use mpl_token_metadata::{
+        instructions::{
+            CreateMetadataAccountV3Cpi, CreateMasterEditionV3Cpi,
+            CreateMetadataAccountV3CpiAccounts, CreateMasterEditionV3CpiAccounts,
+            CreateMetadataAccountV3InstructionArgs, CreateMasterEditionV3InstructionArgs,
+        },
+        types::DataV2,
+    };
     
-    let mut cumulative: u16 = 0;
-    for idx in 0..(prize_count as usize) {
-        let prob = probabilities[idx];
-        if prob == 0 { continue; }
-        cumulative = cumulative.saturating_add(prob);
-        if draw < cumulative {
-            return Some(idx);
-        }
-    }
-    None // Loss - draw fell outside prize probability range
+    let game_key = game.key();
+    let game_id = game.game_id;
+    
+    // Get NFT minting accounts from remaining_accounts
+    let nft_mint = &remaining_accounts[1];
+    let metadata = &remaining_accounts[2];
+    let master_edition = &remaining_accounts[3];
+    let user_nft_token_account = &remaining_accounts[4];
+    let user_account = &remaining_accounts[5];
+    let token_program = &remaining_accounts[6];
+    let associated_token_program = &remaining_accounts[7];
+    let metaplex_program = &remaining_accounts[8];
+    let system_program = &remaining_accounts[9];
+    let rent = &remaining_accounts[10];
+
+    let nft_mint_key = nft_mint.key();
+    require_token_program(token_program)?;
+
+    // Verify Metaplex program
+    require!(
+        metaplex_program.key() == METAPLEX_TOKEN_METADATA_PROGRAM_ID,
+        ErrorCode::InvalidMetaplexProgram
+    );
+
+    // Verify user account matches session user
+    require!(user_account.key() == user_key, ErrorCode::WrongSessionUser);
+
+    // Derive and verify metadata PDA
+    let nft_mint_bytes = nft_mint_key.as_ref();
+    let metadata_seeds = &[
+        b"metadata",
+        METAPLEX_TOKEN_METADATA_PROGRAM_ID.as_ref(),
+        nft_mint_bytes,
+    ];
+    let (metadata_pda, _) = Pubkey::find_program_address(metadata_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
+    require!(metadata.key() == metadata_pda, ErrorCode::WrongPdaDerivation);
+
+    // Derive and verify master edition PDA
+    let master_edition_seeds = &[
+        b"metadata",
+        METAPLEX_TOKEN_METADATA_PROGRAM_ID.as_ref(),
+        nft_mint_bytes,
+        b"edition",
+    ];
+    let (master_edition_pda, _) = Pubkey::find_program_address(master_edition_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
+    require!(master_edition.key() == master_edition_pda, ErrorCode::WrongPdaDerivation);
+
+    // Verify user's token account
+    let expected_ata = get_associated_token_address_with_program_id(&user_key, &nft_mint_key, token_program.key);
+    require!(user_nft_token_account.key() == expected_ata, ErrorCode::WrongPdaDerivation);
+
+    // Derive game PDA bump for signing
+    let (game_pda, game_bump) = seeds::find_game(game_id, program_id);
+    require!(game_key == game_pda, ErrorCode::WrongPdaDerivation);
+
+    // Game PDA seeds for signing
+    let game_id_bytes = game_id.to_le_bytes();
+    let game_bump_array = [game_bump];
+    let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+
+    // 1. Create mint account
+    let mint_rent = Rent::get()?.minimum_balance(82);
+    anchor_lang::system_program::create_account(
+        CpiContext::new(
+            system_program.to_account_info(),
+            anchor_lang::system_program::CreateAccount {
+                from: payer.to_account_info(),
+                to: nft_mint.to_account_info(),
+            },
+        ),
+        mint_rent,
+        82,
+        token_program.key,
+    )?;
+
+    // 2. Initialize mint with game as authority
+    token_interface::initialize_mint2(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token_interface::InitializeMint2 {
+                mint: nft_mint.to_account_info(),
+            },
+        ),
+        0,
+        &game_pda,
+        Some(&game_pda),
+    )?;
+
+    // 3. Create associated token account for user
+    anchor_spl::associated_token::create(
+        CpiContext::new(
+            associated_token_program.to_account_info(),
+            anchor_spl::associated_token::Create {
+                payer: payer.to_account_info(),
+                associated_token: user_nft_token_account.to_account_info(),
+                authority: user_account.to_account_info(),
+                mint: nft_mint.to_account_info(),
+                system_program: system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ),
+    )?;
+
+    // Build metadata
+    let (seller_fee_basis_points, creators) = prize_royalties(game);
+    let data_v2 = DataV2 {
+        name: name.to_string(),
+        symbol: "PRIZE".to_string(),
+        uri: uri.to_string(),
+        seller_fee_basis_points,
+        creators,
+        collection: None,
+        uses: None,
+    };
+
+    // Get account infos
+    let game_info = game.to_account_info();
+    let payer_info = payer.to_account_info();
+
+    // 4. Create metadata account
+    CreateMetadataAccountV3Cpi::new(
+        &metaplex_program.to_account_info(),
+        CreateMetadataAccountV3CpiAccounts {
+            metadata: &metadata.to_account_info(),
+            mint: &nft_mint.to_account_info(),
+            mint_authority: &game_info,
+            payer: &payer_info,
+            update_authority: (&game_info, true),
+            system_program: &system_program.to_account_info(),
+            rent: Some(&rent.to_account_info()),
+        },
+        CreateMetadataAccountV3InstructionArgs {
+            data: data_v2,
+            is_mutable: false,
+            collection_details: None,
+        },
+    ).invoke_signed(&[game_seeds])?;
+
+    // 5. Mint 1 token to user
+    let cpi_accounts = token_interface::MintTo {
+        mint: nft_mint.to_account_info(),
+        to: user_nft_token_account.to_account_info(),
+        authority: game_info.clone(),
+    };
+    let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+    token_interface::mint_to(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds),
+        1,
+    )?;
+
+    // 6. Create master edition
+    CreateMasterEditionV3Cpi::new(
+        &metaplex_program.to_account_info(),
+        CreateMasterEditionV3CpiAccounts {
+            edition: &master_edition.to_account_info(),
+            mint: &nft_mint.to_account_info(),
+            update_authority: &game_info,
+            mint_authority: &game_info,
+            payer: &payer_info,
+            metadata: &metadata.to_account_info(),
+            token_program: &token_program.to_account_info(),
+            system_program: &system_program.to_account_info(),
+            rent: Some(&rent.to_account_info()),
+        },
+        CreateMasterEditionV3InstructionArgs { max_supply: None },
+    ).invoke_signed(&[game_seeds])?;
+
+    Ok(nft_mint_key)
+}
+
+/// Prize NFTs may be minted under the legacy Token program or Token-2022
+fn require_token_program(token_program: &AccountInfo) -> Result<()> {
+    require!(
+        token_program.key() == anchor_spl::token::ID || token_program.key() == anchor_spl::token_2022::ID,
+        ErrorCode::InvalidTokenProgram
+    );
+    Ok(())
 }
 
 fn mint_prize_nft_for_claim(
-    ctx: &Context<ClaimPrize>,
+    program_id: &Pubkey,
+    accounts: &ClaimMintAccounts,
     name: &str,
     uri: &str,
     _tier: &PrizeTier,
     game_id: u64,
     _prize_id: u64,
 ) -> Result<Pubkey> {
-    use anchor_spl::associated_token::get_associated_token_address;
+    use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+    
+    let nft_mint_key = accounts.nft_mint.key();
+    require_token_program(&accounts.token_program)?;
+    
+    // Verify the recipient's token account (the NFT can only go to the session user)
+    let expected_ata = get_associated_token_address_with_program_id(
+        &accounts.recipient,
+        &nft_mint_key,
+        accounts.token_program.key,
+    );
+    require!(accounts.recipient_nft_token_account.key() == expected_ata, ErrorCode::WrongPdaDerivation);
+    
+    // Derive game PDA bump for signing
+    let (game_pda, game_bump) = seeds::find_game(game_id, program_id);
+    require!(accounts.game.key() == game_pda, ErrorCode::WrongPdaDerivation);
+    
+    // Game PDA seeds for signing
+    let game_id_bytes = game_id.to_le_bytes();
+    let game_bump_array = [game_bump];
+    let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
+    
+    // Mint 1 token to the recipient (under whichever token program owns the mint)
+    let cpi_accounts = token_interface::MintTo {
+        mint: accounts.nft_mint.clone(),
+        to: accounts.recipient_nft_token_account.clone(),
+        authority: accounts.game.clone(),
+    };
+    let signer_seeds: &[&[&[u8]]] = &[game_seeds];
+    token_interface::mint_to(
+        CpiContext::new_with_signer(accounts.token_program.clone(), cpi_accounts, signer_seeds),
+        1,
+    )?;
+    
+    create_prize_metadata(program_id, accounts, name, uri, game_id)?;
+    
+    Ok(nft_mint_key)
+}
+
+/// Royalty fields for prize NFT metadata: the game's royalty_bps and its creators
+/// (unverified, since they don't sign the mint), or no creators when none are set
+fn prize_royalties(game: &Game) -> (u16, Option<Vec<mpl_token_metadata::types::Creator>>) {
+    if game.creators.is_empty() {
+        return (0, None);
+    }
+    let creators = game.creators.iter()
+        .map(|c| mpl_token_metadata::types::Creator {
+            address: c.address,
+            verified: false,
+            share: c.share,
+        })
+        .collect();
+    (game.royalty_bps, Some(creators))
+}
+
+/// Create the metadata and master edition for a prize mint whose single token
+/// has already been minted. The master edition takes over mint authority, which
+/// makes the NFT complete and non-mintable.
+fn create_prize_metadata(
+    program_id: &Pubkey,
+    accounts: &ClaimMintAccounts,
+    name: &str,
+    uri: &str,
+    game_id: u64,
+) -> Result<()> {
     use mpl_token_metadata::{
         instructions::{
             CreateMetadataAccountV3Cpi, CreateMasterEditionV3Cpi,
@@ -1152,13 +6949,12 @@ fn mint_prize_nft_for_claim(
         types::DataV2,
     };
     
-    let accounts = &ctx.accounts;
     let nft_mint_key = accounts.nft_mint.key();
     
     // Verify Metaplex program
     require!(
-        accounts.metaplex_token_metadata_program.key() == METAPLEX_TOKEN_METADATA_PROGRAM_ID,
-        ErrorCode::Unauthorized
+        accounts.metaplex_program.key() == METAPLEX_TOKEN_METADATA_PROGRAM_ID,
+        ErrorCode::InvalidMetaplexProgram
     );
     
     // Derive and verify metadata PDA
@@ -1169,7 +6965,7 @@ fn mint_prize_nft_for_claim(
         nft_mint_bytes,
     ];
     let (metadata_pda, _) = Pubkey::find_program_address(metadata_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
-    require!(accounts.metadata.key() == metadata_pda, ErrorCode::Unauthorized);
+    require!(accounts.metadata.key() == metadata_pda, ErrorCode::WrongPdaDerivation);
     
     // Derive and verify master edition PDA
     let master_edition_seeds = &[
@@ -1179,55 +6975,50 @@ fn mint_prize_nft_for_claim(
         b"edition",
     ];
     let (master_edition_pda, _) = Pubkey::find_program_address(master_edition_seeds, &METAPLEX_TOKEN_METADATA_PROGRAM_ID);
-    require!(accounts.master_edition.key() == master_edition_pda, ErrorCode::Unauthorized);
-    
-    // Verify user's token account
-    let expected_ata = get_associated_token_address(&accounts.user.key(), &nft_mint_key);
-    require!(accounts.user_nft_token_account.key() == expected_ata, ErrorCode::Unauthorized);
+    require!(accounts.master_edition.key() == master_edition_pda, ErrorCode::WrongPdaDerivation);
     
     // Derive game PDA bump for signing
-    let (game_pda, game_bump) = Pubkey::find_program_address(
-        &[b"game", &game_id.to_le_bytes()],
-        ctx.program_id,
-    );
-    require!(accounts.game.key() == game_pda, ErrorCode::Unauthorized);
+    let (game_pda, game_bump) = seeds::find_game(game_id, program_id);
+    require!(accounts.game.key() == game_pda, ErrorCode::WrongPdaDerivation);
     
     // Game PDA seeds for signing
     let game_id_bytes = game_id.to_le_bytes();
     let game_bump_array = [game_bump];
-    let game_seeds: &[&[u8]] = &[b"game", &game_id_bytes, &game_bump_array];
+    let game_seeds: &[&[u8]] = &[seeds::GAME, &game_id_bytes, &game_bump_array];
     
-    // Build metadata
+    // Build metadata; the game was checked against its PDA above
+    let game = Game::try_deserialize(&mut &accounts.game.try_borrow_data()?[..])?;
+    let (seller_fee_basis_points, creators) = prize_royalties(&game);
     let data_v2 = DataV2 {
         name: name.to_string(),
         symbol: "PRIZE".to_string(),
         uri: uri.to_string(),
-        seller_fee_basis_points: 0,
-        creators: None,
+        seller_fee_basis_points,
+        creators,
         collection: None,
         uses: None,
     };
     
     // Get account infos
-    let metaplex_program_info = accounts.metaplex_token_metadata_program.to_account_info();
-    let metadata_info = accounts.metadata.to_account_info();
-    let mint_info = accounts.nft_mint.to_account_info();
-    let game_info = accounts.game.to_account_info();
-    let user_info = accounts.user.to_account_info();
-    let system_program_info = accounts.system_program.to_account_info();
-    let rent_info = accounts.rent.to_account_info();
+    let metaplex_program_info = &accounts.metaplex_program;
+    let metadata_info = &accounts.metadata;
+    let mint_info = &accounts.nft_mint;
+    let game_info = &accounts.game;
+    let payer_info = &accounts.payer;
+    let system_program_info = &accounts.system_program;
+    let rent_info = &accounts.rent;
     
     // Create metadata account
     CreateMetadataAccountV3Cpi::new(
-        &metaplex_program_info,
+        metaplex_program_info,
         CreateMetadataAccountV3CpiAccounts {
-            metadata: &metadata_info,
-            mint: &mint_info,
-            mint_authority: &game_info,
-            payer: &user_info,
-            update_authority: (&game_info, true),
-            system_program: &system_program_info,
-            rent: Some(&rent_info),
+            metadata: metadata_info,
+            mint: mint_info,
+            mint_authority: game_info,
+            payer: payer_info,
+            update_authority: (game_info, true),
+            system_program: system_program_info,
+            rent: Some(rent_info),
         },
         CreateMetadataAccountV3InstructionArgs {
             data: data_v2,
@@ -1236,38 +7027,25 @@ fn mint_prize_nft_for_claim(
         },
     ).invoke_signed(&[game_seeds])?;
     
-    // Mint 1 token to user
-    let user_nft_token_account_info = accounts.user_nft_token_account.to_account_info();
-    let cpi_accounts = MintTo {
-        mint: accounts.nft_mint.to_account_info(),
-        to: user_nft_token_account_info,
-        authority: accounts.game.to_account_info(),
-    };
-    let signer_seeds: &[&[&[u8]]] = &[game_seeds];
-    token::mint_to(
-        CpiContext::new_with_signer(accounts.token_program.to_account_info(), cpi_accounts, signer_seeds),
-        1,
-    )?;
-    
     // Create master edition
-    let master_edition_info = accounts.master_edition.to_account_info();
-    let token_program_info = accounts.token_program.to_account_info();
+    let master_edition_info = &accounts.master_edition;
+    let token_program_info = &accounts.token_program;
     
     CreateMasterEditionV3Cpi::new(
-        &metaplex_program_info,
+        metaplex_program_info,
         CreateMasterEditionV3CpiAccounts {
-            edition: &master_edition_info,
-            mint: &mint_info,
-            update_authority: &game_info,
-            mint_authority: &game_info,
-            payer: &user_info,
-            metadata: &metadata_info,
-            token_program: &token_program_info,
-            system_program: &system_program_info,
-            rent: Some(&rent_info),
+            edition: master_edition_info,
+            mint: mint_info,
+            update_authority: game_info,
+            mint_authority: game_info,
+            payer: payer_info,
+            metadata: metadata_info,
+            token_program: token_program_info,
+            system_program: system_program_info,
+            rent: Some(rent_info),
         },
         CreateMasterEditionV3InstructionArgs { max_supply: None },
     ).invoke_signed(&[game_seeds])?;
     
-    Ok(nft_mint_key)
+    Ok(())
 }
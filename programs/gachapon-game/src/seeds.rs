@@ -0,0 +1,126 @@
+//! PDA seed convention for the game program.
+//!
+//! Every PDA starts with one of the prefixes below. Prefixes are unique and none is a
+//! prefix of another, so two different kinds of account can never hash the same seed
+//! bytes. Per-game accounts put the game key right after the prefix, which keeps
+//! accounts of different games apart. Derive addresses with these helpers (and use the
+//! constants in `seeds = [...]` constraints) instead of spelling out byte strings.
+//!
+//! The game treasury is not a PDA: it is the operator's wallet stored on the Game.
+
+use anchor_lang::prelude::*;
+
+pub const CONFIG: &[u8] = b"config";
+pub const FEATURES: &[u8] = b"features";
+pub const GAME: &[u8] = b"game";
+pub const PRIZE: &[u8] = b"prize";
+pub const SESSION: &[u8] = b"session";
+pub const STATS: &[u8] = b"stats";
+pub const PROMO: &[u8] = b"promo";
+pub const PROMO_REDEMPTION: &[u8] = b"user_promo";
+pub const CONSOLATION: &[u8] = b"consolation";
+pub const RNG_AUDIT: &[u8] = b"rng_audit";
+pub const WINNERS: &[u8] = b"winners";
+pub const REDEMPTION: &[u8] = b"redemption";
+pub const CLAIM_DELEGATE: &[u8] = b"claim_delegate";
+pub const INVENTORY: &[u8] = b"inventory";
+pub const PREMINT: &[u8] = b"premint";
+pub const COMPLIANCE: &[u8] = b"compliance";
+pub const PITY: &[u8] = b"pity";
+pub const CLAIM_RECORD: &[u8] = b"claim_record";
+
+/// [b"config"]
+pub fn find_config(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG], program_id)
+}
+
+/// [b"features"]
+pub fn find_features(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEATURES], program_id)
+}
+
+/// [b"game", game_id (u64 LE)]
+pub fn find_game(game_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GAME, &game_id.to_le_bytes()], program_id)
+}
+
+/// [b"prize", game, prize_index]
+pub fn find_prize(game: &Pubkey, prize_index: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PRIZE, game.as_ref(), &[prize_index]], program_id)
+}
+
+/// [b"session", game, user, session_seed]
+pub fn find_session(game: &Pubkey, user: &Pubkey, session_seed: &[u8; 32], program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SESSION, game.as_ref(), user.as_ref(), session_seed], program_id)
+}
+
+/// [b"session", game, user, request_id (u64 LE)] - play_game_with_request_id sessions.
+/// The 8-byte last seed keeps them apart from the 32-byte session_seed form.
+pub fn find_session_by_request_id(game: &Pubkey, user: &Pubkey, request_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SESSION, game.as_ref(), user.as_ref(), &request_id.to_le_bytes()], program_id)
+}
+
+/// [b"stats", game, user]
+pub fn find_stats(game: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATS, game.as_ref(), user.as_ref()], program_id)
+}
+
+/// [b"promo", game, campaign_id (u64 LE)]
+pub fn find_promo(game: &Pubkey, campaign_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROMO, game.as_ref(), &campaign_id.to_le_bytes()], program_id)
+}
+
+/// [b"user_promo", promo_campaign, user]
+pub fn find_promo_redemption(promo_campaign: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROMO_REDEMPTION, promo_campaign.as_ref(), user.as_ref()], program_id)
+}
+
+/// [b"consolation", game]
+pub fn find_consolation(game: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONSOLATION, game.as_ref()], program_id)
+}
+
+/// [b"rng_audit", game]
+pub fn find_rng_audit(game: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RNG_AUDIT, game.as_ref()], program_id)
+}
+
+/// [b"winners", game]
+pub fn find_winners(game: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WINNERS, game.as_ref()], program_id)
+}
+
+/// [b"redemption", nft_mint]
+pub fn find_redemption(nft_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REDEMPTION, nft_mint.as_ref()], program_id)
+}
+
+/// [b"claim_delegate"]
+pub fn find_claim_delegate(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CLAIM_DELEGATE], program_id)
+}
+
+/// [b"compliance", game]
+pub fn find_compliance_log(game: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COMPLIANCE, game.as_ref()], program_id)
+}
+
+/// [b"inventory", prize]
+pub fn find_inventory(prize: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[INVENTORY, prize.as_ref()], program_id)
+}
+
+/// [b"premint", prize, index (u32 LE)]
+pub fn find_premint_mint(prize: &Pubkey, index: u32, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PREMINT, prize.as_ref(), &index.to_le_bytes()], program_id)
+}
+
+/// [b"pity", game, user]
+pub fn find_pity_counter(game: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PITY, game.as_ref(), user.as_ref()], program_id)
+}
+
+/// [b"claim_record", prize, user]
+pub fn find_claim_record(prize: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CLAIM_RECORD, prize.as_ref(), user.as_ref()], program_id)
+}
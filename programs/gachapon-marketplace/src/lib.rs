@@ -1,20 +1,111 @@
 use anchor_lang::prelude::*;
-use anchor_spl::associated_token::{self, AssociatedToken};
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use solana_instructions_sysvar as sysvar_instructions;
+use solana_sdk_ids::{ed25519_program, sysvar};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("4zHkHBrSyBsi2L5J1ikZ5kQwNcGMcE2x3wKrG3FY7UqC");
 
-const PLATFORM_FEE_BPS: u16 = 200; // 2%
+const PLATFORM_FEE_BPS: u16 = 200; // 2%, the fee of configs created before it was configurable
+const MAX_PLATFORM_FEE_BPS: u16 = 1_000; // 10%, the most an operator may charge per sale
+const MAX_ROYALTY_BPS: u16 = 1_000; // 10%, the most set_royalty accepts
+const FEE_CONFIG_VERSION: u8 = 1; // first layout with platform_fee_bps
+const CONFIG_VERSION: u8 = 2; // layout with royalty_bps/royalty_destination; migrate_config upgrades older configs
+const CONFIG_SPACE: usize = 8 + 32 + 32 + 1 + 1 + 1 + 1 + (4 + 32 * MAX_FEE_EXEMPT_SELLERS) + (4 + 40 * MAX_PRICE_CEILINGS) + 8 + 8 + 8 + 8 + 1 + (1 + 8) + 8 + 4 + 1
+  + 2 + 1
+  + 2 + 32;
+const MAX_FEE_EXEMPT_SELLERS: usize = 4;
+const MAX_PRICE_CEILINGS: usize = 8;
+const MAX_BATCH_PRICE_UPDATES: usize = 10; // keeps a batch reprice within compute limits
+const MAX_LISTING_TAGS: u32 = 8; // tag bits a single listing may set
+const LISTING_SPACE: usize = 8 + 256;
+const AUCTION_SPACE: usize = 8 + 32 + 32 + 32 + 8 + 8 + (1 + 32) + 8 + 1 + 8 + 1 + 1 + 2 + 32;
+const BID_REFUND_SPACE: usize = 8 + 32 + 32 + 8 + 1;
+const COLLECTION_BID_SPACE: usize = 8 + 32 + 32 + 32 + 8 + 4 + 4 + 1 + 8 + 1;
+const SELLER_STATS_SPACE: usize = 8 + 32 + 4 + 1;
+const PERMIT_MESSAGE_LEN: usize = 32 + 32 + 32 + 8 + 8 + 8;
+const LISTING_SPACE_WITH_SNAPSHOT: usize = LISTING_SPACE + 1 + (4 + mpl_token_metadata::MAX_NAME_LENGTH) + (4 + mpl_token_metadata::MAX_URI_LENGTH);
 
 #[program]
 pub mod gachapon_marketplace {
   use super::*;
 
-  pub fn initialize_config(ctx: Context<InitializeConfig>, platform_treasury: Pubkey) -> Result<()> {
+  /// `platform_fee_bps` defaults to the original 2% when `None`
+  pub fn initialize_config(
+    ctx: Context<InitializeConfig>,
+    platform_treasury: Pubkey,
+    platform_fee_bps: Option<u16>,
+  ) -> Result<()> {
+    let platform_fee_bps = platform_fee_bps.unwrap_or(PLATFORM_FEE_BPS);
+    require!(platform_fee_bps <= MAX_PLATFORM_FEE_BPS, ErrorCode::PlatformFeeTooHigh);
     let config = &mut ctx.accounts.config;
     config.authority = ctx.accounts.admin.key();
     config.platform_treasury = platform_treasury;
+    config.fee_rounding = FeeRounding::Floor;
+    config.listings_paused = false;
+    config.purchases_paused = false;
+    config.allow_relist = true;
+    config.fee_exempt_sellers = Vec::new();
+    config.price_ceilings = Vec::new();
+    config.total_fees_accrued = 0;
+    config.total_fees_withdrawn = 0;
+    config.listing_deposit_lamports = 0;
+    config.listing_ttl_secs = 0;
+    config.royalty_policy = RoyaltyPolicy::Always;
+    config.fee_free_until = None;
+    config.cancel_fee_lamports = 0;
+    config.max_active_listings = 0;
     config.bump = ctx.bumps.config;
+    config.platform_fee_bps = platform_fee_bps;
+    config.version = CONFIG_VERSION;
+    config.royalty_bps = 0;
+    config.royalty_destination = Pubkey::default();
+    Ok(())
+  }
+
+  /// Bring a config created by an older program up to the current layout: resize it,
+  /// backfill `platform_fee_bps` with the old 2% on configs from before the fee was
+  /// configurable, leave royalties off and stamp `version`. Running it again on a migrated
+  /// config changes nothing.
+  pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+    let config_info = ctx.accounts.config.to_account_info();
+    require!(config_info.owner == ctx.program_id, ErrorCode::InvalidConfigAccount);
+    {
+      // Raw checks: a legacy config may not decode as Config until it has been resized
+      let data = config_info.try_borrow_data()?;
+      require!(data.len() >= 40 && data[..8] == *Config::DISCRIMINATOR, ErrorCode::InvalidConfigAccount);
+      require!(data[8..40] == ctx.accounts.admin.key().to_bytes(), ErrorCode::Unauthorized);
+    }
+    if config_info.data_len() < CONFIG_SPACE {
+      let rent_diff = Rent::get()?
+        .minimum_balance(CONFIG_SPACE)
+        .saturating_sub(config_info.lamports());
+      if rent_diff > 0 {
+        anchor_lang::system_program::transfer(
+          CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+              from: ctx.accounts.admin.to_account_info(),
+              to: config_info.clone(),
+            },
+          ),
+          rent_diff,
+        )?;
+      }
+      config_info.resize(CONFIG_SPACE)?;
+    }
+
+    let mut data = config_info.try_borrow_mut_data()?;
+    let mut config = Config::try_deserialize(&mut &data[..])?;
+    if config.version < CONFIG_VERSION {
+      if config.version < FEE_CONFIG_VERSION {
+        config.platform_fee_bps = PLATFORM_FEE_BPS;
+      }
+      config.royalty_bps = 0;
+      config.royalty_destination = Pubkey::default();
+      config.version = CONFIG_VERSION;
+      config.try_serialize(&mut *data)?;
+    }
     Ok(())
   }
 
@@ -22,6 +113,9 @@ pub mod gachapon_marketplace {
     ctx: Context<UpdateConfig>,
     new_platform_treasury: Option<Pubkey>,
     new_authority: Option<Pubkey>,
+    new_fee_rounding: Option<FeeRounding>,
+    new_allow_relist: Option<bool>,
+    new_platform_fee_bps: Option<u16>,
   ) -> Result<()> {
     let config = &mut ctx.accounts.config;
     if let Some(treasury) = new_platform_treasury {
@@ -30,13 +124,163 @@ pub mod gachapon_marketplace {
     if let Some(auth) = new_authority {
       config.authority = auth;
     }
+    if let Some(rounding) = new_fee_rounding {
+      config.fee_rounding = rounding;
+    }
+    if let Some(allow_relist) = new_allow_relist {
+      config.allow_relist = allow_relist;
+    }
+    if let Some(platform_fee_bps) = new_platform_fee_bps {
+      apply_platform_fee(config, platform_fee_bps)?;
+    }
+    Ok(())
+  }
+
+  /// Marketplace-wide kill switch for new listings and purchases.
+  /// Cancellations stay allowed so sellers can always recover their NFTs.
+  pub fn set_paused(
+    ctx: Context<UpdateConfig>,
+    listings_paused: bool,
+    purchases_paused: bool,
+  ) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.listings_paused = listings_paused;
+    config.purchases_paused = purchases_paused;
+    emit!(MarketplacePauseUpdated {
+      listings_paused,
+      purchases_paused,
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+  }
+
+  /// Add or remove a seller from the platform fee waiver allowlist
+  /// (e.g. a game treasury doing primary sales). The config authority is always exempt.
+  pub fn set_fee_exempt_seller(ctx: Context<UpdateConfig>, seller: Pubkey, exempt: bool) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let existing = config.fee_exempt_sellers.iter().position(|s| *s == seller);
+    match (exempt, existing) {
+      (true, None) => {
+        require!(config.fee_exempt_sellers.len() < MAX_FEE_EXEMPT_SELLERS, ErrorCode::FeeExemptListFull);
+        config.fee_exempt_sellers.push(seller);
+      }
+      (false, Some(idx)) => {
+        config.fee_exempt_sellers.swap_remove(idx);
+      }
+      _ => {}
+    }
+    Ok(())
+  }
+
+  /// Set (or clear with `None`) the maximum listing price for a currency.
+  /// Currencies without a ceiling are unlimited.
+  pub fn set_max_listing_price(
+    ctx: Context<UpdateConfig>,
+    currency_mint: Pubkey,
+    max_price: Option<u64>,
+  ) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let existing = config.price_ceilings.iter().position(|c| c.currency_mint == currency_mint);
+    match (max_price, existing) {
+      (Some(max_price), Some(idx)) => config.price_ceilings[idx].max_price = max_price,
+      (Some(max_price), None) => {
+        require!(config.price_ceilings.len() < MAX_PRICE_CEILINGS, ErrorCode::PriceCeilingListFull);
+        config.price_ceilings.push(PriceCeiling { currency_mint, max_price });
+      }
+      (None, Some(idx)) => {
+        config.price_ceilings.swap_remove(idx);
+      }
+      (None, None) => {}
+    }
+    Ok(())
+  }
+
+  /// Configure the refundable anti-spam listing deposit (in lamports, 0 = none) and how
+  /// long a listing may stay active before anyone can reap it (0 = never expires)
+  pub fn set_listing_deposit(
+    ctx: Context<UpdateConfig>,
+    deposit_lamports: u64,
+    listing_ttl_secs: i64,
+  ) -> Result<()> {
+    require!(listing_ttl_secs >= 0, ErrorCode::InvalidDuration);
+    let config = &mut ctx.accounts.config;
+    config.listing_deposit_lamports = deposit_lamports;
+    config.listing_ttl_secs = listing_ttl_secs;
+    Ok(())
+  }
+
+  /// Set whether sales must honor creator royalties, or let sellers choose per listing
+  pub fn set_royalty_policy(ctx: Context<UpdateConfig>, royalty_policy: RoyaltyPolicy) -> Result<()> {
+    ctx.accounts.config.royalty_policy = royalty_policy;
+    Ok(())
+  }
+
+  /// Waive the platform fee on every sale until `fee_free_until` (unix time), e.g. for a
+  /// collection's launch period. `None` turns the window off.
+  pub fn set_fee_free_until(ctx: Context<UpdateConfig>, fee_free_until: Option<i64>) -> Result<()> {
+    ctx.accounts.config.fee_free_until = fee_free_until;
+    Ok(())
+  }
+
+  /// Charge sellers a flat lamport penalty on cancel_listing (0 = free cancellation).
+  /// It is withheld from the listing deposit first; any remainder is paid by the seller.
+  pub fn set_cancel_fee(ctx: Context<UpdateConfig>, cancel_fee_lamports: u64) -> Result<()> {
+    ctx.accounts.config.cancel_fee_lamports = cancel_fee_lamports;
+    Ok(())
+  }
+
+  /// Set the platform fee charged on sales (at most MAX_PLATFORM_FEE_BPS). The config must
+  /// have been migrated first.
+  pub fn set_platform_fee(ctx: Context<UpdateConfig>, platform_fee_bps: u16) -> Result<()> {
+    apply_platform_fee(&mut ctx.accounts.config, platform_fee_bps)
+  }
+
+  /// Set the royalty sales that enforce royalties pay: `royalty_bps` of the price (at most
+  /// MAX_ROYALTY_BPS), taken from the seller's share and paid to `royalty_destination`, e.g.
+  /// the game treasury. 0 turns royalties off. Listings keep the royalty they were created
+  /// with, and so do auctions and layaways started from them; permit sales and collection
+  /// bid fills pay the royalty configured when they settle.
+  pub fn set_royalty(ctx: Context<UpdateConfig>, royalty_bps: u16, royalty_destination: Pubkey) -> Result<()> {
+    require!(royalty_bps <= MAX_ROYALTY_BPS, ErrorCode::InvalidRoyalty);
+    require!(royalty_bps == 0 || royalty_destination != Pubkey::default(), ErrorCode::InvalidRoyalty);
+    let config = &mut ctx.accounts.config;
+    require!(config.version >= CONFIG_VERSION, ErrorCode::ConfigNotMigrated);
+    config.royalty_bps = royalty_bps;
+    config.royalty_destination = royalty_destination;
     Ok(())
   }
 
-  pub fn list_nft(ctx: Context<ListNFT>, price_in_tokens: u64) -> Result<()> {
+  /// Cap active listings per seller wallet (0 = unlimited)
+  pub fn set_max_active_listings(ctx: Context<UpdateConfig>, max_active_listings: u32) -> Result<()> {
+    ctx.accounts.config.max_active_listings = max_active_listings;
+    Ok(())
+  }
+
+  /// `enforce_royalties` is only honored under `RoyaltyPolicy::Optional`
+  pub fn list_nft(
+    ctx: Context<ListNFT>,
+    price_in_tokens: u64,
+    enforce_royalties: bool,
+    category: ListingCategory,
+    tags: u32,
+  ) -> Result<()> {
+    require!(!ctx.accounts.config.listings_paused, ErrorCode::MarketplacePaused);
     require!(price_in_tokens > 0, ErrorCode::InvalidPrice);
+    require!(tags.count_ones() <= MAX_LISTING_TAGS, ErrorCode::TooManyTags);
+    if let Some(max_price) = ctx.accounts.config.max_listing_price(&ctx.accounts.currency_mint.key()) {
+      require!(price_in_tokens <= max_price, ErrorCode::PriceExceedsMax);
+    }
 
     let listing = &mut ctx.accounts.listing;
+    // A previously used listing PDA (cancelled or sold) can only be reused when relisting is allowed
+    if listing.listed_at != 0 {
+      require!(!listing.is_active, ErrorCode::ListingActive);
+      require!(ctx.accounts.config.allow_relist, ErrorCode::RelistDisabled);
+    }
+    let seller_stats = &mut ctx.accounts.seller_stats;
+    seller_stats.init_if_new(ctx.accounts.seller.key(), ctx.bumps.seller_stats);
+    seller_stats.open_listing(&ctx.accounts.config)?;
+
     listing.seller = ctx.accounts.seller.key();
     listing.nft_mint = ctx.accounts.nft_mint.key();
     listing.currency_mint = ctx.accounts.currency_mint.key();
@@ -46,8 +290,56 @@ pub mod gachapon_marketplace {
     listing.cancelled_at = None;
     listing.sold_at = None;
     listing.buyer = None;
+    listing.royalties_enforced = ctx.accounts.config.resolve_royalties(enforce_royalties);
+    ctx.accounts.config.apply_royalty(listing);
+    listing.category = category;
+    listing.tags = tags;
     listing.bump = ctx.bumps.listing;
 
+    // Optionally snapshot the NFT's on-chain name/uri so frontends can render from the listing alone
+    listing.snapshot = match &ctx.accounts.metadata {
+      Some(metadata) => Some(read_metadata_snapshot(metadata, &listing.nft_mint)?),
+      None => None,
+    };
+    if listing.snapshot.is_some() {
+      // A reused listing PDA may have been created without snapshot space
+      let listing_info = listing.to_account_info();
+      if listing_info.data_len() < LISTING_SPACE_WITH_SNAPSHOT {
+        let rent_diff = Rent::get()?
+          .minimum_balance(LISTING_SPACE_WITH_SNAPSHOT)
+          .saturating_sub(listing_info.lamports());
+        if rent_diff > 0 {
+          anchor_lang::system_program::transfer(
+            CpiContext::new(
+              ctx.accounts.system_program.to_account_info(),
+              anchor_lang::system_program::Transfer {
+                from: ctx.accounts.seller.to_account_info(),
+                to: listing_info.clone(),
+              },
+            ),
+            rent_diff,
+          )?;
+        }
+        listing_info.resize(LISTING_SPACE_WITH_SNAPSHOT)?;
+      }
+    }
+
+    // Lock the refundable listing deposit in the listing PDA
+    let deposit = ctx.accounts.config.listing_deposit_lamports;
+    listing.deposit_lamports = deposit;
+    if deposit > 0 {
+      anchor_lang::system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          anchor_lang::system_program::Transfer {
+            from: ctx.accounts.seller.to_account_info(),
+            to: listing.to_account_info(),
+          },
+        ),
+        deposit,
+      )?;
+    }
+
     // Ensure escrow ATA exists (created above via init_if_needed), then
     // transfer NFT (amount = 1) from seller to escrow
     let cpi_accounts = Transfer {
@@ -62,6 +354,9 @@ pub mod gachapon_marketplace {
       seller: listing.seller,
       nft_mint: listing.nft_mint,
       price: listing.price_in_tokens,
+      category: listing.category,
+      tags: listing.tags,
+      decimals: ctx.accounts.currency_mint.decimals,
       timestamp: listing.listed_at,
     });
     Ok(())
@@ -79,41 +374,71 @@ pub mod gachapon_marketplace {
       to: ctx.accounts.seller_nft_token_account.to_account_info(),
       authority: ctx.accounts.escrow_authority.to_account_info(),
     };
+    let signer: &[&[&[u8]]] = &[signer_seeds];
     let cpi_ctx = CpiContext::new_with_signer(
       ctx.accounts.token_program.to_account_info(),
       cpi_accounts,
-      &[signer_seeds],
+      signer,
     );
     token::transfer(cpi_ctx, 1)?;
 
     listing.is_active = false;
+    ctx.accounts.seller_stats.close_listing();
     listing.cancelled_at = Some(Clock::get()?.unix_timestamp);
 
+    // Cancellation penalty goes to the platform treasury, out of the deposit first
+    let penalty = ctx.accounts.config.cancel_fee_lamports;
+    if penalty > 0 {
+      let treasury = ctx.accounts.platform_treasury.as_ref().ok_or(ErrorCode::MissingPlatformTreasury)?;
+      require_keys_eq!(treasury.key(), ctx.accounts.config.platform_treasury, ErrorCode::WrongTreasury);
+      let from_deposit = penalty.min(listing.deposit_lamports);
+      if from_deposit > 0 {
+        listing.deposit_lamports -= from_deposit;
+        listing.to_account_info().sub_lamports(from_deposit)?;
+        treasury.add_lamports(from_deposit)?;
+      }
+      let from_seller = penalty - from_deposit;
+      if from_seller > 0 {
+        anchor_lang::system_program::transfer(
+          CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+              from: ctx.accounts.seller.to_account_info(),
+              to: treasury.to_account_info(),
+            },
+          ),
+          from_seller,
+        )?;
+      }
+    }
+    release_deposit(listing, &ctx.accounts.seller.to_account_info())?;
+
     emit!(NFTDelisted {
       seller: listing.seller,
       nft_mint: listing.nft_mint,
+      penalty,
       timestamp: listing.cancelled_at.unwrap(),
     });
     Ok(())
   }
 
   pub fn buy_nft(ctx: Context<BuyNFT>) -> Result<()> {
+    require!(!ctx.accounts.config.purchases_paused, ErrorCode::MarketplacePaused);
     let listing = &mut ctx.accounts.listing;
     require!(listing.is_active, ErrorCode::ListingInactive);
     require!(ctx.accounts.currency_mint.key() == listing.currency_mint, ErrorCode::InvalidCurrency);
-    // Verify treasury account belongs to configured platform
-    require!(
-      ctx.accounts.platform_treasury_currency_token_account.owner == ctx.accounts.config.platform_treasury,
-      ErrorCode::Unauthorized
-    );
+    // Buying your own listing only fakes volume (wash trading)
+    require!(ctx.accounts.buyer.key() != listing.seller, ErrorCode::SelfPurchase);
+    // Seller and platform treasury currency ATAs are created on demand (payer = buyer),
+    // and their authorities are pinned to listing.seller / config.platform_treasury
 
-    // Calculate amounts
+    // Calculate amounts (fee + royalty + seller_amount == price regardless of rounding mode);
+    // fee-exempt sellers and sales inside the fee-free window pay no platform fee
     let price = listing.price_in_tokens;
-    let fee = (price as u128)
-      .saturating_mul(PLATFORM_FEE_BPS as u128)
-      .checked_div(10_000)
-      .ok_or(ErrorCode::MathOverflow)? as u64;
-    let seller_amount = price.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+    let now = Clock::get()?.unix_timestamp;
+    let fee_bps = ctx.accounts.config.platform_fee_bps(&listing.seller, now);
+    let (fee, seller_amount) = split_fee(price, fee_bps, ctx.accounts.config.fee_rounding)?;
+    let (royalty, seller_amount) = split_royalty(price, fee, seller_amount, listing.royalty_bps)?;
 
     // Transfer currency tokens: buyer -> seller
     let cpi_accounts1 = Transfer {
@@ -124,6 +449,15 @@ pub mod gachapon_marketplace {
     let cpi_ctx1 = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts1);
     token::transfer(cpi_ctx1, seller_amount)?;
 
+    pay_royalty(
+      royalty,
+      ctx.accounts.royalty_token_account.as_ref(),
+      &ctx.accounts.buyer_currency_token_account,
+      ctx.accounts.buyer.to_account_info(),
+      &[],
+      &ctx.accounts.token_program,
+    )?;
+
     // Transfer fee: buyer -> platform treasury
     let cpi_accounts2 = Transfer {
       from: ctx.accounts.buyer_currency_token_account.to_account_info(),
@@ -131,7 +465,11 @@ pub mod gachapon_marketplace {
       authority: ctx.accounts.buyer.to_account_info(),
     };
     let cpi_ctx2 = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts2);
-    token::transfer(cpi_ctx2, fee)?;
+    if fee > 0 {
+      token::transfer(cpi_ctx2, fee)?;
+      let config = &mut ctx.accounts.config;
+      config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+    }
 
     // Transfer NFT from escrow to buyer using PDA signer
     let bump = ctx.bumps.escrow_authority;
@@ -141,215 +479,2521 @@ pub mod gachapon_marketplace {
       to: ctx.accounts.buyer_nft_token_account.to_account_info(),
       authority: ctx.accounts.escrow_authority.to_account_info(),
     };
+    let signer: &[&[&[u8]]] = &[signer_seeds];
     let cpi_ctx3 = CpiContext::new_with_signer(
       ctx.accounts.token_program.to_account_info(),
       cpi_accounts3,
-      &[signer_seeds],
+      signer,
     );
     token::transfer(cpi_ctx3, 1)?;
 
     listing.is_active = false;
-    listing.sold_at = Some(Clock::get()?.unix_timestamp);
+    ctx.accounts.seller_stats.close_listing();
+    listing.sold_at = Some(now);
     listing.buyer = Some(ctx.accounts.buyer.key());
+    release_deposit(listing, &ctx.accounts.seller.to_account_info())?;
 
     emit!(NFTSold {
       seller: listing.seller,
       buyer: ctx.accounts.buyer.key(),
       nft_mint: listing.nft_mint,
-      price: price,
+      price,
       fee,
-      timestamp: listing.sold_at.unwrap(),
+      royalties_enforced: listing.royalties_enforced,
+      fee_waived: fee_bps == 0,
+      decimals: ctx.accounts.currency_mint.decimals,
+      royalty,
+      timestamp: now,
     });
     Ok(())
   }
 
-  pub fn update_listing_price(ctx: Context<UpdateListingPrice>, new_price_in_tokens: u64) -> Result<()> {
+  /// Buy a listed NFT and relist it for the buyer in the same transaction.
+  /// The purchase leg pays the seller and platform fee exactly like buy_nft; the NFT
+  /// then stays in escrow and the listing PDA is reused with the buyer as its seller.
+  pub fn buy_and_relist(
+    ctx: Context<BuyAndRelist>,
+    new_price_in_tokens: u64,
+    enforce_royalties: bool,
+    category: ListingCategory,
+    tags: u32,
+  ) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(!config.purchases_paused && !config.listings_paused, ErrorCode::MarketplacePaused);
+    require!(config.allow_relist, ErrorCode::RelistDisabled);
+    require!(new_price_in_tokens > 0, ErrorCode::InvalidPrice);
+    require!(tags.count_ones() <= MAX_LISTING_TAGS, ErrorCode::TooManyTags);
+    if let Some(max_price) = config.max_listing_price(&ctx.accounts.currency_mint.key()) {
+      require!(new_price_in_tokens <= max_price, ErrorCode::PriceExceedsMax);
+    }
     let listing = &mut ctx.accounts.listing;
     require!(listing.is_active, ErrorCode::ListingInactive);
-    require!(new_price_in_tokens > 0, ErrorCode::InvalidPrice);
-    let old_price = listing.price_in_tokens;
+    require!(ctx.accounts.currency_mint.key() == listing.currency_mint, ErrorCode::InvalidCurrency);
+    require!(ctx.accounts.buyer.key() != listing.seller, ErrorCode::SelfPurchase);
+
+    // Purchase leg, same split as buy_nft
+    let price = listing.price_in_tokens;
+    let now = Clock::get()?.unix_timestamp;
+    let fee_bps = config.platform_fee_bps(&listing.seller, now);
+    let (fee, seller_amount) = split_fee(price, fee_bps, config.fee_rounding)?;
+    let (royalty, seller_amount) = split_royalty(price, fee, seller_amount, listing.royalty_bps)?;
+
+    let cpi_accounts1 = Transfer {
+      from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+      to: ctx.accounts.seller_currency_token_account.to_account_info(),
+      authority: ctx.accounts.buyer.to_account_info(),
+    };
+    let cpi_ctx1 = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts1);
+    token::transfer(cpi_ctx1, seller_amount)?;
+    pay_royalty(
+      royalty,
+      ctx.accounts.royalty_token_account.as_ref(),
+      &ctx.accounts.buyer_currency_token_account,
+      ctx.accounts.buyer.to_account_info(),
+      &[],
+      &ctx.accounts.token_program,
+    )?;
+
+    if fee > 0 {
+      let cpi_accounts2 = Transfer {
+        from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+        to: ctx.accounts.platform_treasury_currency_token_account.to_account_info(),
+        authority: ctx.accounts.buyer.to_account_info(),
+      };
+      let cpi_ctx2 = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts2);
+      token::transfer(cpi_ctx2, fee)?;
+      let config = &mut ctx.accounts.config;
+      config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    let seller = listing.seller;
+    release_deposit(listing, &ctx.accounts.seller.to_account_info())?;
+    emit!(NFTSold {
+      seller,
+      buyer: ctx.accounts.buyer.key(),
+      nft_mint: listing.nft_mint,
+      price,
+      fee,
+      royalties_enforced: listing.royalties_enforced,
+      fee_waived: fee_bps == 0,
+      decimals: ctx.accounts.currency_mint.decimals,
+      royalty,
+      timestamp: now,
+    });
+
+    // Relist leg: the NFT never leaves escrow, the buyer becomes the seller
+    let config = &ctx.accounts.config;
+    ctx.accounts.seller_stats.close_listing();
+    let buyer_stats = &mut ctx.accounts.buyer_stats;
+    buyer_stats.init_if_new(ctx.accounts.buyer.key(), ctx.bumps.buyer_stats);
+    buyer_stats.open_listing(config)?;
+    listing.seller = ctx.accounts.buyer.key();
     listing.price_in_tokens = new_price_in_tokens;
-    emit!(PriceUpdated {
+    listing.listed_at = now;
+    listing.cancelled_at = None;
+    listing.sold_at = None;
+    listing.buyer = None;
+    listing.royalties_enforced = config.resolve_royalties(enforce_royalties);
+    config.apply_royalty(listing);
+    listing.category = category;
+    listing.tags = tags;
+
+    let deposit = config.listing_deposit_lamports;
+    listing.deposit_lamports = deposit;
+    if deposit > 0 {
+      anchor_lang::system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          anchor_lang::system_program::Transfer {
+            from: ctx.accounts.buyer.to_account_info(),
+            to: listing.to_account_info(),
+          },
+        ),
+        deposit,
+      )?;
+    }
+
+    emit!(NFTListed {
+      seller: listing.seller,
       nft_mint: listing.nft_mint,
-      old_price,
-      new_price: new_price_in_tokens,
-      timestamp: Clock::get()?.unix_timestamp,
+      price: listing.price_in_tokens,
+      category: listing.category,
+      tags: listing.tags,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
     });
     Ok(())
   }
 
-  pub fn withdraw_platform_fees(
-    ctx: Context<WithdrawPlatformFees>,
-    amount: u64,
+  /// Permissionless: return an expired listing's NFT to the seller and forfeit
+  /// its deposit to the platform treasury
+  pub fn reap_expired_listing(ctx: Context<ReapExpiredListing>) -> Result<()> {
+    let ttl = ctx.accounts.config.listing_ttl_secs;
+    let listing = &mut ctx.accounts.listing;
+    require!(listing.is_active, ErrorCode::ListingInactive);
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+      ttl > 0 && now >= listing.listed_at.saturating_add(ttl),
+      ErrorCode::ListingNotExpired
+    );
+
+    // Transfer NFT back to seller from escrow (PDA signer)
+    let bump = ctx.bumps.escrow_authority;
+    let signer_seeds: &[&[u8]] = &[b"escrow", listing.nft_mint.as_ref(), &[bump]];
+    let cpi_accounts = Transfer {
+      from: ctx.accounts.escrow_nft_token_account.to_account_info(),
+      to: ctx.accounts.seller_nft_token_account.to_account_info(),
+      authority: ctx.accounts.escrow_authority.to_account_info(),
+    };
+    let signer: &[&[&[u8]]] = &[signer_seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+      ctx.accounts.token_program.to_account_info(),
+      cpi_accounts,
+      signer,
+    );
+    token::transfer(cpi_ctx, 1)?;
+
+    listing.is_active = false;
+    ctx.accounts.seller_stats.close_listing();
+    listing.cancelled_at = Some(now);
+    let deposit_forfeited = listing.deposit_lamports;
+    release_deposit(listing, &ctx.accounts.platform_treasury.to_account_info())?;
+
+    emit!(ListingReaped {
+      seller: listing.seller,
+      nft_mint: listing.nft_mint,
+      deposit_forfeited,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Convert an active listing into a layaway agreed by both seller and buyer.
+  /// The NFT stays in escrow (the listing goes inactive, so the seller can no longer
+  /// cancel it) while the buyer pays `price_in_tokens` off before `deadline`.
+  /// On a missed deadline payments are refunded to the buyer unless
+  /// `forfeit_on_default` was agreed, in which case the seller keeps them.
+  pub fn start_layaway(
+    ctx: Context<StartLayaway>,
+    down_payment: u64,
+    duration_secs: i64,
+    forfeit_on_default: bool,
   ) -> Result<()> {
-    let config = &ctx.accounts.config;
-    
-    // Transfer tokens from platform treasury to destination
+    require!(!ctx.accounts.config.purchases_paused, ErrorCode::MarketplacePaused);
+    require!(duration_secs > 0, ErrorCode::InvalidDuration);
+    let listing = &mut ctx.accounts.listing;
+    require!(listing.is_active, ErrorCode::ListingInactive);
+    let total_price = listing.price_in_tokens;
+    require!(down_payment < total_price, ErrorCode::InvalidPrice);
+
+    let now = Clock::get()?.unix_timestamp;
+    let layaway = &mut ctx.accounts.layaway;
+    layaway.buyer = ctx.accounts.buyer.key();
+    layaway.seller = listing.seller;
+    layaway.nft_mint = listing.nft_mint;
+    layaway.currency_mint = listing.currency_mint;
+    layaway.total_price = total_price;
+    layaway.paid_so_far = down_payment;
+    layaway.deadline = now.checked_add(duration_secs).ok_or(ErrorCode::MathOverflow)?;
+    layaway.forfeit_on_default = forfeit_on_default;
+    layaway.royalties_enforced = listing.royalties_enforced;
+    layaway.bump = ctx.bumps.layaway;
+    layaway.royalty_bps = listing.royalty_bps;
+    layaway.royalty_destination = listing.royalty_destination;
+
+    if down_payment > 0 {
+      let cpi_accounts = Transfer {
+        from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+        to: ctx.accounts.layaway_currency_token_account.to_account_info(),
+        authority: ctx.accounts.buyer.to_account_info(),
+      };
+      let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+      token::transfer(cpi_ctx, down_payment)?;
+    }
+
+    // The listing ends here; the NFT remains in escrow until the layaway settles or defaults
+    listing.is_active = false;
+    ctx.accounts.seller_stats.close_listing();
+    listing.buyer = Some(layaway.buyer);
+    release_deposit(listing, &ctx.accounts.seller.to_account_info())?;
+
+    emit!(LayawayStarted {
+      seller: layaway.seller,
+      buyer: layaway.buyer,
+      nft_mint: layaway.nft_mint,
+      total_price,
+      down_payment,
+      deadline: layaway.deadline,
+      forfeit_on_default,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Pay toward a layaway. The final payment settles it: the platform fee is taken,
+  /// the seller is paid, the NFT is released to the buyer and the layaway is closed.
+  pub fn make_layaway_payment(ctx: Context<MakeLayawayPayment>, amount: u64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let layaway = &mut ctx.accounts.layaway;
+    require!(now <= layaway.deadline, ErrorCode::LayawayExpired);
+    let remaining = layaway.total_price.saturating_sub(layaway.paid_so_far);
+    require!(amount > 0 && amount <= remaining, ErrorCode::InvalidPrice);
+    let balance_before = ctx.accounts.layaway_currency_token_account.amount;
+
     let cpi_accounts = Transfer {
-      from: ctx.accounts.platform_treasury_token_account.to_account_info(),
-      to: ctx.accounts.destination_token_account.to_account_info(),
-      authority: ctx.accounts.treasury_authority.to_account_info(),
+      from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+      to: ctx.accounts.layaway_currency_token_account.to_account_info(),
+      authority: ctx.accounts.buyer.to_account_info(),
     };
     let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
     token::transfer(cpi_ctx, amount)?;
+    layaway.paid_so_far = layaway.paid_so_far.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
 
-    emit!(PlatformFeesWithdrawn {
+    emit!(LayawayPayment {
+      buyer: layaway.buyer,
+      nft_mint: layaway.nft_mint,
       amount,
-      destination: ctx.accounts.destination_token_account.key(),
-      timestamp: Clock::get()?.unix_timestamp,
+      paid_so_far: layaway.paid_so_far,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
+    });
+    if layaway.paid_so_far < layaway.total_price {
+      return Ok(());
+    }
+
+    // Fully paid: settle exactly like buy_nft, but out of the layaway's token account
+    let price = layaway.total_price;
+    let fee_bps = ctx.accounts.config.platform_fee_bps(&layaway.seller, now);
+    let (fee, seller_amount) = split_fee(price, fee_bps, ctx.accounts.config.fee_rounding)?;
+    let (royalty, seller_amount) = split_royalty(price, fee, seller_amount, layaway.royalty_bps)?;
+    let nft_mint = layaway.nft_mint;
+    let layaway_bump = layaway.bump;
+    let layaway_seeds: &[&[u8]] = &[b"layaway", nft_mint.as_ref(), &[layaway_bump]];
+    let layaway_signer: &[&[&[u8]]] = &[layaway_seeds];
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.layaway_currency_token_account.to_account_info(),
+          to: ctx.accounts.seller_currency_token_account.to_account_info(),
+          authority: ctx.accounts.layaway.to_account_info(),
+        },
+        layaway_signer,
+      ),
+      seller_amount,
+    )?;
+    pay_royalty(
+      royalty,
+      ctx.accounts.royalty_token_account.as_deref(),
+      &ctx.accounts.layaway_currency_token_account,
+      ctx.accounts.layaway.to_account_info(),
+      layaway_signer,
+      &ctx.accounts.token_program,
+    )?;
+    if fee > 0 {
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.layaway_currency_token_account.to_account_info(),
+            to: ctx.accounts.platform_treasury_currency_token_account.to_account_info(),
+            authority: ctx.accounts.layaway.to_account_info(),
+          },
+          layaway_signer,
+        ),
+        fee,
+      )?;
+      let config = &mut ctx.accounts.config;
+      config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+    }
+    // Tokens sent to the layaway account outside of payments would block closing it; hand them to the buyer
+    let stray = balance_before.saturating_add(amount).saturating_sub(price);
+    if stray > 0 {
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.layaway_currency_token_account.to_account_info(),
+            to: ctx.accounts.buyer_currency_token_account.to_account_info(),
+            authority: ctx.accounts.layaway.to_account_info(),
+          },
+          layaway_signer,
+        ),
+        stray,
+      )?;
+    }
+
+    // Transfer NFT from escrow to buyer using PDA signer
+    let bump = ctx.bumps.escrow_authority;
+    let signer_seeds: &[&[u8]] = &[b"escrow", nft_mint.as_ref(), &[bump]];
+    let signer: &[&[&[u8]]] = &[signer_seeds];
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.escrow_nft_token_account.to_account_info(),
+          to: ctx.accounts.buyer_nft_token_account.to_account_info(),
+          authority: ctx.accounts.escrow_authority.to_account_info(),
+        },
+        signer,
+      ),
+      1,
+    )?;
+
+    close_layaway(
+      &ctx.accounts.layaway,
+      &ctx.accounts.layaway_currency_token_account,
+      &ctx.accounts.buyer.to_account_info(),
+      &ctx.accounts.token_program,
+    )?;
+
+    emit!(NFTSold {
+      seller: ctx.accounts.seller.key(),
+      buyer: ctx.accounts.buyer.key(),
+      nft_mint,
+      price,
+      fee,
+      royalties_enforced: ctx.accounts.layaway.royalties_enforced,
+      fee_waived: fee_bps == 0,
+      decimals: ctx.accounts.currency_mint.decimals,
+      royalty,
+      timestamp: now,
     });
     Ok(())
   }
-}
 
-#[account]
-pub struct Listing {
-  pub seller: Pubkey,
-  pub nft_mint: Pubkey,
-  pub currency_mint: Pubkey,
-  pub price_in_tokens: u64,
-  pub is_active: bool,
-  pub listed_at: i64,
-  pub cancelled_at: Option<i64>,
-  pub sold_at: Option<i64>,
-  pub buyer: Option<Pubkey>,
-  pub bump: u8,
-}
+  /// After a missed deadline the seller reclaims the NFT. Partial payments go back
+  /// to the buyer unless the layaway was started with `forfeit_on_default`.
+  pub fn reclaim_defaulted_layaway(ctx: Context<ReclaimDefaultedLayaway>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let layaway = &ctx.accounts.layaway;
+    require!(now > layaway.deadline, ErrorCode::LayawayNotExpired);
+    let payments_recipient = if layaway.forfeit_on_default { layaway.seller } else { layaway.buyer };
+    require!(
+      ctx.accounts.payments_recipient_token_account.owner == payments_recipient,
+      ErrorCode::WrongTokenOwner
+    );
 
-#[derive(Accounts)]
-pub struct InitializeConfig<'info> {
-  #[account(mut)]
-  pub admin: Signer<'info>,
-  #[account(
-    init,
-    payer = admin,
-    space = 8 + 32 + 32 + 1,
-    seeds = [b"config"],
+    let nft_mint = layaway.nft_mint;
+    let paid_so_far = layaway.paid_so_far;
+    // Move the whole balance (not just paid_so_far) so stray deposits can't block closing the account
+    let balance = ctx.accounts.layaway_currency_token_account.amount;
+    if balance > 0 {
+      let layaway_seeds: &[&[u8]] = &[b"layaway", nft_mint.as_ref(), &[layaway.bump]];
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.layaway_currency_token_account.to_account_info(),
+            to: ctx.accounts.payments_recipient_token_account.to_account_info(),
+            authority: ctx.accounts.layaway.to_account_info(),
+          },
+          &[layaway_seeds],
+        ),
+        balance,
+      )?;
+    }
+
+    // Transfer NFT back to seller from escrow (PDA signer)
+    let bump = ctx.bumps.escrow_authority;
+    let signer_seeds: &[&[u8]] = &[b"escrow", nft_mint.as_ref(), &[bump]];
+    let signer: &[&[&[u8]]] = &[signer_seeds];
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.escrow_nft_token_account.to_account_info(),
+          to: ctx.accounts.seller_nft_token_account.to_account_info(),
+          authority: ctx.accounts.escrow_authority.to_account_info(),
+        },
+        signer,
+      ),
+      1,
+    )?;
+
+    let forfeited = ctx.accounts.layaway.forfeit_on_default;
+    close_layaway(
+      &ctx.accounts.layaway,
+      &ctx.accounts.layaway_currency_token_account,
+      &ctx.accounts.buyer.to_account_info(),
+      &ctx.accounts.token_program,
+    )?;
+
+    emit!(LayawayDefaulted {
+      seller: ctx.accounts.seller.key(),
+      buyer: ctx.accounts.buyer.key(),
+      nft_mint,
+      paid_so_far,
+      payments_forfeited: forfeited,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Convert an active listing into an auction ending `duration_secs` from now.
+  /// The NFT stays in escrow; bids are held by the auction PDA's currency ATA.
+  pub fn start_auction(ctx: Context<StartAuction>, reserve_price: u64, duration_secs: i64) -> Result<()> {
+    require!(!ctx.accounts.config.listings_paused, ErrorCode::MarketplacePaused);
+    require!(duration_secs > 0, ErrorCode::InvalidDuration);
+    let listing = &mut ctx.accounts.listing;
+    require!(listing.is_active, ErrorCode::ListingInactive);
+
+    let now = Clock::get()?.unix_timestamp;
+    let auction = &mut ctx.accounts.auction;
+    auction.seller = listing.seller;
+    auction.nft_mint = listing.nft_mint;
+    auction.currency_mint = listing.currency_mint;
+    auction.reserve_price = reserve_price;
+    auction.highest_bid = 0;
+    auction.highest_bidder = None;
+    auction.end_time = now.checked_add(duration_secs).ok_or(ErrorCode::MathOverflow)?;
+    auction.royalties_enforced = listing.royalties_enforced;
+    auction.pending_refunds = 0;
+    auction.settled = false;
+    auction.bump = ctx.bumps.auction;
+    auction.royalty_bps = listing.royalty_bps;
+    auction.royalty_destination = listing.royalty_destination;
+
+    // The listing ends here; the NFT remains in escrow until the auction settles
+    listing.is_active = false;
+    ctx.accounts.seller_stats.close_listing();
+    release_deposit(listing, &ctx.accounts.seller.to_account_info())?;
+
+    emit!(AuctionStarted {
+      seller: auction.seller,
+      nft_mint: auction.nft_mint,
+      reserve_price,
+      end_time: auction.end_time,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Bid on an auction. The bid is escrowed and the previous highest bid is refunded
+  /// in the same instruction to `previous_bidder_currency_token_account` (their ATA).
+  /// If that account can't receive it (closed, frozen or re-owned) the refund is
+  /// recorded in the `[b"bid_refund", auction, previous_bidder]` PDA instead and paid
+  /// out with claim_bid_refund, so a bidder can never block being outbid.
+  pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+    require!(!ctx.accounts.config.purchases_paused, ErrorCode::MarketplacePaused);
+    let now = Clock::get()?.unix_timestamp;
+    let auction = &ctx.accounts.auction;
+    require!(!auction.settled && now < auction.end_time, ErrorCode::AuctionEnded);
+    require!(amount >= auction.reserve_price && amount > auction.highest_bid, ErrorCode::BidTooLow);
+
+    token::transfer(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.bidder_currency_token_account.to_account_info(),
+          to: ctx.accounts.auction_currency_token_account.to_account_info(),
+          authority: ctx.accounts.bidder.to_account_info(),
+        },
+      ),
+      amount,
+    )?;
+
+    let previous_bidder = auction.highest_bidder;
+    let previous_bid = auction.highest_bid;
+    let mut refund_claimable = false;
+    if let Some(previous_bidder) = previous_bidder {
+      let currency_mint = ctx.accounts.currency_mint.key();
+      let refund_account = ctx
+        .accounts
+        .previous_bidder_currency_token_account
+        .as_ref()
+        .ok_or(ErrorCode::MissingRefundAccount)?;
+      require_keys_eq!(
+        refund_account.key(),
+        anchor_spl::associated_token::get_associated_token_address(&previous_bidder, &currency_mint),
+        ErrorCode::WrongTokenOwner
+      );
+
+      if can_receive_refund(&refund_account.to_account_info(), &previous_bidder, &currency_mint) {
+        let nft_mint = auction.nft_mint;
+        let auction_seeds: &[&[u8]] = &[b"auction", nft_mint.as_ref(), &[auction.bump]];
+        token::transfer(
+          CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+              from: ctx.accounts.auction_currency_token_account.to_account_info(),
+              to: refund_account.to_account_info(),
+              authority: ctx.accounts.auction.to_account_info(),
+            },
+            &[auction_seeds],
+          ),
+          previous_bid,
+        )?;
+      } else {
+        let bid_refund = ctx.accounts.bid_refund.as_ref().ok_or(ErrorCode::MissingRefundAccount)?;
+        credit_bid_refund(
+          &bid_refund.to_account_info(),
+          &ctx.accounts.bidder.to_account_info(),
+          &ctx.accounts.system_program,
+          &ctx.accounts.auction.key(),
+          &previous_bidder,
+          previous_bid,
+          ctx.program_id,
+        )?;
+        let auction = &mut ctx.accounts.auction;
+        auction.pending_refunds = auction.pending_refunds.checked_add(previous_bid).ok_or(ErrorCode::MathOverflow)?;
+        refund_claimable = true;
+      }
+    }
+
+    let auction = &mut ctx.accounts.auction;
+    auction.highest_bid = amount;
+    auction.highest_bidder = Some(ctx.accounts.bidder.key());
+
+    emit!(BidPlaced {
+      bidder: ctx.accounts.bidder.key(),
+      nft_mint: auction.nft_mint,
+      amount,
+      previous_bidder,
+      refund_claimable,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Pay out an outbid refund that place_bid could not deliver. Closes the refund
+  /// record, and the auction too once it is settled and owes nothing else.
+  pub fn claim_bid_refund(ctx: Context<ClaimBidRefund>) -> Result<()> {
+    let amount = ctx.accounts.bid_refund.amount;
+    let auction = &ctx.accounts.auction;
+    let nft_mint = auction.nft_mint;
+    let auction_seeds: &[&[u8]] = &[b"auction", nft_mint.as_ref(), &[auction.bump]];
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.auction_currency_token_account.to_account_info(),
+          to: ctx.accounts.bidder_currency_token_account.to_account_info(),
+          authority: ctx.accounts.auction.to_account_info(),
+        },
+        &[auction_seeds],
+      ),
+      amount,
+    )?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.pending_refunds = auction.pending_refunds.saturating_sub(amount);
+    ctx.accounts.auction_currency_token_account.reload()?;
+    if ctx.accounts.auction.settled
+      && ctx.accounts.auction.pending_refunds == 0
+      && ctx.accounts.auction_currency_token_account.amount == 0
+    {
+      close_auction(
+        &ctx.accounts.auction,
+        &ctx.accounts.auction_currency_token_account,
+        &ctx.accounts.seller.to_account_info(),
+        &ctx.accounts.token_program,
+      )?;
+    }
+    Ok(())
+  }
+
+  /// Permissionless once the auction has ended: pay the seller (minus the platform
+  /// fee) and send the NFT to the highest bidder, or back to the seller without bids.
+  /// The auction is closed unless outbid refunds are still waiting to be claimed.
+  pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let auction = &ctx.accounts.auction;
+    require!(!auction.settled, ErrorCode::AuctionEnded);
+    require!(now >= auction.end_time, ErrorCode::AuctionNotEnded);
+
+    let nft_mint = auction.nft_mint;
+    let price = auction.highest_bid;
+    let winner = auction.highest_bidder;
+    let auction_seeds: &[&[u8]] = &[b"auction", nft_mint.as_ref(), &[auction.bump]];
+    let balance_after = ctx.accounts.auction_currency_token_account.amount.saturating_sub(price);
+
+    if let Some(winner) = winner {
+      let fee_bps = ctx.accounts.config.platform_fee_bps(&auction.seller, now);
+      let (fee, seller_amount) = split_fee(price, fee_bps, ctx.accounts.config.fee_rounding)?;
+      let (royalty, seller_amount) = split_royalty(price, fee, seller_amount, auction.royalty_bps)?;
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.auction_currency_token_account.to_account_info(),
+            to: ctx.accounts.seller_currency_token_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+          },
+          &[auction_seeds],
+        ),
+        seller_amount,
+      )?;
+      pay_royalty(
+        royalty,
+        ctx.accounts.royalty_token_account.as_deref(),
+        &ctx.accounts.auction_currency_token_account,
+        ctx.accounts.auction.to_account_info(),
+        &[auction_seeds],
+        &ctx.accounts.token_program,
+      )?;
+      if fee > 0 {
+        token::transfer(
+          CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+              from: ctx.accounts.auction_currency_token_account.to_account_info(),
+              to: ctx.accounts.platform_treasury_currency_token_account.to_account_info(),
+              authority: ctx.accounts.auction.to_account_info(),
+            },
+            &[auction_seeds],
+          ),
+          fee,
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+      }
+      emit!(NFTSold {
+        seller: ctx.accounts.seller.key(),
+        buyer: winner,
+        nft_mint,
+        price,
+        fee,
+        royalties_enforced: ctx.accounts.auction.royalties_enforced,
+        fee_waived: fee_bps == 0,
+        decimals: ctx.accounts.currency_mint.decimals,
+        royalty,
+        timestamp: now,
+      });
+    }
+
+    // NFT to the winner (the seller when nobody bid), escrow PDA signs
+    let bump = ctx.bumps.escrow_authority;
+    let signer_seeds: &[&[u8]] = &[b"escrow", nft_mint.as_ref(), &[bump]];
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.escrow_nft_token_account.to_account_info(),
+          to: ctx.accounts.winner_nft_token_account.to_account_info(),
+          authority: ctx.accounts.escrow_authority.to_account_info(),
+        },
+        &[signer_seeds],
+      ),
+      1,
+    )?;
+
+    ctx.accounts.auction.settled = true;
+    emit!(AuctionSettled {
+      seller: ctx.accounts.seller.key(),
+      winner,
+      nft_mint,
+      price,
+      timestamp: now,
+    });
+    if ctx.accounts.auction.pending_refunds == 0 && balance_after == 0 {
+      close_auction(
+        &ctx.accounts.auction,
+        &ctx.accounts.auction_currency_token_account,
+        &ctx.accounts.seller.to_account_info(),
+        &ctx.accounts.token_program,
+      )?;
+    }
+    Ok(())
+  }
+
+  /// Offer `price_per_nft` for each of up to `quantity` NFTs of a verified Metaplex
+  /// collection. The whole amount is escrowed in the `[b"collection_bid", bidder, collection]`
+  /// PDA's currency ATA and paid out one NFT at a time by accept_collection_bid.
+  pub fn place_collection_bid(
+    ctx: Context<PlaceCollectionBid>,
+    collection: Pubkey,
+    price_per_nft: u64,
+    quantity: u32,
+  ) -> Result<()> {
+    require!(!ctx.accounts.config.purchases_paused, ErrorCode::MarketplacePaused);
+    require!(price_per_nft > 0, ErrorCode::InvalidPrice);
+    require!(quantity > 0, ErrorCode::InvalidQuantity);
+    let total = price_per_nft.checked_mul(quantity as u64).ok_or(ErrorCode::MathOverflow)?;
+
+    token::transfer(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.bidder_currency_token_account.to_account_info(),
+          to: ctx.accounts.bid_currency_token_account.to_account_info(),
+          authority: ctx.accounts.bidder.to_account_info(),
+        },
+      ),
+      total,
+    )?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let bid = &mut ctx.accounts.collection_bid;
+    bid.bidder = ctx.accounts.bidder.key();
+    bid.collection = collection;
+    bid.currency_mint = ctx.accounts.currency_mint.key();
+    bid.price_per_nft = price_per_nft;
+    bid.quantity = quantity;
+    bid.filled = 0;
+    bid.royalties_enforced = ctx.accounts.config.resolve_royalties(true);
+    bid.created_at = now;
+    bid.bump = ctx.bumps.collection_bid;
+
+    emit!(CollectionBidPlaced {
+      bidder: bid.bidder,
+      collection,
+      price_per_nft,
+      quantity,
+      decimals: ctx.accounts.currency_mint.decimals,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Sell one NFT of the bid's collection into a collection bid at its per-NFT price,
+  /// minus the platform fee. The fill that meets the bid's quantity closes it and returns
+  /// anything left in its escrow to the bidder.
+  ///
+  /// Fills write the same bid account, so they never run concurrently: when two holders
+  /// race for the last slot, the later transaction finds the bid filled (or already
+  /// closed) and fails as a whole, leaving that holder's NFT where it was.
+  /// `expected_price` must match the bid, so a bid cancelled and re-placed lower in
+  /// between can't be filled at a price the seller never saw.
+  pub fn accept_collection_bid(ctx: Context<AcceptCollectionBid>, expected_price: u64) -> Result<()> {
+    require!(!ctx.accounts.config.purchases_paused, ErrorCode::MarketplacePaused);
+    let bid = &ctx.accounts.collection_bid;
+    require!(bid.filled < bid.quantity, ErrorCode::CollectionBidFilled);
+    require!(bid.price_per_nft == expected_price, ErrorCode::BidPriceChanged);
+    let nft_mint = ctx.accounts.nft_mint.key();
+    require!(
+      is_collection_member(&ctx.accounts.metadata, &nft_mint, &bid.collection)?,
+      ErrorCode::NotInCollection
+    );
+
+    // NFT: seller -> bidder
+    token::transfer(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.seller_nft_token_account.to_account_info(),
+          to: ctx.accounts.bidder_nft_token_account.to_account_info(),
+          authority: ctx.accounts.seller.to_account_info(),
+        },
+      ),
+      1,
+    )?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let price = bid.price_per_nft;
+    let bidder = bid.bidder;
+    let collection = bid.collection;
+    let seller = ctx.accounts.seller.key();
+    let fee_bps = ctx.accounts.config.platform_fee_bps(&seller, now);
+    let (fee, seller_amount) = split_fee(price, fee_bps, ctx.accounts.config.fee_rounding)?;
+    // Bids aren't tied to a listing, so an enforcing bid pays the currently configured royalty
+    let royalty_bps = ctx.accounts.config.sale_royalty_bps(bid.royalties_enforced);
+    let (royalty, seller_amount) = split_royalty(price, fee, seller_amount, royalty_bps)?;
+    let bid_seeds: &[&[u8]] = &[b"collection_bid", bidder.as_ref(), collection.as_ref(), &[bid.bump]];
+    token::transfer(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+          from: ctx.accounts.bid_currency_token_account.to_account_info(),
+          to: ctx.accounts.seller_currency_token_account.to_account_info(),
+          authority: ctx.accounts.collection_bid.to_account_info(),
+        },
+        &[bid_seeds],
+      ),
+      seller_amount,
+    )?;
+    pay_royalty(
+      royalty,
+      ctx.accounts.royalty_token_account.as_deref(),
+      &ctx.accounts.bid_currency_token_account,
+      ctx.accounts.collection_bid.to_account_info(),
+      &[bid_seeds],
+      &ctx.accounts.token_program,
+    )?;
+    if fee > 0 {
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.bid_currency_token_account.to_account_info(),
+            to: ctx.accounts.platform_treasury_currency_token_account.to_account_info(),
+            authority: ctx.accounts.collection_bid.to_account_info(),
+          },
+          &[bid_seeds],
+        ),
+        fee,
+      )?;
+      let config = &mut ctx.accounts.config;
+      config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    let bid = &mut ctx.accounts.collection_bid;
+    bid.filled = bid.filled.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    emit!(NFTSold {
+      seller,
+      buyer: bidder,
+      nft_mint,
+      price,
+      fee,
+      royalties_enforced: bid.royalties_enforced,
+      fee_waived: fee_bps == 0,
+      decimals: ctx.accounts.currency_mint.decimals,
+      royalty,
+      timestamp: now,
+    });
+
+    if bid.filled == bid.quantity {
+      // Only tokens sent to the escrow from outside can be left over at this point
+      ctx.accounts.bid_currency_token_account.reload()?;
+      let remainder = ctx.accounts.bid_currency_token_account.amount;
+      if remainder > 0 {
+        let refund_account = ctx
+          .accounts
+          .bidder_currency_token_account
+          .as_ref()
+          .ok_or(ErrorCode::MissingRefundAccount)?;
+        token::transfer(
+          CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+              from: ctx.accounts.bid_currency_token_account.to_account_info(),
+              to: refund_account.to_account_info(),
+              authority: ctx.accounts.collection_bid.to_account_info(),
+            },
+            &[bid_seeds],
+          ),
+          remainder,
+        )?;
+      }
+      close_collection_bid(
+        &ctx.accounts.collection_bid,
+        &ctx.accounts.bid_currency_token_account,
+        &ctx.accounts.bidder.to_account_info(),
+        &ctx.accounts.token_program,
+      )?;
+      emit!(CollectionBidClosed {
+        bidder,
+        collection,
+        filled: ctx.accounts.collection_bid.filled,
+        refunded: remainder,
+        timestamp: now,
+      });
+    }
+    Ok(())
+  }
+
+  /// Withdraw a collection bid: the unfilled remainder of its escrow goes back to the
+  /// bidder and the bid closes
+  pub fn cancel_collection_bid(ctx: Context<CancelCollectionBid>) -> Result<()> {
+    let bid = &ctx.accounts.collection_bid;
+    let refunded = ctx.accounts.bid_currency_token_account.amount;
+    let bid_seeds: &[&[u8]] = &[b"collection_bid", bid.bidder.as_ref(), bid.collection.as_ref(), &[bid.bump]];
+    if refunded > 0 {
+      token::transfer(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          Transfer {
+            from: ctx.accounts.bid_currency_token_account.to_account_info(),
+            to: ctx.accounts.bidder_currency_token_account.to_account_info(),
+            authority: ctx.accounts.collection_bid.to_account_info(),
+          },
+          &[bid_seeds],
+        ),
+        refunded,
+      )?;
+    }
+    close_collection_bid(
+      &ctx.accounts.collection_bid,
+      &ctx.accounts.bid_currency_token_account,
+      &ctx.accounts.bidder.to_account_info(),
+      &ctx.accounts.token_program,
+    )?;
+
+    emit!(CollectionBidClosed {
+      bidder: ctx.accounts.collection_bid.bidder,
+      collection: ctx.accounts.collection_bid.collection,
+      filled: ctx.accounts.collection_bid.filled,
+      refunded,
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+  }
+
+  /// Escrow-less instant buy: the seller signs a permit off-chain and approves the
+  /// `[b"permit_delegate"]` PDA as delegate on their NFT account; the buyer submits the
+  /// permit with an ed25519 verify instruction immediately before this one.
+  ///
+  /// Signed message (little-endian): program id | nft_mint | currency_mint | price | nonce | expiry.
+  /// Each (seller, nonce) can be used once; sellers revoke a permit with `cancel_permit`.
+  pub fn buy_with_permit(ctx: Context<BuyWithPermit>, price: u64, nonce: u64, expiry: i64) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(!config.listings_paused && !config.purchases_paused, ErrorCode::MarketplacePaused);
+    require!(price > 0, ErrorCode::InvalidPrice);
+    if let Some(max_price) = config.max_listing_price(&ctx.accounts.currency_mint.key()) {
+      require!(price <= max_price, ErrorCode::PriceExceedsMax);
+    }
+    let now = Clock::get()?.unix_timestamp;
+    require!(now <= expiry, ErrorCode::PermitExpired);
+
+    let seller_key = ctx.accounts.seller.key();
+    let mut message = Vec::with_capacity(PERMIT_MESSAGE_LEN);
+    message.extend_from_slice(crate::ID.as_ref());
+    message.extend_from_slice(ctx.accounts.nft_mint.key().as_ref());
+    message.extend_from_slice(ctx.accounts.currency_mint.key().as_ref());
+    message.extend_from_slice(&price.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&expiry.to_le_bytes());
+    verify_ed25519_permit(&ctx.accounts.instructions_sysvar, &seller_key, &message)?;
+
+    // Consuming the nonce PDA (init) is the replay guard
+    let permit_nonce = &mut ctx.accounts.permit_nonce;
+    permit_nonce.seller = seller_key;
+    permit_nonce.bump = ctx.bumps.permit_nonce;
+
+    let fee_bps = config.platform_fee_bps(&seller_key, now);
+    let (fee, seller_amount) = split_fee(price, fee_bps, config.fee_rounding)?;
+    let royalties_enforced = config.royalty_policy != RoyaltyPolicy::Never;
+    let (royalty, seller_amount) =
+      split_royalty(price, fee, seller_amount, config.sale_royalty_bps(royalties_enforced))?;
+
+    // Transfer currency tokens: buyer -> seller
+    let cpi_accounts = Transfer {
+      from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+      to: ctx.accounts.seller_currency_token_account.to_account_info(),
+      authority: ctx.accounts.buyer.to_account_info(),
+    };
+    token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), seller_amount)?;
+    pay_royalty(
+      royalty,
+      ctx.accounts.royalty_token_account.as_deref(),
+      &ctx.accounts.buyer_currency_token_account,
+      ctx.accounts.buyer.to_account_info(),
+      &[],
+      &ctx.accounts.token_program,
+    )?;
+
+    // Transfer fee: buyer -> platform treasury
+    if fee > 0 {
+      let cpi_accounts = Transfer {
+        from: ctx.accounts.buyer_currency_token_account.to_account_info(),
+        to: ctx.accounts.platform_treasury_currency_token_account.to_account_info(),
+        authority: ctx.accounts.buyer.to_account_info(),
+      };
+      token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), fee)?;
+      let config = &mut ctx.accounts.config;
+      config.total_fees_accrued = config.total_fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    // Transfer NFT straight from the seller using the delegate PDA
+    let bump = ctx.bumps.permit_delegate;
+    let signer_seeds: &[&[u8]] = &[b"permit_delegate", &[bump]];
+    let signer: &[&[&[u8]]] = &[signer_seeds];
+    let cpi_accounts = Transfer {
+      from: ctx.accounts.seller_nft_token_account.to_account_info(),
+      to: ctx.accounts.buyer_nft_token_account.to_account_info(),
+      authority: ctx.accounts.permit_delegate.to_account_info(),
+    };
+    token::transfer(
+      CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer),
+      1,
+    )?;
+
+    emit!(NFTSold {
+      seller: seller_key,
+      buyer: ctx.accounts.buyer.key(),
+      nft_mint: ctx.accounts.nft_mint.key(),
+      price,
+      fee,
+      royalties_enforced,
+      fee_waived: fee_bps == 0,
+      decimals: ctx.accounts.currency_mint.decimals,
+      royalty,
+      timestamp: now,
+    });
+    Ok(())
+  }
+
+  /// Revoke an unused permit by consuming its nonce
+  pub fn cancel_permit(ctx: Context<CancelPermit>, _nonce: u64) -> Result<()> {
+    let permit_nonce = &mut ctx.accounts.permit_nonce;
+    permit_nonce.seller = ctx.accounts.seller.key();
+    permit_nonce.bump = ctx.bumps.permit_nonce;
+    Ok(())
+  }
+
+  pub fn update_listing_price(ctx: Context<UpdateListingPrice>, new_price_in_tokens: u64) -> Result<()> {
+    let listing = &mut ctx.accounts.listing;
+    require!(listing.is_active, ErrorCode::ListingInactive);
+    require!(new_price_in_tokens > 0, ErrorCode::InvalidPrice);
+    let old_price = listing.price_in_tokens;
+    listing.price_in_tokens = new_price_in_tokens;
+    emit!(PriceUpdated {
+      nft_mint: listing.nft_mint,
+      old_price,
+      new_price: new_price_in_tokens,
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+  }
+
+  /// Hand an active listing to another wallet (wallet migration, OTC handoff). The NFT
+  /// stays in escrow; the new seller controls cancel and price, and receives the sale
+  /// proceeds and the listing deposit.
+  pub fn transfer_listing(ctx: Context<TransferListing>, new_seller: Pubkey) -> Result<()> {
+    let listing = &mut ctx.accounts.listing;
+    require!(listing.is_active, ErrorCode::ListingInactive);
+    let previous_seller = listing.seller;
+    ctx.accounts.seller_stats.close_listing();
+    let new_seller_stats = &mut ctx.accounts.new_seller_stats;
+    new_seller_stats.init_if_new(new_seller, ctx.bumps.new_seller_stats);
+    new_seller_stats.open_listing(&ctx.accounts.config)?;
+    listing.seller = new_seller;
+    emit!(ListingTransferred {
+      nft_mint: listing.nft_mint,
+      previous_seller,
+      new_seller,
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+  }
+
+  /// Reprice several of the signer's listings at once (passed writable in remaining_accounts).
+  /// Inactive listings are skipped so one sale mid-batch doesn't fail the rest.
+  pub fn update_listing_prices_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UpdateListingPricesBatch<'info>>,
+    update: BatchPriceUpdate,
+  ) -> Result<()> {
+    let listings = ctx.remaining_accounts;
+    require!(!listings.is_empty() && listings.len() <= MAX_BATCH_PRICE_UPDATES, ErrorCode::InvalidBatchSize);
+    if let BatchPriceUpdate::Prices(prices) = &update {
+      require!(prices.len() == listings.len(), ErrorCode::InvalidBatchSize);
+    }
+
+    let seller_key = ctx.accounts.seller.key();
+    let now = Clock::get()?.unix_timestamp;
+    for (i, listing_info) in listings.iter().enumerate() {
+      let mut listing = Account::<Listing>::try_from(listing_info)?;
+      require_keys_eq!(listing.seller, seller_key, ErrorCode::WrongSeller);
+      if !listing.is_active {
+        continue;
+      }
+
+      let old_price = listing.price_in_tokens;
+      let new_price = match &update {
+        BatchPriceUpdate::MultiplierBps(bps) => (old_price as u128)
+          .checked_mul(*bps as u128)
+          .and_then(|v| v.checked_div(10_000))
+          .and_then(|v| u64::try_from(v).ok())
+          .ok_or(ErrorCode::MathOverflow)?,
+        BatchPriceUpdate::Prices(prices) => prices[i],
+      };
+      require!(new_price > 0, ErrorCode::InvalidPrice);
+
+      listing.price_in_tokens = new_price;
+      listing.exit(ctx.program_id)?;
+      emit!(PriceUpdated {
+        nft_mint: listing.nft_mint,
+        old_price,
+        new_price,
+        timestamp: now,
+      });
+    }
+    Ok(())
+  }
+
+  pub fn withdraw_platform_fees(
+    ctx: Context<WithdrawPlatformFees>,
+    amount: u64,
+  ) -> Result<()> {
+    // Only fees the marketplace has collected may leave the treasury
+    let config = &mut ctx.accounts.config;
+    let available = config.total_fees_accrued.saturating_sub(config.total_fees_withdrawn);
+    require!(amount <= available, ErrorCode::ExceedsAccruedFees);
+    config.total_fees_withdrawn = config.total_fees_withdrawn.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+    // Transfer tokens from platform treasury to destination
+    let cpi_accounts = Transfer {
+      from: ctx.accounts.platform_treasury_token_account.to_account_info(),
+      to: ctx.accounts.destination_token_account.to_account_info(),
+      authority: ctx.accounts.treasury_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    emit!(PlatformFeesWithdrawn {
+      amount,
+      destination: ctx.accounts.destination_token_account.key(),
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+  }
+}
+
+/// Move a listing's locked deposit out of the listing PDA to `recipient`
+fn release_deposit(listing: &mut Account<Listing>, recipient: &AccountInfo) -> Result<()> {
+  let deposit = listing.deposit_lamports;
+  if deposit > 0 {
+    listing.deposit_lamports = 0;
+    listing.to_account_info().sub_lamports(deposit)?;
+    recipient.add_lamports(deposit)?;
+  }
+  Ok(())
+}
+
+/// Close a settled layaway's (now empty) currency account and the layaway itself,
+/// returning both rents to the buyer who funded them
+fn close_layaway<'info>(
+  layaway: &Account<'info, Layaway>,
+  layaway_currency_token_account: &Account<'info, TokenAccount>,
+  buyer: &AccountInfo<'info>,
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  let layaway_seeds: &[&[u8]] = &[b"layaway", layaway.nft_mint.as_ref(), &[layaway.bump]];
+  token::close_account(CpiContext::new_with_signer(
+    token_program.to_account_info(),
+    CloseAccount {
+      account: layaway_currency_token_account.to_account_info(),
+      destination: buyer.clone(),
+      authority: layaway.to_account_info(),
+    },
+    &[layaway_seeds],
+  ))?;
+  layaway.close(buyer.clone())
+}
+
+/// Close a settled auction's (now empty) currency account and the auction itself,
+/// returning both rents to the seller who funded them
+fn close_auction<'info>(
+  auction: &Account<'info, Auction>,
+  auction_currency_token_account: &Account<'info, TokenAccount>,
+  seller: &AccountInfo<'info>,
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  let auction_seeds: &[&[u8]] = &[b"auction", auction.nft_mint.as_ref(), &[auction.bump]];
+  token::close_account(CpiContext::new_with_signer(
+    token_program.to_account_info(),
+    CloseAccount {
+      account: auction_currency_token_account.to_account_info(),
+      destination: seller.clone(),
+      authority: auction.to_account_info(),
+    },
+    &[auction_seeds],
+  ))?;
+  auction.close(seller.clone())
+}
+
+/// Close a collection bid's (emptied) currency account and the bid itself,
+/// returning both rents to the bidder who funded them
+fn close_collection_bid<'info>(
+  bid: &Account<'info, CollectionBid>,
+  bid_currency_token_account: &Account<'info, TokenAccount>,
+  bidder: &AccountInfo<'info>,
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  let bid_seeds: &[&[u8]] = &[b"collection_bid", bid.bidder.as_ref(), bid.collection.as_ref(), &[bid.bump]];
+  token::close_account(CpiContext::new_with_signer(
+    token_program.to_account_info(),
+    CloseAccount {
+      account: bid_currency_token_account.to_account_info(),
+      destination: bidder.clone(),
+      authority: bid.to_account_info(),
+    },
+    &[bid_seeds],
+  ))?;
+  bid.close(bidder.clone())
+}
+
+/// Whether `info` is an open, unfrozen `currency_mint` token account of `owner`,
+/// i.e. a transfer into it cannot fail
+fn can_receive_refund(info: &AccountInfo, owner: &Pubkey, currency_mint: &Pubkey) -> bool {
+  if info.owner != &token::ID || info.data_is_empty() {
+    return false;
+  }
+  let Ok(data) = info.try_borrow_data() else {
+    return false;
+  };
+  match TokenAccount::try_deserialize(&mut &data[..]) {
+    Ok(account) => account.owner == *owner && account.mint == *currency_mint && !account.is_frozen(),
+    Err(_) => false,
+  }
+}
+
+/// Add `amount` to the bidder's `[b"bid_refund", auction, bidder]` record, creating it
+/// (paid by `payer`) on first use
+fn credit_bid_refund<'info>(
+  bid_refund: &AccountInfo<'info>,
+  payer: &AccountInfo<'info>,
+  system_program: &Program<'info, System>,
+  auction: &Pubkey,
+  bidder: &Pubkey,
+  amount: u64,
+  program_id: &Pubkey,
+) -> Result<()> {
+  let (expected, bump) = Pubkey::find_program_address(&[b"bid_refund", auction.as_ref(), bidder.as_ref()], program_id);
+  require_keys_eq!(bid_refund.key(), expected, ErrorCode::MissingRefundAccount);
+
+  if bid_refund.owner == program_id {
+    let mut data = bid_refund.try_borrow_mut_data()?;
+    let mut refund = BidRefund::try_deserialize(&mut &data[..])?;
+    refund.amount = refund.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+    return refund.try_serialize(&mut &mut data[..]);
+  }
+
+  // Fund, allocate and assign separately so lamports sent to the address ahead of
+  // time can't make account creation fail
+  let seeds: &[&[u8]] = &[b"bid_refund", auction.as_ref(), bidder.as_ref(), &[bump]];
+  let top_up = Rent::get()?.minimum_balance(BID_REFUND_SPACE).saturating_sub(bid_refund.lamports());
+  if top_up > 0 {
+    anchor_lang::system_program::transfer(
+      CpiContext::new(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Transfer { from: payer.clone(), to: bid_refund.clone() },
+      ),
+      top_up,
+    )?;
+  }
+  anchor_lang::system_program::allocate(
+    CpiContext::new_with_signer(
+      system_program.to_account_info(),
+      anchor_lang::system_program::Allocate { account_to_allocate: bid_refund.clone() },
+      &[seeds],
+    ),
+    BID_REFUND_SPACE as u64,
+  )?;
+  anchor_lang::system_program::assign(
+    CpiContext::new_with_signer(
+      system_program.to_account_info(),
+      anchor_lang::system_program::Assign { account_to_assign: bid_refund.clone() },
+      &[seeds],
+    ),
+    program_id,
+  )?;
+  let refund = BidRefund { auction: *auction, bidder: *bidder, amount, bump };
+  let mut data = bid_refund.try_borrow_mut_data()?;
+  refund.try_serialize(&mut &mut data[..])
+}
+
+/// Check that the instruction right before this one is an ed25519 verification of
+/// exactly `message` signed by `signer` (all data inline in that instruction)
+fn verify_ed25519_permit(instructions_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+  let current_index = sysvar_instructions::load_current_index_checked(instructions_sysvar)?;
+  require!(current_index > 0, ErrorCode::InvalidPermit);
+  let ix = sysvar_instructions::load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+  require!(ix.program_id == ed25519_program::ID, ErrorCode::InvalidPermit);
+
+  // Layout: num_signatures (1), padding (1), then 7 u16 offsets per signature
+  let data = &ix.data;
+  require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidPermit);
+  let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+  let public_key_offset = read_u16(6) as usize;
+  let message_offset = read_u16(10) as usize;
+  let message_size = read_u16(12) as usize;
+  // Signature, public key and message must all come from the ed25519 instruction itself
+  for index_at in [4, 8, 14] {
+    require!(read_u16(index_at) == u16::MAX, ErrorCode::InvalidPermit);
+  }
+  let public_key = data
+    .get(public_key_offset..public_key_offset + 32)
+    .ok_or(ErrorCode::InvalidPermit)?;
+  let signed_message = data
+    .get(message_offset..message_offset + message_size)
+    .ok_or(ErrorCode::InvalidPermit)?;
+  require!(public_key == signer.as_ref(), ErrorCode::InvalidPermit);
+  require!(signed_message == message, ErrorCode::InvalidPermit);
+  Ok(())
+}
+
+/// Decode `nft_mint`'s Metaplex metadata, checking the account is its metadata PDA
+fn load_metadata(metadata: &AccountInfo, nft_mint: &Pubkey) -> Result<mpl_token_metadata::accounts::Metadata> {
+  let (metadata_pda, _) = Pubkey::find_program_address(
+    &[b"metadata", mpl_token_metadata::ID.as_ref(), nft_mint.as_ref()],
+    &mpl_token_metadata::ID,
+  );
+  require!(metadata.key() == metadata_pda, ErrorCode::InvalidMetadata);
+  require!(*metadata.owner == mpl_token_metadata::ID, ErrorCode::InvalidMetadata);
+  let data = metadata.try_borrow_data()?;
+  mpl_token_metadata::accounts::Metadata::safe_deserialize(&data).map_err(|_| error!(ErrorCode::InvalidMetadata))
+}
+
+/// Whether the NFT's metadata names `collection` as its verified Metaplex collection.
+/// Unverified collection fields can be set by anyone and don't count.
+fn is_collection_member(metadata: &AccountInfo, nft_mint: &Pubkey, collection: &Pubkey) -> Result<bool> {
+  let metadata = load_metadata(metadata, nft_mint)?;
+  Ok(metadata.collection.is_some_and(|c| c.verified && c.key == *collection))
+}
+
+/// Read name/uri from an NFT's Metaplex metadata account (strings are null-padded on chain)
+fn read_metadata_snapshot(metadata: &AccountInfo, nft_mint: &Pubkey) -> Result<ListingSnapshot> {
+  let metadata = load_metadata(metadata, nft_mint)?;
+  // Metaplex caps name/uri at MAX_NAME_LENGTH/MAX_URI_LENGTH bytes, so they always fit
+  Ok(ListingSnapshot {
+    name: metadata.name.trim_end_matches('\0').to_string(),
+    uri: metadata.uri.trim_end_matches('\0').to_string(),
+  })
+}
+
+/// Set the platform fee (capped at MAX_PLATFORM_FEE_BPS) on a migrated config, emitting
+/// ConfigUpdated when it changes
+fn apply_platform_fee(config: &mut Config, platform_fee_bps: u16) -> Result<()> {
+  require!(platform_fee_bps <= MAX_PLATFORM_FEE_BPS, ErrorCode::PlatformFeeTooHigh);
+  require!(config.version >= CONFIG_VERSION, ErrorCode::ConfigNotMigrated);
+  let previous_platform_fee_bps = config.platform_fee_bps;
+  if previous_platform_fee_bps != platform_fee_bps {
+    config.platform_fee_bps = platform_fee_bps;
+    emit!(ConfigUpdated {
+      previous_platform_fee_bps,
+      platform_fee_bps,
+      timestamp: Clock::get()?.unix_timestamp,
+    });
+  }
+  Ok(())
+}
+
+/// Split a sale price into (fee, seller_amount) so that fee + seller_amount == price.
+/// The product is taken in u128, so any u64 price is safe (u64::MAX * 10_000 < u128::MAX),
+/// and with fee_bps <= 10_000 the fee never exceeds the price.
+fn split_fee(price: u64, fee_bps: u16, rounding: FeeRounding) -> Result<(u64, u64)> {
+  require!(fee_bps <= 10_000, ErrorCode::InvalidFeeBps);
+  let numerator = (price as u128)
+    .checked_mul(fee_bps as u128)
+    .ok_or(ErrorCode::MathOverflow)?;
+  let fee = match rounding {
+    FeeRounding::Floor => numerator / 10_000,
+    FeeRounding::HalfUp => numerator
+      .checked_add(5_000)
+      .ok_or(ErrorCode::MathOverflow)?
+      / 10_000,
+  };
+  let fee = u64::try_from(fee).map_err(|_| ErrorCode::MathOverflow)?;
+  let seller_amount = price.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+  Ok((fee, seller_amount))
+}
+
+/// Take a listing's royalty (royalty_bps of the price, rounded down) out of the seller's
+/// share of a sale. Returns (royalty, seller_amount) with fee + royalty + seller_amount == price.
+fn split_royalty(price: u64, fee: u64, seller_amount: u64, royalty_bps: u16) -> Result<(u64, u64)> {
+  let royalty = (price as u128)
+    .checked_mul(royalty_bps as u128)
+    .ok_or(ErrorCode::MathOverflow)?
+    / 10_000;
+  let royalty = u64::try_from(royalty).map_err(|_| ErrorCode::MathOverflow)?;
+  let seller_amount = seller_amount.checked_sub(royalty).ok_or(ErrorCode::MathOverflow)?;
+  let total = fee
+    .checked_add(royalty)
+    .and_then(|paid| paid.checked_add(seller_amount))
+    .ok_or(ErrorCode::MathOverflow)?;
+  require!(total == price, ErrorCode::MathOverflow);
+  Ok((royalty, seller_amount))
+}
+
+/// Pay a sale's royalty to the royalty destination, out of `from` (the buyer's account,
+/// or an escrow PDA's account with `signer` holding its seeds)
+fn pay_royalty<'info>(
+  royalty: u64,
+  royalty_token_account: Option<&Account<'info, TokenAccount>>,
+  from: &Account<'info, TokenAccount>,
+  authority: AccountInfo<'info>,
+  signer: &[&[&[u8]]],
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  if royalty == 0 {
+    return Ok(());
+  }
+  let royalty_token_account = royalty_token_account.ok_or(ErrorCode::MissingRoyaltyAccount)?;
+  let cpi_accounts = Transfer {
+    from: from.to_account_info(),
+    to: royalty_token_account.to_account_info(),
+    authority,
+  };
+  token::transfer(
+    CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer),
+    royalty,
+  )
+}
+
+#[account]
+pub struct Listing {
+  pub seller: Pubkey,
+  pub nft_mint: Pubkey,
+  pub currency_mint: Pubkey,
+  pub price_in_tokens: u64,
+  pub is_active: bool,
+  pub listed_at: i64,
+  pub cancelled_at: Option<i64>,
+  pub sold_at: Option<i64>,
+  pub buyer: Option<Pubkey>,
+  pub deposit_lamports: u64, // refundable anti-spam deposit held in this PDA
+  pub royalties_enforced: bool, // resolved from Config.royalty_policy at list time
+  pub category: ListingCategory,
+  pub tags: u32, // bitfield; bit meanings are defined off-chain
+  pub bump: u8,
+  // Appended after bump so listings created before snapshots existed still decode (as None)
+  pub snapshot: Option<ListingSnapshot>,
+  // Appended after snapshot so listings created before royalties existed decode as paying none
+  pub royalty_bps: u16, // Config.royalty_bps at list time when royalties are enforced, else 0
+  pub royalty_destination: Pubkey, // owner of the token account the royalty is paid to
+}
+
+/// Cached copy of the NFT's on-chain Metaplex name and uri
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ListingSnapshot {
+  pub name: String, // max MAX_NAME_LENGTH
+  pub uri: String,  // max MAX_URI_LENGTH
+}
+
+/// Installment purchase of an escrowed NFT; payments are held by this PDA's currency ATA
+#[account]
+pub struct Layaway {
+  pub buyer: Pubkey,
+  pub seller: Pubkey,
+  pub nft_mint: Pubkey,
+  pub currency_mint: Pubkey,
+  pub total_price: u64,
+  pub paid_so_far: u64,
+  pub deadline: i64,
+  pub forfeit_on_default: bool, // seller keeps partial payments on a missed deadline
+  pub royalties_enforced: bool, // carried over from the listing
+  pub bump: u8,
+  pub royalty_bps: u16,            // carried over from the listing, paid at settlement
+  pub royalty_destination: Pubkey, // carried over from the listing
+}
+
+/// English auction of an escrowed NFT; bids are held by this PDA's currency ATA
+#[account]
+pub struct Auction {
+  pub seller: Pubkey,
+  pub nft_mint: Pubkey,
+  pub currency_mint: Pubkey,
+  pub reserve_price: u64,
+  pub highest_bid: u64,
+  pub highest_bidder: Option<Pubkey>,
+  pub end_time: i64,
+  pub royalties_enforced: bool, // carried over from the listing
+  pub pending_refunds: u64,     // outbid refunds recorded in BidRefund PDAs, still escrowed here
+  pub settled: bool,
+  pub bump: u8,
+  pub royalty_bps: u16,            // carried over from the listing, paid at settlement
+  pub royalty_destination: Pubkey, // carried over from the listing
+}
+
+/// Active listings of one seller wallet, for Config::max_active_listings
+#[account]
+pub struct SellerStats {
+  pub seller: Pubkey,
+  pub active_listings: u32,
+  pub bump: u8,
+}
+
+impl SellerStats {
+  /// Fill in a stats account just created by init_if_needed
+  pub fn init_if_new(&mut self, seller: Pubkey, bump: u8) {
+    if self.seller == Pubkey::default() {
+      self.seller = seller;
+      self.bump = bump;
+    }
+  }
+
+  /// Count a newly active listing, enforcing the configured cap
+  pub fn open_listing(&mut self, config: &Config) -> Result<()> {
+    if config.max_active_listings > 0 {
+      require!(self.active_listings < config.max_active_listings, ErrorCode::TooManyActiveListings);
+    }
+    self.active_listings = self.active_listings.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+  }
+
+  pub fn close_listing(&mut self) {
+    self.active_listings = self.active_listings.saturating_sub(1);
+  }
+}
+
+/// Outbid refund that couldn't be sent to the bidder's ATA; paid by claim_bid_refund
+#[account]
+pub struct BidRefund {
+  pub auction: Pubkey,
+  pub bidder: Pubkey,
+  pub amount: u64,
+  pub bump: u8,
+}
+
+/// Standing offer for up to `quantity` NFTs of a collection; the unfilled part of the
+/// offer is held by this PDA's currency ATA
+#[account]
+pub struct CollectionBid {
+  pub bidder: Pubkey,
+  pub collection: Pubkey, // verified Metaplex collection mint
+  pub currency_mint: Pubkey,
+  pub price_per_nft: u64,
+  pub quantity: u32,
+  pub filled: u32,
+  pub royalties_enforced: bool, // resolved from Config.royalty_policy when placed
+  pub created_at: i64,
+  pub bump: u8,
+}
+
+/// Marks a (seller, nonce) permit as used or revoked
+#[account]
+pub struct PermitNonce {
+  pub seller: Pubkey,
+  pub bump: u8,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(
+    init,
+    payer = admin,
+    space = CONFIG_SPACE,
+    seeds = [b"config"],
+    bump
+  )]
+  pub config: Account<'info, Config>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ListNFT<'info> {
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  /// CHECK: Optional Metaplex metadata PDA for nft_mint; when present its name/uri
+  /// are snapshotted into the listing (verified in read_metadata_snapshot)
+  pub metadata: Option<UncheckedAccount<'info>>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    space = if metadata.is_some() { LISTING_SPACE_WITH_SNAPSHOT } else { LISTING_SPACE },
+    seeds = [b"listing", nft_mint.key().as_ref()],
+    bump
+  )]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    space = SELLER_STATS_SPACE,
+    seeds = [b"seller_stats", seller.key().as_ref()],
+    bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  pub nft_mint: Account<'info, Mint>,
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    constraint = seller_nft_token_account.owner == seller.key(),
+    constraint = seller_nft_token_account.mint == nft_mint.key()
+  )]
+  pub seller_nft_token_account: Account<'info, TokenAccount>,
+  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  #[account(
+    seeds = [b"escrow", nft_mint.key().as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = nft_mint,
+    associated_token::authority = escrow_authority,
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+  #[account(mut, has_one = seller)]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  /// CHECK: Receives the cancellation penalty; required only when config.cancel_fee_lamports > 0
+  /// (must be config.platform_treasury, checked in the instruction)
+  #[account(mut)]
+  pub platform_treasury: Option<UncheckedAccount<'info>>,
+  #[account(address = listing.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Account<'info, Mint>,
+  /// CHECK: PDA authority for escrow (validated manually in instruction)
+  #[account(
+    seeds = [b"escrow", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == listing.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = nft_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_nft_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReapExpiredListing<'info> {
+  #[account(mut)]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  /// Anyone may reap; pays for the seller's NFT ATA if it was closed
+  #[account(mut)]
+  pub reaper: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(address = listing.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Account<'info, Mint>,
+  /// CHECK: Listing seller, receives the NFT back
+  #[account(address = listing.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  /// CHECK: Receives the forfeited deposit
+  #[account(mut, address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  #[account(
+    seeds = [b"escrow", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == listing.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = reaper,
+    associated_token::mint = nft_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_nft_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyNFT<'info> {
+  #[account(mut)]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  #[account(mut)]
+  pub buyer: Signer<'info>,
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump
+  )]
+  pub config: Account<'info, Config>,
+  pub currency_mint: Account<'info, Mint>,
+  #[account(address = listing.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Account<'info, Mint>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = buyer,
+  )]
+  pub buyer_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Listing seller wallet; authority of the seller currency ATA and
+  /// recipient of the refunded listing deposit
+  #[account(mut, address = listing.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Account<'info, TokenAccount>,
+  /// Royalty destination's currency account; required when the listing pays a royalty
+  #[account(
+    mut,
+    constraint = royalty_token_account.mint == listing.currency_mint @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == listing.royalty_destination @ ErrorCode::WrongTokenOwner
+  )]
+  pub royalty_token_account: Option<Account<'info, TokenAccount>>,
+  /// CHECK: PDA authority for escrow
+  #[account(
+    seeds = [b"escrow", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == listing.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = nft_mint,
+    associated_token::authority = buyer,
+  )]
+  pub buyer_nft_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyAndRelist<'info> {
+  /// Sold, then reused as the buyer's listing (the NFT stays in its escrow)
+  #[account(mut)]
+  pub listing: Account<'info, Listing>,
+  /// Active-listing count of the selling wallet
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  /// Pays for the purchase and the new listing's deposit; becomes the seller
+  #[account(mut)]
+  pub buyer: Signer<'info>,
+  /// Active-listing count of the buyer, who becomes the seller
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    space = SELLER_STATS_SPACE,
+    seeds = [b"seller_stats", buyer.key().as_ref()],
+    bump
+  )]
+  pub buyer_stats: Account<'info, SellerStats>,
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump
+  )]
+  pub config: Account<'info, Config>,
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = buyer,
+  )]
+  pub buyer_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Listing seller wallet; authority of the seller currency ATA and
+  /// recipient of the refunded listing deposit
+  #[account(mut, address = listing.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Account<'info, TokenAccount>,
+  /// Royalty destination's currency account; required when the listing pays a royalty
+  #[account(
+    mut,
+    constraint = royalty_token_account.mint == listing.currency_mint @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == listing.royalty_destination @ ErrorCode::WrongTokenOwner
+  )]
+  pub royalty_token_account: Option<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartLayaway<'info> {
+  #[account(mut, has_one = seller)]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  /// Seller co-signs to agree to the layaway terms
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(mut)]
+  pub buyer: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(address = listing.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    init,
+    payer = buyer,
+    space = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 2 + 32,
+    seeds = [b"layaway", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub layaway: Account<'info, Layaway>,
+  #[account(
+    mut,
+    constraint = buyer_currency_token_account.owner == buyer.key(),
+    constraint = buyer_currency_token_account.mint == currency_mint.key()
+  )]
+  pub buyer_currency_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = layaway,
+  )]
+  pub layaway_currency_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MakeLayawayPayment<'info> {
+  #[account(
+    mut,
+    has_one = buyer,
+    seeds = [b"layaway", layaway.nft_mint.as_ref()],
+    bump = layaway.bump
+  )]
+  pub layaway: Account<'info, Layaway>,
+  #[account(mut)]
+  pub buyer: Signer<'info>,
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(address = layaway.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
+  #[account(address = layaway.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    constraint = buyer_currency_token_account.owner == buyer.key(),
+    constraint = buyer_currency_token_account.mint == currency_mint.key()
+  )]
+  pub buyer_currency_token_account: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = layaway,
+  )]
+  pub layaway_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Layaway seller wallet, only used as the authority of the seller currency ATA
+  #[account(address = layaway.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// Royalty destination's currency account; required when the layaway pays a royalty
+  #[account(
+    mut,
+    constraint = royalty_token_account.mint == layaway.currency_mint @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == layaway.royalty_destination @ ErrorCode::WrongTokenOwner
+  )]
+  pub royalty_token_account: Option<Box<Account<'info, TokenAccount>>>,
+  /// CHECK: PDA authority for escrow
+  #[account(
+    seeds = [b"escrow", layaway.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == layaway.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = nft_mint,
+    associated_token::authority = buyer,
+  )]
+  pub buyer_nft_token_account: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimDefaultedLayaway<'info> {
+  #[account(
+    mut,
+    has_one = seller,
+    has_one = buyer,
+    seeds = [b"layaway", layaway.nft_mint.as_ref()],
+    bump = layaway.bump
+  )]
+  pub layaway: Account<'info, Layaway>,
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  /// CHECK: Layaway buyer, receives the layaway and currency account rents back
+  #[account(mut)]
+  pub buyer: UncheckedAccount<'info>,
+  #[account(address = layaway.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    associated_token::mint = layaway.currency_mint,
+    associated_token::authority = layaway,
+  )]
+  pub layaway_currency_token_account: Account<'info, TokenAccount>,
+  /// Buyer's currency account (refund) or seller's (forfeit), checked in the instruction
+  #[account(
+    mut,
+    constraint = payments_recipient_token_account.mint == layaway.currency_mint @ ErrorCode::InvalidCurrency
+  )]
+  pub payments_recipient_token_account: Account<'info, TokenAccount>,
+  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  #[account(
+    seeds = [b"escrow", layaway.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == layaway.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = nft_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_nft_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartAuction<'info> {
+  #[account(mut, has_one = seller)]
+  pub listing: Account<'info, Listing>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(address = listing.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    init,
+    payer = seller,
+    space = AUCTION_SPACE,
+    seeds = [b"auction", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub auction: Account<'info, Auction>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = currency_mint,
+    associated_token::authority = auction,
+  )]
+  pub auction_currency_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+  #[account(
+    mut,
+    seeds = [b"auction", auction.nft_mint.as_ref()],
+    bump = auction.bump
+  )]
+  pub auction: Account<'info, Auction>,
+  /// Pays the bid, and the BidRefund rent when the previous bidder's refund can't be sent
+  #[account(mut)]
+  pub bidder: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(address = auction.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    constraint = bidder_currency_token_account.owner == bidder.key() @ ErrorCode::WrongTokenOwner,
+    constraint = bidder_currency_token_account.mint == currency_mint.key() @ ErrorCode::InvalidCurrency
+  )]
+  pub bidder_currency_token_account: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = auction,
+  )]
+  pub auction_currency_token_account: Account<'info, TokenAccount>,
+  /// CHECK: Previous highest bidder's currency ATA, required once the auction has a bid.
+  /// Address checked in the instruction; may be closed, in which case the refund is
+  /// recorded in bid_refund
+  #[account(mut)]
+  pub previous_bidder_currency_token_account: Option<UncheckedAccount<'info>>,
+  /// CHECK: `[b"bid_refund", auction, previous_bidder]`, only needed when the refund
+  /// can't be sent; derived and created in the instruction
+  #[account(mut)]
+  pub bid_refund: Option<UncheckedAccount<'info>>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBidRefund<'info> {
+  #[account(mut)]
+  pub bidder: Signer<'info>,
+  #[account(
+    mut,
+    seeds = [b"auction", auction.nft_mint.as_ref()],
+    bump = auction.bump
+  )]
+  pub auction: Account<'info, Auction>,
+  /// CHECK: Auction seller, receives the auction rents if this claim lets it close
+  #[account(mut, address = auction.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    has_one = bidder,
+    has_one = auction,
+    seeds = [b"bid_refund", auction.key().as_ref(), bidder.key().as_ref()],
+    bump = bid_refund.bump,
+    close = bidder
+  )]
+  pub bid_refund: Account<'info, BidRefund>,
+  #[account(address = auction.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = auction,
+  )]
+  pub auction_currency_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = bidder,
+    associated_token::mint = currency_mint,
+    associated_token::authority = bidder,
+  )]
+  pub bidder_currency_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+  /// Anyone may settle; pays for any token accounts that have to be created
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  #[account(
+    mut,
+    seeds = [b"auction", auction.nft_mint.as_ref()],
+    bump = auction.bump
+  )]
+  pub auction: Box<Account<'info, Auction>>,
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Box<Account<'info, Config>>,
+  /// CHECK: Auction seller, paid the winning bid and refunded the auction rents
+  #[account(mut, address = auction.seller @ ErrorCode::WrongSeller)]
+  pub seller: UncheckedAccount<'info>,
+  /// CHECK: Highest bidder, or the seller when nobody bid; receives the NFT
+  #[account(constraint = winner.key() == auction.highest_bidder.unwrap_or(auction.seller) @ ErrorCode::WrongTokenOwner)]
+  pub winner: UncheckedAccount<'info>,
+  #[account(address = auction.nft_mint @ ErrorCode::WrongMint)]
+  pub nft_mint: Box<Account<'info, Mint>>,
+  #[account(address = auction.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Box<Account<'info, Mint>>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = auction,
+  )]
+  pub auction_currency_token_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// Royalty destination's currency account; required when the auction pays a royalty
+  #[account(
+    mut,
+    constraint = royalty_token_account.mint == auction.currency_mint @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == auction.royalty_destination @ ErrorCode::WrongTokenOwner
+  )]
+  pub royalty_token_account: Option<Box<Account<'info, TokenAccount>>>,
+  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  #[account(
+    seeds = [b"escrow", auction.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  #[account(
+    mut,
+    constraint = escrow_nft_token_account.mint == auction.nft_mint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+  )]
+  pub escrow_nft_token_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = nft_mint,
+    associated_token::authority = winner,
+  )]
+  pub winner_nft_token_account: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(collection: Pubkey)]
+pub struct PlaceCollectionBid<'info> {
+  #[account(mut)]
+  pub bidder: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(
+    init,
+    payer = bidder,
+    space = COLLECTION_BID_SPACE,
+    seeds = [b"collection_bid", bidder.key().as_ref(), collection.as_ref()],
     bump
   )]
-  pub config: Account<'info, Config>,
+  pub collection_bid: Account<'info, CollectionBid>,
+  pub currency_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    constraint = bidder_currency_token_account.owner == bidder.key() @ ErrorCode::WrongTokenOwner,
+    constraint = bidder_currency_token_account.mint == currency_mint.key() @ ErrorCode::InvalidCurrency
+  )]
+  pub bidder_currency_token_account: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = bidder,
+    associated_token::mint = currency_mint,
+    associated_token::authority = collection_bid,
+  )]
+  pub bid_currency_token_account: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ListNFT<'info> {
+pub struct AcceptCollectionBid<'info> {
+  /// Holder selling into the bid; pays for any token accounts that have to be created
   #[account(mut)]
   pub seller: Signer<'info>,
   #[account(
-    init,
-    payer = seller,
-    space = 8 + 256,
-    seeds = [b"listing", nft_mint.key().as_ref()],
-    bump
+    mut,
+    seeds = [b"config"],
+    bump = config.bump
   )]
-  pub listing: Account<'info, Listing>,
-  pub nft_mint: Account<'info, Mint>,
-  pub currency_mint: Account<'info, Mint>,
+  pub config: Box<Account<'info, Config>>,
   #[account(
     mut,
-    constraint = seller_nft_token_account.owner == seller.key(),
-    constraint = seller_nft_token_account.mint == nft_mint.key()
+    seeds = [b"collection_bid", collection_bid.bidder.as_ref(), collection_bid.collection.as_ref()],
+    bump = collection_bid.bump
   )]
-  pub seller_nft_token_account: Account<'info, TokenAccount>,
-  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  pub collection_bid: Box<Account<'info, CollectionBid>>,
+  /// CHECK: Bidder wallet; receives the NFT and the bid rents once the bid is filled
+  #[account(mut, address = collection_bid.bidder @ ErrorCode::WrongTokenOwner)]
+  pub bidder: UncheckedAccount<'info>,
+  pub nft_mint: Box<Account<'info, Mint>>,
+  /// CHECK: Metaplex metadata PDA of nft_mint, checked for collection membership in the instruction
+  pub metadata: UncheckedAccount<'info>,
   #[account(
-    seeds = [b"escrow", nft_mint.key().as_ref()],
-    bump
+    mut,
+    constraint = seller_nft_token_account.owner == seller.key() @ ErrorCode::WrongTokenOwner,
+    constraint = seller_nft_token_account.mint == nft_mint.key() @ ErrorCode::WrongMint
   )]
-  pub escrow_authority: UncheckedAccount<'info>,
+  pub seller_nft_token_account: Box<Account<'info, TokenAccount>>,
   #[account(
     init_if_needed,
     payer = seller,
     associated_token::mint = nft_mint,
-    associated_token::authority = escrow_authority,
+    associated_token::authority = bidder,
   )]
-  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  pub bidder_nft_token_account: Box<Account<'info, TokenAccount>>,
+  #[account(address = collection_bid.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Box<Account<'info, Mint>>,
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = collection_bid,
+  )]
+  pub bid_currency_token_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
+  )]
+  pub seller_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// Royalty destination's currency account; required when the bid enforces royalties
+  /// and a royalty is configured
+  #[account(
+    mut,
+    constraint = royalty_token_account.mint == collection_bid.currency_mint @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == config.royalty_destination @ ErrorCode::WrongTokenOwner
+  )]
+  pub royalty_token_account: Option<Box<Account<'info, TokenAccount>>>,
+  /// Bidder's currency ATA; required only when the final fill leaves tokens in the escrow
+  #[account(
+    mut,
+    associated_token::mint = currency_mint,
+    associated_token::authority = bidder,
+  )]
+  pub bidder_currency_token_account: Option<Box<Account<'info, TokenAccount>>>,
   pub token_program: Program<'info, Token>,
   pub associated_token_program: Program<'info, AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CancelListing<'info> {
-  #[account(mut, has_one = seller)]
-  pub listing: Account<'info, Listing>,
-  pub seller: Signer<'info>,
-  /// CHECK: PDA authority for escrow (validated manually in instruction)
+pub struct CancelCollectionBid<'info> {
+  #[account(mut)]
+  pub bidder: Signer<'info>,
   #[account(
-    seeds = [b"escrow", listing.nft_mint.as_ref()],
-    bump
+    mut,
+    has_one = bidder,
+    seeds = [b"collection_bid", bidder.key().as_ref(), collection_bid.collection.as_ref()],
+    bump = collection_bid.bump
   )]
-  pub escrow_authority: UncheckedAccount<'info>,
+  pub collection_bid: Account<'info, CollectionBid>,
+  #[account(address = collection_bid.currency_mint @ ErrorCode::InvalidCurrency)]
+  pub currency_mint: Account<'info, Mint>,
   #[account(
     mut,
-    constraint = escrow_nft_token_account.mint == listing.nft_mint,
-    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+    associated_token::mint = currency_mint,
+    associated_token::authority = collection_bid,
   )]
-  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  pub bid_currency_token_account: Account<'info, TokenAccount>,
   #[account(
     init_if_needed,
-    payer = seller,
-    associated_token::mint = listing.nft_mint,
-    associated_token::authority = seller,
+    payer = bidder,
+    associated_token::mint = currency_mint,
+    associated_token::authority = bidder,
   )]
-  pub seller_nft_token_account: Account<'info, TokenAccount>,
+  pub bidder_currency_token_account: Account<'info, TokenAccount>,
   pub token_program: Program<'info, Token>,
   pub associated_token_program: Program<'info, AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct BuyNFT<'info> {
+#[instruction(price: u64, nonce: u64)]
+pub struct BuyWithPermit<'info> {
   #[account(mut)]
-  pub listing: Account<'info, Listing>,
   pub buyer: Signer<'info>,
+  /// CHECK: Permit signer; verified against the ed25519 instruction
+  pub seller: UncheckedAccount<'info>,
   #[account(
+    mut,
     seeds = [b"config"],
-    bump
+    bump = config.bump
   )]
-  pub config: Account<'info, Config>,
-  pub currency_mint: Account<'info, Mint>,
+  pub config: Box<Account<'info, Config>>,
+  pub nft_mint: Box<Account<'info, Mint>>,
+  pub currency_mint: Box<Account<'info, Mint>>,
   #[account(
-    init_if_needed,
+    init,
     payer = buyer,
-    associated_token::mint = currency_mint,
-    associated_token::authority = buyer,
+    space = 8 + 32 + 1,
+    seeds = [b"permit", seller.key().as_ref(), &nonce.to_le_bytes()],
+    bump
   )]
-  pub buyer_currency_token_account: Account<'info, TokenAccount>,
+  pub permit_nonce: Account<'info, PermitNonce>,
   #[account(
     mut,
-    constraint = seller_currency_token_account.mint == currency_mint.key()
+    constraint = seller_nft_token_account.owner == seller.key(),
+    constraint = seller_nft_token_account.mint == nft_mint.key()
   )]
-  pub seller_currency_token_account: Account<'info, TokenAccount>,
+  pub seller_nft_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: PDA the seller approved as delegate on their NFT account
+  #[account(
+    seeds = [b"permit_delegate"],
+    bump
+  )]
+  pub permit_delegate: UncheckedAccount<'info>,
   #[account(
     mut,
-    constraint = platform_treasury_currency_token_account.mint == currency_mint.key()
+    constraint = buyer_currency_token_account.owner == buyer.key(),
+    constraint = buyer_currency_token_account.mint == currency_mint.key()
   )]
-  pub platform_treasury_currency_token_account: Account<'info, TokenAccount>,
-  /// CHECK: PDA authority for escrow
+  pub buyer_currency_token_account: Box<Account<'info, TokenAccount>>,
   #[account(
-    seeds = [b"escrow", listing.nft_mint.as_ref()],
-    bump
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = seller,
   )]
-  pub escrow_authority: UncheckedAccount<'info>,
+  pub seller_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: Platform treasury wallet, only used as the authority of the treasury currency ATA
+  #[account(address = config.platform_treasury @ ErrorCode::WrongTreasury)]
+  pub platform_treasury: UncheckedAccount<'info>,
+  #[account(
+    init_if_needed,
+    payer = buyer,
+    associated_token::mint = currency_mint,
+    associated_token::authority = platform_treasury,
+  )]
+  pub platform_treasury_currency_token_account: Box<Account<'info, TokenAccount>>,
+  /// Royalty destination's currency account; required when royalties are enforced
+  /// and a royalty is configured
   #[account(
     mut,
-    constraint = escrow_nft_token_account.mint == listing.nft_mint,
-    constraint = escrow_nft_token_account.owner == escrow_authority.key()
+    constraint = royalty_token_account.mint == currency_mint.key() @ ErrorCode::InvalidCurrency,
+    constraint = royalty_token_account.owner == config.royalty_destination @ ErrorCode::WrongTokenOwner
   )]
-  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  pub royalty_token_account: Option<Box<Account<'info, TokenAccount>>>,
   #[account(
     init_if_needed,
     payer = buyer,
-    associated_token::mint = listing.nft_mint,
+    associated_token::mint = nft_mint,
     associated_token::authority = buyer,
   )]
-  pub buyer_nft_token_account: Account<'info, TokenAccount>,
+  pub buyer_nft_token_account: Box<Account<'info, TokenAccount>>,
+  /// CHECK: Instructions sysvar, used to read the ed25519 verify instruction
+  #[account(address = sysvar::instructions::ID)]
+  pub instructions_sysvar: UncheckedAccount<'info>,
   pub token_program: Program<'info, Token>,
   pub associated_token_program: Program<'info, AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CancelPermit<'info> {
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(
+    init,
+    payer = seller,
+    space = 8 + 32 + 1,
+    seeds = [b"permit", seller.key().as_ref(), &nonce.to_le_bytes()],
+    bump
+  )]
+  pub permit_nonce: Account<'info, PermitNonce>,
+  pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateListingPrice<'info> {
   #[account(mut, has_one = seller)]
@@ -357,6 +3001,65 @@ pub struct UpdateListingPrice<'info> {
   pub seller: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(new_seller: Pubkey)]
+pub struct TransferListing<'info> {
+  #[account(mut, has_one = seller)]
+  pub listing: Account<'info, Listing>,
+  /// Pays for the new seller's stats account on first use
+  #[account(mut)]
+  pub seller: Signer<'info>,
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump
+  )]
+  pub config: Account<'info, Config>,
+  #[account(
+    mut,
+    seeds = [b"seller_stats", listing.seller.as_ref()],
+    bump = seller_stats.bump
+  )]
+  pub seller_stats: Account<'info, SellerStats>,
+  #[account(
+    init_if_needed,
+    payer = seller,
+    space = SELLER_STATS_SPACE,
+    seeds = [b"seller_stats", new_seller.as_ref()],
+    bump
+  )]
+  pub new_seller_stats: Account<'info, SellerStats>,
+  /// CHECK: PDA authority for escrow, derived and checked by seeds
+  #[account(
+    seeds = [b"escrow", listing.nft_mint.as_ref()],
+    bump
+  )]
+  pub escrow_authority: UncheckedAccount<'info>,
+  /// The listed NFT must still be escrowed
+  #[account(
+    constraint = escrow_nft_token_account.mint == listing.nft_mint @ ErrorCode::WrongMint,
+    constraint = escrow_nft_token_account.owner == escrow_authority.key() @ ErrorCode::WrongTokenOwner,
+    constraint = escrow_nft_token_account.amount == 1 @ ErrorCode::NftNotEscrowed
+  )]
+  pub escrow_nft_token_account: Account<'info, TokenAccount>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateListingPricesBatch<'info> {
+  pub seller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  /// CHECK: Config PDA, possibly in the pre-migration layout; owner, discriminator and
+  /// authority are checked manually since it may not decode as Config yet
+  #[account(mut, seeds = [b"config"], bump)]
+  pub config: UncheckedAccount<'info>,
+  pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateConfig<'info> {
   pub admin: Signer<'info>,
@@ -373,6 +3076,7 @@ pub struct UpdateConfig<'info> {
 pub struct WithdrawPlatformFees<'info> {
   pub admin: Signer<'info>,
   #[account(
+    mut,
     seeds = [b"config"],
     bump = config.bump,
     constraint = config.authority == admin.key() @ ErrorCode::Unauthorized
@@ -401,6 +3105,10 @@ pub struct NFTListed {
   pub seller: Pubkey,
   pub nft_mint: Pubkey,
   pub price: u64,
+  pub category: ListingCategory,
+  pub tags: u32,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
   pub timestamp: i64,
 }
 
@@ -408,6 +3116,8 @@ pub struct NFTListed {
 pub struct NFTDelisted {
   pub seller: Pubkey,
   pub nft_mint: Pubkey,
+  /// Cancellation penalty paid to the platform treasury (lamports)
+  pub penalty: u64,
   pub timestamp: i64,
 }
 
@@ -418,6 +3128,14 @@ pub struct NFTSold {
   pub nft_mint: Pubkey,
   pub price: u64,
   pub fee: u64,
+  /// Whether this sale was subject to creator royalties
+  pub royalties_enforced: bool,
+  /// Platform fee waived (fee-exempt seller or fee-free launch window)
+  pub fee_waived: bool,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  /// Paid to the listing's royalty destination out of the seller's share
+  pub royalty: u64,
   pub timestamp: i64,
 }
 
@@ -429,6 +3147,127 @@ pub struct PriceUpdated {
   pub timestamp: i64,
 }
 
+#[event]
+pub struct ListingTransferred {
+  pub nft_mint: Pubkey,
+  pub previous_seller: Pubkey,
+  pub new_seller: Pubkey,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketplacePauseUpdated {
+  pub listings_paused: bool,
+  pub purchases_paused: bool,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct ListingReaped {
+  pub seller: Pubkey,
+  pub nft_mint: Pubkey,
+  pub deposit_forfeited: u64,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct LayawayStarted {
+  pub seller: Pubkey,
+  pub buyer: Pubkey,
+  pub nft_mint: Pubkey,
+  pub total_price: u64,
+  pub down_payment: u64,
+  pub deadline: i64,
+  pub forfeit_on_default: bool,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct LayawayPayment {
+  pub buyer: Pubkey,
+  pub nft_mint: Pubkey,
+  pub amount: u64,
+  pub paid_so_far: u64,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct LayawayDefaulted {
+  pub seller: Pubkey,
+  pub buyer: Pubkey,
+  pub nft_mint: Pubkey,
+  pub paid_so_far: u64,
+  pub payments_forfeited: bool,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct AuctionStarted {
+  pub seller: Pubkey,
+  pub nft_mint: Pubkey,
+  pub reserve_price: u64,
+  pub end_time: i64,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct BidPlaced {
+  pub bidder: Pubkey,
+  pub nft_mint: Pubkey,
+  pub amount: u64,
+  pub previous_bidder: Option<Pubkey>,
+  /// The previous bid went to a BidRefund record instead of the bidder's ATA
+  pub refund_claimable: bool,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct AuctionSettled {
+  pub seller: Pubkey,
+  pub winner: Option<Pubkey>,
+  pub nft_mint: Pubkey,
+  pub price: u64,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct CollectionBidPlaced {
+  pub bidder: Pubkey,
+  pub collection: Pubkey,
+  pub price_per_nft: u64,
+  pub quantity: u32,
+  /// Currency mint decimals, for formatting the token amounts above
+  pub decimals: u8,
+  pub timestamp: i64,
+}
+
+#[event]
+pub struct CollectionBidClosed {
+  pub bidder: Pubkey,
+  pub collection: Pubkey,
+  /// NFTs bought through the bid (== quantity when it closed by filling)
+  pub filled: u32,
+  /// Escrowed tokens returned to the bidder
+  pub refunded: u64,
+  pub timestamp: i64,
+}
+
+/// Emitted when the platform fee changes (update_config or set_platform_fee)
+#[event]
+pub struct ConfigUpdated {
+  pub previous_platform_fee_bps: u16,
+  pub platform_fee_bps: u16,
+  pub timestamp: i64,
+}
+
 #[event]
 pub struct PlatformFeesWithdrawn {
   pub amount: u64,
@@ -441,7 +3280,129 @@ pub struct PlatformFeesWithdrawn {
 pub struct Config {
   pub authority: Pubkey,
   pub platform_treasury: Pubkey,
+  pub fee_rounding: FeeRounding,
+  pub listings_paused: bool,
+  pub purchases_paused: bool,
+  pub allow_relist: bool,
+  pub fee_exempt_sellers: Vec<Pubkey>, // max MAX_FEE_EXEMPT_SELLERS
+  pub price_ceilings: Vec<PriceCeiling>, // max MAX_PRICE_CEILINGS
+  /// Fees collected by buy_nft and paid out by withdraw_platform_fees (raw token units)
+  pub total_fees_accrued: u64,
+  pub total_fees_withdrawn: u64,
+  pub listing_deposit_lamports: u64,
+  pub listing_ttl_secs: i64, // 0 = listings never expire
+  pub royalty_policy: RoyaltyPolicy,
+  pub fee_free_until: Option<i64>, // platform fee waived on sales before this unix time
+  pub cancel_fee_lamports: u64, // charged on cancel_listing (0 = free)
+  pub max_active_listings: u32, // per seller wallet (0 = unlimited)
   pub bump: u8,
+  // Added after bump so legacy configs keep their layout; zero until migrate_config
+  pub platform_fee_bps: u16,
+  pub version: u8, // CONFIG_VERSION once migrated (0 = legacy, fee falls back to PLATFORM_FEE_BPS)
+  pub royalty_bps: u16, // royalty new enforcing listings pay on sale (0 = none)
+  pub royalty_destination: Pubkey,
+}
+
+/// Per-currency listing price ceiling to reject obviously mis-priced listings
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceCeiling {
+  pub currency_mint: Pubkey,
+  pub max_price: u64,
+}
+
+impl Config {
+  pub fn is_fee_exempt(&self, seller: &Pubkey) -> bool {
+    *seller == self.authority || self.fee_exempt_sellers.contains(seller)
+  }
+
+  /// Platform fee for a sale by `seller` at `now`
+  pub fn platform_fee_bps(&self, seller: &Pubkey, now: i64) -> u16 {
+    let in_fee_free_window = self.fee_free_until.is_some_and(|until| now < until);
+    if in_fee_free_window || self.is_fee_exempt(seller) {
+      0
+    } else if self.version < FEE_CONFIG_VERSION {
+      PLATFORM_FEE_BPS
+    } else {
+      self.platform_fee_bps
+    }
+  }
+
+  /// Whether a new listing enforces royalties, given the seller's choice
+  pub fn resolve_royalties(&self, enforce_royalties: bool) -> bool {
+    match self.royalty_policy {
+      RoyaltyPolicy::Always => true,
+      RoyaltyPolicy::Optional => enforce_royalties,
+      RoyaltyPolicy::Never => false,
+    }
+  }
+
+  /// Record the royalty a listing pays on sale: the configured one when the listing
+  /// enforces royalties, none otherwise
+  pub fn apply_royalty(&self, listing: &mut Listing) {
+    listing.royalty_bps = self.sale_royalty_bps(listing.royalties_enforced);
+    if listing.royalty_bps > 0 {
+      listing.royalty_destination = self.royalty_destination;
+    } else {
+      listing.royalty_destination = Pubkey::default();
+    }
+  }
+
+  /// Royalty a sale pays right now: the configured one when it enforces royalties
+  pub fn sale_royalty_bps(&self, royalties_enforced: bool) -> u16 {
+    if royalties_enforced {
+      self.royalty_bps
+    } else {
+      0
+    }
+  }
+
+  pub fn max_listing_price(&self, currency_mint: &Pubkey) -> Option<u64> {
+    self.price_ceilings
+      .iter()
+      .find(|c| c.currency_mint == *currency_mint)
+      .map(|c| c.max_price)
+  }
+}
+
+/// How the platform fee is rounded to whole token units
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeeRounding {
+  /// Truncate the fee (favors the seller)
+  Floor,
+  /// Round the fee to the nearest unit, halves rounding up
+  HalfUp,
+}
+
+/// How update_listing_prices_batch derives each listing's new price
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum BatchPriceUpdate {
+  /// Scale every price by bps / 10_000 (9_000 = 10% off)
+  MultiplierBps(u32),
+  /// Explicit new price per listing, in remaining_accounts order
+  Prices(Vec<u64>),
+}
+
+/// Browsing category a seller files a listing under
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListingCategory {
+  #[default]
+  Uncategorized,
+  Figures,
+  Cards,
+  Plush,
+  Accessories,
+  Other,
+}
+
+/// Whether sales must pay creator royalties
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RoyaltyPolicy {
+  /// Every listing enforces royalties (default)
+  Always,
+  /// Seller chooses per listing at list time
+  Optional,
+  /// Royalties are never enforced
+  Never,
 }
 
 // Errors
@@ -452,6 +3413,44 @@ pub enum ErrorCode {
   #[msg("Invalid price")] InvalidPrice,
   #[msg("Invalid currency mint")] InvalidCurrency,
   #[msg("Math overflow")] MathOverflow,
+  #[msg("Marketplace is paused")] MarketplacePaused,
+  #[msg("Listing is still active")] ListingActive,
+  #[msg("Relisting is disabled")] RelistDisabled,
+  #[msg("Fee-exempt seller list is full")] FeeExemptListFull,
+  #[msg("Invalid NFT metadata account")] InvalidMetadata,
+  #[msg("Price exceeds the maximum allowed for this currency")] PriceExceedsMax,
+  #[msg("Price ceiling list is full")] PriceCeilingListFull,
+  #[msg("Withdrawal exceeds accrued platform fees")] ExceedsAccruedFees,
+  #[msg("Invalid duration")] InvalidDuration,
+  #[msg("Listing has not expired")] ListingNotExpired,
+  #[msg("Layaway deadline has passed")] LayawayExpired,
+  #[msg("Layaway deadline has not passed")] LayawayNotExpired,
+  #[msg("Permit has expired")] PermitExpired,
+  #[msg("Missing or invalid permit signature")] InvalidPermit,
+  #[msg("Invalid batch size")] InvalidBatchSize,
+  #[msg("Platform treasury account is required")] MissingPlatformTreasury,
+  #[msg("Fee basis points exceed 10000")] InvalidFeeBps,
+  #[msg("Too many listing tags")] TooManyTags,
+  #[msg("Platform treasury does not match config")] WrongTreasury,
+  #[msg("Token account is owned by the wrong wallet")] WrongTokenOwner,
+  #[msg("NFT mint does not match the listing, layaway or auction")] WrongMint,
+  #[msg("Account is not the listing, layaway or auction seller")] WrongSeller,
+  #[msg("Auction has ended")] AuctionEnded,
+  #[msg("Auction has not ended")] AuctionNotEnded,
+  #[msg("Bid is below the reserve or the current highest bid")] BidTooLow,
+  #[msg("Outbid refund account is missing or invalid")] MissingRefundAccount,
+  #[msg("Listed NFT is not held in escrow")] NftNotEscrowed,
+  #[msg("Seller has reached the maximum number of active listings")] TooManyActiveListings,
+  #[msg("Account is not a marketplace config")] InvalidConfigAccount,
+  #[msg("Config must be migrated with migrate_config first")] ConfigNotMigrated,
+  #[msg("Invalid quantity")] InvalidQuantity,
+  #[msg("Collection bid is already filled")] CollectionBidFilled,
+  #[msg("Collection bid price differs from the expected price")] BidPriceChanged,
+  #[msg("NFT is not a verified member of the bid's collection")] NotInCollection,
+  #[msg("Platform fee exceeds the 10% maximum")] PlatformFeeTooHigh,
+  #[msg("Royalty exceeds the 10% maximum or has no destination")] InvalidRoyalty,
+  #[msg("Royalty token account is required for this listing")] MissingRoyaltyAccount,
+  #[msg("Sellers cannot buy their own listing")] SelfPurchase,
 }
 
 
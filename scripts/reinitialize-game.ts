@@ -211,13 +211,16 @@ async function main() {
         "Win adorable prizes in this pastel gachapon!",
         "https://example.com/game.png",
         new BN(500), // $5.00 cost
-        tokenMint
+        tokenMint,
+        null, // starts_at
+        null // ends_at
       )
       .accounts({
         authority: wallet.publicKey,
         config: configPda,
         game: gamePda,
         treasury: treasury.publicKey,
+        tokenMint,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
@@ -1,12 +1,24 @@
 import * as anchor from "@coral-xyz/anchor";
 import { BN, Program } from "@coral-xyz/anchor";
 import {
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
+  approve,
+  createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
   createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getMint,
+  getMintLen,
   getOrCreateAssociatedTokenAccount,
   mintTo,
 } from "@solana/spl-token";
-import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { Keypair, PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { createHash } from "crypto";
 
 describe("gachapon-game", () => {
   // Set up provider with localnet
@@ -118,6 +130,8 @@ describe("gachapon-game", () => {
         user: user.publicKey,
         userTokenAccount: userAta,
         treasuryTokenAccount: treasuryAta,
+        creatorTokenAccount: null,
+        priceUpdate: null,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])
@@ -148,4 +162,3353 @@ describe("gachapon-game", () => {
       .accounts({ game: gamePda, authority: wallet.publicKey })
       .rpc();
   });
+
+  it("rejects plays on a game whose prize probabilities sum to 0", async () => {
+    const zeroGameId = new BN(2);
+    const [configPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config")],
+      gameProgram.programId
+    );
+    if (!(await provider.connection.getAccountInfo(configPda))) {
+      await gameProgram.methods
+        .initializeProgram()
+        .accounts({ authority: wallet.publicKey })
+        .rpc();
+    }
+
+    const [zeroGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(zeroGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(zeroGameId, "Pure Loss", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: zeroGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+
+    // Supply is stocked (so the game activates) but nothing can ever be drawn
+    await gameProgram.methods
+      .addPrize(
+        0,
+        new BN(1),
+        "Unwinnable",
+        "",
+        "",
+        "ipfs://unwinnable",
+        "SKU-NONE",
+        { common: {} } as any,
+        0,
+        new BN(100),
+        0,
+        0,
+        0,
+        0,
+        10,
+        true
+      )
+      .accounts({ authority: wallet.publicKey, game: zeroGamePda })
+      .rpc();
+
+    const userAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        wallet.payer as any,
+        tokenMint,
+        user.publicKey
+      )
+    ).address;
+    const treasuryAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        wallet.payer as any,
+        tokenMint,
+        treasury.publicKey
+      )
+    ).address;
+
+    try {
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...Keypair.generate().publicKey.toBytes()], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: zeroGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: treasuryAta,
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      expect.fail("play should be rejected when no prize is winnable");
+    } catch (err: any) {
+      expect(err.toString()).to.include("NoWinnableProbability");
+    }
+  });
+
+  it("detects drift between the game supply counter and its prizes", async () => {
+    const driftGameId = new BN(3);
+    const [driftGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(driftGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(driftGameId, "Drift", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: driftGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+
+    const prizePdas: PublicKey[] = [];
+    for (const index of [0, 1]) {
+      await gameProgram.methods
+        .addPrize(
+          index,
+          new BN(index + 1),
+          `Prize ${index}`,
+          "",
+          "",
+          "ipfs://prize",
+          "SKU",
+          { common: {} } as any,
+          1000,
+          new BN(100),
+          0,
+          0,
+          0,
+          0,
+          5,
+          true
+        )
+        .accounts({ authority: wallet.publicKey, game: driftGamePda })
+        .rpc();
+      prizePdas.push(
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("prize"), driftGamePda.toBuffer(), Buffer.from([index])],
+          gameProgram.programId
+        )[0]
+      );
+    }
+
+    const supplyDriftEvents = async () => {
+      const sig = await gameProgram.methods
+        .checkSupply(0)
+        .accounts({ game: driftGamePda })
+        .remainingAccounts(
+          prizePdas.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+        )
+        .rpc({ commitment: "confirmed" });
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(
+        gameProgram.programId,
+        new anchor.BorshCoder(gameProgram.idl)
+      );
+      return [...parser.parseLogs(tx!.meta!.logMessages!)].filter(
+        (e) => e.name === "supplyDrift" || e.name === "SupplyDrift"
+      );
+    };
+
+    expect(await supplyDriftEvents()).to.have.length(0);
+
+    // Closing a prize leaves its 5 units counted in the game total
+    await gameProgram.methods
+      .closePrize()
+      .accounts({ game: driftGamePda, prize: prizePdas[1], authority: wallet.publicKey })
+      .rpc();
+
+    const drift = await supplyDriftEvents();
+    expect(drift).to.have.length(1);
+    expect(drift[0].data.gameSupplyRemaining).to.equal(10);
+    expect(drift[0].data.prizeSupplyRemaining).to.equal(5);
+  });
+
+  it("rebuilds prize_count and probabilities from live prize accounts", async () => {
+    const healGameId = new BN(4);
+    const [healGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(healGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(healGameId, "Heal", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: healGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+
+    const prizePdas: PublicKey[] = [];
+    for (const index of [0, 1]) {
+      await gameProgram.methods
+        .addPrize(
+          index,
+          new BN(index + 1),
+          `Prize ${index}`,
+          "",
+          "",
+          "ipfs://prize",
+          "SKU",
+          { common: {} } as any,
+          1000,
+          new BN(100),
+          0,
+          0,
+          0,
+          0,
+          5,
+          true
+        )
+        .accounts({ authority: wallet.publicKey, game: healGamePda })
+        .rpc();
+      prizePdas.push(
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("prize"), healGamePda.toBuffer(), Buffer.from([index])],
+          gameProgram.programId
+        )[0]
+      );
+    }
+
+    await gameProgram.methods
+      .closePrize()
+      .accounts({ game: healGamePda, prize: prizePdas[1], authority: wallet.publicKey })
+      .rpc();
+    await gameProgram.methods
+      .verifyPrizes()
+      .accounts({ game: healGamePda, authority: wallet.publicKey })
+      .remainingAccounts(
+        prizePdas.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+      )
+      .rpc();
+
+    const game: any = await gameProgram.account.game.fetch(healGamePda);
+    expect(game.prizeCount).to.equal(1);
+    expect(game.prizeProbabilities[0]).to.equal(1000);
+    expect(game.prizeProbabilities[1]).to.equal(0);
+  });
+
+  it("grants promo plays up to the per-user limit", async () => {
+    const promoGameId = new BN(5);
+    const [promoGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(promoGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(promoGameId, "Promo", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: promoGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(
+        0,
+        new BN(1),
+        "Promo Prize",
+        "",
+        "",
+        "ipfs://promo",
+        "SKU",
+        { common: {} } as any,
+        5000,
+        new BN(100),
+        0,
+        0,
+        0,
+        0,
+        10,
+        true
+      )
+      .accounts({ authority: wallet.publicKey, game: promoGamePda })
+      .rpc();
+
+    const campaignId = new BN(1);
+    const [promoCampaign] = PublicKey.findProgramAddressSync(
+      [Buffer.from("promo"), promoGamePda.toBuffer(), Buffer.from(campaignId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const now = Math.floor(Date.now() / 1000);
+    await gameProgram.methods
+      .createPromoCampaign(campaignId, 100, 1, new BN(now - 60), new BN(now + 3600))
+      .accounts({ game: promoGamePda, authority: wallet.publicKey, promoCampaign })
+      .rpc();
+
+    const playWithPromo = () =>
+      gameProgram.methods
+        .playWithPromo(Array.from(Keypair.generate().publicKey.toBytes()))
+        .accounts({ game: promoGamePda, user: user.publicKey, promoCampaign })
+        .signers([user])
+        .rpc();
+
+    await playWithPromo();
+    const campaign: any = await gameProgram.account.promoCampaign.fetch(promoCampaign);
+    expect(campaign.remainingFreePlays).to.equal(99);
+
+    try {
+      await playWithPromo();
+      expect.fail("second promo play should exceed the per-user limit");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PromoLimitReached");
+    }
+  });
+
+  it("requires a proof-of-work nonce once the game sets a difficulty", async () => {
+    const powGameId = new BN(6);
+    const [powGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(powGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(powGameId, "PoW", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: powGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "PoW Prize", "", "", "ipfs://pow", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 10, true)
+      .accounts({ authority: wallet.publicKey, game: powGamePda })
+      .rpc();
+
+    try {
+      await gameProgram.methods
+        .setPowDifficulty(17)
+        .accounts({ game: powGamePda, authority: wallet.publicKey })
+        .rpc();
+      expect.fail("difficulty above the maximum should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidPowDifficulty");
+    }
+    const difficulty = 8;
+    await gameProgram.methods
+      .setPowDifficulty(difficulty)
+      .accounts({ game: powGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, user.publicKey)
+    ).address;
+    const treasuryAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, treasury.publicKey)
+    ).address;
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const zeroBits = (nonce: number) => {
+      const hash = createHash("sha256")
+        .update(sessionSeed)
+        .update(user.publicKey.toBuffer())
+        .update(Buffer.from(new BN(nonce).toArray("le", 8)))
+        .digest();
+      let bits = 0;
+      for (const byte of hash) {
+        if (byte !== 0) return bits + Math.clz32(byte) - 24;
+        bits += 8;
+      }
+      return bits;
+    };
+    let validNonce = 0;
+    while (zeroBits(validNonce) < difficulty) validNonce++;
+    let invalidNonce = 0;
+    while (zeroBits(invalidNonce) >= difficulty) invalidNonce++;
+
+    const play = (nonce: number) =>
+      gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(nonce), null, null, new BN(100_000))
+        .accounts({
+          game: powGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: treasuryAta,
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+    try {
+      await play(invalidNonce);
+      expect.fail("a nonce below the difficulty should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidProofOfWork");
+    }
+    await play(validNonce);
+  });
+
+  it("splits play payments between the treasury and the game's creator", async () => {
+    const splitGameId = new BN(7);
+    const [splitGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(splitGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(splitGameId, "Collab", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: splitGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Collab Prize", "", "", "ipfs://collab", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 10, true)
+      .accounts({ authority: wallet.publicKey, game: splitGamePda })
+      .rpc();
+
+    const creator = Keypair.generate();
+    await gameProgram.methods
+      .setCreatorSplit(creator.publicKey, 2500)
+      .accounts({ game: splitGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    const treasuryAta = await ata(treasury.publicKey);
+    const creatorAta = await ata(creator.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const balance = async (account: PublicKey) =>
+      Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+    const treasuryBefore = await balance(treasuryAta);
+
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...Keypair.generate().publicKey.toBytes()], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: splitGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: treasuryAta,
+        creatorTokenAccount: creatorAta,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    expect(await balance(creatorAta)).to.equal(25_000);
+    expect((await balance(treasuryAta)) - treasuryBefore).to.equal(75_000);
+  });
+
+  it("blocks closing a prize while a won session still has to claim it", async () => {
+    const closeGameId = new BN(8);
+    const [closeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(closeGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), closeGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(closeGameId, "Sure Win", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: closeGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Sure Prize", "", "", "ipfs://sure", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, true)
+      .accounts({ authority: wallet.publicKey, game: closeGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), closeGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: closeGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    // Resolve the win without mint accounts: the session stays unclaimed
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: closeGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+    const prize: any = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.pendingClaims).to.equal(1);
+
+    try {
+      await gameProgram.methods
+        .closePrize()
+        .accounts({ game: closeGamePda, prize: prizePda, authority: wallet.publicKey })
+        .rpc();
+      expect.fail("a prize with a pending claim should not close");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PendingClaimsExist");
+    }
+  });
+
+  it("rejects backend-signed randomness once the game requires VRF", async () => {
+    const vrfGameId = new BN(9);
+    const [vrfGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(vrfGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), vrfGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(vrfGameId, "VRF Only", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: vrfGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "VRF Prize", "", "", "ipfs://vrf", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 5, true)
+      .accounts({ authority: wallet.publicKey, game: vrfGamePda })
+      .rpc();
+    let game: any = await gameProgram.account.game.fetch(vrfGamePda);
+    expect(game.randomnessPolicy).to.deep.equal({ allowBackendSigned: {} });
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), vrfGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: vrfGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    await gameProgram.methods
+      .setRandomnessPolicy({ vrfOnly: {} } as any)
+      .accounts({ authority: wallet.publicKey, game: vrfGamePda })
+      .rpc();
+    game = await gameProgram.account.game.fetch(vrfGamePda);
+    expect(game.randomnessPolicy).to.deep.equal({ vrfOnly: {} });
+
+    try {
+      await gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32, 3)], null)
+        .accounts({
+          playSession: sessionPda,
+          game: vrfGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: null,
+          claimRecord: null,
+        })
+        .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+        .rpc();
+      expect.fail("backend-signed randomness should be rejected under VrfOnly");
+    } catch (err: any) {
+      expect(err.toString()).to.include("RandomnessPolicyViolation");
+    }
+
+    // Binding needs an account owned by the Switchboard program
+    try {
+      await gameProgram.methods
+        .requestRandomness()
+        .accounts({ playSession: sessionPda, game: vrfGamePda, user: user.publicKey, randomnessAccount: prizePda })
+        .signers([user])
+        .rpc();
+      expect.fail("a non-Switchboard randomness account should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidVrfAccount");
+    }
+    // An unbound play can't be settled from a randomness account either
+    try {
+      await gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32)], null)
+        .accounts({
+          playSession: sessionPda,
+          game: vrfGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: prizePda,
+          pityCounter: null,
+          claimRecord: null,
+        })
+        .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+        .rpc();
+      expect.fail("finalizing an unbound play from VRF should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("VrfNotRequested");
+    }
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.vrfAccount).to.be.null;
+  });
+
+  it("emits effective odds over in-stock prizes", async () => {
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+
+    const createGame = async (id: number, prizes: [number, number][]) => {
+      const [pda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("game"), Buffer.from(new BN(id).toArray("le", 8))],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .initializeGame(new BN(id), `Odds ${id}`, "", "", new BN(500), tokenMint, null, null)
+        .accounts({ authority: wallet.publicKey, game: pda, treasury: treasury.publicKey, tokenMint })
+        .rpc();
+      for (const [index, [probability, supply]] of prizes.entries()) {
+        await gameProgram.methods
+          .addPrize(index, new BN(100 + index), `Prize ${index}`, "", "", "ipfs://odds", "SKU", { common: {} } as any, probability, new BN(100), 0, 0, 0, 0, supply, true)
+          .accounts({ authority: wallet.publicKey, game: pda })
+          .rpc();
+      }
+      return pda;
+    };
+    // Finalizes one play with a draw of 19, which lands on prize 0
+    const winPrizeZero = async (game: PublicKey) => {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), game.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      const [prizePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), game.toBuffer(), Buffer.from([0])],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32, 3)], null)
+        .accounts({
+          playSession: sessionPda,
+          game,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: null,
+          claimRecord: null,
+        })
+        .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+        .rpc();
+    };
+    const odds = async (game: PublicKey) => {
+      const { events } = await gameProgram.methods.emitEffectiveOdds().accounts({ game }).simulate();
+      const event: any = events.find((e: any) => e.name.toLowerCase() === "effectiveodds")!.data;
+      return { prizes: event.prizeOddsBp.slice(0, 2), loss: event.lossOddsBp };
+    };
+
+    // Under 100%: the unassigned remainder is the loss odds
+    const mixed = await createGame(12, [[2500, 1], [5000, 5]]);
+    expect(await odds(mixed)).to.deep.equal({ prizes: [2500, 5000], loss: 2500 });
+
+    // Prize 0 sells out: the rest is scaled over the live 7500 bp
+    await winPrizeZero(mixed);
+    expect(await odds(mixed)).to.deep.equal({ prizes: [0, 6666], loss: 3334 });
+
+    // Everything sold out: every play loses
+    const single = await createGame(13, [[10000, 1]]);
+    await winPrizeZero(single);
+    expect(await odds(single)).to.deep.equal({ prizes: [0, 0], loss: 10000 });
+  });
+
+  it("flags a prize once a win takes it below its low-stock threshold", async () => {
+    const lowGameId = new BN(10);
+    const [lowGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(lowGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), lowGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(lowGameId, "Low Stock", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: lowGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Last Few", "", "", "ipfs://low", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 3, true)
+      .accounts({ authority: wallet.publicKey, game: lowGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setLowStockThreshold(3)
+      .accounts({ authority: wallet.publicKey, game: lowGamePda, prize: prizePda, inventory: null })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), lowGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: lowGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: lowGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const prize: any = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.supplyRemaining).to.equal(2);
+    expect(prize.lowStockThreshold).to.equal(3);
+    expect(prize.lowStockWarned).to.equal(true);
+  });
+
+  it("rejects claims inside the game's claim cooldown", async () => {
+    const cooldownGameId = new BN(11);
+    const [cooldownGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(cooldownGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), cooldownGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(cooldownGameId, "Slow Reveal", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: cooldownGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Hidden Prize", "", "", "ipfs://hidden", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, true)
+      .accounts({ authority: wallet.publicKey, game: cooldownGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setClaimCooldown(3600)
+      .accounts({ authority: wallet.publicKey, game: cooldownGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), cooldownGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: cooldownGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: cooldownGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.resolvedAt.toNumber()).to.be.greaterThan(0);
+
+    const nftMint = Keypair.generate();
+    try {
+      await gameProgram.methods
+        .initPrizeMint()
+        .accounts({
+          playSession: sessionPda,
+          game: cooldownGamePda,
+          prize: prizePda,
+          payer: user.publicKey,
+          user: user.publicKey,
+          nftMint: nftMint.publicKey,
+        })
+        .signers([user, nftMint])
+        .rpc();
+      expect.fail("claims inside the cooldown should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ClaimTooSoon");
+    }
+  });
+
+  it("rejects an all-zero session seed", async () => {
+    // Reuses the still-active game from the cooldown test
+    const [seedGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(11).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    try {
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...Buffer.alloc(32, 0)], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: seedGamePda,
+          user: user.publicKey,
+          userTokenAccount: await ata(user.publicKey),
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      expect.fail("an all-zero session seed should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidSessionSeed");
+    }
+  });
+
+  it("refuses to pre-mint past what a prize can still award", async () => {
+    const poolGameId = new BN(14);
+    const [poolGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(poolGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), poolGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(poolGameId, "Pooled", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: poolGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    // No supply left and no pending wins: the pool may not hold anything
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Sold Out", "", "", "ipfs://sold-out", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 0, true)
+      .accounts({ authority: wallet.publicKey, game: poolGamePda })
+      .rpc();
+
+    const [inventoryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("inventory"), prizePda.toBuffer()],
+      gameProgram.programId
+    );
+    const [nftMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("premint"), prizePda.toBuffer(), Buffer.alloc(4)],
+      gameProgram.programId
+    );
+    const metaplex = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer()],
+      metaplex
+    );
+    const [masterEdition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer(), Buffer.from("edition")],
+      metaplex
+    );
+    try {
+      await gameProgram.methods
+        .premintPrize()
+        .accounts({
+          game: poolGamePda,
+          prize: prizePda,
+          inventory: inventoryPda,
+          authority: wallet.publicKey,
+          nftMint,
+          metadata,
+          masterEdition,
+          metaplexTokenMetadataProgram: metaplex,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+      expect.fail("pre-minting beyond the remaining supply should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PrizePoolFull");
+    }
+  });
+
+  it("flags replenishment past the original supply and honors a supply lock", async () => {
+    const limitedGameId = new BN(15);
+    const [limitedGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(limitedGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), limitedGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(limitedGameId, "Limited Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: limitedGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Numbered", "", "", "ipfs://numbered", "SKU", { rare: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 10, true)
+      .accounts({ authority: wallet.publicKey, game: limitedGamePda })
+      .rpc();
+
+    const sim = await gameProgram.methods
+      .replenishPrizeSupply(5)
+      .accounts({ game: limitedGamePda, prize: prizePda, authority: wallet.publicKey })
+      .simulate();
+    const replenished: any = sim.events.find((e: any) => e.name.toLowerCase() === "supplyreplenished")?.data;
+    expect(replenished.originalSupply).to.equal(10);
+    expect(replenished.supplyTotal).to.equal(15);
+    expect(replenished.exceedsOriginal).to.equal(true);
+
+    await gameProgram.methods
+      .lockSupply()
+      .accounts({ game: limitedGamePda, authority: wallet.publicKey })
+      .rpc();
+    try {
+      await gameProgram.methods
+        .replenishPrizeSupply(5)
+        .accounts({ game: limitedGamePda, prize: prizePda, authority: wallet.publicKey })
+        .rpc();
+      expect.fail("replenishing a locked game should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("SupplyLocked");
+    }
+    const prize: any = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.originalSupply).to.equal(10);
+    expect(prize.supplyTotal).to.equal(10);
+  });
+
+  it("rejects replenishes past the per-call and total supply ceilings", async () => {
+    const ceilingGameId = new BN(25);
+    const [ceilingGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(ceilingGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), ceilingGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(ceilingGameId, "Capped Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: ceilingGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Capped", "", "", "ipfs://capped", "SKU", { rare: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 10, true)
+      .accounts({ authority: wallet.publicKey, game: ceilingGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setSupplyCeilings(5, 20)
+      .accounts({ game: ceilingGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const replenish = (amount: number) =>
+      gameProgram.methods
+        .replenishPrizeSupply(amount)
+        .accounts({ game: ceilingGamePda, prize: prizePda, authority: wallet.publicKey })
+        .rpc();
+    const expectCeiling = async (amount: number) => {
+      try {
+        await replenish(amount);
+        expect.fail(`replenishing ${amount} should hit the ceiling`);
+      } catch (err: any) {
+        expect(err.toString()).to.include("ExceedsSupplyCeiling");
+      }
+    };
+
+    // Per call: 5 is allowed, 6 is not
+    await expectCeiling(6);
+    await replenish(5);
+    // Total: 15 + 5 lands exactly on 20, one more is over
+    await replenish(5);
+    await expectCeiling(1);
+    let prize: any = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.supplyTotal).to.equal(20);
+
+    // Clearing the ceilings restores unlimited replenishes
+    await gameProgram.methods
+      .setSupplyCeilings(null, null)
+      .accounts({ game: ceilingGamePda, authority: wallet.publicKey })
+      .rpc();
+    await replenish(6);
+    prize = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.supplyTotal).to.equal(26);
+  });
+
+  it("claims a prize minted under Token-2022", async () => {
+    const t22GameId = new BN(16);
+    const [t22GamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(t22GameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), t22GamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(t22GameId, "Token-2022 Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: t22GamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "T22 Prize", "", "", "ipfs://t22", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: t22GamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), t22GamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: t22GamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    // No mint accounts: the win is recorded and claimed separately
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: t22GamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+    // The win keeps the prize as it was at resolution, and the claim mints from that
+    const resolved: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(resolved.prizeSnapshot).to.deep.equal({ name: "T22 Prize", metadataUri: "ipfs://t22" });
+
+    const nftMint = await createMint(
+      provider.connection,
+      wallet.payer as any,
+      t22GamePda,
+      t22GamePda,
+      0,
+      Keypair.generate(),
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const userNftAta = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      nftMint,
+      user.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const metaplex = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer()],
+      metaplex
+    );
+    const [masterEdition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer(), Buffer.from("edition")],
+      metaplex
+    );
+    await gameProgram.methods
+      .claimPrize()
+      .accounts({
+        playSession: sessionPda,
+        game: t22GamePda,
+        prize: prizePda,
+        user: user.publicKey,
+        nftMint,
+        metadata,
+        masterEdition,
+        userNftTokenAccount: userNftAta,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        metaplexTokenMetadataProgram: metaplex,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    const held = await getAccount(provider.connection, userNftAta, undefined, TOKEN_2022_PROGRAM_ID);
+    expect(held.amount).to.equal(BigInt(1));
+    const mintedMetadata = await provider.connection.getAccountInfo(metadata);
+    expect(mintedMetadata!.data.toString()).to.include("T22 Prize").and.to.include("ipfs://t22");
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.isClaimed).to.equal(true);
+  });
+
+  it("records reproducible draw inputs in compliance mode", async () => {
+    const compGameId = new BN(17);
+    const [compGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(compGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const prizePda = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), compGamePda.toBuffer(), Buffer.from([index])],
+        gameProgram.programId
+      )[0];
+    const [complianceLog] = PublicKey.findProgramAddressSync(
+      [Buffer.from("compliance"), compGamePda.toBuffer()],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(compGameId, "Audited", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: compGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    // Prize 0 is sold out from the start; prize 1 takes every live draw
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Gone", "", "", "ipfs://gone", "SKU", { rare: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 0, true)
+      .accounts({ authority: wallet.publicKey, game: compGamePda })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(1, new BN(2), "Left", "", "", "ipfs://left", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 3, true)
+      .accounts({ authority: wallet.publicKey, game: compGamePda })
+      .rpc();
+    await gameProgram.methods
+      .enableComplianceMode(4)
+      .accounts({ game: compGamePda, authority: wallet.publicKey, complianceLog })
+      .remainingAccounts([0, 1].map((i) => ({ pubkey: prizePda(i), isSigner: false, isWritable: false })))
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), compGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: compGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    const randomValue = Buffer.alloc(32, 9);
+    await gameProgram.methods
+      .finalizePlay([...randomValue], null)
+      .accounts({
+        playSession: sessionPda,
+        game: compGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda(1), isSigner: false, isWritable: true }])
+      .rpc();
+
+    const log: any = await gameProgram.account.complianceLog.fetch(complianceLog);
+    expect(log.entries.length).to.equal(1);
+    const draw = log.entries[0];
+    expect(draw.session.toBase58()).to.equal(sessionPda.toBase58());
+    expect(Buffer.from(draw.randomValue).equals(randomValue)).to.equal(true);
+    expect(draw.probabilities.slice(0, 2)).to.deep.equal([5000, 5000]);
+    expect(draw.supplies.slice(0, 2)).to.deep.equal([0, 3]);
+    expect(draw.prizeCount).to.equal(2);
+    // Reproduce: the live range is 10000 - 5000 (sold out) and prize 1 covers all of it
+    expect(draw.prizeIndex).to.equal(1);
+
+    try {
+      await gameProgram.methods
+        .setTierWeights(true, [10000, 0, 0, 0])
+        .accounts({ game: compGamePda, authority: wallet.publicKey })
+        .remainingAccounts([0, 1].map((i) => ({ pubkey: prizePda(i), isSigner: false, isWritable: false })))
+        .rpc();
+      expect.fail("tier weighting should be rejected in compliance mode");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ComplianceModeConflict");
+    }
+  });
+
+  it("sweeps treasury balance above the cap to the overflow destination", async () => {
+    const capGameId = new BN(18);
+    const [capGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(capGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(capGameId, "Capped Treasury", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: capGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    const cold = Keypair.generate();
+    await gameProgram.methods
+      .setTreasuryCap(new BN(1_000), cold.publicKey)
+      .accounts({ game: capGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const treasuryAta = await ata(treasury.publicKey);
+    const coldAta = await ata(cold.publicKey);
+    const before = (await getAccount(provider.connection, treasuryAta)).amount;
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, treasuryAta, wallet.publicKey, 5_000);
+    const balance = before + BigInt(5_000);
+    // The treasury wallet lets the game PDA move its tokens
+    await approve(provider.connection, wallet.payer as any, treasuryAta, capGamePda, treasury, balance);
+
+    await gameProgram.methods
+      .sweepOverflow()
+      .accounts({
+        game: capGamePda,
+        treasuryTokenAccount: treasuryAta,
+        destinationTokenAccount: coldAta,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    expect((await getAccount(provider.connection, treasuryAta)).amount).to.equal(BigInt(1_000));
+    expect((await getAccount(provider.connection, coldAta)).amount).to.equal(balance - BigInt(1_000));
+
+    try {
+      await gameProgram.methods
+        .sweepOverflow()
+        .accounts({
+          game: capGamePda,
+          treasuryTokenAccount: treasuryAta,
+          destinationTokenAccount: coldAta,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("a treasury at its cap has nothing to sweep");
+    } catch (err: any) {
+      expect(err.toString()).to.include("NoTreasuryOverflow");
+    }
+  });
+
+  it("rejects a reused metadata URI once a game requires unique prizes", async () => {
+    const uniqueGameId = new BN(19);
+    const [uniqueGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(uniqueGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [firstPrize] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), uniqueGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(uniqueGameId, "One of One", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: uniqueGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Original", "", "", "ipfs://one-of-one", "SKU", { legendary: {} } as any, 100, new BN(100), 0, 0, 0, 0, 1, false)
+      .accounts({ authority: wallet.publicKey, game: uniqueGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setUniqueMetadataUris(true)
+      .accounts({ game: uniqueGamePda, authority: wallet.publicKey })
+      .remainingAccounts([{ pubkey: firstPrize, isSigner: false, isWritable: false }])
+      .rpc();
+
+    try {
+      await gameProgram.methods
+        .addPrize(1, new BN(2), "Copy", "", "", "ipfs://one-of-one", "SKU", { legendary: {} } as any, 100, new BN(100), 0, 0, 0, 0, 1, false)
+        .accounts({ authority: wallet.publicKey, game: uniqueGamePda })
+        .rpc();
+      expect.fail("a duplicate metadata URI should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("DuplicateMetadataUri");
+    }
+    await gameProgram.methods
+      .addPrize(1, new BN(2), "Second", "", "", "ipfs://another-one", "SKU", { legendary: {} } as any, 100, new BN(100), 0, 0, 0, 0, 1, false)
+      .accounts({ authority: wallet.publicKey, game: uniqueGamePda })
+      .rpc();
+  });
+
+  it("rejects a play whose expected session doesn't match the derived PDA", async () => {
+    // Reuses the still-active game from the cooldown test
+    const [activeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(11).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    // Derived with the seeds in the wrong order, as a buggy client might
+    const [wrongSession] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), user.publicKey.toBuffer(), activeGamePda.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    try {
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), wrongSession, null, new BN(100_000))
+        .accounts({
+          game: activeGamePda,
+          user: user.publicKey,
+          userTokenAccount: await ata(user.publicKey),
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      expect.fail("a mismatched expected session should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("SessionPdaMismatch");
+    }
+  });
+
+  it("refunds batch pulls that resolve against exhausted supply", async () => {
+    const refundGameId = new BN(20);
+    const [refundGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(refundGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), refundGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(refundGameId, "Last One", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: refundGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Only Prize", "", "", "ipfs://only", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 1, true)
+      .accounts({ authority: wallet.publicKey, game: refundGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    const treasuryAta = await ata(treasury.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    // Two pulls against a single unit of supply
+    const sessions: PublicKey[] = [];
+    for (let i = 0; i < 2; i++) {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), refundGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: refundGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: treasuryAta,
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      sessions.push(sessionPda);
+    }
+    const paid = (await gameProgram.account.playSession.fetch(sessions[1]) as any).amountPaid;
+    const balanceBefore = (await getAccount(provider.connection, userAta)).amount;
+
+    await gameProgram.methods
+      .finalizeBatch([...Buffer.alloc(32, 9)])
+      .accounts({
+        game: refundGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        treasury: treasury.publicKey,
+        treasuryTokenAccount: treasuryAta,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: prizePda, isSigner: false, isWritable: true },
+        ...sessions.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
+        ...sessions.map(() => ({ pubkey: userAta, isSigner: false, isWritable: true })),
+      ])
+      .signers([treasury])
+      .rpc();
+
+    const won: any = await gameProgram.account.playSession.fetch(sessions[0]);
+    expect(won.prizeIndex).to.equal(0);
+    expect(won.supplyRefunded).to.equal(false);
+    const refunded: any = await gameProgram.account.playSession.fetch(sessions[1]);
+    expect(refunded.prizeIndex).to.equal(null);
+    expect(refunded.supplyRefunded).to.equal(true);
+    expect(refunded.amountPaid.toNumber()).to.equal(0);
+    expect((await getAccount(provider.connection, userAta)).amount).to.equal(balanceBefore + BigInt(paid.toString()));
+  });
+
+  it("rejects prize changes while the game's config is frozen", async () => {
+    const frozenGameId = new BN(21);
+    const [frozenGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(frozenGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(frozenGameId, "Fixed Table", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: frozenGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .freezeConfig()
+      .accounts({ game: frozenGamePda, authority: wallet.publicKey })
+      .rpc();
+    try {
+      await gameProgram.methods
+        .addPrize(0, new BN(1), "Late Prize", "", "", "ipfs://late", "SKU", { common: {} } as any, 100, new BN(100), 0, 0, 0, 0, 1, false)
+        .accounts({ authority: wallet.publicKey, game: frozenGamePda })
+        .rpc();
+      expect.fail("a frozen game should not accept new prizes");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ConfigFrozen");
+    }
+
+    await gameProgram.methods
+      .thawConfig()
+      .accounts({ game: frozenGamePda, authority: wallet.publicKey })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Late Prize", "", "", "ipfs://late", "SKU", { common: {} } as any, 100, new BN(100), 0, 0, 0, 0, 1, false)
+      .accounts({ authority: wallet.publicKey, game: frozenGamePda })
+      .rpc();
+    const game: any = await gameProgram.account.game.fetch(frozenGamePda);
+    expect(game.configFrozen).to.equal(false);
+    expect(game.prizeCount).to.equal(1);
+  });
+
+  it("derives request-id sessions the same way on the client and the program", async () => {
+    // Reuses the still-active game from the cooldown test
+    const [activeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(11).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const requestId = new BN(Date.now());
+    const [requestSession] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), activeGamePda.toBuffer(), user.publicKey.toBuffer(), Buffer.from(requestId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const playWithRequestId = async (maxTokenAmount: number) =>
+      gameProgram.methods
+        .playGameWithRequestId(new BN(100_000), requestId, new BN(0), requestSession, new BN(maxTokenAmount))
+        .accounts({
+          game: activeGamePda,
+          user: user.publicKey,
+          userTokenAccount: await ata(user.publicKey),
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    try {
+      await playWithRequestId(99_999);
+      expect.fail("charging more than the authorized maximum should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("SlippageExceeded");
+    }
+    // The program fails the play if its PDA differs from the one derived here
+    await playWithRequestId(100_000);
+    const session: any = await gameProgram.account.playSession.fetch(requestSession);
+    expect(session.user.toBase58()).to.equal(user.publicKey.toBase58());
+    expect(Buffer.from(session.sessionSeed.slice(0, 8)).equals(Buffer.from(requestId.toArray("le", 8)))).to.equal(true);
+  });
+
+  it("only finalizes a committed play from its reveal", async () => {
+    // Reuses the still-active game from the cooldown test
+    const [activeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(11).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), activeGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), activeGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    // Not a keccak preimage of anything the backend will reveal
+    const commitment = Buffer.alloc(32, 9);
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, [...commitment], new BN(100_000))
+      .accounts({
+        game: activeGamePda,
+        user: user.publicKey,
+        userTokenAccount: await ata(user.publicKey),
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(Buffer.from(session.commitment).equals(commitment)).to.equal(true);
+
+    const finalize = (reveal: number[] | null) =>
+      gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32, 3)], reveal)
+        .accounts({
+          playSession: sessionPda,
+          game: activeGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: null,
+          claimRecord: null,
+        })
+        .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+        .rpc();
+    // The backend can't fall back to a value of its choosing...
+    try {
+      await finalize(null);
+      expect.fail("a committed play should need its reveal");
+    } catch (err: any) {
+      expect(err.toString()).to.include("RevealRequired");
+    }
+    // ...or reveal anything but the committed secret
+    try {
+      await finalize([...Buffer.alloc(32, 1)]);
+      expect.fail("a reveal that doesn't hash to the commitment should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("RevealMismatch");
+    }
+    expect((await gameProgram.account.playSession.fetch(sessionPda)).isFulfilled).to.equal(false);
+  });
+
+  it("retires a half-minted prize mint and reopens the claim", async () => {
+    const stuckGameId = new BN(22);
+    const [stuckGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(stuckGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), stuckGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(stuckGameId, "Stuck Mint", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: stuckGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Sure Prize", "", "", "ipfs://stuck", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: stuckGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), stuckGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: stuckGamePda,
+        user: user.publicKey,
+        userTokenAccount: await ata(user.publicKey),
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 5)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: stuckGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+
+    // Step 1 of the two-phase claim runs, step 2 never does
+    const nftMint = Keypair.generate();
+    await gameProgram.methods
+      .initPrizeMint()
+      .accounts({
+        playSession: sessionPda,
+        game: stuckGamePda,
+        prize: prizePda,
+        payer: user.publicKey,
+        user: user.publicKey,
+        nftMint: nftMint.publicKey,
+      })
+      .signers([user, nftMint])
+      .rpc();
+    const stuckAta = getAssociatedTokenAddressSync(nftMint.publicKey, user.publicKey);
+
+    await gameProgram.methods
+      .recoverStuckMint()
+      .accounts({
+        config: configPda,
+        authority: wallet.publicKey,
+        game: stuckGamePda,
+        playSession: sessionPda,
+        nftMint: nftMint.publicKey,
+        stuckTokenAccount: stuckAta,
+      })
+      .rpc();
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.pendingClaimMint).to.equal(null);
+    expect(session.isClaimed).to.equal(false);
+    expect((await getMint(provider.connection, nftMint.publicKey)).mintAuthority).to.equal(null);
+    expect((await getAccount(provider.connection, stuckAta)).isFrozen).to.equal(true);
+  });
+
+  it("reports which prizes can currently be won", async () => {
+    const prizePdas = (game: PublicKey, count: number) =>
+      [...Array(count).keys()].map((index) => ({
+        pubkey: PublicKey.findProgramAddressSync(
+          [Buffer.from("prize"), game.toBuffer(), Buffer.from([index])],
+          gameProgram.programId
+        )[0],
+        isSigner: false,
+        isWritable: false,
+      }));
+    const winnable = async (game: PublicKey, count: number) => {
+      const { events } = await gameProgram.methods
+        .emitWinnability()
+        .accounts({ game })
+        .remainingAccounts(prizePdas(game, count))
+        .simulate();
+      const event: any = events.find((e: any) => e.name.toLowerCase() === "winnability")!.data;
+      return event.winnable.slice(0, count);
+    };
+
+    // Sold out: prize 0 of the odds test's first game was won, prize 1 is still live
+    const [soldOutGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(12).toArray("le", 8))],
+      gameProgram.programId
+    );
+    expect(await winnable(soldOutGamePda, 2)).to.deep.equal([false, true]);
+
+    const winnableGameId = new BN(23);
+    const [winnableGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(winnableGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(winnableGameId, "Winnable", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: winnableGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    for (const [index, probability] of [5000, 0].entries()) {
+      await gameProgram.methods
+        .addPrize(index, new BN(index + 1), `Prize ${index}`, "", "", `ipfs://winnable-${index}`, "SKU", { common: {} } as any, probability, new BN(100), 0, 0, 0, 0, 5, false)
+        .accounts({ authority: wallet.publicKey, game: winnableGamePda })
+        .rpc();
+    }
+    // Zero probability: in stock but never drawn
+    expect(await winnable(winnableGamePda, 2)).to.deep.equal([true, false]);
+
+    // Inactive game: nothing can be won
+    await gameProgram.methods
+      .updateGameStatus(false)
+      .accounts({ game: winnableGamePda, authority: wallet.publicKey })
+      .rpc();
+    expect(await winnable(winnableGamePda, 2)).to.deep.equal([false, false]);
+  });
+
+  it("validates a game's royalty creators", async () => {
+    const royaltyGameId = new BN(24);
+    const [royaltyGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(royaltyGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(royaltyGameId, "Collab Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({
+        authority: wallet.publicKey,
+        game: royaltyGamePda,
+        treasury: treasury.publicKey,
+        tokenMint,
+      })
+      .rpc();
+    const setCreators = (creators: { address: PublicKey; share: number }[]) =>
+      gameProgram.methods
+        .setGameCreators(creators, 500)
+        .accounts({ game: royaltyGamePda, authority: wallet.publicKey })
+        .rpc();
+    const creator = () => Keypair.generate().publicKey;
+
+    for (const invalid of [
+      [{ address: creator(), share: 60 }, { address: creator(), share: 30 }],
+      [...Array(6).keys()].map((i) => ({ address: creator(), share: i === 0 ? 50 : 10 })),
+    ]) {
+      try {
+        await setCreators(invalid);
+        expect.fail("invalid royalty creators should be rejected");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidRoyaltyCreators");
+      }
+    }
+
+    const creators = [{ address: creator(), share: 70 }, { address: creator(), share: 30 }];
+    await setCreators(creators);
+    const game: any = await gameProgram.account.game.fetch(royaltyGamePda);
+    expect(game.royaltyBps).to.equal(500);
+    expect(game.creators.map((c: any) => [c.address.toBase58(), c.share])).to.deep.equal(
+      creators.map((c) => [c.address.toBase58(), c.share])
+    );
+  });
+
+  it("simulates play outcomes without changing any state", async () => {
+    // The odds test's first game: prize 0 (2500 bp) is sold out, so draws cover the live
+    // 7500 bp, prize 1 takes 0..5000 and the rest loses
+    const [simGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(12).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const randomFor = (draw: number) => {
+      const value = Buffer.alloc(32);
+      value.writeUInt32LE(draw);
+      return [...value];
+    };
+    const outcome = async (draw: number) => {
+      const { events } = await gameProgram.methods
+        .simulatePlay(randomFor(draw))
+        .accounts({ game: simGamePda })
+        .simulate();
+      const event: any = events.find((e: any) => e.name.toLowerCase() === "simulatedoutcome")!.data;
+      return { prizeIndex: event.prizeIndex, soldOut: event.drawnSoldOut };
+    };
+    expect(await outcome(3000)).to.deep.equal({ prizeIndex: 1, soldOut: false });
+    // Once prize 0's range, now renormalized onto prize 1 instead of failing the play
+    expect(await outcome(100)).to.deep.equal({ prizeIndex: 1, soldOut: false });
+    expect(await outcome(6000)).to.deep.equal({ prizeIndex: null, soldOut: false });
+    // 9000 wraps into the live range (9000 % 7500 = 1500)
+    expect(await outcome(9000)).to.deep.equal({ prizeIndex: 1, soldOut: false });
+
+    // Landed for real, the instruction leaves the game byte-for-byte unchanged
+    const before = await provider.connection.getAccountInfo(simGamePda);
+    await gameProgram.methods
+      .simulatePlay(randomFor(3000))
+      .accounts({ game: simGamePda })
+      .rpc();
+    const after = await provider.connection.getAccountInfo(simGamePda);
+    expect(after!.data.equals(before!.data)).to.equal(true);
+    expect(after!.lamports).to.equal(before!.lamports);
+  });
+
+  it("reaps expired wins and timed-out plays in one keeper batch", async () => {
+    const reapGameId = new BN(26);
+    const [reapGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(reapGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), reapGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(reapGameId, "Keeper Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: reapGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Reapable", "", "", "ipfs://reap", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: reapGamePda })
+      .rpc();
+    // Wins expire and unfinalized plays time out after 1s; keepers earn 10 per session
+    await gameProgram.methods
+      .setExpiryPolicy(1, 1, new BN(10))
+      .accounts({ game: reapGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    const treasuryAta = await ata(treasury.publicKey);
+    const keeperAta = await ata(wallet.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const play = async () => {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), reapGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: reapGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: treasuryAta,
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      return sessionPda;
+    };
+    const wonSession = await play();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: wonSession,
+        game: reapGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+    const stalledSession = await play();
+    expect((await gameProgram.account.prize.fetch(prizePda)).supplyRemaining).to.equal(4);
+
+    await new Promise((resolve) => setTimeout(resolve, 2_500));
+    // Refunds and the bounty move treasury tokens through the game PDA's delegation
+    await approve(provider.connection, wallet.payer as any, treasuryAta, reapGamePda, treasury, 1_000_000);
+    const balance = async (account: PublicKey) => (await getAccount(provider.connection, account)).amount;
+    const userBefore = await balance(userAta);
+    const keeperBefore = await balance(keeperAta);
+    const reap = () =>
+      gameProgram.methods
+        .reapExpiredBatch()
+        .accounts({
+          keeper: wallet.publicKey,
+          game: reapGamePda,
+          treasuryTokenAccount: treasuryAta,
+          keeperTokenAccount: keeperAta,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          { pubkey: prizePda, isSigner: false, isWritable: true },
+          { pubkey: wonSession, isSigner: false, isWritable: true },
+          { pubkey: userAta, isSigner: false, isWritable: true },
+          { pubkey: stalledSession, isSigner: false, isWritable: true },
+          { pubkey: userAta, isSigner: false, isWritable: true },
+          // Listed twice: skipped the second time instead of being refunded again
+          { pubkey: stalledSession, isSigner: false, isWritable: true },
+          { pubkey: userAta, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+    await reap();
+
+    expect(await balance(userAta)).to.equal(userBefore + BigInt(100_000));
+    expect(await balance(keeperAta)).to.equal(keeperBefore + BigInt(20));
+    expect((await gameProgram.account.prize.fetch(prizePda)).supplyRemaining).to.equal(5);
+    const expired: any = await gameProgram.account.playSession.fetch(wonSession);
+    expect(expired.prizeIndex).to.be.null;
+    const refunded: any = await gameProgram.account.playSession.fetch(stalledSession);
+    expect(refunded.isFulfilled).to.equal(true);
+    expect(refunded.amountPaid.toNumber()).to.equal(0);
+
+    // Nothing is left to reap
+    try {
+      await reap();
+      expect.fail("a batch of already reaped sessions should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("NothingToReap");
+    }
+  });
+
+  it("lets anyone refund a play the backend never finalized", async () => {
+    const refundGameId = new BN(27);
+    const [refundGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(refundGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(refundGameId, "Offline Backend", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: refundGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Never Drawn", "", "", "ipfs://never", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: refundGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setExpiryPolicy(0, 1, new BN(0))
+      .accounts({ game: refundGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    const treasuryAta = await ata(treasury.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), refundGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: refundGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: treasuryAta,
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    expect((await gameProgram.account.playSession.fetch(sessionPda)).expirySeconds.toNumber()).to.equal(1);
+    // Turning refunds off afterwards doesn't strand a play paid under the old timeout
+    await gameProgram.methods
+      .setExpiryPolicy(0, 0, new BN(0))
+      .accounts({ game: refundGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    await new Promise((resolve) => setTimeout(resolve, 2_500));
+    await approve(provider.connection, wallet.payer as any, treasuryAta, refundGamePda, treasury, 100_000);
+    const balance = async () => (await getAccount(provider.connection, userAta)).amount;
+    const before = await balance();
+    const refund = () =>
+      gameProgram.methods
+        .refundExpiredSession()
+        .accounts({
+          playSession: sessionPda,
+          game: refundGamePda,
+          treasuryTokenAccount: treasuryAta,
+          userTokenAccount: userAta,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    await refund();
+
+    expect(await balance()).to.equal(before + BigInt(100_000));
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.isFulfilled).to.equal(true);
+    expect(session.timeoutRefunded).to.equal(true);
+    expect(session.prizeIndex).to.be.null;
+    try {
+      await refund();
+      expect.fail("a refunded play should not be refunded again");
+    } catch (err: any) {
+      expect(err.toString()).to.include("AlreadyFulfilled");
+    }
+  });
+
+  it("requires a valid Pyth price for USD-priced games", async () => {
+    const pricedGameId = new BN(28);
+    const [pricedGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(pricedGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(pricedGameId, "USD Priced", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: pricedGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Priced Prize", "", "", "ipfs://priced", "SKU", { common: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: pricedGamePda })
+      .rpc();
+    const priceFeed = Keypair.generate().publicKey;
+    // An oracle-priced game needs a staleness window
+    try {
+      await gameProgram.methods
+        .setPriceFeed(priceFeed, 0)
+        .accounts({ game: pricedGamePda, authority: wallet.publicKey })
+        .rpc();
+      expect.fail("a price feed without a max age should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidPriceFeed");
+    }
+    await gameProgram.methods
+      .setPriceFeed(priceFeed, 60)
+      .accounts({ game: pricedGamePda, authority: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const play = async (priceUpdate: PublicKey | null) =>
+      gameProgram.methods
+        .playGame(new BN(100_000), [...Keypair.generate().publicKey.toBytes()], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: pricedGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    // Without the price account, or with one the Pyth receiver doesn't own, nothing is charged
+    for (const priceUpdate of [null, priceFeed]) {
+      try {
+        await play(priceUpdate);
+        expect.fail("a play without a verified price should be rejected");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidPriceFeed");
+      }
+    }
+
+    // The slippage limit binds whatever the price says
+    await gameProgram.methods
+      .setPriceFeed(PublicKey.default, 0)
+      .accounts({ game: pricedGamePda, authority: wallet.publicKey })
+      .rpc();
+    try {
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...Keypair.generate().publicKey.toBytes()], new BN(0), null, null, new BN(99_999))
+        .accounts({
+          game: pricedGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      expect.fail("charging more than the authorized maximum should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("SlippageExceeded");
+    }
+
+    // Without a feed, payments are unchecked
+    await play(null);
+    expect((await gameProgram.account.game.fetch(pricedGamePda)).priceFeed.toBase58()).to.equal(PublicKey.default.toBase58());
+  });
+
+  it("forces the best in-stock prize after a pity streak", async () => {
+    const pityGameId = new BN(29);
+    const [pityGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(pityGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const prizePdaAt = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), pityGamePda.toBuffer(), Buffer.from([index])],
+        gameProgram.programId
+      )[0];
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    const [pityCounterPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pity"), pityGamePda.toBuffer(), user.publicKey.toBuffer()],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(pityGameId, "Pity Drop", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: pityGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    // 1 bp each: draw 19 loses
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Common", "", "", "ipfs://common", "SKU", { common: {} } as any, 1, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: pityGamePda })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(1, new BN(2), "Rare", "", "", "ipfs://rare", "SKU", { rare: {} } as any, 1, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: pityGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setPityThreshold(1)
+      .accounts({ game: pityGamePda, authority: wallet.publicKey })
+      .rpc();
+    await gameProgram.methods
+      .openPityCounter()
+      .accounts({ game: pityGamePda, user: user.publicKey, payer: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const play = async () => {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), pityGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: pityGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      return sessionPda;
+    };
+    const playAndFinalize = async (prizeAccount: PublicKey) => {
+      const sessionPda = await play();
+      await gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32, 3)], null)
+        .accounts({
+          playSession: sessionPda,
+          game: pityGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: pityCounterPda,
+          claimRecord: null,
+        })
+        .remainingAccounts([{ pubkey: prizeAccount, isSigner: false, isWritable: true }])
+        .rpc();
+      return gameProgram.account.playSession.fetch(sessionPda);
+    };
+
+    const lost: any = await playAndFinalize(prizePdaAt(0));
+    expect(lost.prizeIndex).to.be.null;
+    expect((await gameProgram.account.pityCounter.fetch(pityCounterPda)).losses).to.equal(1);
+
+    // Same losing draw, but the streak hit the threshold: the rare prize is forced
+    const won: any = await playAndFinalize(prizePdaAt(1));
+    expect(won.prizeIndex).to.equal(1);
+    expect((await gameProgram.account.pityCounter.fetch(pityCounterPda)).losses).to.equal(0);
+    expect((await gameProgram.account.prize.fetch(prizePdaAt(1))).supplyRemaining).to.equal(4);
+
+    // finalize_batch takes no pity counters, so it refuses pity games outright
+    const batchSession = await play();
+    try {
+      await gameProgram.methods
+        .finalizeBatch([...Buffer.alloc(32, 3)])
+        .accounts({
+          game: pityGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          treasury: null,
+          treasuryTokenAccount: null,
+          tokenMint: null,
+          tokenProgram: null,
+        })
+        .remainingAccounts([
+          { pubkey: prizePdaAt(0), isSigner: false, isWritable: true },
+          { pubkey: prizePdaAt(1), isSigner: false, isWritable: true },
+          { pubkey: batchSession, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+      expect.fail("finalize_batch should reject games with a pity threshold");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PityCounterMissing");
+    }
+  });
+
+  it("resolves every draw of a multi-pull play in one finalize", async () => {
+    const multiGameId = new BN(30);
+    const [multiGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(multiGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(multiGameId, "Ten Pull", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: multiGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    // 1 bp: draws 6123, 9619 and 9218 (sha256(value || i) % 10000) all lose
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Common", "", "", "ipfs://common", "SKU", { common: {} } as any, 1, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: multiGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const playMulti = async (sessionSeed: Buffer, count: number) =>
+      gameProgram.methods
+        .playGameMulti(new BN(100_000), [...sessionSeed], count, new BN(0), new BN(100_000 * count))
+        .accounts({
+          game: multiGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+    try {
+      await playMulti(Buffer.from(Keypair.generate().publicKey.toBytes()), 11);
+      expect.fail("more than MAX_MULTI_PULLS pulls should be rejected");
+    } catch (err: any) {
+      expect(err.error?.errorCode?.code).to.equal("InvalidPullCount");
+    }
+
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), multiGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await playMulti(sessionSeed, 3);
+    const pending: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(pending.draws).to.equal(3);
+    expect(pending.amountPaid.toNumber()).to.equal(300_000);
+
+    const playsBefore = (await gameProgram.account.game.fetch(multiGamePda)).totalPlays.toNumber();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: multiGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .rpc();
+    const resolved: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(resolved.isFulfilled).to.be.true;
+    expect(resolved.prizeIndex).to.be.null;
+    expect(resolved.isClaimed).to.be.false;
+    expect((await gameProgram.account.game.fetch(multiGamePda)).totalPlays.toNumber()).to.equal(playsBefore + 3);
+  });
+
+  it("removes a middle prize and reindexes the last one into its slot", async () => {
+    const removeGameId = new BN(31);
+    const [removeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(removeGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const prizePdaAt = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), removeGamePda.toBuffer(), Buffer.from([index])],
+        gameProgram.programId
+      )[0];
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(removeGameId, "Retire", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: removeGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    for (const [index, probability] of [1000, 2000, 3000].entries()) {
+      await gameProgram.methods
+        .addPrize(index, new BN(index + 1), `Prize ${index}`, "", "", `ipfs://retire-${index}`, "SKU", { common: {} } as any, probability, new BN(100), 0, 0, 0, 0, index + 1, false)
+        .accounts({ authority: wallet.publicKey, game: removeGamePda })
+        .rpc();
+    }
+
+    await gameProgram.methods
+      .removePrize()
+      .accounts({ game: removeGamePda, prize: prizePdaAt(1), authority: wallet.publicKey })
+      .rpc();
+    // The emptied slot can't be drawn until the last prize moves into it
+    let game: any = await gameProgram.account.game.fetch(removeGamePda);
+    expect(game.prizeCount).to.equal(3);
+    expect(game.prizeProbabilities.slice(0, 3)).to.deep.equal([1000, 0, 3000]);
+    expect(await provider.connection.getAccountInfo(prizePdaAt(1))).to.be.null;
+
+    await gameProgram.methods
+      .reindexPrize(1)
+      .accounts({
+        authority: wallet.publicKey,
+        game: removeGamePda,
+        lastPrize: prizePdaAt(2),
+        inventory: PublicKey.findProgramAddressSync(
+          [Buffer.from("inventory"), prizePdaAt(2).toBuffer()],
+          gameProgram.programId
+        )[0],
+        prize: prizePdaAt(1),
+      })
+      .rpc();
+
+    game = await gameProgram.account.game.fetch(removeGamePda);
+    expect(game.prizeCount).to.equal(2);
+    expect(game.prizeProbabilities.slice(0, 3)).to.deep.equal([1000, 3000, 0]);
+    expect(game.prizeSupply.slice(0, 3)).to.deep.equal([1, 3, 0]);
+    expect(game.totalSupplyRemaining).to.equal(4);
+    expect(await provider.connection.getAccountInfo(prizePdaAt(2))).to.be.null;
+    const moved: any = await gameProgram.account.prize.fetch(prizePdaAt(1));
+    expect(moved.prizeIndex).to.equal(1);
+    expect(moved.prizeId.toNumber()).to.equal(3);
+    expect(moved.name).to.equal("Prize 2");
+
+    // Draw 2000 lands in the moved prize's range (1000..4000) and wins it at its new index
+    const userAta = (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, user.publicKey)).address;
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), removeGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: removeGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, treasury.publicKey)).address,
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    const randomValue = Buffer.alloc(32);
+    randomValue.writeUInt32LE(2000);
+    await gameProgram.methods
+      .finalizePlay([...randomValue], null)
+      .accounts({
+        playSession: sessionPda,
+        game: removeGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePdaAt(1), isSigner: false, isWritable: true }])
+      .rpc();
+    const session: any = await gameProgram.account.playSession.fetch(sessionPda);
+    expect(session.prizeIndex).to.equal(1);
+    expect((await gameProgram.account.prize.fetch(prizePdaAt(1))).supplyRemaining).to.equal(2);
+  });
+
+  it("edits only the prize fields that are passed", async () => {
+    // Prize 0 of the remove_prize game
+    const [gamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(31).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), gamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    // Changing the metadata URI takes the prize's inventory, even one never preminted
+    const [inventoryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("inventory"), prizePda.toBuffer()],
+      gameProgram.programId
+    );
+    const { events } = await gameProgram.methods
+      .updatePrize("Prize Zero", null, null, "ipfs://retire-0-fixed", null, null, 250, null, 650, null)
+      .accounts({ game: gamePda, prize: prizePda, authority: wallet.publicKey, inventory: inventoryPda })
+      .simulate();
+    const event: any = events.find((e: any) => e.name.toLowerCase() === "prizeupdated")!.data;
+    // PRIZE_FIELD_NAME | PRIZE_FIELD_METADATA_URI | PRIZE_FIELD_WEIGHT_GRAMS | PRIZE_FIELD_DIMENSIONS
+    expect(event.changedFields).to.equal(0b11001001);
+
+    await gameProgram.methods
+      .updatePrize("Prize Zero", null, null, "ipfs://retire-0-fixed", null, null, 250, null, 650, null)
+      .accounts({ game: gamePda, prize: prizePda, authority: wallet.publicKey, inventory: inventoryPda })
+      .rpc();
+    const prize: any = await gameProgram.account.prize.fetch(prizePda);
+    expect(prize.name).to.equal("Prize Zero");
+    expect(prize.metadataUri).to.equal("ipfs://retire-0-fixed");
+    expect(prize.physicalSku).to.equal("SKU");
+    expect(prize.weightGrams).to.equal(250);
+    expect(prize.lengthHundredths).to.equal(0);
+    expect(prize.widthHundredths).to.equal(650);
+    expect(prize.probabilityBp).to.equal(1000);
+
+    try {
+      await gameProgram.methods
+        .updatePrize("x".repeat(51), null, null, null, null, null, null, null, null, null)
+        .accounts({ game: gamePda, prize: prizePda, authority: wallet.publicKey, inventory: null })
+        .rpc();
+      expect.fail("a name over 50 bytes should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("StringTooLong");
+    }
+  });
+
+  it("rebalances a prize's odds only with an explicit live edit", async () => {
+    // The remove_prize game: prize 0 at 1000 bp, prize 1 at 3000 bp, game active
+    const [gamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(new BN(31).toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), gamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const update = (probability: number, allowLiveEdit: boolean) =>
+      gameProgram.methods
+        .updatePrizeProbability(0, probability, allowLiveEdit)
+        .accounts({ game: gamePda, prize: prizePda, authority: wallet.publicKey, inventory: null })
+        .rpc();
+
+    for (const [probability, allowLiveEdit, code] of [
+      [2000, false, "LiveOddsEdit"],
+      [8000, true, "InvalidProbabilities"],
+    ] as const) {
+      try {
+        await update(probability, allowLiveEdit);
+        expect.fail(`${code} expected`);
+      } catch (err: any) {
+        expect(err.toString()).to.include(code);
+      }
+    }
+
+    // The plain setter is the same edit without the opt-in
+    try {
+      await gameProgram.methods
+        .setPrizeProbability(2000)
+        .accounts({ game: gamePda, prize: prizePda, authority: wallet.publicKey, inventory: null })
+        .rpc();
+      expect.fail("LiveOddsEdit expected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("LiveOddsEdit");
+    }
+
+    await update(2000, true);
+    expect((await gameProgram.account.prize.fetch(prizePda)).probabilityBp).to.equal(2000);
+    const game: any = await gameProgram.account.game.fetch(gamePda);
+    expect(game.prizeProbabilities.slice(0, 2)).to.deep.equal([2000, 3000]);
+  });
+
+  it("turns wins past a prize's per-user limit into losses", async () => {
+    const cappedGameId = new BN(32);
+    const [cappedGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(cappedGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), cappedGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [claimRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("claim_record"), prizePda.toBuffer(), user.publicKey.toBuffer()],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(cappedGameId, "One Each", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: cappedGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    // Every draw wins the only prize
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Limited", "", "", "ipfs://limited", "SKU", { legendary: {} } as any, 10000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: cappedGamePda })
+      .rpc();
+    const setCap = () =>
+      gameProgram.methods
+        .setPrizeMaxPerUser(1)
+        .accounts({ game: cappedGamePda, prize: prizePda, authority: wallet.publicKey, inventory: null });
+    const { events } = await setCap().simulate();
+    const capped: any = events.find((e: any) => e.name.toLowerCase() === "prizemaxperuserupdated")!.data;
+    expect(capped.oldMaxPerUser).to.equal(0);
+    expect(capped.maxPerUser).to.equal(1);
+    await setCap().rpc();
+    await gameProgram.methods
+      .openClaimRecord()
+      .accounts({ prize: prizePda, user: user.publicKey, payer: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const play = async () => {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), cappedGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: cappedGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      return sessionPda;
+    };
+    const finalize = (sessionPda: PublicKey, claimRecord: PublicKey | null) =>
+      gameProgram.methods
+        .finalizePlay([...Buffer.alloc(32, 3)], null)
+        .accounts({
+          playSession: sessionPda,
+          game: cappedGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: null,
+          claimRecord,
+        })
+        .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+        .rpc();
+
+    const first = await play();
+    try {
+      await finalize(first, null);
+      expect.fail("a capped prize needs the user's claim record");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ClaimRecordMissing");
+    }
+    await finalize(first, claimRecordPda);
+    expect((await gameProgram.account.playSession.fetch(first)).prizeIndex).to.equal(0);
+    expect((await gameProgram.account.prizeClaimRecord.fetch(claimRecordPda)).wins).to.equal(1);
+
+    // Same winning draw, but the user already has their one
+    const second = await play();
+    await finalize(second, claimRecordPda);
+    expect((await gameProgram.account.playSession.fetch(second)).prizeIndex).to.be.null;
+    expect((await gameProgram.account.prizeClaimRecord.fetch(claimRecordPda)).wins).to.equal(1);
+    expect((await gameProgram.account.prize.fetch(prizePda)).supplyRemaining).to.equal(4);
+  });
+
+  it("checks the per-user cap of the drawn prize, not of the prize passed in", async () => {
+    const mixedGameId = new BN(36);
+    const [mixedGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(mixedGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const prizePda = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), mixedGamePda.toBuffer(), Buffer.from([index])],
+        gameProgram.programId
+      )[0];
+    const [cappedRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("claim_record"), prizePda(1).toBuffer(), user.publicKey.toBuffer()],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(mixedGameId, "Mixed Caps", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: mixedGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    // Draws 0..9 win prize 0, draws 10..109 (including draw 19) win prize 1
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Open", "", "", "ipfs://open", "SKU", { common: {} } as any, 10, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: mixedGamePda })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(1, new BN(2), "Capped", "", "", "ipfs://capped", "SKU", { rare: {} } as any, 100, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: mixedGamePda })
+      .rpc();
+    await gameProgram.methods
+      .setPrizeMaxPerUser(1)
+      .accounts({ game: mixedGamePda, prize: prizePda(1), authority: wallet.publicKey, inventory: null })
+      .rpc();
+    await gameProgram.methods
+      .openClaimRecord()
+      .accounts({ prize: prizePda(1), user: user.publicKey, payer: wallet.publicKey })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const play = async () => {
+      const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const [sessionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("session"), mixedGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+        gameProgram.programId
+      );
+      await gameProgram.methods
+        .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: mixedGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      return sessionPda;
+    };
+    const finalize = (sessionPda: PublicKey, random: Buffer, prize: PublicKey, claimRecord: PublicKey | null) =>
+      gameProgram.methods
+        .finalizePlay([...random], null)
+        .accounts({
+          playSession: sessionPda,
+          game: mixedGamePda,
+          config: configPda,
+          backendAuthority: wallet.publicKey,
+          slotHashes: null,
+          recentWinners: null,
+          rngAudit: null,
+          complianceLog: null,
+          randomnessAccount: null,
+          pityCounter: null,
+          claimRecord,
+        })
+        .remainingAccounts([{ pubkey: prize, isSigner: false, isWritable: true }])
+        .rpc();
+
+    // The user uses up their one capped prize
+    const first = await play();
+    await finalize(first, Buffer.alloc(32, 3), prizePda(1), cappedRecordPda);
+    expect((await gameProgram.account.playSession.fetch(first)).prizeIndex).to.equal(1);
+
+    // Draw 5 wins prize 0; passing the capped prize instead must not turn it into a loss
+    const drawFive = Buffer.alloc(32);
+    drawFive[0] = 5;
+    const second = await play();
+    try {
+      await finalize(second, drawFive, prizePda(1), cappedRecordPda);
+      expect.fail("the passed prize must be the drawn one");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PrizeNotFound");
+    }
+    await finalize(second, drawFive, prizePda(0), null);
+    expect((await gameProgram.account.playSession.fetch(second)).prizeIndex).to.equal(0);
+  });
+
+  it("only takes plays inside the game's scheduled window", async () => {
+    const scheduledGameId = new BN(33);
+    const [scheduledGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(scheduledGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const now = Math.floor(Date.now() / 1000);
+    await gameProgram.methods
+      .initializeGame(scheduledGameId, "Midnight Drop", "", "", new BN(500), tokenMint, new BN(now + 3600), null)
+      .accounts({ authority: wallet.publicKey, game: scheduledGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Drop", "", "", "ipfs://drop", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: scheduledGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    const play = async () =>
+      gameProgram.methods
+        .playGame(new BN(100_000), [...Keypair.generate().publicKey.toBytes()], new BN(0), null, null, new BN(100_000))
+        .accounts({
+          game: scheduledGamePda,
+          user: user.publicKey,
+          userTokenAccount: userAta,
+          treasuryTokenAccount: await ata(treasury.publicKey),
+          creatorTokenAccount: null,
+          priceUpdate: null,
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    const setSchedule = (startsAt: number | null, endsAt: number | null) =>
+      gameProgram.methods
+        .setGameSchedule(startsAt === null ? null : new BN(startsAt), endsAt === null ? null : new BN(endsAt))
+        .accounts({ game: scheduledGamePda, authority: wallet.publicKey })
+        .rpc();
+    const expectError = async (action: () => Promise<unknown>, code: string) => {
+      try {
+        await action();
+        expect.fail(`${code} expected`);
+      } catch (err: any) {
+        expect(err.toString()).to.include(code);
+      }
+    };
+
+    await expectError(play, "GameNotStarted");
+    await setSchedule(now - 7200, now - 3600);
+    await expectError(play, "GameEnded");
+    await expectError(() => setSchedule(now, now), "InvalidSchedule");
+
+    const game: any = await gameProgram.account.game.fetch(scheduledGamePda);
+    expect(game.startsAt.toNumber()).to.equal(now - 7200);
+    expect(game.endsAt.toNumber()).to.equal(now - 3600);
+  });
+
+  it("caches the token mint's decimals when the game is created", async () => {
+    const decimalsGameId = new BN(34);
+    const [decimalsGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(decimalsGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const otherMint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 9);
+    const init = (mintAccount: PublicKey) =>
+      gameProgram.methods
+        .initializeGame(decimalsGameId, "Decimals", "", "", new BN(500), tokenMint, null, null)
+        .accounts({ authority: wallet.publicKey, game: decimalsGamePda, treasury: treasury.publicKey, tokenMint: mintAccount })
+        .rpc();
+
+    try {
+      await init(otherMint);
+      expect.fail("the mint account must be the game's token_mint");
+    } catch (err: any) {
+      expect(err.toString()).to.include("WrongMint");
+    }
+    await init(tokenMint);
+    expect((await gameProgram.account.game.fetch(decimalsGamePda)).tokenDecimals).to.equal(6);
+
+    // Backfilling is permissionless but only ever reads the game's own mint
+    try {
+      await gameProgram.methods
+        .cacheTokenDecimals()
+        .accounts({ game: decimalsGamePda, tokenMint: otherMint })
+        .rpc();
+      expect.fail("another mint's decimals cannot be cached");
+    } catch (err: any) {
+      expect(err.toString()).to.include("WrongMint");
+    }
+    await gameProgram.methods
+      .cacheTokenDecimals()
+      .accounts({ game: decimalsGamePda, tokenMint })
+      .rpc();
+    expect((await gameProgram.account.game.fetch(decimalsGamePda)).tokenDecimals).to.equal(6);
+  });
+
+  it("pays and withdraws with a Token-2022 mint carrying a transfer fee", async () => {
+    // A TransferFeeConfig extension makes the mint account larger than the base layout
+    const feeMint = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: wallet.publicKey,
+          newAccountPubkey: feeMint.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          feeMint.publicKey,
+          wallet.publicKey,
+          wallet.publicKey,
+          100, // 1%
+          BigInt(1_000_000),
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(feeMint.publicKey, 9, wallet.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [feeMint]
+    );
+
+    const feeGameId = new BN(35);
+    const [feeGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(feeGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .initializeGame(feeGameId, "Fee Drop", "", "", new BN(500), feeMint.publicKey, null, null)
+      .accounts({ authority: wallet.publicKey, game: feeGamePda, treasury: treasury.publicKey, tokenMint: feeMint.publicKey })
+      .rpc();
+    expect((await gameProgram.account.game.fetch(feeGamePda)).tokenDecimals).to.equal(9);
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Fee Prize", "", "", "ipfs://fee", "SKU", { common: {} } as any, 5000, new BN(100), 0, 0, 0, 0, 5, false)
+      .accounts({ authority: wallet.publicKey, game: feeGamePda })
+      .rpc();
+
+    const ata = async (owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          wallet.payer as any,
+          feeMint.publicKey,
+          owner,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).address;
+    const userAta = await ata(user.publicKey);
+    const treasuryAta = await ata(treasury.publicKey);
+    const destinationAta = await ata(wallet.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, feeMint.publicKey, userAta, wallet.publicKey, 1_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), feeGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: feeGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: treasuryAta,
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint: feeMint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    const balance = async (account: PublicKey) =>
+      Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+    expect(await balance(treasuryAta)).to.equal(99_000);
+    // The session records what the treasury received, so a refund can't overpay
+    expect((await gameProgram.account.playSession.fetch(sessionPda)).amountPaid.toNumber()).to.equal(99_000);
+
+    await gameProgram.methods
+      .withdrawTreasury(new BN(50_000))
+      .accounts({
+        game: feeGamePda,
+        authority: wallet.publicKey,
+        treasury: treasury.publicKey,
+        treasuryTokenAccount: treasuryAta,
+        destinationTokenAccount: destinationAta,
+        tokenMint: feeMint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([treasury])
+      .rpc();
+    expect(await balance(treasuryAta)).to.equal(49_000);
+    expect(await balance(destinationAta)).to.equal(49_500);
+
+    // With a creator split the creator's share never reaches the treasury, so it isn't
+    // part of what a refund from the treasury pays back
+    const creator = Keypair.generate();
+    await gameProgram.methods
+      .setCreatorSplit(creator.publicKey, 2500)
+      .accounts({ game: feeGamePda, authority: wallet.publicKey })
+      .rpc();
+    const creatorAta = await ata(creator.publicKey);
+    const splitSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [splitSessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), feeGamePda.toBuffer(), user.publicKey.toBuffer(), splitSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...splitSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: feeGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: treasuryAta,
+        creatorTokenAccount: creatorAta,
+        priceUpdate: null,
+        tokenMint: feeMint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    // 75_000 to the treasury and 25_000 to the creator, each less the 1% fee
+    expect(await balance(treasuryAta)).to.equal(49_000 + 74_250);
+    expect(await balance(creatorAta)).to.equal(24_750);
+    expect((await gameProgram.account.playSession.fetch(splitSessionPda)).amountPaid.toNumber()).to.equal(74_250);
+  });
+
+  it("redeems a physical prize won before its metadata URI was edited", async () => {
+    const shipGameId = new BN(37);
+    const [shipGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(shipGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const [prizePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), shipGamePda.toBuffer(), Buffer.from([0])],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    await gameProgram.methods
+      .initializeGame(shipGameId, "Ship It", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: shipGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    const [posterPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), shipGamePda.toBuffer(), Buffer.from([1])],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .addPrize(0, new BN(1), "Figure", "", "", "ipfs://figure-v1", "FIG-1", { common: {} } as any, 9000, new BN(100), 300, 0, 0, 0, 2, true)
+      .accounts({ authority: wallet.publicKey, game: shipGamePda })
+      .rpc();
+    await gameProgram.methods
+      .addPrize(1, new BN(2), "Poster", "", "", "ipfs://poster-v1", "POS-1", { common: {} } as any, 1000, new BN(100), 50, 0, 0, 0, 1, true)
+      .accounts({ authority: wallet.publicKey, game: shipGamePda })
+      .rpc();
+    const inventoryOf = (prize: PublicKey) =>
+      PublicKey.findProgramAddressSync([Buffer.from("inventory"), prize.toBuffer()], gameProgram.programId)[0];
+    const editUri = (uri: string, prize = prizePda) =>
+      gameProgram.methods
+        .updatePrize(null, null, null, uri, null, null, null, null, null, null)
+        .accounts({ game: shipGamePda, prize, authority: wallet.publicKey, inventory: inventoryOf(prize) })
+        .rpc();
+    // Nothing has been won yet, so the URI can still change
+    await editUri("ipfs://figure-v2");
+
+    const ata = async (owner: PublicKey) =>
+      (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, tokenMint, owner)).address;
+    const userAta = await ata(user.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, tokenMint, userAta, wallet.publicKey, 1_000_000);
+    const sessionSeed = Buffer.from(Keypair.generate().publicKey.toBytes());
+    const [sessionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), shipGamePda.toBuffer(), user.publicKey.toBuffer(), sessionSeed],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .playGame(new BN(100_000), [...sessionSeed], new BN(0), null, null, new BN(100_000))
+      .accounts({
+        game: shipGamePda,
+        user: user.publicKey,
+        userTokenAccount: userAta,
+        treasuryTokenAccount: await ata(treasury.publicKey),
+        creatorTokenAccount: null,
+        priceUpdate: null,
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+    await gameProgram.methods
+      .finalizePlay([...Buffer.alloc(32, 3)], null)
+      .accounts({
+        playSession: sessionPda,
+        game: shipGamePda,
+        config: configPda,
+        backendAuthority: wallet.publicKey,
+        slotHashes: null,
+        recentWinners: null,
+        rngAudit: null,
+        complianceLog: null,
+        randomnessAccount: null,
+        pityCounter: null,
+        claimRecord: null,
+      })
+      .remainingAccounts([{ pubkey: prizePda, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const nftMint = await createMint(provider.connection, wallet.payer as any, shipGamePda, shipGamePda, 0);
+    const userNftAta = await createAssociatedTokenAccount(provider.connection, wallet.payer as any, nftMint, user.publicKey);
+    const metaplex = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer()],
+      metaplex
+    );
+    const [masterEdition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer(), Buffer.from("edition")],
+      metaplex
+    );
+    await gameProgram.methods
+      .claimPrize()
+      .accounts({
+        playSession: sessionPda,
+        game: shipGamePda,
+        prize: prizePda,
+        user: user.publicKey,
+        nftMint,
+        metadata,
+        masterEdition,
+        userNftTokenAccount: userNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        metaplexTokenMetadataProgram: metaplex,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    // The minted NFT carries ipfs://figure-v2 for good, so the prize's URI is now locked
+    try {
+      await editUri("ipfs://figure-v3");
+      expect.fail("a won physical prize's URI should not change");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PrizeUriLocked");
+    }
+
+    const [redemption] = PublicKey.findProgramAddressSync(
+      [Buffer.from("redemption"), nftMint.toBuffer()],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .redeemPhysical([...Buffer.alloc(32, 7)])
+      .accounts({
+        game: shipGamePda,
+        prize: prizePda,
+        owner: user.publicKey,
+        nftMint,
+        ownerNftTokenAccount: userNftAta,
+        metadata,
+        redemption,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+    const redeemed: any = await gameProgram.account.redemption.fetch(redemption);
+    expect(redeemed.nftMint.toBase58()).to.equal(nftMint.toBase58());
+    expect(await provider.connection.getAccountInfo(userNftAta)).to.be.null;
+
+    // A pooled NFT carries the URI as well, though premint_prize leaves the supply untouched
+    const [poolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("premint"), posterPda.toBuffer(), Buffer.alloc(4)],
+      gameProgram.programId
+    );
+    await gameProgram.methods
+      .premintPrize()
+      .accounts({
+        game: shipGamePda,
+        prize: posterPda,
+        inventory: inventoryOf(posterPda),
+        authority: wallet.publicKey,
+        nftMint: poolMint,
+        metadata: PublicKey.findProgramAddressSync(
+          [Buffer.from("metadata"), metaplex.toBuffer(), poolMint.toBuffer()],
+          metaplex
+        )[0],
+        masterEdition: PublicKey.findProgramAddressSync(
+          [Buffer.from("metadata"), metaplex.toBuffer(), poolMint.toBuffer(), Buffer.from("edition")],
+          metaplex
+        )[0],
+        metaplexTokenMetadataProgram: metaplex,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    expect((await gameProgram.account.prize.fetch(posterPda)).supplyRemaining).to.equal(1);
+    try {
+      await editUri("ipfs://poster-v2", posterPda);
+      expect.fail("a preminted prize's URI should not change");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PrizeUriLocked");
+    }
+  });
+
+  it("won't reindex a prize with pool NFTs left in its inventory", async () => {
+    const poolGameId = new BN(38);
+    const [poolGamePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("game"), Buffer.from(poolGameId.toArray("le", 8))],
+      gameProgram.programId
+    );
+    const prizePdaAt = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("prize"), poolGamePda.toBuffer(), Buffer.from([index])],
+        gameProgram.programId
+      )[0];
+    await gameProgram.methods
+      .initializeGame(poolGameId, "Pooled Reindex", "", "", new BN(500), tokenMint, null, null)
+      .accounts({ authority: wallet.publicKey, game: poolGamePda, treasury: treasury.publicKey, tokenMint })
+      .rpc();
+    for (const index of [0, 1]) {
+      await gameProgram.methods
+        .addPrize(index, new BN(index + 1), `Prize ${index}`, "", "", `ipfs://pooled-${index}`, "SKU", { common: {} } as any, 1000, new BN(100), 0, 0, 0, 0, 1, false)
+        .accounts({ authority: wallet.publicKey, game: poolGamePda })
+        .rpc();
+    }
+
+    const [inventoryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("inventory"), prizePdaAt(1).toBuffer()],
+      gameProgram.programId
+    );
+    const [nftMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("premint"), prizePdaAt(1).toBuffer(), Buffer.alloc(4)],
+      gameProgram.programId
+    );
+    const metaplex = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer()],
+      metaplex
+    );
+    const [masterEdition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metaplex.toBuffer(), nftMint.toBuffer(), Buffer.from("edition")],
+      metaplex
+    );
+    await gameProgram.methods
+      .premintPrize()
+      .accounts({
+        game: poolGamePda,
+        prize: prizePdaAt(1),
+        inventory: inventoryPda,
+        authority: wallet.publicKey,
+        nftMint,
+        metadata,
+        masterEdition,
+        metaplexTokenMetadataProgram: metaplex,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    await gameProgram.methods
+      .removePrize()
+      .accounts({ game: poolGamePda, prize: prizePdaAt(0), authority: wallet.publicKey })
+      .rpc();
+    // The pooled NFT is claimed through the inventory at the old prize address
+    try {
+      await gameProgram.methods
+        .reindexPrize(0)
+        .accounts({
+          authority: wallet.publicKey,
+          game: poolGamePda,
+          lastPrize: prizePdaAt(1),
+          inventory: inventoryPda,
+          prize: prizePdaAt(0),
+        })
+        .rpc();
+      expect.fail("reindexing a prize with pooled NFTs should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PrizeNotMovable");
+    }
+  });
+
+  it("sets and clears the program's claim delegate", async () => {
+    const [claimDelegatePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("claim_delegate")],
+      gameProgram.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], gameProgram.programId);
+    const custodian = Keypair.generate().publicKey;
+
+    await gameProgram.methods
+      .setClaimDelegate(custodian)
+      .accounts({ config: configPda, authority: wallet.publicKey, claimDelegate: claimDelegatePda })
+      .rpc();
+    let claimDelegate: any = await gameProgram.account.claimDelegate.fetch(claimDelegatePda);
+    expect(claimDelegate.delegate.toBase58()).to.equal(custodian.toBase58());
+
+    await gameProgram.methods
+      .setClaimDelegate(null)
+      .accounts({ config: configPda, authority: wallet.publicKey, claimDelegate: claimDelegatePda })
+      .rpc();
+    claimDelegate = await gameProgram.account.claimDelegate.fetch(claimDelegatePda);
+    expect(claimDelegate.delegate).to.equal(null);
+  });
 });
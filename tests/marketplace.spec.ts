@@ -1,10 +1,20 @@
 import * as anchor from "@coral-xyz/anchor";
 import { BN, Program } from "@coral-xyz/anchor";
-import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  Ed25519Program,
+  Keypair,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SystemProgram,
+} from "@solana/web3.js";
+import { expect } from "chai";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
+  approve,
+  closeAccount,
   createMint,
+  getAccount,
   getAssociatedTokenAddressSync,
   getOrCreateAssociatedTokenAccount,
   getMint,
@@ -69,15 +79,56 @@ describe("gachapon-marketplace", () => {
     )).address;
     await mintTo(provider.connection, wallet.payer as any, currencyMint, buyerCurrencyAta, wallet.publicKey, Number(price));
 
-    // Derive config PDA and initialize
+    // Derive config PDA and initialize, unless the validator preloaded the legacy config
     [configPda, configBump] = PublicKey.findProgramAddressSync([Buffer.from("config")], marketplace.programId);
-    const platformTreasury = wallet.publicKey;
-    await marketplace.methods.initializeConfig(platformTreasury)
-      .accounts({
-        admin: wallet.publicKey,
-        config: configPda,
-        systemProgram: SystemProgram.programId,
-      })
+    if (!(await provider.connection.getAccountInfo(configPda))) {
+      const platformTreasury = wallet.publicKey;
+      await marketplace.methods.initializeConfig(platformTreasury, null)
+        .accounts({
+          admin: wallet.publicKey,
+          config: configPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+  });
+
+  it("migrates a config created before the platform fee was configurable", async function () {
+    // Anchor.toml preloads tests/fixtures/legacy-marketplace-config.json, a config in the
+    // old layout (no platform_fee_bps/version) owned by the fixture authority
+    const legacyAuthority = Keypair.fromSecretKey(Uint8Array.from(require("./fixtures/legacy-config-authority.json")));
+    const legacy = await provider.connection.getAccountInfo(configPda);
+    if (!legacy || !new PublicKey(legacy.data.subarray(8, 40)).equals(legacyAuthority.publicKey)) {
+      this.skip();
+    }
+    const legacySize = legacy!.data.length;
+    const sig = await provider.connection.requestAirdrop(legacyAuthority.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(sig, "confirmed");
+
+    const migrate = () =>
+      marketplace.methods.migrateConfig()
+        .accounts({ admin: legacyAuthority.publicKey, config: configPda })
+        .signers([legacyAuthority])
+        .rpc();
+    await migrate();
+    const migrated = await provider.connection.getAccountInfo(configPda);
+    expect(migrated!.data.length).to.equal(legacySize + 3 + 34);
+    let config: any = await marketplace.account.config.fetch(configPda);
+    expect(config.version).to.equal(2);
+    expect(config.platformFeeBps).to.equal(200);
+    expect(config.allowRelist).to.equal(true);
+    expect(config.royaltyBps).to.equal(0);
+
+    // A second run leaves the migrated config alone
+    await migrate();
+    config = await marketplace.account.config.fetch(configPda);
+    expect(config.version).to.equal(2);
+    expect(config.platformFeeBps).to.equal(200);
+
+    // Hand the config to the wallet the rest of the suite administers it with
+    await marketplace.methods.updateConfig(wallet.publicKey, wallet.publicKey, null, null, null)
+      .accounts({ admin: legacyAuthority.publicKey, config: configPda })
+      .signers([legacyAuthority])
       .rpc();
   });
 
@@ -93,9 +144,11 @@ describe("gachapon-marketplace", () => {
     const sellerNftAta = getAssociatedTokenAddressSync(nftMint, seller.publicKey);
     const escrowNftAta = getAssociatedTokenAddressSync(nftMint, escrowAuthority, true);
 
-    await marketplace.methods.listNft(price)
+    await marketplace.methods.listNft(price, true, { figures: {} }, 0b101)
       .accounts({
         seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
         listing: listingPda,
         nftMint,
         currencyMint,
@@ -111,6 +164,9 @@ describe("gachapon-marketplace", () => {
 
     const escrowAtaInfo = await getMint(provider.connection, nftMint);
     // no throws means ATA exists and transfer succeeded
+    const listing = await marketplace.account.listing.fetch(listingPda);
+    expect(listing.category).to.deep.equal({ figures: {} });
+    expect(listing.tags).to.equal(0b101);
   });
 
   it("cancels listing, returning NFT to seller", async () => {
@@ -129,6 +185,9 @@ describe("gachapon-marketplace", () => {
       .accounts({
         listing: listingPda,
         seller: seller.publicKey,
+        config: configPda,
+        platformTreasury: null,
+        nftMint,
         escrowAuthority,
         escrowNftTokenAccount: escrowNftAta,
         sellerNftTokenAccount: sellerNftAta,
@@ -152,9 +211,11 @@ describe("gachapon-marketplace", () => {
     );
     const sellerNftAta = getAssociatedTokenAddressSync(nftMint, seller.publicKey);
     const escrowNftAta = getAssociatedTokenAddressSync(nftMint, escrowAuthority, true);
-    await marketplace.methods.listNft(price)
+    await marketplace.methods.listNft(price, true, { uncategorized: {} }, 0)
       .accounts({
         seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
         listing: listingPda,
         nftMint,
         currencyMint,
@@ -179,9 +240,13 @@ describe("gachapon-marketplace", () => {
         buyer: buyer.publicKey,
         config: configPda,
         currencyMint,
+        nftMint,
         buyerCurrencyTokenAccount: buyerCurrencyAta,
+        seller: seller.publicKey,
         sellerCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, seller.publicKey),
+        platformTreasury: wallet.publicKey,
         platformTreasuryCurrencyTokenAccount: platformTreasuryAta,
+        royaltyTokenAccount: null,
         escrowAuthority,
         escrowNftTokenAccount: escrowNftAta,
         buyerNftTokenAccount: buyerNftAta,
@@ -193,6 +258,819 @@ describe("gachapon-marketplace", () => {
       .rpc();
   });
 
+  it("buys NFT in a currency the platform treasury has never held", async () => {
+    // Fresh currency: neither the seller nor the platform treasury has an ATA for it yet
+    const newCurrencyMint = await createMint(
+      provider.connection,
+      wallet.payer as any,
+      wallet.publicKey,
+      null,
+      6,
+    );
+    const freshNftMint = await createMint(
+      provider.connection,
+      wallet.payer as any,
+      wallet.publicKey,
+      null,
+      0,
+    );
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      freshNftMint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, freshNftMint, sellerNftAta, wallet.publicKey, 1);
+    const buyerCurrencyAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      newCurrencyMint,
+      buyer.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, newCurrencyMint, buyerCurrencyAta, wallet.publicKey, Number(price));
+
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), freshNftMint.toBuffer()],
+      marketplace.programId,
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), freshNftMint.toBuffer()],
+      marketplace.programId,
+    );
+    const escrowNftAta = getAssociatedTokenAddressSync(freshNftMint, escrowAuthority, true);
+    await marketplace.methods.listNft(price, true, { uncategorized: {} }, 0)
+      .accounts({
+        seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
+        listing: listingPda,
+        nftMint: freshNftMint,
+        currencyMint: newCurrencyMint,
+        sellerNftTokenAccount: sellerNftAta,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+
+    const platformTreasuryAta = getAssociatedTokenAddressSync(newCurrencyMint, wallet.publicKey);
+    const sellerCurrencyAta = getAssociatedTokenAddressSync(newCurrencyMint, seller.publicKey);
+    expect(await provider.connection.getAccountInfo(platformTreasuryAta)).to.be.null;
+
+    await marketplace.methods.buyNft()
+      .accounts({
+        listing: listingPda,
+        buyer: buyer.publicKey,
+        config: configPda,
+        currencyMint: newCurrencyMint,
+        nftMint: freshNftMint,
+        buyerCurrencyTokenAccount: buyerCurrencyAta,
+        seller: seller.publicKey,
+        sellerCurrencyTokenAccount: sellerCurrencyAta,
+        platformTreasury: wallet.publicKey,
+        platformTreasuryCurrencyTokenAccount: platformTreasuryAta,
+        royaltyTokenAccount: null,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        buyerNftTokenAccount: getAssociatedTokenAddressSync(freshNftMint, buyer.publicKey),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const treasuryAccount = await getAccount(provider.connection, platformTreasuryAta);
+    expect(treasuryAccount.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(Number(treasuryAccount.amount)).to.equal(Number(price) * 200 / 10_000);
+  });
+
+  // Lists a freshly minted NFT at `listPrice` and buys it as `purchaser`, returning the amounts received
+  async function listAndBuyFresh(
+    listPrice: number | bigint,
+    currency: PublicKey,
+    royaltyTokenAccount: PublicKey | null = null,
+    purchaser: Keypair = buyer,
+  ) {
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      mint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, sellerNftAta, wallet.publicKey, 1);
+    const buyerCurrencyAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      currency,
+      purchaser.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, currency, buyerCurrencyAta, wallet.publicKey, listPrice);
+
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const escrowNftAta = getAssociatedTokenAddressSync(mint, escrowAuthority, true);
+    await marketplace.methods.listNft(new BN(listPrice.toString()), true, { uncategorized: {} }, 0)
+      .accounts({
+        seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
+        listing: listingPda,
+        nftMint: mint,
+        currencyMint: currency,
+        sellerNftTokenAccount: sellerNftAta,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+
+    const sellerCurrencyAta = getAssociatedTokenAddressSync(currency, seller.publicKey);
+    const treasuryCurrencyAta = getAssociatedTokenAddressSync(currency, wallet.publicKey);
+    const balance = async (ata: PublicKey) =>
+      (await provider.connection.getAccountInfo(ata)) ? (await getAccount(provider.connection, ata)).amount : 0n;
+    const sellerBefore = await balance(sellerCurrencyAta);
+    const treasuryBefore = await balance(treasuryCurrencyAta);
+
+    await marketplace.methods.buyNft()
+      .accounts({
+        listing: listingPda,
+        buyer: purchaser.publicKey,
+        config: configPda,
+        currencyMint: currency,
+        nftMint: mint,
+        buyerCurrencyTokenAccount: buyerCurrencyAta,
+        seller: seller.publicKey,
+        sellerCurrencyTokenAccount: sellerCurrencyAta,
+        platformTreasury: wallet.publicKey,
+        platformTreasuryCurrencyTokenAccount: treasuryCurrencyAta,
+        royaltyTokenAccount,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        buyerNftTokenAccount: getAssociatedTokenAddressSync(mint, purchaser.publicKey),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([purchaser])
+      .rpc();
+
+    const sellerExact = (await balance(sellerCurrencyAta)) - sellerBefore;
+    const feeExact = (await balance(treasuryCurrencyAta)) - treasuryBefore;
+    return { seller: Number(sellerExact), fee: Number(feeExact), sellerExact, feeExact };
+  }
+
+  it("splits boundary prices without losing or creating tokens", async () => {
+    for (const [rounding, expectedFees] of [
+      [{ floor: {} }, { 1: 0, 49: 0, 50: 1 }],
+      [{ halfUp: {} }, { 1: 0, 49: 1, 50: 1 }],
+    ] as const) {
+      await marketplace.methods.updateConfig(null, null, rounding as any, null, null)
+        .accounts({ admin: wallet.publicKey, config: configPda })
+        .rpc();
+      for (const boundaryPrice of [1, 49, 50]) {
+        const { seller: sellerReceived, fee } = await listAndBuyFresh(boundaryPrice, currencyMint);
+        expect(fee).to.equal((expectedFees as Record<number, number>)[boundaryPrice]);
+        expect(sellerReceived + fee).to.equal(boundaryPrice);
+      }
+    }
+    await marketplace.methods.updateConfig(null, null, { floor: {} } as any, null, null)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+  });
+
+  it("splits a u64::MAX price without overflowing", async () => {
+    const maxPrice = 2n ** 64n - 1n;
+    const bigCurrency = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 9);
+    const { sellerExact, feeExact } = await listAndBuyFresh(maxPrice, bigCurrency);
+    expect(feeExact).to.equal((maxPrice * 200n) / 10_000n);
+    expect(sellerExact + feeExact).to.equal(maxPrice);
+  });
+
+  it("creates the seller's currency ATA when the seller never held the currency", async () => {
+    const sellerOnlyNewCurrency = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 6);
+    const sellerCurrencyAta = getAssociatedTokenAddressSync(sellerOnlyNewCurrency, seller.publicKey);
+    expect(await provider.connection.getAccountInfo(sellerCurrencyAta)).to.be.null;
+
+    const { seller: sellerReceived, fee } = await listAndBuyFresh(10_000, sellerOnlyNewCurrency);
+
+    const sellerAccount = await getAccount(provider.connection, sellerCurrencyAta);
+    expect(sellerAccount.owner.toBase58()).to.equal(seller.publicKey.toBase58());
+    expect(sellerAccount.mint.toBase58()).to.equal(sellerOnlyNewCurrency.toBase58());
+    expect(sellerReceived).to.equal(10_000 - fee);
+  });
+
+  it("waives the platform fee for exempt sellers only", async () => {
+    await marketplace.methods.setFeeExemptSeller(seller.publicKey, true)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    const exempt = await listAndBuyFresh(10_000, currencyMint);
+    expect(exempt.fee).to.equal(0);
+    expect(exempt.seller).to.equal(10_000);
+
+    await marketplace.methods.setFeeExemptSeller(seller.publicKey, false)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    const normal = await listAndBuyFresh(10_000, currencyMint);
+    expect(normal.fee).to.equal(200);
+    expect(normal.seller).to.equal(9_800);
+  });
+
+  it("waives the platform fee inside the fee-free window only", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await marketplace.methods.setFeeFreeUntil(new BN(now + 3600))
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    const launch = await listAndBuyFresh(10_000, currencyMint);
+    expect(launch.fee).to.equal(0);
+    expect(launch.seller).to.equal(10_000);
+
+    await marketplace.methods.setFeeFreeUntil(new BN(now - 3600))
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    const afterWindow = await listAndBuyFresh(10_000, currencyMint);
+    expect(afterWindow.fee).to.equal(200);
+
+    await marketplace.methods.setFeeFreeUntil(null)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+  });
+
+  it("charges the platform fee set through update_config, up to 10%", async () => {
+    const setFee = async (bps: number) => {
+      const sig = await marketplace.methods.updateConfig(null, null, null, null, bps)
+        .accounts({ admin: wallet.publicKey, config: configPda })
+        .rpc({ commitment: "confirmed" });
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(marketplace.programId, new anchor.BorshCoder(marketplace.idl));
+      return [...parser.parseLogs(tx!.meta!.logMessages!)].filter(
+        (e) => e.name === "configUpdated" || e.name === "ConfigUpdated"
+      );
+    };
+
+    const [raised] = await setFee(500);
+    expect(raised.data.previousPlatformFeeBps).to.equal(200);
+    expect(raised.data.platformFeeBps).to.equal(500);
+    expect((await marketplace.account.config.fetch(configPda)).platformFeeBps).to.equal(500);
+    const promo = await listAndBuyFresh(10_000, currencyMint);
+    expect(promo.fee).to.equal(500);
+    expect(promo.seller).to.equal(9_500);
+
+    // Re-sending the current fee changes nothing, so nothing is emitted
+    expect(await setFee(500)).to.have.length(0);
+    try {
+      await setFee(1_001);
+      expect.fail("fees above 10% are rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("PlatformFeeTooHigh");
+    }
+
+    await setFee(200);
+    const normal = await listAndBuyFresh(10_000, currencyMint);
+    expect(normal.fee).to.equal(200);
+  });
+
+  it("rejects a seller buying their own listing", async () => {
+    try {
+      await listAndBuyFresh(10_000, currencyMint, null, seller);
+      expect.fail("sellers cannot buy their own listings");
+    } catch (err: any) {
+      expect(err.toString()).to.include("SelfPurchase");
+    }
+  });
+
+  it("pays the configured royalty out of the seller's share on enforcing listings", async () => {
+    const royaltyWallet = Keypair.generate().publicKey;
+    const royaltyAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      currencyMint,
+      royaltyWallet,
+    )).address;
+    const setRoyalty = (bps: number, destination: PublicKey) =>
+      marketplace.methods.setRoyalty(bps, destination)
+        .accounts({ admin: wallet.publicKey, config: configPda })
+        .rpc();
+
+    try {
+      await setRoyalty(1_001, royaltyWallet);
+      expect.fail("royalties above 10% are rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("InvalidRoyalty");
+    }
+    await setRoyalty(500, royaltyWallet);
+
+    try {
+      await listAndBuyFresh(10_000, currencyMint);
+      expect.fail("a royalty-paying listing needs the royalty token account");
+    } catch (err: any) {
+      expect(err.toString()).to.include("MissingRoyaltyAccount");
+    }
+    const sale = await listAndBuyFresh(10_000, currencyMint, royaltyAta);
+    expect(sale.fee).to.equal(200);
+    expect(sale.seller).to.equal(9_300);
+    expect(Number((await getAccount(provider.connection, royaltyAta)).amount)).to.equal(500);
+
+    await setRoyalty(0, PublicKey.default);
+  });
+
+  it("buys and relists an NFT in one transaction, keeping it in escrow", async () => {
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      mint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, sellerNftAta, wallet.publicKey, 1);
+    const buyerCurrencyAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      currencyMint,
+      buyer.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, currencyMint, buyerCurrencyAta, wallet.publicKey, 10_000);
+
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const escrowNftAta = getAssociatedTokenAddressSync(mint, escrowAuthority, true);
+    await marketplace.methods.listNft(new BN(10_000), true, { uncategorized: {} }, 0)
+      .accounts({
+        seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
+        listing: listingPda,
+        nftMint: mint,
+        currencyMint,
+        sellerNftTokenAccount: sellerNftAta,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+
+    const sellerCurrencyAta = getAssociatedTokenAddressSync(currencyMint, seller.publicKey);
+    const sellerBefore = (await getAccount(provider.connection, sellerCurrencyAta)).amount;
+
+    await marketplace.methods.buyAndRelist(new BN(15_000), true, { cards: {} }, 0b10)
+      .accounts({
+        listing: listingPda,
+        buyer: buyer.publicKey,
+        config: configPda,
+        currencyMint,
+        buyerCurrencyTokenAccount: buyerCurrencyAta,
+        seller: seller.publicKey,
+        sellerCurrencyTokenAccount: sellerCurrencyAta,
+        platformTreasury: wallet.publicKey,
+        platformTreasuryCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, wallet.publicKey),
+        royaltyTokenAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const sellerAfter = (await getAccount(provider.connection, sellerCurrencyAta)).amount;
+    expect(Number(sellerAfter - sellerBefore)).to.equal(9_800);
+
+    const listing = await marketplace.account.listing.fetch(listingPda);
+    expect(listing.seller.toBase58()).to.equal(buyer.publicKey.toBase58());
+    expect(listing.priceInTokens.toNumber()).to.equal(15_000);
+    expect(listing.isActive).to.equal(true);
+    expect(listing.category).to.deep.equal({ cards: {} });
+    expect(listing.tags).to.equal(0b10);
+    expect(Number((await getAccount(provider.connection, escrowNftAta)).amount)).to.equal(1);
+  });
+
+  it("settles a layaway on the final payment and blocks seller cancels meanwhile", async () => {
+    const layawayPrice = 10_000;
+    // The listing's royalty is carried onto the layaway and paid when it settles
+    const royaltyWallet = Keypair.generate().publicKey;
+    const royaltyAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      currencyMint,
+      royaltyWallet,
+    )).address;
+    await marketplace.methods.setRoyalty(500, royaltyWallet)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      mint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, sellerNftAta, wallet.publicKey, 1);
+    const buyerCurrencyAta = getAssociatedTokenAddressSync(currencyMint, buyer.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, currencyMint, buyerCurrencyAta, wallet.publicKey, layawayPrice);
+
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const [layawayPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("layaway"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const escrowNftAta = getAssociatedTokenAddressSync(mint, escrowAuthority, true);
+    const layawayCurrencyAta = getAssociatedTokenAddressSync(currencyMint, layawayPda, true);
+    await marketplace.methods.listNft(new BN(layawayPrice), true, { uncategorized: {} }, 0)
+      .accounts({
+        seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
+        listing: listingPda,
+        nftMint: mint,
+        currencyMint,
+        sellerNftTokenAccount: sellerNftAta,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+
+    await marketplace.methods.startLayaway(new BN(4_000), new BN(3600), false)
+      .accounts({
+        listing: listingPda,
+        seller: seller.publicKey,
+        buyer: buyer.publicKey,
+        config: configPda,
+        currencyMint,
+        layaway: layawayPda,
+        buyerCurrencyTokenAccount: buyerCurrencyAta,
+        layawayCurrencyTokenAccount: layawayCurrencyAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller, buyer])
+      .rpc();
+    expect(Number((await getAccount(provider.connection, layawayCurrencyAta)).amount)).to.equal(4_000);
+
+    try {
+      await marketplace.methods.cancelListing()
+        .accounts({
+          listing: listingPda,
+          seller: seller.publicKey,
+          config: configPda,
+          platformTreasury: null,
+          nftMint: mint,
+          escrowAuthority,
+          escrowNftTokenAccount: escrowNftAta,
+          sellerNftTokenAccount: sellerNftAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+      expect.fail("seller should not be able to pull the NFT mid-layaway");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ListingInactive");
+    }
+
+    const treasuryCurrencyAta = getAssociatedTokenAddressSync(currencyMint, wallet.publicKey);
+    const treasuryBefore = Number((await getAccount(provider.connection, treasuryCurrencyAta)).amount);
+    const buyerNftAta = getAssociatedTokenAddressSync(mint, buyer.publicKey);
+    const sellerCurrencyAta = getAssociatedTokenAddressSync(currencyMint, seller.publicKey);
+    const sellerBefore = (await provider.connection.getAccountInfo(sellerCurrencyAta))
+      ? Number((await getAccount(provider.connection, sellerCurrencyAta)).amount)
+      : 0;
+    const finalPayment = (royaltyTokenAccount: PublicKey | null) =>
+      marketplace.methods.makeLayawayPayment(new BN(6_000))
+        .accounts({
+          layaway: layawayPda,
+          buyer: buyer.publicKey,
+          config: configPda,
+          currencyMint,
+          nftMint: mint,
+          buyerCurrencyTokenAccount: buyerCurrencyAta,
+          layawayCurrencyTokenAccount: layawayCurrencyAta,
+          seller: seller.publicKey,
+          sellerCurrencyTokenAccount: sellerCurrencyAta,
+          platformTreasury: wallet.publicKey,
+          platformTreasuryCurrencyTokenAccount: treasuryCurrencyAta,
+          royaltyTokenAccount,
+          escrowAuthority,
+          escrowNftTokenAccount: escrowNftAta,
+          buyerNftTokenAccount: buyerNftAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+    try {
+      await finalPayment(null);
+      expect.fail("a royalty-paying layaway needs the royalty token account");
+    } catch (err: any) {
+      expect(err.toString()).to.include("MissingRoyaltyAccount");
+    }
+    await finalPayment(royaltyAta);
+    await marketplace.methods.setRoyalty(0, PublicKey.default)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, buyerNftAta)).amount)).to.equal(1);
+    expect(Number((await getAccount(provider.connection, treasuryCurrencyAta)).amount) - treasuryBefore).to.equal(200);
+    expect(Number((await getAccount(provider.connection, royaltyAta)).amount)).to.equal(500);
+    expect(Number((await getAccount(provider.connection, sellerCurrencyAta)).amount) - sellerBefore).to.equal(9_300);
+    expect(await provider.connection.getAccountInfo(layawayPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(layawayCurrencyAta)).to.be.null;
+  });
+
+  // Lists a freshly minted NFT from the seller at a fixed price
+  async function listFresh() {
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      mint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, sellerNftAta, wallet.publicKey, 1);
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const escrowNftAta = getAssociatedTokenAddressSync(mint, escrowAuthority, true);
+    await marketplace.methods.listNft(new BN(10_000), true, { uncategorized: {} }, 0)
+      .accounts({
+        seller: seller.publicKey,
+        config: configPda,
+        metadata: null,
+        listing: listingPda,
+        nftMint: mint,
+        currencyMint,
+        sellerNftTokenAccount: sellerNftAta,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+    return { mint, listingPda, escrowAuthority, escrowNftAta };
+  }
+
+  // Lists a freshly minted NFT and turns the listing into an auction
+  async function startFreshAuction() {
+    const { mint, listingPda } = await listFresh();
+
+    const [auctionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("auction"), mint.toBuffer()],
+      marketplace.programId,
+    );
+    const auctionCurrencyAta = getAssociatedTokenAddressSync(currencyMint, auctionPda, true);
+    await marketplace.methods.startAuction(new BN(1_000), new BN(3600))
+      .accounts({
+        listing: listingPda,
+        seller: seller.publicKey,
+        config: configPda,
+        currencyMint,
+        auction: auctionPda,
+        auctionCurrencyTokenAccount: auctionCurrencyAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+    return { auctionPda, auctionCurrencyAta };
+  }
+
+  async function fundedBidder(amount: number) {
+    const bidder = Keypair.generate();
+    const sig = await provider.connection.requestAirdrop(bidder.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    const ata = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      currencyMint,
+      bidder.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, currencyMint, ata, wallet.publicKey, amount);
+    return { bidder, ata };
+  }
+
+  async function placeBid(
+    auction: { auctionPda: PublicKey; auctionCurrencyAta: PublicKey },
+    bidder: Keypair,
+    amount: number,
+    previousBidder: PublicKey | null,
+  ) {
+    await marketplace.methods.placeBid(new BN(amount))
+      .accounts({
+        auction: auction.auctionPda,
+        bidder: bidder.publicKey,
+        config: configPda,
+        currencyMint,
+        bidderCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, bidder.publicKey),
+        auctionCurrencyTokenAccount: auction.auctionCurrencyAta,
+        previousBidderCurrencyTokenAccount: previousBidder
+          ? getAssociatedTokenAddressSync(currencyMint, previousBidder)
+          : null,
+        bidRefund: previousBidder
+          ? PublicKey.findProgramAddressSync(
+            [Buffer.from("bid_refund"), auction.auctionPda.toBuffer(), previousBidder.toBuffer()],
+            marketplace.programId,
+          )[0]
+          : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([bidder])
+      .rpc();
+  }
+
+  it("refunds the previous bidder's escrowed bid when they are outbid", async () => {
+    const auction = await startFreshAuction();
+    const first = await fundedBidder(1_000);
+    const second = await fundedBidder(1_500);
+
+    await placeBid(auction, first.bidder, 1_000, null);
+    expect(Number((await getAccount(provider.connection, first.ata)).amount)).to.equal(0);
+
+    await placeBid(auction, second.bidder, 1_500, first.bidder.publicKey);
+    expect(Number((await getAccount(provider.connection, first.ata)).amount)).to.equal(1_000);
+    expect(Number((await getAccount(provider.connection, auction.auctionCurrencyAta)).amount)).to.equal(1_500);
+
+    const state = await marketplace.account.auction.fetch(auction.auctionPda);
+    expect(state.highestBidder.toBase58()).to.equal(second.bidder.publicKey.toBase58());
+    expect(state.highestBid.toNumber()).to.equal(1_500);
+    expect(state.pendingRefunds.toNumber()).to.equal(0);
+  });
+
+  it("records a claimable refund when the outbid bidder's ATA was closed", async () => {
+    const auction = await startFreshAuction();
+    const first = await fundedBidder(1_000);
+    const second = await fundedBidder(1_500);
+
+    await placeBid(auction, first.bidder, 1_000, null);
+    // The whole balance is escrowed, so the bidder can close their ATA
+    await closeAccount(provider.connection, first.bidder, first.ata, first.bidder.publicKey, first.bidder);
+    expect(await provider.connection.getAccountInfo(first.ata)).to.be.null;
+
+    await placeBid(auction, second.bidder, 1_500, first.bidder.publicKey);
+    const [bidRefundPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bid_refund"), auction.auctionPda.toBuffer(), first.bidder.publicKey.toBuffer()],
+      marketplace.programId,
+    );
+    const bidRefund = await marketplace.account.bidRefund.fetch(bidRefundPda);
+    expect(bidRefund.bidder.toBase58()).to.equal(first.bidder.publicKey.toBase58());
+    expect(bidRefund.amount.toNumber()).to.equal(1_000);
+    let state = await marketplace.account.auction.fetch(auction.auctionPda);
+    expect(state.pendingRefunds.toNumber()).to.equal(1_000);
+    expect(Number((await getAccount(provider.connection, auction.auctionCurrencyAta)).amount)).to.equal(2_500);
+
+    await marketplace.methods.claimBidRefund()
+      .accounts({
+        bidder: first.bidder.publicKey,
+        auction: auction.auctionPda,
+        seller: seller.publicKey,
+        bidRefund: bidRefundPda,
+        currencyMint,
+        auctionCurrencyTokenAccount: auction.auctionCurrencyAta,
+        bidderCurrencyTokenAccount: first.ata,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([first.bidder])
+      .rpc();
+    expect(Number((await getAccount(provider.connection, first.ata)).amount)).to.equal(1_000);
+    expect(await provider.connection.getAccountInfo(bidRefundPda)).to.be.null;
+    state = await marketplace.account.auction.fetch(auction.auctionPda);
+    expect(state.pendingRefunds.toNumber()).to.equal(0);
+  });
+
+  it("buys with a seller-signed permit once and rejects a replay", async () => {
+    const permitPrice = 10_000;
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const sellerNftAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer as any,
+      mint,
+      seller.publicKey,
+    )).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, sellerNftAta, wallet.publicKey, 1);
+    const buyerCurrencyAta = getAssociatedTokenAddressSync(currencyMint, buyer.publicKey);
+    await mintTo(provider.connection, wallet.payer as any, currencyMint, buyerCurrencyAta, wallet.publicKey, permitPrice);
+
+    // Seller approves the program's delegate PDA and signs the permit off-chain
+    const [permitDelegate] = PublicKey.findProgramAddressSync([Buffer.from("permit_delegate")], marketplace.programId);
+    await approve(provider.connection, wallet.payer as any, sellerNftAta, permitDelegate, seller, 1);
+    const nonce = new BN(1);
+    const expiry = new BN(Math.floor(Date.now() / 1000) + 3600);
+    const message = Buffer.concat([
+      marketplace.programId.toBuffer(),
+      mint.toBuffer(),
+      currencyMint.toBuffer(),
+      new BN(permitPrice).toArrayLike(Buffer, "le", 8),
+      nonce.toArrayLike(Buffer, "le", 8),
+      expiry.toArrayLike(Buffer, "le", 8),
+    ]);
+    const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: seller.secretKey,
+      message,
+    });
+    const [permitNonce] = PublicKey.findProgramAddressSync(
+      [Buffer.from("permit"), seller.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      marketplace.programId,
+    );
+    const buyerNftAta = getAssociatedTokenAddressSync(mint, buyer.publicKey);
+    const buyWithPermit = () =>
+      marketplace.methods.buyWithPermit(new BN(permitPrice), nonce, expiry)
+        .accounts({
+          buyer: buyer.publicKey,
+          seller: seller.publicKey,
+          config: configPda,
+          nftMint: mint,
+          currencyMint,
+          permitNonce,
+          sellerNftTokenAccount: sellerNftAta,
+          permitDelegate,
+          buyerCurrencyTokenAccount: buyerCurrencyAta,
+          sellerCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, seller.publicKey),
+          platformTreasury: wallet.publicKey,
+          platformTreasuryCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, wallet.publicKey),
+          royaltyTokenAccount: null,
+          buyerNftTokenAccount: buyerNftAta,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([verifyIx])
+        .signers([buyer])
+        .rpc();
+
+    await buyWithPermit();
+    expect(Number((await getAccount(provider.connection, buyerNftAta)).amount)).to.equal(1);
+
+    try {
+      await buyWithPermit();
+      expect.fail("a permit nonce must only be usable once");
+    } catch (err: any) {
+      expect(err.toString()).to.not.include("a permit nonce must only be usable once");
+    }
+  });
+
+  it("rejects listings and purchases while paused but still allows cancels", async () => {
+    await marketplace.methods.setPaused(true, true)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+    try {
+      await listAndBuyFresh(1_000, currencyMint);
+      expect.fail("listing should be rejected while paused");
+    } catch (err: any) {
+      expect(err.toString()).to.include("MarketplacePaused");
+    }
+    await marketplace.methods.setPaused(false, false)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+  });
+
   it("updates listing price", async () => {
     const [listingPda] = PublicKey.findProgramAddressSync(
       [Buffer.from("listing"), nftMint.toBuffer()],
@@ -207,9 +1085,247 @@ describe("gachapon-marketplace", () => {
       .rpc();
   });
 
+  it("transfers a listing to a new seller without moving the NFT", async () => {
+    const { listingPda, escrowAuthority, escrowNftAta } = await listFresh();
+    const newSeller = Keypair.generate().publicKey;
+    await marketplace.methods.transferListing(newSeller)
+      .accounts({
+        listing: listingPda,
+        seller: seller.publicKey,
+        escrowAuthority,
+        escrowNftTokenAccount: escrowNftAta,
+      })
+      .signers([seller])
+      .rpc();
+
+    const listing = await marketplace.account.listing.fetch(listingPda);
+    expect(listing.seller.toBase58()).to.equal(newSeller.toBase58());
+    expect(listing.isActive).to.equal(true);
+    expect(Number((await getAccount(provider.connection, escrowNftAta)).amount)).to.equal(1);
+
+    // The old wallet no longer controls the listing
+    try {
+      await marketplace.methods.updateListingPrice(new BN(1))
+        .accounts({ listing: listingPda, seller: seller.publicKey })
+        .signers([seller])
+        .rpc();
+      expect.fail("the previous seller should not be able to reprice");
+    } catch (err: any) {
+      expect(err.toString()).to.include("ConstraintHasOne");
+    }
+  });
+
+  it("caps active listings per seller once a maximum is configured", async () => {
+    const [sellerStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_stats"), seller.publicKey.toBuffer()],
+      marketplace.programId,
+    );
+    const before = (await marketplace.account.sellerStats.fetch(sellerStatsPda)).activeListings;
+    await marketplace.methods.setMaxActiveListings(before + 1)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+
+    await listFresh();
+    expect((await marketplace.account.sellerStats.fetch(sellerStatsPda)).activeListings).to.equal(before + 1);
+    try {
+      await listFresh();
+      expect.fail("listing past the cap should be rejected");
+    } catch (err: any) {
+      expect(err.toString()).to.include("TooManyActiveListings");
+    }
+
+    await marketplace.methods.setMaxActiveListings(0)
+      .accounts({ admin: wallet.publicKey, config: configPda })
+      .rpc();
+  });
+
+  it("reprices listings in a batch with a multiplier", async () => {
+    const [listingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), nftMint.toBuffer()],
+      marketplace.programId,
+    );
+    await marketplace.methods.updateListingPricesBatch({ multiplierBps: { 0: 9_000 } } as any)
+      .accounts({ seller: seller.publicKey })
+      .remainingAccounts([{ pubkey: listingPda, isSigner: false, isWritable: true }])
+      .signers([seller])
+      .rpc();
+
+    const listing: any = await marketplace.account.listing.fetch(listingPda);
+    expect(listing.priceInTokens.toNumber()).to.equal(1_800_000);
+  });
+
+  // Minimal Metaplex instructions (borsh by hand) to build a verified collection
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  const metadataPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID,
+    )[0];
+  const editionPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer(), Buffer.from("edition")],
+      TOKEN_METADATA_PROGRAM_ID,
+    )[0];
+  const borshString = (value: string) => {
+    const bytes = Buffer.from(value);
+    const len = Buffer.alloc(4);
+    len.writeUInt32LE(bytes.length);
+    return Buffer.concat([len, bytes]);
+  };
+  const metaplexIx = (data: Buffer, keys: [PublicKey, boolean, boolean][]) =>
+    new anchor.web3.TransactionInstruction({
+      programId: TOKEN_METADATA_PROGRAM_ID,
+      keys: keys.map(([pubkey, isSigner, isWritable]) => ({ pubkey, isSigner, isWritable })),
+      data,
+    });
+
+  // Mint a 1/1 to `owner` with metadata (wallet as update authority), optionally
+  // naming `collection` (unverified until verifyCollection)
+  async function mintWithMetadata(owner: PublicKey, collection: PublicKey | null) {
+    const mint = await createMint(provider.connection, wallet.payer as any, wallet.publicKey, null, 0);
+    const ata = (await getOrCreateAssociatedTokenAccount(provider.connection, wallet.payer as any, mint, owner)).address;
+    await mintTo(provider.connection, wallet.payer as any, mint, ata, wallet.publicKey, 1);
+    const data = Buffer.concat([
+      Buffer.from([33]), // CreateMetadataAccountV3
+      borshString("Prize"),
+      borshString("PRIZE"),
+      borshString("https://example.com/prize.json"),
+      Buffer.from([0, 0, 0]), // seller_fee_basis_points, creators: None
+      collection ? Buffer.concat([Buffer.from([1, 0]), collection.toBuffer()]) : Buffer.from([0]),
+      Buffer.from([0, 1, 0]), // uses: None, is_mutable, collection_details: None
+    ]);
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(metaplexIx(data, [
+      [metadataPda(mint), false, true],
+      [mint, false, false],
+      [wallet.publicKey, true, false],
+      [wallet.publicKey, true, true],
+      [wallet.publicKey, true, false],
+      [SystemProgram.programId, false, false],
+      [anchor.web3.SYSVAR_RENT_PUBKEY, false, false],
+    ])));
+    return mint;
+  }
+
+  it("fills a collection bid across sellers, then closes it and rejects the late filler", async () => {
+    // Collection NFT with a master edition, then members verified against it
+    const collectionMint = await mintWithMetadata(wallet.publicKey, null);
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(metaplexIx(
+      Buffer.concat([Buffer.from([17, 1]), Buffer.alloc(8)]), // CreateMasterEditionV3, max_supply Some(0)
+      [
+        [editionPda(collectionMint), false, true],
+        [collectionMint, false, true],
+        [wallet.publicKey, true, false],
+        [wallet.publicKey, true, false],
+        [wallet.publicKey, true, true],
+        [metadataPda(collectionMint), false, true],
+        [TOKEN_PROGRAM_ID, false, false],
+        [SystemProgram.programId, false, false],
+        [anchor.web3.SYSVAR_RENT_PUBKEY, false, false],
+      ],
+    )));
+    const verifiedMember = async (owner: PublicKey) => {
+      const mint = await mintWithMetadata(owner, collectionMint);
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(metaplexIx(Buffer.from([18]), [
+        [metadataPda(mint), false, true],
+        [wallet.publicKey, true, true],
+        [wallet.publicKey, true, true],
+        [wallet.publicKey, false, false],
+        [collectionMint, false, false],
+        [metadataPda(collectionMint), false, false],
+        [editionPda(collectionMint), false, false],
+      ])));
+      return mint;
+    };
+    const first = await verifiedMember(seller.publicKey);
+    const second = await verifiedMember(buyer.publicKey);
+    const late = await verifiedMember(seller.publicKey);
+    const unverified = await mintWithMetadata(buyer.publicKey, collectionMint);
+
+    const pricePerNft = 2_000;
+    const { bidder, ata: bidderAta } = await fundedBidder(2 * pricePerNft);
+    const [bidPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collection_bid"), bidder.publicKey.toBuffer(), collectionMint.toBuffer()],
+      marketplace.programId,
+    );
+    const bidCurrencyAta = getAssociatedTokenAddressSync(currencyMint, bidPda, true);
+    await marketplace.methods.placeCollectionBid(collectionMint, new BN(pricePerNft), 2)
+      .accounts({
+        bidder: bidder.publicKey,
+        config: configPda,
+        collectionBid: bidPda,
+        currencyMint,
+        bidderCurrencyTokenAccount: bidderAta,
+        bidCurrencyTokenAccount: bidCurrencyAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([bidder])
+      .rpc();
+    expect(Number((await getAccount(provider.connection, bidCurrencyAta)).amount)).to.equal(2 * pricePerNft);
+
+    const treasury = (await marketplace.account.config.fetch(configPda)).platformTreasury as PublicKey;
+    const accept = (holder: Keypair, mint: PublicKey) =>
+      marketplace.methods.acceptCollectionBid(new BN(pricePerNft))
+        .accounts({
+          seller: holder.publicKey,
+          config: configPda,
+          collectionBid: bidPda,
+          bidder: bidder.publicKey,
+          nftMint: mint,
+          metadata: metadataPda(mint),
+          sellerNftTokenAccount: getAssociatedTokenAddressSync(mint, holder.publicKey),
+          bidderNftTokenAccount: getAssociatedTokenAddressSync(mint, bidder.publicKey),
+          currencyMint,
+          bidCurrencyTokenAccount: bidCurrencyAta,
+          sellerCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, holder.publicKey),
+          platformTreasury: treasury,
+          platformTreasuryCurrencyTokenAccount: getAssociatedTokenAddressSync(currencyMint, treasury),
+          royaltyTokenAccount: null,
+          bidderCurrencyTokenAccount: bidderAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([holder])
+        .rpc();
+
+    await accept(seller, first);
+    let bid = await marketplace.account.collectionBid.fetch(bidPda);
+    expect(bid.filled).to.equal(1);
+    expect(Number((await getAccount(provider.connection, bidCurrencyAta)).amount)).to.equal(pricePerNft);
+    expect(Number((await getAccount(provider.connection, getAssociatedTokenAddressSync(first, bidder.publicKey))).amount))
+      .to.equal(1);
+
+    // An unverified collection field doesn't make an NFT a member
+    try {
+      await accept(buyer, unverified);
+      expect.fail("an unverified NFT should not fill the bid");
+    } catch (err: any) {
+      expect(err.toString()).to.include("NotInCollection");
+    }
+
+    // The last slot: the bid closes and its rents go back to the bidder
+    await accept(buyer, second);
+    expect(await provider.connection.getAccountInfo(bidPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(bidCurrencyAta)).to.be.null;
+    expect(Number((await getAccount(provider.connection, getAssociatedTokenAddressSync(second, bidder.publicKey))).amount))
+      .to.equal(1);
+
+    // A holder racing for the same slot fails and keeps the NFT
+    try {
+      await accept(seller, late);
+      expect.fail("a filled bid should not accept more NFTs");
+    } catch (err: any) {
+      expect(err.toString()).to.include("AccountNotInitialized");
+    }
+    expect(Number((await getAccount(provider.connection, getAssociatedTokenAddressSync(late, seller.publicKey))).amount))
+      .to.equal(1);
+  });
+
   it("updates config (treasury and authority)", async () => {
     const newTreasury = buyer.publicKey;
-    await marketplace.methods.updateConfig(newTreasury, buyer.publicKey)
+    await marketplace.methods.updateConfig(newTreasury, buyer.publicKey, null, null, null)
       .accounts({
         admin: wallet.publicKey,
         config: configPda,
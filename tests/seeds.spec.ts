@@ -0,0 +1,79 @@
+import { BN } from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+
+// Mirrors programs/gachapon-game/src/seeds.rs
+describe("gachapon-game PDA seeds", () => {
+  const gameProgramIdl = require("../target/idl/gachapon_game.json");
+  const programId = new PublicKey(gameProgramIdl.address);
+
+  const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, programId)[0];
+  const u64 = (n: number) => Buffer.from(new BN(n).toArray("le", 8));
+
+  it("uses prefix-free seed prefixes for every PDA in the IDL", () => {
+    const prefixes = new Set<string>();
+    for (const ix of gameProgramIdl.instructions) {
+      for (const account of ix.accounts) {
+        const first = account.pda?.seeds?.[0];
+        if (first?.kind === "const") {
+          prefixes.add(Buffer.from(first.value).toString());
+        }
+      }
+    }
+    expect(prefixes.size).to.be.greaterThan(0);
+    for (const a of prefixes) {
+      for (const b of prefixes) {
+        if (a !== b) {
+          expect(b.startsWith(a), `"${a}" is a prefix of "${b}"`).to.equal(false);
+        }
+      }
+    }
+  });
+
+  it("never derives the same address for two logical accounts", () => {
+    const users = [Keypair.generate().publicKey, Keypair.generate().publicKey];
+    const sessionSeed = Buffer.alloc(32, 7);
+    const addresses = new Map<string, string>();
+    const record = (label: string, address: PublicKey) => {
+      const existing = addresses.get(address.toBase58());
+      expect(existing, `${label} collides with ${existing}`).to.equal(undefined);
+      addresses.set(address.toBase58(), label);
+    };
+
+    record("config", pda(Buffer.from("config")));
+    record("features", pda(Buffer.from("features")));
+    record("claim_delegate", pda(Buffer.from("claim_delegate")));
+    for (const gameId of [1, 2]) {
+      const game = pda(Buffer.from("game"), u64(gameId));
+      record(`game ${gameId}`, game);
+      record(`consolation ${gameId}`, pda(Buffer.from("consolation"), game.toBuffer()));
+      record(`rng_audit ${gameId}`, pda(Buffer.from("rng_audit"), game.toBuffer()));
+      record(`winners ${gameId}`, pda(Buffer.from("winners"), game.toBuffer()));
+      record(`compliance ${gameId}`, pda(Buffer.from("compliance"), game.toBuffer()));
+      for (const index of [0, 1]) {
+        const prize = pda(Buffer.from("prize"), game.toBuffer(), Buffer.from([index]));
+        record(`prize ${gameId}/${index}`, prize);
+        record(`inventory ${gameId}/${index}`, pda(Buffer.from("inventory"), prize.toBuffer()));
+        users.forEach((user, u) => {
+          record(`claim_record ${gameId}/${index}/${u}`, pda(Buffer.from("claim_record"), prize.toBuffer(), user.toBuffer()));
+        });
+        for (const mintIndex of [0, 1]) {
+          const le = Buffer.alloc(4);
+          le.writeUInt32LE(mintIndex);
+          record(`premint ${gameId}/${index}/${mintIndex}`, pda(Buffer.from("premint"), prize.toBuffer(), le));
+        }
+      }
+      const promo = pda(Buffer.from("promo"), game.toBuffer(), u64(1));
+      record(`promo ${gameId}`, promo);
+      users.forEach((user, u) => {
+        record(`session ${gameId}/${u}`, pda(Buffer.from("session"), game.toBuffer(), user.toBuffer(), sessionSeed));
+        // play_game_with_request_id sessions, including the id the 32-byte seed starts with
+        record(`request session ${gameId}/${u}`, pda(Buffer.from("session"), game.toBuffer(), user.toBuffer(), u64(7)));
+        record(`request session ${gameId}/${u}/seed`, pda(Buffer.from("session"), game.toBuffer(), user.toBuffer(), sessionSeed.subarray(0, 8)));
+        record(`stats ${gameId}/${u}`, pda(Buffer.from("stats"), game.toBuffer(), user.toBuffer()));
+        record(`pity ${gameId}/${u}`, pda(Buffer.from("pity"), game.toBuffer(), user.toBuffer()));
+        record(`user_promo ${gameId}/${u}`, pda(Buffer.from("user_promo"), promo.toBuffer(), user.toBuffer()));
+      });
+    }
+  });
+});